@@ -0,0 +1,612 @@
+use crate::web::shell_quote;
+
+use serde::{Deserialize, Serialize};
+use stackable_operator::{
+    k8s_openapi::api::core::v1::{KeyToPath, SecretVolumeSource, Volume},
+    schemars::{self, JsonSchema},
+};
+use strum::{Display, EnumIter};
+
+pub const ADDONS_VOLUME_NAME: &str = "addons";
+/// The addons unpacked from archives, in a directory per source which is added to the
+/// `addons_path` of Odoo.
+pub const ADDONS_DIR: &str = "/stackable/addons";
+pub const ADDONS_DOWNLOAD_VOLUME_NAME: &str = "addons-download";
+/// The archives are downloaded to this directory before they are unpacked.
+pub const ADDONS_DOWNLOAD_DIR: &str = "/stackable/addons-download";
+
+pub const ADDONS_PIP_VOLUME_NAME: &str = "addons-pip";
+/// The packages of `addonsPip` are installed into this directory, which is added to the
+/// `PYTHONPATH` of Odoo.
+pub const ADDONS_PIP_DIR: &str = "/stackable/addons-pip";
+
+/// Key of the access key in the credentials Secret of `addonsBucket`.
+pub const BUCKET_ACCESS_KEY: &str = "accessKey";
+/// Key of the secret key in the credentials Secret of `addonsBucket`.
+pub const BUCKET_SECRET_KEY: &str = "secretKey";
+
+/// The containers verifying the downloaded addons are named with this prefix, so their failures
+/// can be reported in the status.
+pub const ADDONS_VERIFY_CONTAINER_PREFIX: &str = "addons-verify";
+
+const REGISTRY_CONFIG_VOLUME_NAME: &str = "addons-registry-config";
+/// The directory of the registry credentials of `addonsOci`.
+pub const REGISTRY_CONFIG_DIR: &str = "/stackable/addons-registry-config";
+const COSIGN_KEY_VOLUME_NAME: &str = "addons-cosign-key";
+const COSIGN_KEY_DIR: &str = "/stackable/addons-cosign-key";
+/// cosign writes the payloads of the verified signatures to this file, so the digest it
+/// verified is pulled rather than the tag again.
+const COSIGN_OUTPUT_FILE: &str = "/stackable/addons-download/oci-signatures.json";
+
+/// A source of addons, which is added as directory to the `addons_path` of Odoo.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Display, EnumIter, Eq, JsonSchema, PartialEq, Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum AddonsSource {
+    /// The `gitFolder` of `addonsGitSync`.
+    Git,
+    /// The addons of `addonsOci`.
+    Oci,
+    /// The addons of `addonsBucket`.
+    Bucket,
+    /// The addons installed from `addonsPip`.
+    Pip,
+}
+
+/// Pulls the addons from an OCI registry with [ORAS](https://oras.land), e.g. an artifact pushed
+/// with `oras push ghcr.io/acme/odoo-addons:1.4.0 addons.tar.gz`. Every file of the artifact is
+/// unpacked into the addons directory.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonsOci {
+    /// Reference of the artifact, e.g. `ghcr.io/acme/odoo-addons:1.4.0`. Pin a digest to deploy
+    /// the same addons on every pod.
+    pub reference: String,
+    /// The media type of the files of the artifact, which determines how they are unpacked.
+    #[serde(default)]
+    pub media_type: AddonsMediaType,
+    /// Secret of the type `kubernetes.io/dockerconfigjson` with the credentials of the registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_secret: Option<String>,
+    /// The expected SHA-256 checksum of the files of the artifact. The pods do not start if a
+    /// file does not match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = r"^[0-9a-fA-F]{64}$"))]
+    pub sha256: Option<String>,
+    /// Verify the signature of the artifact with cosign before it is pulled. The pods do not
+    /// start if it is not signed by the key. The digest verified by cosign is pulled, so a tag
+    /// moved in between is not deployed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosign: Option<AddonsCosign>,
+    /// The image running `oras pull`.
+    #[serde(default = "default_oras_image")]
+    pub image: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonsCosign {
+    /// Secret containing the public key under the key `cosign.pub`.
+    pub public_key_secret: String,
+    /// The image running `cosign verify`.
+    #[serde(default = "default_cosign_image")]
+    pub image: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum AddonsMediaType {
+    #[default]
+    #[serde(rename = "application/vnd.oci.image.layer.v1.tar+gzip")]
+    TarGzip,
+    #[serde(rename = "application/vnd.oci.image.layer.v1.tar")]
+    Tar,
+    #[serde(rename = "application/zip")]
+    Zip,
+}
+
+/// Downloads the addons from an S3 compatible object storage, e.g. release archives published by
+/// CI. Google Cloud Storage is supported with its XML API, i.e. the endpoint
+/// `https://storage.googleapis.com` and an HMAC key.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonsBucket {
+    /// The endpoint of the object storage, e.g. `https://minio.example.com:9000`. Defaults to
+    /// AWS S3.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// The region of the bucket, if the object storage requires it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub bucket: String,
+    /// Every archive below this prefix is downloaded and unpacked, e.g. `odoo-addons/1.4.0/`.
+    /// Archives are recognized by the extensions `.tar.gz`, `.tgz`, `.tar` and `.zip`.
+    #[serde(default)]
+    pub prefix: String,
+    /// Secret containing the keys `accessKey` and `secretKey`. Public buckets are read
+    /// anonymously.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_secret: Option<String>,
+    /// The expected SHA-256 checksum of the downloaded archives, so usually the prefix selects a
+    /// single archive. The pods do not start if an archive does not match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = r"^[0-9a-fA-F]{64}$"))]
+    pub sha256: Option<String>,
+    /// Image providing the AWS CLI to download the archives.
+    #[serde(default = "default_aws_cli_image")]
+    pub image: String,
+}
+
+impl AddonsOci {
+    /// The directory the artifact is pulled to.
+    pub fn download_dir() -> String {
+        format!("{ADDONS_DOWNLOAD_DIR}/oci")
+    }
+
+    /// The directory the artifact is unpacked to.
+    pub fn addons_dir() -> String {
+        format!("{ADDONS_DIR}/oci")
+    }
+
+    /// The repository of `reference`, i.e. without its tag or digest.
+    pub fn repository(&self) -> &str {
+        let reference = self
+            .reference
+            .split_once('@')
+            .map_or(self.reference.as_str(), |(repository, _)| repository);
+        match reference.rsplit_once(':') {
+            // A colon before the last slash separates the port of the registry
+            Some((repository, tag)) if !tag.contains('/') => repository,
+            _ => reference,
+        }
+    }
+
+    /// Shell command running the ORAS CLI to pull the artifact. If the signature is verified,
+    /// the digest verified by cosign is pulled from the repository, otherwise `reference`.
+    pub fn pull_command(&self) -> String {
+        let mut command = "set -eu; ".to_string();
+        if self.cosign.is_some() {
+            command.push_str(&format!(
+                "digest=$(grep -o '\"docker-manifest-digest\": *\"sha256:[0-9a-f]*\"' \
+                {COSIGN_OUTPUT_FILE} | grep -o 'sha256:[0-9a-f]*' | head -n 1); \
+                if [ -z \"$digest\" ]; then \
+                echo 'cosign did not report the verified digest' >&2; exit 1; fi; \
+                reference={}@\"$digest\"; ",
+                shell_quote(self.repository())
+            ));
+        } else {
+            command.push_str(&format!("reference={}; ", shell_quote(&self.reference)));
+        }
+        command.push_str(&format!(
+            "oras pull \"$reference\" --output={}",
+            Self::download_dir()
+        ));
+        if self.pull_secret.is_some() {
+            command.push_str(&format!(
+                " --registry-config={REGISTRY_CONFIG_DIR}/config.json"
+            ));
+        }
+        command
+    }
+
+    /// The arguments of cosign, which is the entrypoint of its image, to verify the signature
+    /// with the key of `cosign`. cosign resolves the digest of `reference` once and writes the
+    /// verified payloads to a file in `ADDONS_DOWNLOAD_DIR`, which must be mounted.
+    pub fn verify_signature_args(&self) -> Vec<String> {
+        vec![
+            "verify".to_string(),
+            format!("--key={COSIGN_KEY_DIR}/cosign.pub"),
+            format!("--output-file={COSIGN_OUTPUT_FILE}"),
+            self.reference.clone(),
+        ]
+    }
+
+    /// The volume of the pull secret, mounted to the ORAS and the cosign container. cosign reads
+    /// it from `$DOCKER_CONFIG/config.json`.
+    pub fn volumes(&self) -> Vec<(Volume, &'static str)> {
+        self.pull_secret
+            .iter()
+            .map(|pull_secret| {
+                (
+                    Volume {
+                        name: REGISTRY_CONFIG_VOLUME_NAME.to_string(),
+                        secret: Some(SecretVolumeSource {
+                            secret_name: Some(pull_secret.clone()),
+                            items: Some(vec![KeyToPath {
+                                key: ".dockerconfigjson".to_string(),
+                                path: "config.json".to_string(),
+                                ..KeyToPath::default()
+                            }]),
+                            ..SecretVolumeSource::default()
+                        }),
+                        ..Volume::default()
+                    },
+                    REGISTRY_CONFIG_DIR,
+                )
+            })
+            .collect()
+    }
+}
+
+impl AddonsCosign {
+    /// The volume of the public key, mounted to the cosign container.
+    pub fn volume(&self) -> (Volume, &'static str) {
+        (
+            Volume {
+                name: COSIGN_KEY_VOLUME_NAME.to_string(),
+                secret: Some(SecretVolumeSource {
+                    secret_name: Some(self.public_key_secret.clone()),
+                    ..SecretVolumeSource::default()
+                }),
+                ..Volume::default()
+            },
+            COSIGN_KEY_DIR,
+        )
+    }
+}
+
+impl AddonsBucket {
+    /// The directory the archives are downloaded to.
+    pub fn download_dir() -> String {
+        format!("{ADDONS_DOWNLOAD_DIR}/bucket")
+    }
+
+    /// The directory the archives are unpacked to.
+    pub fn addons_dir() -> String {
+        format!("{ADDONS_DIR}/bucket")
+    }
+
+    /// Shell command downloading the archives with the AWS CLI. The values of the spec are quoted,
+    /// so they cannot inject commands.
+    pub fn download_command(&self) -> String {
+        let prefix = self.prefix.trim_start_matches('/');
+        let mut command = vec![
+            "aws s3 cp --recursive".to_string(),
+            shell_quote(&format!("s3://{}/{prefix}", self.bucket)),
+            Self::download_dir(),
+            "--exclude '*'".to_string(),
+        ];
+        command.extend(
+            ARCHIVE_EXTENSIONS
+                .iter()
+                .map(|extension| format!("--include '*{extension}'")),
+        );
+        if let Some(endpoint) = &self.endpoint {
+            command.push(format!("--endpoint-url {}", shell_quote(endpoint)));
+        }
+        if self.credentials_secret.is_none() {
+            command.push("--no-sign-request".to_string());
+        }
+        command.join(" ")
+    }
+
+    /// Shell command unpacking the downloaded archives, depending on their extension.
+    pub fn unpack_command(&self) -> String {
+        unpack_command(
+            &Self::download_dir(),
+            &Self::addons_dir(),
+            &format!(
+                "case \"$archive\" in \
+                *.zip) {}; ;; \
+                *.tar) {}; ;; \
+                *) {}; ;; \
+                esac",
+                AddonsMediaType::Zip.unpack(),
+                AddonsMediaType::Tar.unpack(),
+                AddonsMediaType::TarGzip.unpack()
+            ),
+        )
+    }
+}
+
+impl AddonsMediaType {
+    /// Shell command unpacking the pulled files.
+    pub fn unpack_command(&self) -> String {
+        unpack_command(
+            &AddonsOci::download_dir(),
+            &AddonsOci::addons_dir(),
+            self.unpack(),
+        )
+    }
+
+    /// Shell command unpacking the file `$archive` into `$addons_dir`.
+    fn unpack(&self) -> &'static str {
+        match self {
+            AddonsMediaType::TarGzip => "tar -xzf \"$archive\" -C \"$addons_dir\"",
+            AddonsMediaType::Tar => "tar -xf \"$archive\" -C \"$addons_dir\"",
+            // unzip is not part of the product image
+            AddonsMediaType::Zip => "python3 -m zipfile -e \"$archive\" \"$addons_dir\"",
+        }
+    }
+}
+
+const ARCHIVE_EXTENSIONS: &[&str] = &[".tar.gz", ".tgz", ".tar", ".zip"];
+
+/// The directory of the addons installed with pip. Packaged addons like the ones of the OCA are
+/// installed into the namespace package `odoo.addons`.
+pub fn pip_addons_dir() -> String {
+    format!("{ADDONS_PIP_DIR}/odoo/addons")
+}
+
+/// Shell command installing the `packages`, which are requirement specifiers like
+/// `odoo-addon-queue-job==16.0.*`, into `ADDONS_PIP_DIR`. The options end before the packages,
+/// so a package cannot be taken as option of pip.
+pub fn pip_install_command(packages: &[String]) -> String {
+    let packages = packages
+        .iter()
+        .map(|package| shell_quote(package))
+        .collect::<Vec<_>>();
+    format!(
+        "pip install --no-cache-dir --target {ADDONS_PIP_DIR} -- {}",
+        packages.join(" ")
+    )
+}
+
+/// Whether `sha256` is a SHA-256 checksum, i.e. 64 hex characters as required by the CRD.
+pub fn is_sha256(sha256: &str) -> bool {
+    sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Shell command failing if a file in `download_dir` does not have the SHA-256 checksum
+/// `sha256`. The message is the termination message of the container.
+pub fn verify_checksum_command(download_dir: &str, sha256: &str) -> String {
+    format!(
+        "set -e; expected={}; find {download_dir} -type f | sort | \
+        while read -r archive; do \
+        actual=$(sha256sum \"$archive\" | cut -d' ' -f1); \
+        if [ \"$actual\" != \"$expected\" ]; then \
+        echo \"The SHA-256 checksum of $archive is $actual, expected $expected\" >&2; exit 1; \
+        fi; done",
+        shell_quote(&sha256.to_lowercase())
+    )
+}
+
+/// Runs `unpack` for every file in `download_dir` in a stable order, to unpack it into
+/// `addons_dir`.
+fn unpack_command(download_dir: &str, addons_dir: &str, unpack: &str) -> String {
+    format!(
+        "set -e; addons_dir={addons_dir}; mkdir -p \"$addons_dir\"; \
+        find {download_dir} -type f | sort | \
+        while read -r archive; do echo \"Unpacking $archive\"; {unpack}; done"
+    )
+}
+
+fn default_oras_image() -> String {
+    "ghcr.io/oras-project/oras:v1.1.0".to_string()
+}
+
+fn default_cosign_image() -> String {
+    "gcr.io/projectsigstore/cosign:v2.2.2".to_string()
+}
+
+fn default_aws_cli_image() -> String {
+    "docker.io/amazon/aws-cli:2.15.0".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addons_oci() {
+        let addons_oci: AddonsOci = serde_yaml::from_str(
+            "
+            reference: ghcr.io/acme/odoo-addons:1.4.0
+            mediaType: application/zip
+            pullSecret: ghcr-credentials
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "set -eu; reference='ghcr.io/acme/odoo-addons:1.4.0'; \
+            oras pull \"$reference\" --output=/stackable/addons-download/oci \
+            --registry-config=/stackable/addons-registry-config/config.json",
+            addons_oci.pull_command()
+        );
+        assert!(addons_oci
+            .media_type
+            .unpack_command()
+            .starts_with("set -e; addons_dir=/stackable/addons/oci;"));
+        assert!(addons_oci
+            .media_type
+            .unpack_command()
+            .ends_with("python3 -m zipfile -e \"$archive\" \"$addons_dir\"; done"));
+    }
+
+    #[test]
+    fn test_repository() {
+        for (reference, repository) in [
+            ("ghcr.io/acme/odoo-addons:1.4.0", "ghcr.io/acme/odoo-addons"),
+            (
+                "registry:5000/acme/odoo-addons",
+                "registry:5000/acme/odoo-addons",
+            ),
+            (
+                "registry:5000/acme/odoo-addons:1.4",
+                "registry:5000/acme/odoo-addons",
+            ),
+            (
+                "ghcr.io/acme/odoo-addons:1.4.0@sha256:e3b0c442",
+                "ghcr.io/acme/odoo-addons",
+            ),
+        ] {
+            let addons_oci: AddonsOci =
+                serde_yaml::from_str(&format!("reference: {reference}")).unwrap();
+            assert_eq!(repository, addons_oci.repository());
+        }
+    }
+
+    #[test]
+    fn test_signed_artifact_is_pulled_by_the_verified_digest() {
+        let addons_oci: AddonsOci = serde_yaml::from_str(
+            "
+            reference: ghcr.io/acme/odoo-addons:1.4.0
+            cosign:
+              publicKeySecret: cosign-key
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "verify",
+                "--key=/stackable/addons-cosign-key/cosign.pub",
+                "--output-file=/stackable/addons-download/oci-signatures.json",
+                "ghcr.io/acme/odoo-addons:1.4.0",
+            ],
+            addons_oci.verify_signature_args()
+        );
+
+        let dir = std::env::temp_dir().join(format!("addons-oci-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let digest = format!("sha256:{}", "a".repeat(64));
+        std::fs::write(
+            dir.join("oci-signatures.json"),
+            format!(
+                "[{{\"critical\":{{\"identity\":{{\"docker-reference\":\
+                \"ghcr.io/acme/odoo-addons\"}},\"image\":{{\"docker-manifest-digest\":\
+                \"{digest}\"}},\"type\":\"cosign container image signature\"}},\
+                \"optional\":null}}]"
+            ),
+        )
+        .unwrap();
+        // oras is stubbed to print its arguments
+        let pull = |command: String| {
+            let command = format!(
+                "oras() {{ printf '%s\\n' \"$@\"; }}\n{}",
+                command.replace(ADDONS_DOWNLOAD_DIR, dir.to_str().unwrap())
+            );
+            std::process::Command::new("sh")
+                .args(["-c", &command])
+                .output()
+                .unwrap()
+        };
+
+        let output = pull(addons_oci.pull_command());
+        assert!(output.status.success());
+        assert_eq!(
+            format!(
+                "pull\nghcr.io/acme/odoo-addons@{digest}\n--output={}/oci\n",
+                dir.display()
+            ),
+            String::from_utf8_lossy(&output.stdout)
+        );
+
+        std::fs::write(dir.join("oci-signatures.json"), "[]").unwrap();
+        let output = pull(addons_oci.pull_command());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+
+    #[test]
+    fn test_verify_checksum_command() {
+        let command = verify_checksum_command(
+            "/stackable/addons-download/bucket",
+            "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855",
+        );
+        assert!(command.starts_with(
+            "set -e; \
+            expected='e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855'; "
+        ));
+        assert!(command.contains("if [ \"$actual\" != \"$expected\" ]; then"));
+        assert!(command.ends_with(">&2; exit 1; fi; done"));
+
+        assert!(is_sha256(
+            "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855"
+        ));
+        assert!(!is_sha256(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b85; id"
+        ));
+        assert!(!is_sha256("e3b0c442"));
+    }
+
+    #[test]
+    fn test_pip_install_command() {
+        assert_eq!(
+            "pip install --no-cache-dir --target /stackable/addons-pip -- \
+            'odoo-addon-queue-job==16.0.*' 'odoo-addon-web-responsive>=16.0.1'",
+            pip_install_command(&[
+                "odoo-addon-queue-job==16.0.*".to_string(),
+                "odoo-addon-web-responsive>=16.0.1".to_string()
+            ])
+        );
+
+        // The specifiers are passed as they are, even with quotes
+        let packages = [
+            "odoo-addon-queue-job==16.0.*".to_string(),
+            "odoo-addon-x'; echo injected; '$(echo injected)".to_string(),
+        ];
+        // pip is stubbed to print its arguments
+        let command = format!(
+            "pip() {{ printf '%s\\n' \"$@\"; }}\n{}",
+            pip_install_command(&packages)
+        );
+        let output = std::process::Command::new("bash")
+            .args(["-c", &command])
+            .output()
+            .unwrap();
+        assert_eq!(
+            format!(
+                "install\n--no-cache-dir\n--target\n/stackable/addons-pip\n--\n{}\n",
+                packages.join("\n")
+            ),
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    #[test]
+    fn test_addons_bucket() {
+        let addons_bucket: AddonsBucket = serde_yaml::from_str(
+            "
+            endpoint: https://storage.googleapis.com
+            bucket: acme-releases
+            prefix: /odoo-addons/1.4.0/
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "aws s3 cp --recursive 's3://acme-releases/odoo-addons/1.4.0/' \
+            /stackable/addons-download/bucket --exclude '*' --include '*.tar.gz' \
+            --include '*.tgz' --include '*.tar' --include '*.zip' \
+            --endpoint-url 'https://storage.googleapis.com' --no-sign-request",
+            addons_bucket.download_command()
+        );
+        assert!(addons_bucket
+            .unpack_command()
+            .contains("case \"$archive\" in *.zip) python3 -m zipfile"));
+    }
+
+    #[test]
+    fn test_addons_bucket_values_are_quoted() {
+        let addons_bucket: AddonsBucket = serde_yaml::from_str(
+            "
+            endpoint: https://example.com'; echo injected; '
+            bucket: acme$(echo injected)
+            prefix: odoo-addons; echo injected
+            ",
+        )
+        .unwrap();
+
+        // aws is stubbed to print its arguments
+        let command = format!(
+            "aws() {{ printf '%s\\n' \"$@\"; }}\n{}",
+            addons_bucket.download_command()
+        );
+        let output = std::process::Command::new("bash")
+            .args(["-c", &command])
+            .output()
+            .unwrap();
+        let args = String::from_utf8_lossy(&output.stdout);
+        assert!(!args.lines().any(|arg| arg == "injected"));
+        assert_eq!(
+            Some("s3://acme$(echo injected)/odoo-addons; echo injected"),
+            args.lines().nth(3)
+        );
+        assert!(args
+            .lines()
+            .any(|arg| arg == "https://example.com'; echo injected; '"));
+    }
+}
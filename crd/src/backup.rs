@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::{
+    commons::s3::S3BucketDef,
+    schemars::{self, JsonSchema},
+};
+
+/// Dumps the database and the filestore to an S3 bucket before it is migrated to a new product version. The
+/// migration is not started if the backup fails.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeBackupConfig {
+    /// The dumps are stored as `<prefix>/<cluster>/pre-upgrade-<version>.dump` in the
+    /// custom format of `pg_dump`, the filestore next to them as
+    /// `<prefix>/<cluster>/pre-upgrade-<version>.filestore.tar.gz`.
+    pub bucket: S3BucketDef,
+    #[serde(default)]
+    pub prefix: String,
+    /// Image providing `pg_dump`, which must not be older than the PostgreSQL server.
+    #[serde(default = "UpgradeBackupConfig::default_pg_dump_image")]
+    pub pg_dump_image: String,
+    /// Image providing the AWS CLI to upload the dump.
+    #[serde(default = "UpgradeBackupConfig::default_upload_image")]
+    pub upload_image: String,
+}
+
+impl UpgradeBackupConfig {
+    fn default_pg_dump_image() -> String {
+        "docker.io/bitnami/postgresql:16".to_string()
+    }
+
+    fn default_upload_image() -> String {
+        "docker.io/amazon/aws-cli:2.15.0".to_string()
+    }
+
+    /// The object key of the dump taken before upgrading to the given version.
+    pub fn object_key(&self, cluster_name: &str, to_version: &str) -> String {
+        self.key(&format!("{cluster_name}/pre-upgrade-{to_version}.dump"))
+    }
+
+    /// The object key of the filestore snapshot taken before upgrading to the given version.
+    pub fn filestore_object_key(&self, cluster_name: &str, to_version: &str) -> String {
+        self.key(&format!(
+            "{cluster_name}/pre-upgrade-{to_version}.filestore.tar.gz"
+        ))
+    }
+
+    fn key(&self, file: &str) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            file.to_string()
+        } else {
+            format!("{prefix}/{file}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_keys() {
+        let mut config: UpgradeBackupConfig = serde_yaml::from_str(
+            "
+            bucket:
+              reference: backups
+            prefix: /odoo/
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "odoo/erp/pre-upgrade-17.0.dump",
+            config.object_key("erp", "17.0")
+        );
+        assert_eq!(
+            "odoo/erp/pre-upgrade-17.0.filestore.tar.gz",
+            config.filestore_object_key("erp", "17.0")
+        );
+
+        config.prefix = String::new();
+        assert_eq!(
+            "erp/pre-upgrade-17.0.dump",
+            config.object_key("erp", "17.0")
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::{
+    commons::product_image_selection::ProductImage,
+    schemars::{self, JsonSchema},
+};
+use strum::Display;
+
+/// Runs the webservers in two generations, `blue` and `green`. The role Service only sends the
+/// traffic to the active one, so a new version can be tried out on the other one before all
+/// users are switched over at once.
+///
+/// The workers, the schedulers and the database migration follow `spec.image`, so the preview
+/// should run an image of the same product version until it is activated.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlueGreenConfig {
+    /// The generation the role Service selects. It runs `spec.image`.
+    #[serde(default)]
+    pub active_color: Color,
+    /// Runs the other generation with this image, reachable via the Service
+    /// `<cluster>-webserver-preview`. It is switched over by changing `activeColor` and
+    /// `spec.image` to the preview while removing `previewImage`, which also deletes the
+    /// previously active generation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_image: Option<ProductImage>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Color {
+    #[default]
+    Blue,
+    Green,
+}
+
+impl Color {
+    pub fn other(self) -> Self {
+        match self {
+            Color::Blue => Color::Green,
+            Color::Green => Color::Blue,
+        }
+    }
+
+    /// The name of the rolegroup in this generation. The blue generation keeps the names of the
+    /// rolegroups, so enabling blue/green does not replace the running webservers.
+    pub fn rolegroup_name(self, role_group: &str) -> String {
+        match self {
+            Color::Blue => role_group.to_string(),
+            Color::Green => format!("{role_group}-{self}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolegroup_name() {
+        assert_eq!("default", Color::Blue.rolegroup_name("default"));
+        assert_eq!("default-green", Color::Green.rolegroup_name("default"));
+        assert_eq!(Color::Blue, Color::Green.other());
+
+        let config: BlueGreenConfig = serde_yaml::from_str("activeColor: green").unwrap();
+        assert_eq!(Color::Green, config.active_color);
+        assert_eq!(None, config.preview_image);
+    }
+}
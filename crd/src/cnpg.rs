@@ -0,0 +1,87 @@
+//! The parts of the CloudNativePG `Cluster` resource which are needed to connect to it.
+//!
+//! See <https://cloudnative-pg.io/documentation/current/cloudnative-pg.v1/#postgresql-cnpg-io-v1-Cluster>
+use serde::{Deserialize, Serialize};
+use stackable_operator::{
+    kube::CustomResource,
+    schemars::{self, JsonSchema},
+};
+
+/// Key of the connection URI in the app Secret generated by CloudNativePG.
+pub const CNPG_APP_SECRET_URI_KEY: &str = "uri";
+
+const CNPG_PHASE_HEALTHY: &str = "Cluster in healthy state";
+
+#[derive(Clone, CustomResource, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[kube(
+group = "postgresql.cnpg.io",
+version = "v1",
+kind = "Cluster",
+struct = "CnpgCluster",
+plural = "clusters",
+status = "CnpgClusterStatus",
+namespaced,
+crates(
+kube_core = "stackable_operator::kube::core",
+k8s_openapi = "stackable_operator::k8s_openapi",
+schemars = "stackable_operator::schemars"
+)
+)]
+pub struct CnpgClusterSpec {}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CnpgClusterStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready_instances: Option<i32>,
+}
+
+impl CnpgCluster {
+    /// The primary accepts connections once the cluster reports a healthy state.
+    pub fn is_ready(&self) -> bool {
+        self.status.as_ref().map_or(false, |status| {
+            status.phase.as_deref() == Some(CNPG_PHASE_HEALTHY)
+                && status.ready_instances.unwrap_or_default() > 0
+        })
+    }
+}
+
+/// Name of the Secret containing the credentials of the application user.
+pub fn cnpg_app_secret_name(cnpg_cluster_name: &str) -> String {
+    format!("{cnpg_cluster_name}-app")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ready() {
+        let cnpg_cluster: CnpgCluster = serde_yaml::from_str(
+            "
+            apiVersion: postgresql.cnpg.io/v1
+            kind: Cluster
+            metadata:
+              name: odoo-postgresql
+            spec:
+              instances: 3
+            status:
+              phase: Setting up primary
+              readyInstances: 0
+            ",
+        )
+        .unwrap();
+        assert!(!cnpg_cluster.is_ready());
+
+        let cnpg_cluster = CnpgCluster {
+            status: Some(CnpgClusterStatus {
+                phase: Some("Cluster in healthy state".to_string()),
+                ready_instances: Some(3),
+            }),
+            ..cnpg_cluster
+        };
+        assert!(cnpg_cluster.is_ready());
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::{
+    k8s_openapi::api::core::v1::{CSIVolumeSource, Volume},
+    schemars::{self, JsonSchema},
+};
+use std::collections::BTreeMap;
+
+pub const CREDENTIALS_VOLUME_NAME: &str = "credentials";
+pub const CREDENTIALS_DIR: &str = "/stackable/credentials";
+
+const SECRETS_STORE_CSI_DRIVER: &str = "secrets-store.csi.k8s.io";
+
+pub const SECRET_KEY_ENV: &str = "AIRFLOW__WEBSERVER__SECRET_KEY";
+
+/// The environment variables read from the credentials, mapped to the key in the credentials
+/// Secret.
+pub const CREDENTIALS_ENV_VARS: &[(&str, &str)] = &[
+    // The secret key is used to run the webserver flask app and also used to authorize
+    // requests to Celery workers when logs are retrieved.
+    (SECRET_KEY_ENV, "connections.secretKey"),
+    (
+        "AIRFLOW__CELERY__RESULT_BACKEND",
+        "connections.celeryResultBackend",
+    ),
+    ("AIRFLOW__CELERY__BROKER_URL", "connections.celeryBrokerUrl"),
+];
+
+/// Alternative sources for the credentials which are otherwise read from the
+/// `credentialsSecret`. The credentials are mounted as files named like the keys of the
+/// credentials Secret (e.g. `connections.sqlalchemyDatabaseUri`), so they never have to be
+/// stored in a Kubernetes Secret.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CredentialsProvider {
+    /// Mount the credentials with the Secrets Store CSI driver, e.g. from HashiCorp Vault.
+    #[serde(rename_all = "camelCase")]
+    SecretsStoreCsi {
+        /// Name of the `SecretProviderClass` describing which secrets are mounted.
+        secret_provider_class: String,
+    },
+}
+
+impl CredentialsProvider {
+    /// The volume providing the credential files.
+    pub fn volume(&self) -> Volume {
+        match self {
+            CredentialsProvider::SecretsStoreCsi {
+                secret_provider_class,
+            } => Volume {
+                name: CREDENTIALS_VOLUME_NAME.to_string(),
+                csi: Some(CSIVolumeSource {
+                    driver: SECRETS_STORE_CSI_DRIVER.to_string(),
+                    read_only: Some(true),
+                    volume_attributes: Some(BTreeMap::from([(
+                        "secretProviderClass".to_string(),
+                        secret_provider_class.clone(),
+                    )])),
+                    ..CSIVolumeSource::default()
+                }),
+                ..Volume::default()
+            },
+        }
+    }
+}
+
+/// The admin user which is created from the `adminUser.*` credentials when the database is
+/// initialized.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminUserConfig {
+    /// Create the admin user. Disable it if the users are managed otherwise, e.g. with
+    /// OdooUsers or an AuthenticationClass. The `adminUser.*` credentials are not needed then.
+    #[serde(default = "default_create_admin_user")]
+    pub create: bool,
+    /// The role of the admin user.
+    #[serde(default = "default_admin_role")]
+    pub role: String,
+}
+
+impl Default for AdminUserConfig {
+    fn default() -> Self {
+        Self {
+            create: default_create_admin_user(),
+            role: default_admin_role(),
+        }
+    }
+}
+
+fn default_create_admin_user() -> bool {
+    true
+}
+
+fn default_admin_role() -> String {
+    "Admin".to_string()
+}
+
+/// Returns the path the credential with the given key is mounted at.
+pub fn credentials_file(key: &str) -> String {
+    format!("{CREDENTIALS_DIR}/{key}")
+}
@@ -0,0 +1,558 @@
+use crate::{
+    cnpg::cnpg_app_secret_name, DatabaseConnectionFieldMissingSnafu, Error,
+    InvalidDatabaseSchemaSnafu,
+};
+
+use serde::{Deserialize, Serialize};
+use snafu::ensure;
+use stackable_operator::{
+    k8s_openapi::api::core::v1::{EnvVar, SecretVolumeSource, Volume, VolumeMount},
+    schemars::{self, JsonSchema},
+};
+use strum::Display;
+
+pub const SQL_ALCHEMY_CONN_ENV: &str = "AIRFLOW__CORE__SQL_ALCHEMY_CONN";
+
+/// The credentials providing the connection if the secret format is `uri`, as pairs of the
+/// environment variable and the key in the credentials.
+const URI_CONNECTION_CREDENTIALS: &[(&str, &str)] =
+    &[(SQL_ALCHEMY_CONN_ENV, "connections.sqlalchemyDatabaseUri")];
+/// The credentials providing the connection if the secret format is `plain`. They are passed as
+/// libpq environment variables, so tools like `pg_dump` can connect without the URI.
+const PLAIN_CONNECTION_CREDENTIALS: &[(&str, &str)] = &[
+    ("PGHOST", "db.host"),
+    ("PGPORT", "db.port"),
+    ("PGDATABASE", "db.name"),
+    ("PGUSER", "db.user"),
+    ("PGPASSWORD", "db.password"),
+];
+/// The keys of the Secrets generated by the Crunchy Postgres operator for each user.
+const CRUNCHY_CONNECTION_CREDENTIALS: &[(&str, &str)] = &[
+    ("PGHOST", "host"),
+    ("PGPORT", "port"),
+    ("PGDATABASE", "dbname"),
+    ("PGUSER", "user"),
+    ("PGPASSWORD", "password"),
+];
+/// The keys of the app Secret generated by CloudNativePG.
+const CNPG_CONNECTION_CREDENTIALS: &[(&str, &str)] = &[
+    ("PGHOST", "host"),
+    ("PGPORT", "port"),
+    ("PGDATABASE", "dbname"),
+    ("PGUSER", "username"),
+    ("PGPASSWORD", "password"),
+];
+/// The Secrets generated by the Zalando Postgres operator only contain the credentials.
+const ZALANDO_CONNECTION_CREDENTIALS: &[(&str, &str)] =
+    &[("PGUSER", "username"), ("PGPASSWORD", "password")];
+
+const DATABASE_TLS_CA_VOLUME_NAME: &str = "database-tls-ca";
+const DATABASE_TLS_CA_DIR: &str = "/stackable/database-tls/ca";
+const DATABASE_TLS_CLIENT_VOLUME_NAME: &str = "database-tls-client";
+const DATABASE_TLS_CLIENT_DIR: &str = "/stackable/database-tls/client";
+
+/// libpq refuses private keys which are readable by others, group read access is only allowed
+/// for root owned files, which is the case for Secret volumes.
+const SECRET_FILE_MODE: i32 = 0o640;
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooDatabaseConfig {
+    /// The port PostgreSQL listens on, used to allow the traffic to the database if the
+    /// egress policy is `strict`. CloudNativePG uses the default port as well.
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Connect to PostgreSQL via TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<DatabaseTlsConfig>,
+    /// Name of a CloudNativePG `Cluster` in the same namespace. The connection URI is then
+    /// read from its app Secret instead of the credentials, and the Odoo cluster is not
+    /// reconciled before the PostgreSQL cluster is ready.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cnpg_cluster_ref: Option<String>,
+    /// How the connection is stored in the credentials.
+    #[serde(default)]
+    pub secret_format: DatabaseSecretFormat,
+    /// Name of the Secret containing the connection in the layout of the `secretFormat`, e.g.
+    /// a Secret generated by a Postgres operator. Defaults to the `credentialsSecret`. Not used
+    /// if the credentials are read from a `credentialsProvider`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_secret: Option<String>,
+    /// Host of the database server, required by the `zalando` secret format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Name of the database, required by the `zalando` secret format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_name: Option<String>,
+    /// Schema containing the tables of Odoo, if the database is shared by several tenants with
+    /// a schema each. It is set as `search_path` of all connections and created by the init job
+    /// if it does not exist. Must be a lowercase identifier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// The `max_connections` of the PostgreSQL server. If set, the cluster is only deployed if
+    /// its pods cannot open more connections, based on `dbMaxconn`, `workerProcesses` and
+    /// `maxCronThreads` of the roles. Not checked if PgBouncer is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u32>,
+    /// A read-only replica of the database, used for reports by the roles which enable
+    /// `useDatabaseReplica`. The replica is accessed with the same credentials as the primary.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replica: Option<DatabaseReplicaConfig>,
+}
+
+impl Default for OdooDatabaseConfig {
+    fn default() -> Self {
+        Self {
+            port: default_port(),
+            tls: None,
+            cnpg_cluster_ref: None,
+            secret_format: DatabaseSecretFormat::default(),
+            connection_secret: None,
+            host: None,
+            database_name: None,
+            schema: None,
+            max_connections: None,
+            replica: None,
+        }
+    }
+}
+
+impl OdooDatabaseConfig {
+    /// The Secret containing the connection URI under
+    /// [`CNPG_APP_SECRET_URI_KEY`](crate::cnpg::CNPG_APP_SECRET_URI_KEY), if the
+    /// database is managed by CloudNativePG.
+    pub fn cnpg_app_secret(&self) -> Option<String> {
+        self.cnpg_cluster_ref.as_deref().map(cnpg_app_secret_name)
+    }
+
+    /// The environment variables providing the connection, mapped to the keys in the
+    /// credentials. Empty if the database is managed by CloudNativePG.
+    pub fn connection_credentials(&self) -> &'static [(&'static str, &'static str)] {
+        if self.cnpg_cluster_ref.is_some() {
+            return &[];
+        }
+        match self.secret_format {
+            DatabaseSecretFormat::Uri => URI_CONNECTION_CREDENTIALS,
+            DatabaseSecretFormat::Plain => PLAIN_CONNECTION_CREDENTIALS,
+            DatabaseSecretFormat::Zalando => ZALANDO_CONNECTION_CREDENTIALS,
+            DatabaseSecretFormat::Crunchy => CRUNCHY_CONNECTION_CREDENTIALS,
+            DatabaseSecretFormat::Cnpg => CNPG_CONNECTION_CREDENTIALS,
+        }
+    }
+
+    /// The Secret the [`Self::connection_credentials`] are read from.
+    pub fn connection_secret<'a>(&'a self, credentials_secret: &'a str) -> &'a str {
+        self.connection_secret
+            .as_deref()
+            .unwrap_or(credentials_secret)
+    }
+
+    /// The parts of the connection which are not contained in the Secret of the secret format,
+    /// and the `search_path` of the [`Self::schema`].
+    pub fn connection_env_vars(&self) -> Vec<EnvVar> {
+        let mut env_vars = Vec::new();
+        if self.cnpg_cluster_ref.is_none() && self.secret_format == DatabaseSecretFormat::Zalando {
+            env_vars.extend([
+                ("PGHOST", self.host.clone()),
+                ("PGPORT", Some(self.port.to_string())),
+                ("PGDATABASE", self.database_name.clone()),
+            ]);
+        }
+        // Picked up by psycopg2 as well, as it is based on libpq
+        env_vars.push((
+            "PGOPTIONS",
+            self.schema
+                .as_ref()
+                .map(|schema| format!("-c search_path=\"{schema}\"")),
+        ));
+        env_vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                Some(EnvVar {
+                    name: name.to_string(),
+                    value: Some(value?),
+                    ..EnvVar::default()
+                })
+            })
+            .collect()
+    }
+
+    /// Checks that the connection fields which are not contained in the Secret of the secret
+    /// format are given and that the [`Self::schema`] is a lowercase identifier, as it is
+    /// rendered into the connection options and the commands of the init job and the backup.
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(schema) = &self.schema {
+            ensure!(
+                is_lowercase_identifier(schema),
+                InvalidDatabaseSchemaSnafu { schema }
+            );
+        }
+        if self.cnpg_cluster_ref.is_none() && self.secret_format == DatabaseSecretFormat::Zalando {
+            for (field, value) in [("host", &self.host), ("databaseName", &self.database_name)] {
+                ensure!(
+                    value.is_some(),
+                    DatabaseConnectionFieldMissingSnafu {
+                        secret_format: self.secret_format,
+                        field,
+                    }
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the connection URI has to be assembled from the connection fields.
+    pub fn assembles_connection_uri(&self) -> bool {
+        self.cnpg_cluster_ref.is_none() && self.secret_format != DatabaseSecretFormat::Uri
+    }
+}
+
+/// Matches `^[a-z_][a-z0-9_]*$`.
+fn is_lowercase_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some('a'..='z' | '_'))
+        && chars.all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_'))
+}
+
+fn default_port() -> u16 {
+    5432
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "lowercase")]
+pub enum DatabaseSecretFormat {
+    /// The SQLAlchemy connection URI under the key `connections.sqlalchemyDatabaseUri`.
+    #[default]
+    Uri,
+    /// The connection fields under the keys `db.host`, `db.port`, `db.name`, `db.user` and
+    /// `db.password`. The operator assembles the connection URI from them, so special
+    /// characters in the password don't have to be escaped.
+    Plain,
+    /// A Secret of the Zalando Postgres operator, containing `username` and `password`. The
+    /// `host` and the `databaseName` have to be set.
+    Zalando,
+    /// A user Secret of the Crunchy Postgres operator.
+    Crunchy,
+    /// A Secret of CloudNativePG. Prefer `cnpgClusterRef` for clusters in the same namespace.
+    Cnpg,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseReplicaConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+/// Lets the init job create the database and its role before the schema is initialized, so
+/// the database does not have to be created beforehand. Name, role and password are taken from
+/// the connection URI in the credentials. Existing databases and roles are left untouched.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseProvisioning {
+    /// Name of the Secret containing the credentials of a PostgreSQL superuser (or a role with
+    /// `CREATEDB` and `CREATEROLE`) under the keys `username` and `password`.
+    pub superuser_secret: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionPooling {
+    /// Run PgBouncer as sidecar in all Odoo pods. The Odoo containers connect to the local
+    /// PgBouncer instead of the database server given in the connection URI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pgbouncer: Option<PgBouncerConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PgBouncerConfig {
+    #[serde(default = "PgBouncerConfig::default_image")]
+    pub image: String,
+    /// Image of the Prometheus exporter for the PgBouncer statistics.
+    #[serde(default = "PgBouncerConfig::default_exporter_image")]
+    pub exporter_image: String,
+    #[serde(default)]
+    pub pool_mode: PgBouncerPoolMode,
+    /// Server connections per user and database.
+    #[serde(default = "PgBouncerConfig::default_pool_size")]
+    pub default_pool_size: u16,
+    #[serde(default = "PgBouncerConfig::default_max_client_conn")]
+    pub max_client_conn: u16,
+}
+
+impl PgBouncerConfig {
+    fn default_image() -> String {
+        "docker.io/bitnami/pgbouncer:1.21.0".to_string()
+    }
+
+    fn default_exporter_image() -> String {
+        "quay.io/prometheuscommunity/pgbouncer-exporter:v0.7.0".to_string()
+    }
+
+    fn default_pool_size() -> u16 {
+        20
+    }
+
+    fn default_max_client_conn() -> u16 {
+        100
+    }
+}
+
+/// See <https://www.pgbouncer.org/config.html#pool_mode>
+#[derive(Clone, Debug, Default, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "lowercase")]
+pub enum PgBouncerPoolMode {
+    #[default]
+    Session,
+    Transaction,
+    Statement,
+}
+
+/// The TLS settings are passed to libpq as `PGSSL*` environment variables, so they don't have
+/// to be part of the connection URI.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseTlsConfig {
+    /// See <https://www.postgresql.org/docs/current/libpq-ssl.html#LIBPQ-SSL-PROTECTION>
+    #[serde(default)]
+    pub ssl_mode: PostgresSslMode,
+    /// Name of the Secret containing the CA certificate under the key `ca.crt`, used to verify
+    /// the server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_secret: Option<String>,
+    /// Name of the Secret containing the client certificate and key under the keys `tls.crt`
+    /// and `tls.key`, e.g. a Secret of type `kubernetes.io/tls`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_certificate_secret: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "kebab-case")]
+pub enum PostgresSslMode {
+    Disable,
+    Allow,
+    Prefer,
+    #[default]
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl DatabaseTlsConfig {
+    pub fn volumes(&self) -> Vec<Volume> {
+        [
+            (DATABASE_TLS_CA_VOLUME_NAME, &self.ca_secret),
+            (
+                DATABASE_TLS_CLIENT_VOLUME_NAME,
+                &self.client_certificate_secret,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(volume_name, secret)| {
+            secret.as_ref().map(|secret| Volume {
+                name: volume_name.to_string(),
+                secret: Some(SecretVolumeSource {
+                    secret_name: Some(secret.clone()),
+                    default_mode: Some(SECRET_FILE_MODE),
+                    ..SecretVolumeSource::default()
+                }),
+                ..Volume::default()
+            })
+        })
+        .collect()
+    }
+
+    pub fn volume_mounts(&self) -> Vec<VolumeMount> {
+        let mut volume_mounts = Vec::new();
+        if self.ca_secret.is_some() {
+            volume_mounts.push(VolumeMount {
+                name: DATABASE_TLS_CA_VOLUME_NAME.to_string(),
+                mount_path: DATABASE_TLS_CA_DIR.to_string(),
+                read_only: Some(true),
+                ..VolumeMount::default()
+            });
+        }
+        if self.client_certificate_secret.is_some() {
+            volume_mounts.push(VolumeMount {
+                name: DATABASE_TLS_CLIENT_VOLUME_NAME.to_string(),
+                mount_path: DATABASE_TLS_CLIENT_DIR.to_string(),
+                read_only: Some(true),
+                ..VolumeMount::default()
+            });
+        }
+        volume_mounts
+    }
+
+    /// The libpq environment variables pointing to the mounted certificates.
+    pub fn env_vars(&self) -> Vec<EnvVar> {
+        let mut env = vec![("PGSSLMODE", self.ssl_mode.to_string())];
+        if self.ca_secret.is_some() {
+            env.push(("PGSSLROOTCERT", format!("{DATABASE_TLS_CA_DIR}/ca.crt")));
+        }
+        if self.client_certificate_secret.is_some() {
+            env.push(("PGSSLCERT", format!("{DATABASE_TLS_CLIENT_DIR}/tls.crt")));
+            env.push(("PGSSLKEY", format!("{DATABASE_TLS_CLIENT_DIR}/tls.key")));
+        }
+        env.into_iter()
+            .map(|(name, value)| EnvVar {
+                name: name.to_string(),
+                value: Some(value),
+                ..EnvVar::default()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_vars() {
+        let tls: DatabaseTlsConfig = serde_yaml::from_str(
+            "
+            sslMode: verifyFull
+            caSecret: postgresql-ca
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(1, tls.volumes().len());
+        assert_eq!(
+            vec![
+                ("PGSSLMODE".to_string(), Some("verify-full".to_string())),
+                (
+                    "PGSSLROOTCERT".to_string(),
+                    Some("/stackable/database-tls/ca/ca.crt".to_string())
+                ),
+            ],
+            tls.env_vars()
+                .into_iter()
+                .map(|env| (env.name, env.value))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_connection_credentials() {
+        let database: OdooDatabaseConfig = serde_yaml::from_str("secretFormat: plain").unwrap();
+        assert!(database.assembles_connection_uri());
+        assert_eq!(
+            vec!["db.host", "db.port", "db.name", "db.user", "db.password"],
+            database
+                .connection_credentials()
+                .iter()
+                .map(|(_, key)| *key)
+                .collect::<Vec<_>>()
+        );
+
+        let database = OdooDatabaseConfig {
+            cnpg_cluster_ref: Some("odoo-postgresql".to_string()),
+            ..database
+        };
+        assert!(!database.assembles_connection_uri());
+        assert!(database.connection_credentials().is_empty());
+    }
+
+    #[test]
+    fn test_zalando_connection() {
+        let database: OdooDatabaseConfig = serde_yaml::from_str(
+            "
+            secretFormat: zalando
+            connectionSecret: odoo.acid-odoo.credentials.postgresql.acid.zalan.do
+            host: acid-odoo
+            databaseName: odoo
+            ",
+        )
+        .unwrap();
+
+        assert!(database.assembles_connection_uri());
+        assert_eq!(
+            "odoo.acid-odoo.credentials.postgresql.acid.zalan.do",
+            database.connection_secret("odoo-credentials")
+        );
+        assert_eq!(
+            vec![
+                ("PGHOST".to_string(), Some("acid-odoo".to_string())),
+                ("PGPORT".to_string(), Some("5432".to_string())),
+                ("PGDATABASE".to_string(), Some("odoo".to_string())),
+            ],
+            database
+                .connection_env_vars()
+                .into_iter()
+                .map(|env| (env.name, env.value))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_validate_zalando_connection() {
+        let mut database: OdooDatabaseConfig = serde_yaml::from_str(
+            "
+            secretFormat: zalando
+            host: acid-odoo
+            databaseName: odoo
+            ",
+        )
+        .unwrap();
+        assert!(database.validate().is_ok());
+
+        database.database_name = None;
+        assert!(matches!(
+            database.validate(),
+            Err(Error::DatabaseConnectionFieldMissing { field, .. }) if field == "databaseName"
+        ));
+
+        database.host = None;
+        assert!(matches!(
+            database.validate(),
+            Err(Error::DatabaseConnectionFieldMissing { field, .. }) if field == "host"
+        ));
+
+        // The fields are only needed by the zalando format
+        database.secret_format = DatabaseSecretFormat::Crunchy;
+        assert!(database.validate().is_ok());
+    }
+
+    #[test]
+    fn test_schema() {
+        let database: OdooDatabaseConfig = serde_yaml::from_str("schema: tenant_a").unwrap();
+
+        assert_eq!(
+            vec![(
+                "PGOPTIONS".to_string(),
+                Some("-c search_path=\"tenant_a\"".to_string())
+            )],
+            database
+                .connection_env_vars()
+                .into_iter()
+                .map(|env| (env.name, env.value))
+                .collect::<Vec<_>>()
+        );
+        assert!(database.validate().is_ok());
+
+        for schema in [
+            "Tenant_A",
+            "1tenant",
+            "tenant-a",
+            "tenant\"; DROP SCHEMA public; --",
+            "",
+        ] {
+            let database = OdooDatabaseConfig {
+                schema: Some(schema.to_string()),
+                ..database.clone()
+            };
+            assert!(matches!(
+                database.validate(),
+                Err(Error::InvalidDatabaseSchema { .. })
+            ));
+        }
+    }
+}
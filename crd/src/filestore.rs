@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::{
+    commons::resources::{
+        CpuLimitsFragment, MemoryLimitsFragment, NoRuntimeLimits, NoRuntimeLimitsFragment,
+        NoStorage, NoStorageFragment, Resources, ResourcesFragment,
+    },
+    config::{
+        fragment::{self, ValidationError},
+        merge::Merge,
+    },
+    k8s_openapi::{
+        api::core::v1::{PersistentVolumeClaimVolumeSource, Volume},
+        apimachinery::pkg::api::resource::Quantity,
+    },
+    schemars::{self, JsonSchema},
+};
+
+pub const FILESTORE_VOLUME_NAME: &str = "filestore";
+/// The `data_dir` of Odoo, containing the attachments, the sessions and the downloaded addons.
+pub const FILESTORE_DIR: &str = "/stackable/filestore";
+pub const FILESTORE_SOURCE_VOLUME_NAME: &str = "filestore-source";
+pub const FILESTORE_SOURCE_DIR: &str = "/stackable/filestore-source";
+
+/// Keeps the data directory of Odoo on a PersistentVolumeClaim, so attachments survive restarts
+/// of the pods.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilestoreConfig {
+    /// Name of an existing PersistentVolumeClaim in the same namespace. It is mounted by all
+    /// pods, so it must support `ReadWriteMany` if they are scheduled on different nodes.
+    pub claim_name: String,
+    /// Prepare the claim with a job before the cluster is rolled out. The job runs again when
+    /// the claim or the source changes, but a source is only copied once into a claim, so
+    /// existing filestores are left untouched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initialization: Option<FilestoreInitialization>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilestoreInitialization {
+    /// Copy an existing filestore, e.g. of a migrated installation, before the directory layout
+    /// is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<FilestoreSource>,
+    /// The resources of the initialization job. Defaults to 100m to 400m CPU and 128Mi memory.
+    #[serde(default)]
+    pub resources: ResourcesFragment<NoStorage, NoRuntimeLimits>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilestoreSource {
+    /// Name of the PersistentVolumeClaim containing the filestore to copy. It is mounted
+    /// read-only.
+    pub claim_name: String,
+    /// The data directory within the claim, i.e. the directory containing `filestore` and
+    /// `sessions`. Defaults to the root of the claim.
+    #[serde(default)]
+    pub path: String,
+}
+
+impl FilestoreConfig {
+    pub fn volume(&self) -> Volume {
+        claim_volume(FILESTORE_VOLUME_NAME, &self.claim_name, false)
+    }
+
+    /// The filestore volume for pods which only read it, like the backup.
+    pub fn read_only_volume(&self) -> Volume {
+        claim_volume(FILESTORE_VOLUME_NAME, &self.claim_name, true)
+    }
+}
+
+impl FilestoreInitialization {
+    /// The configured resources of the job, completed by the defaults.
+    pub fn resources(&self) -> Result<Resources<NoStorage, NoRuntimeLimits>, ValidationError> {
+        let mut resources = self.resources.clone();
+        resources.merge(&default_resources());
+        fragment::validate(resources)
+    }
+
+    /// Identifies the copied source, so that a changed source is copied again. It is empty if
+    /// nothing is copied.
+    pub fn source_id(&self) -> String {
+        self.source
+            .as_ref()
+            .map(|source| format!("{}/{}", source.claim_name, source.path.trim_matches('/')))
+            .unwrap_or_default()
+    }
+}
+
+fn default_resources() -> ResourcesFragment<NoStorage, NoRuntimeLimits> {
+    ResourcesFragment {
+        cpu: CpuLimitsFragment {
+            min: Some(Quantity("100m".to_owned())),
+            max: Some(Quantity("400m".to_owned())),
+        },
+        memory: MemoryLimitsFragment {
+            limit: Some(Quantity("128Mi".to_owned())),
+            runtime_limits: NoRuntimeLimitsFragment {},
+        },
+        storage: NoStorageFragment {},
+    }
+}
+
+impl FilestoreSource {
+    pub fn volume(&self) -> Volume {
+        claim_volume(FILESTORE_SOURCE_VOLUME_NAME, &self.claim_name, true)
+    }
+
+    /// The directory the filestore is copied from.
+    pub fn dir(&self) -> String {
+        let path = self.path.trim_matches('/');
+        if path.is_empty() {
+            FILESTORE_SOURCE_DIR.to_string()
+        } else {
+            format!("{FILESTORE_SOURCE_DIR}/{path}")
+        }
+    }
+}
+
+fn claim_volume(name: &str, claim_name: &str, read_only: bool) -> Volume {
+    Volume {
+        name: name.to_string(),
+        persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
+            claim_name: claim_name.to_string(),
+            read_only: Some(read_only),
+        }),
+        ..Volume::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_dir() {
+        let config: FilestoreConfig = serde_yaml::from_str(
+            "
+            claimName: odoo-filestore
+            initialization:
+              source:
+                claimName: legacy-odoo
+                path: /var/lib/odoo/
+            ",
+        )
+        .unwrap();
+
+        let source = config.initialization.unwrap().source.unwrap();
+        assert_eq!("/stackable/filestore-source/var/lib/odoo", source.dir());
+        assert_eq!(
+            Some(true),
+            source
+                .volume()
+                .persistent_volume_claim
+                .and_then(|claim| claim.read_only)
+        );
+    }
+
+    #[test]
+    fn test_initialization_resources() {
+        let initialization: FilestoreInitialization = serde_yaml::from_str(
+            "
+            resources:
+              memory:
+                limit: 1Gi
+            ",
+        )
+        .unwrap();
+
+        let resources = initialization.resources().unwrap();
+        assert_eq!(Some(Quantity("1Gi".to_owned())), resources.memory.limit);
+        assert_eq!(Some(Quantity("400m".to_owned())), resources.cpu.max);
+        assert_eq!(
+            Some(Quantity("128Mi".to_owned())),
+            FilestoreInitialization::default()
+                .resources()
+                .unwrap()
+                .memory
+                .limit
+        );
+    }
+
+    #[test]
+    fn test_source_id() {
+        let mut initialization: FilestoreInitialization = serde_yaml::from_str(
+            "
+            source:
+              claimName: legacy-odoo
+              path: /odoo/
+            ",
+        )
+        .unwrap();
+        assert_eq!("legacy-odoo/odoo", initialization.source_id());
+
+        initialization.source = None;
+        assert_eq!("", initialization.source_id());
+    }
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, Snafu};
+use stackable_operator::schemars::{self, JsonSchema};
+use strum::{Display, EnumString};
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display(
+        "image {image} uses the mutable tag {tag:?}, which the image policy forbids"
+    ))]
+    MutableTag { image: String, tag: String },
+    #[snafu(display("image {image} is not pinned by digest, which the image policy requires"))]
+    MissingDigest { image: String },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Tags which do not identify a single image and must not be used under a policy.
+const MUTABLE_TAGS: &[&str] = &["latest", "nightly"];
+
+/// Restricts which product images the operator deploys, to satisfy supply-chain policies.
+/// The policies are ordered by their strictness.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Display,
+    EnumString,
+    Eq,
+    JsonSchema,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "kebab-case")]
+pub enum ImagePolicy {
+    /// Every image is allowed.
+    #[default]
+    Permissive,
+    /// Images with a missing or mutable tag like `latest` are rejected.
+    RejectMutableTags,
+    /// Images must be pinned by digest, e.g. `odoo@sha256:...`.
+    RequireDigests,
+}
+
+impl ImagePolicy {
+    /// The policy which applies to a cluster: a cluster can tighten the policy of the operator,
+    /// but not relax it.
+    pub fn effective(self, cluster_policy: Option<ImagePolicy>) -> ImagePolicy {
+        self.max(cluster_policy.unwrap_or_default())
+    }
+
+    /// Checks the fully resolved image reference against the policy.
+    pub fn validate(&self, image: &str) -> Result<()> {
+        let (name, digest) = match image.split_once('@') {
+            Some((name, digest)) => (name, Some(digest)),
+            None => (image, None),
+        };
+
+        match self {
+            ImagePolicy::Permissive => Ok(()),
+            ImagePolicy::RejectMutableTags => {
+                // A digest pins the image regardless of the tag
+                if digest.is_some() {
+                    return Ok(());
+                }
+                let tag = image_tag(name).unwrap_or("latest");
+                ensure!(!MUTABLE_TAGS.contains(&tag), MutableTagSnafu { image, tag });
+                Ok(())
+            }
+            ImagePolicy::RequireDigests => {
+                ensure!(digest.is_some(), MissingDigestSnafu { image });
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the tag of an image reference without digest. The registry part may contain a port,
+/// so only a colon after the last slash starts the tag.
+fn image_tag(image: &str) -> Option<&str> {
+    let last_segment = image.rsplit('/').next().unwrap_or(image);
+    last_segment.split_once(':').map(|(_, tag)| tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        for (policy, image, valid) in [
+            (ImagePolicy::Permissive, "odoo:latest", true),
+            (ImagePolicy::RejectMutableTags, "odoo:latest", false),
+            (ImagePolicy::RejectMutableTags, "registry:5000/odoo", false),
+            (
+                ImagePolicy::RejectMutableTags,
+                "registry:5000/odoo:16.0",
+                true,
+            ),
+            (
+                ImagePolicy::RejectMutableTags,
+                "odoo:latest@sha256:0123",
+                true,
+            ),
+            (ImagePolicy::RequireDigests, "odoo:16.0", false),
+            (ImagePolicy::RequireDigests, "odoo@sha256:0123", true),
+        ] {
+            assert_eq!(valid, policy.validate(image).is_ok(), "{policy} {image}");
+        }
+    }
+
+    #[test]
+    fn test_effective() {
+        assert_eq!(
+            ImagePolicy::RequireDigests,
+            ImagePolicy::Permissive.effective(Some(ImagePolicy::RequireDigests))
+        );
+        assert_eq!(
+            ImagePolicy::RejectMutableTags,
+            ImagePolicy::RejectMutableTags.effective(Some(ImagePolicy::Permissive))
+        );
+        assert_eq!(
+            ImagePolicy::RejectMutableTags,
+            ImagePolicy::RejectMutableTags.effective(None)
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ImagePolicy::RejectMutableTags,
+            "reject-mutable-tags".parse().unwrap()
+        );
+        assert_eq!(
+            ImagePolicy::RequireDigests,
+            serde_yaml::from_str("requireDigests").unwrap()
+        );
+        assert!("latest".parse::<ImagePolicy>().is_err());
+    }
+}
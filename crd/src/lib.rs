@@ -1,12 +1,35 @@
+pub mod addons;
 pub mod affinity;
+pub mod backup;
+pub mod blue_green;
+pub mod cnpg;
+pub mod credentials;
+pub mod database;
+pub mod filestore;
+pub mod image_policy;
+pub mod load_balancer;
+pub mod oauth;
+pub mod odoodatabase;
 pub mod odoodb;
+pub mod odoouser;
+pub mod web;
 
+use crate::addons::{is_sha256, pip_addons_dir, AddonsBucket, AddonsOci, AddonsSource};
 use crate::affinity::get_affinity;
+use crate::backup::UpgradeBackupConfig;
+use crate::credentials::{AdminUserConfig, CredentialsProvider};
+use crate::database::{ConnectionPooling, DatabaseProvisioning, OdooDatabaseConfig};
+use crate::filestore::FilestoreConfig;
+use crate::image_policy::ImagePolicy;
+use crate::load_balancer::LoadBalancerConfig;
+use crate::oauth::OdooOAuthConfig;
+use crate::web::{shell_quote, OdooWebConfig};
 use serde::{Deserialize, Serialize};
-use snafu::{OptionExt, ResultExt, Snafu};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
 use stackable_operator::commons::affinity::StackableAffinity;
+use stackable_operator::commons::opa::OpaConfig;
 use stackable_operator::commons::product_image_selection::ProductImage;
-use stackable_operator::kube::ResourceExt;
+use stackable_operator::kube::{runtime::reflector::ObjectRef, ResourceExt};
 use stackable_operator::memory::{BinaryMultiple, MemoryQuantity};
 use stackable_operator::role_utils::RoleGroup;
 use stackable_operator::{
@@ -15,10 +38,18 @@ use stackable_operator::{
         CpuLimitsFragment, MemoryLimitsFragment, NoRuntimeLimits, NoRuntimeLimitsFragment,
         Resources, ResourcesFragment,
     },
-    config::{fragment, fragment::Fragment, fragment::ValidationError, merge::Merge},
+    config::{
+        fragment,
+        fragment::Fragment,
+        fragment::ValidationError,
+        merge::{Atomic, Merge},
+    },
     k8s_openapi::{
-        api::core::v1::{Volume, VolumeMount},
-        apimachinery::pkg::api::resource::Quantity,
+        api::core::v1::{
+            ConfigMapVolumeSource, EmptyDirVolumeSource, LocalObjectReference,
+            PersistentVolumeClaimVolumeSource, SecretVolumeSource, Volume, VolumeMount,
+        },
+        apimachinery::pkg::{api::resource::Quantity, apis::meta::v1::Time},
     },
     kube::CustomResource,
     labels::ObjectLabels,
@@ -27,7 +58,7 @@ use stackable_operator::{
     product_logging::{self, spec::Logging},
     role_utils::{Role, RoleGroupRef},
     schemars::{self, JsonSchema},
-    status::condition::{ClusterCondition, HasStatusCondition},
+    status::condition::{ClusterCondition, ClusterConditionStatus, HasStatusCondition},
 };
 
 use std::collections::BTreeMap;
@@ -42,14 +73,57 @@ pub const STACKABLE_LOG_DIR: &str = "/stackable/log";
 pub const LOG_CONFIG_DIR: &str = "/stackable/app/log_config";
 pub const AIRFLOW_HOME: &str = "/stackable/odoo";
 pub const AIRFLOW_CONFIG_FILENAME: &str = "webserver_config.py";
+/// The server options of Odoo, read from `ODOO_RC`.
+pub const ODOO_CONFIG_FILENAME: &str = "odoo.conf";
 pub const GIT_SYNC_DIR: &str = "/stackable/app/git";
 pub const GIT_CONTENT: &str = "content-from-git";
 pub const GIT_ROOT: &str = "/tmp/git";
 pub const GIT_LINK: &str = "current";
 pub const GIT_SYNC_NAME: &str = "gitsync";
+/// Link to the copy of the last revision with a verified signature, relative to `GIT_ROOT`.
+pub const GIT_VERIFIED_LINK: &str = "verified";
+pub const GIT_SYNC_HOOKS_DIR: &str = "/stackable/gitsync-hooks";
+pub const GIT_SYNC_TRUSTED_KEYS_DIR: &str = "/stackable/gitsync-trusted-keys";
+/// Serves the health and the Prometheus metrics of git-sync in the mode continuous.
+pub const GIT_SYNC_HTTP_PORT_NAME: &str = "gitsync-metrics";
+pub const GIT_SYNC_HTTP_PORT: i32 = 9082;
+/// Runs after every sync, written by the git-sync container.
+pub const GIT_SYNC_HOOK_SCRIPT: &str = "/stackable/gitsync-hooks/on-sync.sh";
+/// The revision synced last, written by the hook.
+pub const GIT_SYNC_REVISION_FILE: &str = "/stackable/gitsync-hooks/synced-revision";
+/// Serves the revision synced last in the mode continuous.
+pub const GIT_SYNC_REVISION_PORT_NAME: &str = "gitsync-revision";
+pub const GIT_SYNC_REVISION_PORT: i32 = 9083;
+/// The revision a pod has synced, reported by the hook of its git-sync container and annotated
+/// by the operator.
+pub const SYNCED_REVISION_ANNOTATION: &str = "odoo.sovrin.cloud/synced-revision";
+/// The revision the pods of a StatefulSet were last restarted for, see `restartOnChange`.
+pub const RESTARTED_FOR_REVISION_ANNOTATION: &str = "odoo.sovrin.cloud/restarted-for-revision";
+/// Changing this annotation of an OdooCluster, e.g. to the current time, rolls all of its pods.
+/// Suffixed with the role, e.g. `odoo.sovrin.cloud/restart-webserver`, only the pods of the role
+/// are rolled.
+pub const RESTART_ANNOTATION: &str = "odoo.sovrin.cloud/restart";
+/// Name of the known_hosts file, in the ConfigMaps as well as in the Secret of the SSH key.
+const GIT_SYNC_KNOWN_HOSTS_FILE: &str = "known_hosts";
+const GIT_SYNC_SPARSE_CHECKOUT_FILE: &str = "sparse-checkout";
+pub const ADDONS_PACKAGES_VOLUME_NAME: &str = "addons-packages";
+/// The Python packages required by the addons, see `installRequirements`.
+pub const ADDONS_PACKAGES_DIR: &str = "/stackable/addons-packages";
+pub const SAML_METADATA_DIR: &str = "/stackable/saml";
+pub const SAML_METADATA_FILE: &str = "metadata.xml";
+pub const KERBEROS_DIR: &str = "/stackable/kerberos";
 
 const GIT_SYNC_DEPTH: u8 = 1u8;
 const GIT_SYNC_WAIT: u16 = 20u16;
+const GIT_SYNC_SSH_VOLUME_NAME: &str = "gitsync-ssh";
+const GIT_SYNC_SSH_DIR: &str = "/stackable/gitsync-ssh";
+const GIT_SYNC_SSH_KEY_FILE: &str = "ssh";
+const GIT_SYNC_KNOWN_HOSTS_VOLUME_NAME: &str = "gitsync-known-hosts";
+const GIT_SYNC_KNOWN_HOSTS_DIR: &str = "/stackable/gitsync-known-hosts";
+const GIT_SYNC_HOOKS_VOLUME_NAME: &str = "gitsync-hooks";
+const GIT_SYNC_TRUSTED_KEYS_VOLUME_NAME: &str = "gitsync-trusted-keys";
+const ODOO_DEFAULT_DB_MAXCONN: u16 = 64;
+const ODOO_DEFAULT_MAX_CRON_THREADS: u16 = 2;
 
 pub const MAX_LOG_FILES_SIZE: MemoryQuantity = MemoryQuantity {
     value: 10.0,
@@ -62,6 +136,36 @@ pub enum Error {
     UnknownOdooRole { role: String, roles: Vec<String> },
     #[snafu(display("fragment validation failure"))]
     FragmentValidationFailure { source: ValidationError },
+    #[snafu(display("the module upgrade policy list requires upgradeModules"))]
+    UpgradeModulesMissing,
+    #[snafu(display("the addons source {addons_source} in addonsPathOrder is not configured"))]
+    AddonsSourceNotConfigured { addons_source: AddonsSource },
+    #[snafu(display("the addons source {addons_source} is listed twice in addonsPathOrder"))]
+    AddonsSourceDuplicated { addons_source: AddonsSource },
+    #[snafu(display("the sha256 {sha256:?} of {addons_source} is not 64 hex characters"))]
+    InvalidAddonsChecksum {
+        addons_source: AddonsSource,
+        sha256: String,
+    },
+    #[snafu(display("git-sync cannot pin a revision and follow a branch at the same time"))]
+    GitSyncBranchAndRevision,
+    #[snafu(display("git-sync in the mode once requires a revision"))]
+    GitSyncRevisionMissing,
+    #[snafu(display("either credentialsSecret or credentialsProvider must be specified"))]
+    CredentialsMissing,
+    #[snafu(display("the kerberos API auth backend requires authenticationConfig.kerberos"))]
+    KerberosConfigMissing,
+    #[snafu(display("the secret format {secret_format} requires database.{field}"))]
+    DatabaseConnectionFieldMissing {
+        secret_format: database::DatabaseSecretFormat,
+        field: String,
+    },
+    #[snafu(display("the database schema {schema:?} must match ^[a-z_][a-z0-9_]*$"))]
+    InvalidDatabaseSchema { schema: String },
+    #[snafu(display("invalid logFileMaxSize"))]
+    InvalidLogFileMaxSize {
+        source: stackable_operator::error::Error,
+    },
 }
 
 #[derive(Display, EnumIter, EnumString)]
@@ -81,11 +185,25 @@ pub enum OdooConfigOptions {
     AuthLdapEmailField,
     AuthLdapGroupField,
     AuthRolesSyncAtLogin,
+    AuthRolesMapping,
     AuthLdapTlsDemand,
     AuthLdapTlsCertfile,
     AuthLdapTlsKeyfile,
     AuthLdapTlsCacertfile,
     AuthLdapAllowSelfSigned,
+    AuthLdapFallbackServers,
+    AuthDbFallback,
+    OauthProviders,
+    AuthOpaUrl,
+    AuthOpaCacheTtlSeconds,
+    AuthOpaCacheMaxEntries,
+    SecurityManagerClass,
+    AuthSamlIdpMetadataUrl,
+    AuthSamlIdpMetadataFile,
+    AuthSamlSpEntityId,
+    AuthSamlAttributeMapping,
+    AuthRemoteUserEnvVar,
+    EnableProxyFix,
 }
 
 impl FlaskAppConfigOptions for OdooConfigOptions {
@@ -95,6 +213,7 @@ impl FlaskAppConfigOptions for OdooConfigOptions {
             OdooConfigOptions::AuthUserRegistration => PythonType::BoolLiteral,
             OdooConfigOptions::AuthUserRegistrationRole => PythonType::StringLiteral,
             OdooConfigOptions::AuthRolesSyncAtLogin => PythonType::BoolLiteral,
+            OdooConfigOptions::AuthRolesMapping => PythonType::Expression,
             OdooConfigOptions::AuthLdapServer => PythonType::StringLiteral,
             OdooConfigOptions::AuthLdapBindUser => PythonType::Expression,
             OdooConfigOptions::AuthLdapBindPassword => PythonType::Expression,
@@ -110,6 +229,19 @@ impl FlaskAppConfigOptions for OdooConfigOptions {
             OdooConfigOptions::AuthLdapTlsKeyfile => PythonType::StringLiteral,
             OdooConfigOptions::AuthLdapTlsCacertfile => PythonType::StringLiteral,
             OdooConfigOptions::AuthLdapAllowSelfSigned => PythonType::BoolLiteral,
+            OdooConfigOptions::AuthLdapFallbackServers => PythonType::Expression,
+            OdooConfigOptions::AuthDbFallback => PythonType::BoolLiteral,
+            OdooConfigOptions::OauthProviders => PythonType::Expression,
+            OdooConfigOptions::AuthOpaUrl => PythonType::StringLiteral,
+            OdooConfigOptions::AuthOpaCacheTtlSeconds => PythonType::Expression,
+            OdooConfigOptions::AuthOpaCacheMaxEntries => PythonType::Expression,
+            OdooConfigOptions::SecurityManagerClass => PythonType::Expression,
+            OdooConfigOptions::AuthSamlIdpMetadataUrl => PythonType::StringLiteral,
+            OdooConfigOptions::AuthSamlIdpMetadataFile => PythonType::StringLiteral,
+            OdooConfigOptions::AuthSamlSpEntityId => PythonType::StringLiteral,
+            OdooConfigOptions::AuthSamlAttributeMapping => PythonType::Expression,
+            OdooConfigOptions::AuthRemoteUserEnvVar => PythonType::StringLiteral,
+            OdooConfigOptions::EnableProxyFix => PythonType::BoolLiteral,
         }
     }
 }
@@ -150,13 +282,95 @@ pub struct OdooClusterSpec {
 #[derive(Clone, Deserialize, Debug, Default, JsonSchema, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OdooClusterConfig {
+    /// Write audit events of the `auditlog` module to a separate log file, which is shipped
+    /// by Vector independently of the application logs. The module is installed when the
+    /// database is initialized.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_logging: Option<OdooAuditLoggingConfig>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub authentication_config: Option<OdooClusterAuthenticationConfig>,
+    /// Delegate authorization decisions to an Open Policy Agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<OdooAuthorization>,
+    /// The authentication backend of the REST API, which is handled separately from the
+    /// web authentication. Defaults to `denyAll`.
+    #[serde(default)]
+    pub api_auth_backend: ApiAuthBackend,
+    /// Let the operator manage the webserver secret key instead of reading it from the
+    /// credentials. Bumping the generation rotates the key and rolls all pods, which
+    /// invalidates all existing sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_key_generation: Option<u32>,
+    /// Keep the data directory of Odoo on a PersistentVolumeClaim.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filestore: Option<FilestoreConfig>,
+    /// Back up the database before it is migrated to a new product version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade_backup: Option<UpgradeBackupConfig>,
+    /// Update the installed modules with `odoo -u` when the product image changes, before the
+    /// pods are rolled out with it, so the database schema matches the code.
+    #[serde(default)]
+    pub module_upgrade_policy: ModuleUpgradePolicy,
+    /// The modules updated with the policy `list`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upgrade_modules: Vec<String>,
+    /// Name of the Secret containing the admin user and connection credentials.
+    /// Not needed if a `credentialsProvider` is specified.
+    #[serde(default)]
     pub credentials_secret: String,
+    /// Read the credentials from files provided by an external secret store instead of the
+    /// `credentialsSecret`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_provider: Option<CredentialsProvider>,
+    /// The admin user created from the credentials when the database is initialized.
+    #[serde(default)]
+    pub admin_user: AdminUserConfig,
+    /// Connection settings for the PostgreSQL database.
     #[serde(default)]
-    pub dags_git_sync: Vec<GitSync>,
+    pub database: OdooDatabaseConfig,
+    /// Pool the database connections of the Odoo pods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_pooling: Option<ConnectionPooling>,
+    /// Create the database and its role if they do not exist yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_provisioning: Option<DatabaseProvisioning>,
+    /// Sync addons from a git repository into the pods. `gitFolder` is the directory of the
+    /// addons within the repository, it is added to the `addons_path` of Odoo.
+    #[serde(default, alias = "dagsGitSync")]
+    pub addons_git_sync: Vec<GitSync>,
+    /// Pull the addons from an OCI registry. They are added to the `addons_path` of Odoo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub addons_oci: Option<AddonsOci>,
+    /// Download addon archives from an S3 compatible bucket. They are added to the
+    /// `addons_path` of Odoo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub addons_bucket: Option<AddonsBucket>,
+    /// Install packaged addons from PyPI, e.g. `odoo-addon-queue-job==16.0.*`. They are added to
+    /// the `addons_path` of Odoo.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addons_pip: Vec<String>,
+    /// The order of the addons sources in the `addons_path` of Odoo, which determines the
+    /// module found first if several sources contain it. Every listed source must be
+    /// configured, sources which are not listed follow in the order `git`, `oci`, `bucket`,
+    /// `pip`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addons_path_order: Vec<AddonsSource>,
+    /// Run Odoo in developer mode, so changes of the addons are picked up without restarting the
+    /// pods. The probes are relaxed, as the server restarts on its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev_mode: Option<DevMode>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub database_initialization: Option<odoodb::OdooDbConfigFragment>,
+    /// What happens to the objects outliving the pods when the cluster is deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistence: Option<PersistenceConfig>,
+    /// How long the worker pods removed by a scale-down may finish their running jobs before
+    /// they are stopped. Defaults to 10 minutes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker_drain_timeout_seconds: Option<u64>,
+    /// Run the webservers in a blue and a green generation, to switch to a new version at once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blue_green: Option<blue_green::BlueGreenConfig>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub executor: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -176,6 +390,29 @@ pub struct OdooClusterConfig {
     /// * external-stable: Use a LoadBalancer service
     #[serde(default)]
     pub listener_class: CurrentlySupportedListenerClasses,
+    /// Additional CA certificates trusted by all containers, e.g. for self-managed LDAP,
+    /// SMTP or payment provider endpoints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<CaBundle>,
+    /// Restricts which product images this cluster may use. It can only tighten the image
+    /// policy of the operator: the stricter of both applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_policy: Option<ImagePolicy>,
+    /// Security compliance settings applied to all generated pods.
+    #[serde(default)]
+    pub compliance: OdooComplianceConfig,
+    /// Restrict the outgoing traffic of all pods with a NetworkPolicy.
+    #[serde(default)]
+    pub egress_policy: EgressPolicy,
+    /// The networks in which the hosts outside of the Kubernetes cluster are reached if the
+    /// egress policy is `strict`, e.g. `10.0.0.0/8` for a database within the VPC. Defaults to
+    /// all addresses.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub egress_cidrs: Vec<String>,
+    /// Provider specific presets (idle timeout) for the load balancer created for the
+    /// `external-stable` listener class.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_balancer: Option<LoadBalancerConfig>,
     /// Name of the Vector aggregator discovery ConfigMap.
     /// It must contain the key `ADDRESS` with the address of the Vector aggregator.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -184,6 +421,238 @@ pub struct OdooClusterConfig {
     pub volumes: Option<Vec<Volume>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub volume_mounts: Option<Vec<VolumeMount>>,
+    /// External URLs of the web interface and an optional Ingress exposing it.
+    #[serde(default)]
+    pub web: OdooWebConfig,
+}
+
+impl OdooClusterConfig {
+    /// Checks that the credentials are read from somewhere, because `credentialsSecret` may
+    /// only be omitted if a `credentialsProvider` is given.
+    pub fn validate_credentials(&self) -> Result<(), Error> {
+        ensure!(
+            !self.credentials_secret.is_empty() || self.credentials_provider.is_some(),
+            CredentialsMissingSnafu
+        );
+        Ok(())
+    }
+
+    /// Checks that the checksums of the addons are SHA-256 checksums, which the CRD enforces
+    /// only for objects created after the pattern was added.
+    pub fn validate_addons_checksums(&self) -> Result<(), Error> {
+        let checksums = [
+            (
+                AddonsSource::Oci,
+                self.addons_oci.as_ref().and_then(|oci| oci.sha256.as_ref()),
+            ),
+            (
+                AddonsSource::Bucket,
+                self.addons_bucket
+                    .as_ref()
+                    .and_then(|bucket| bucket.sha256.as_ref()),
+            ),
+        ];
+        for (addons_source, sha256) in checksums {
+            if let Some(sha256) = sha256 {
+                ensure!(
+                    is_sha256(sha256),
+                    InvalidAddonsChecksumSnafu {
+                        addons_source,
+                        sha256
+                    }
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the configuration required by the API auth backend is given.
+    pub fn validate_api_auth_backend(&self) -> Result<(), Error> {
+        if self.api_auth_backend == ApiAuthBackend::Kerberos {
+            ensure!(
+                matches!(
+                    &self.authentication_config,
+                    Some(OdooClusterAuthenticationConfig {
+                        kerberos: Some(_),
+                        ..
+                    })
+                ),
+                KerberosConfigMissingSnafu
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooAuditLoggingConfig {
+    /// The stream the audit events are tagged with, so the aggregator can route them to a
+    /// different index than the application logs.
+    #[serde(default = "default_audit_log_stream")]
+    pub stream: String,
+}
+
+fn default_audit_log_stream() -> String {
+    "odoo-audit".to_string()
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaBundle {
+    /// Name of the ConfigMap containing the PEM encoded certificates.
+    pub config_map_name: String,
+    /// The key of the bundle in the ConfigMap.
+    #[serde(default = "default_ca_bundle_key")]
+    pub key: String,
+}
+
+fn default_ca_bundle_key() -> String {
+    "ca.crt".to_string()
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooComplianceConfig {
+    /// The Pod Security Standard the generated pods must comply with.
+    #[serde(default)]
+    pub pod_security_standard: PodSecurityStandard,
+    /// Drop all capabilities and disallow privilege escalation in all generated containers.
+    /// Only disable this for images which really need additional privileges, it cannot be
+    /// disabled for the `restricted` Pod Security Standard.
+    #[serde(default = "default_harden_containers")]
+    pub harden_containers: bool,
+    /// Mount a ServiceAccount token into the generated pods. Odoo does not talk to the
+    /// Kubernetes API, so this defaults to false, unless the `KubernetesExecutor` is used,
+    /// which launches pods on its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub automount_service_account_token: Option<bool>,
+}
+
+impl Default for OdooComplianceConfig {
+    fn default() -> Self {
+        Self {
+            pod_security_standard: PodSecurityStandard::default(),
+            harden_containers: default_harden_containers(),
+            automount_service_account_token: None,
+        }
+    }
+}
+
+fn default_harden_containers() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EgressPolicy {
+    #[default]
+    Unrestricted,
+    /// Only allow DNS and the endpoints derived from the cluster config, e.g. the database, LDAP
+    /// servers, mail servers, the Vector aggregator, OPA and the git repositories. The traffic
+    /// is restricted to the namespaces of the Services and to the `egressCidrs`.
+    Strict,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModuleUpgradePolicy {
+    #[default]
+    None,
+    /// Update all installed modules.
+    All,
+    /// Update the modules in `upgradeModules`.
+    List,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistenceConfig {
+    /// Whether the OdooDB, its init job and the ConfigMaps generated for it are retained or
+    /// deleted together with the cluster. With `Delete` the OdooDB is owned by the cluster.
+    /// Takes precedence over `databaseInitialization.deletionPolicy`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deletion_policy: Option<odoodb::DeletionPolicy>,
+}
+
+/// Runs Odoo with `--dev`, which is not meant for production clusters.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevMode {
+    #[serde(default)]
+    pub enabled: bool,
+    /// The features of the developer mode.
+    #[serde(default = "default_dev_mode_options")]
+    pub options: Vec<DevModeOption>,
+}
+
+impl DevMode {
+    /// The argument appended to the Odoo command, if the developer mode is enabled.
+    pub fn dev_arg(&self) -> Option<String> {
+        if !self.enabled || self.options.is_empty() {
+            return None;
+        }
+        let options = self
+            .options
+            .iter()
+            .map(DevModeOption::to_string)
+            .collect::<Vec<_>>();
+        Some(format!("--dev={}", options.join(",")))
+    }
+}
+
+fn default_dev_mode_options() -> Vec<DevModeOption> {
+    vec![DevModeOption::Reload, DevModeOption::Qweb, DevModeOption::Xml]
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum DevModeOption {
+    /// Restart the server when Python files change, e.g. when git-sync synced a new revision,
+    /// so `restartOnChange` is not needed.
+    Reload,
+    /// Read the QWeb templates from the files instead of the database.
+    Qweb,
+    /// Read the views from the XML files instead of the database.
+    Xml,
+}
+
+/// See <https://kubernetes.io/docs/concepts/security/pod-security-standards/>
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PodSecurityStandard {
+    #[default]
+    Baseline,
+    /// Run all containers non-root with the `RuntimeDefault` seccomp profile, without
+    /// privilege escalation and with all capabilities dropped.
+    Restricted,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ApiAuthBackend {
+    /// Reject all requests to the API.
+    #[default]
+    DenyAll,
+    /// Authenticate API requests with the username and password of an Odoo user.
+    Basic,
+    /// Authenticate API requests with the session cookie of the web interface.
+    Session,
+    /// Authenticate API requests with SPNEGO, requires `authenticationConfig.kerberos`.
+    Kerberos,
+}
+
+impl ApiAuthBackend {
+    /// The module implementing the backend.
+    pub fn module(&self) -> &'static str {
+        match self {
+            ApiAuthBackend::DenyAll => "odoo.api.auth.backend.deny_all",
+            ApiAuthBackend::Basic => "odoo.api.auth.backend.basic_auth",
+            ApiAuthBackend::Session => "odoo.api.auth.backend.session",
+            ApiAuthBackend::Kerberos => "odoo.api.auth.backend.kerberos_auth",
+        }
+    }
 }
 
 // TODO: Temporary solution until listener-operator is finished
@@ -218,34 +687,327 @@ pub struct GitSync {
     pub depth: Option<u8>,
     pub wait: Option<u16>,
     pub credentials_secret: Option<String>,
+    /// The keys read from `credentialsSecret` and how they are sent to the git server.
+    #[serde(default)]
+    pub credentials_format: GitCredentialsFormat,
+    /// The revision to deploy, a tag or a commit hash, instead of the head of a branch. Cannot
+    /// be combined with `branch` and is required in the mode `once`.
+    #[serde(default, alias = "rev", skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// The major version of git-sync in the image, which determines the names of the arguments
+    /// and environment variables.
+    #[serde(default)]
+    pub version: GitSyncVersion,
+    /// Only deploy revisions whose commit is signed by a trusted key. Requires the product
+    /// image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<GitSyncVerification>,
+    /// Run git-sync from a dedicated image instead of the product image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<GitSyncImage>,
+    #[serde(default)]
+    pub mode: GitSyncMode,
+    /// How the submodules of the repository are checked out. Defaults to `recursive`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submodules: Option<GitSyncSubmodules>,
+    /// Only check out these paths of the repository, e.g. `/addons/`. The paths are patterns
+    /// of a git sparse-checkout file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sparse_checkout_paths: Vec<String>,
+    /// Leave these paths out of the synced content, e.g. `/tests/` or `doc/`, so they do not end
+    /// up in the `addons_path`. The paths are glob patterns like in a `.gitignore` file, which
+    /// are excluded in the git sparse-checkout file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_paths: Vec<String>,
+    /// Sync into a shared ReadWriteMany PersistentVolumeClaim by a single Deployment
+    /// `<cluster name>-gitsync`, instead of a git-sync container in every pod. The pods mount the
+    /// claim read-only. Only used in the mode `continuous`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_volume: Option<GitSyncSharedVolume>,
+    /// Trigger a sync with a POST request to the port `gitsync-webhook` of the Service
+    /// `<cluster name>-gitsync`, e.g. from CI, instead of waiting for the next poll. Requires
+    /// `sharedVolume`, so a single pod syncs for the whole cluster, and `webhookTokenSecret`.
+    /// Only used in the mode `continuous` and requires a git-sync version supporting
+    /// `--sync-on-signal`.
+    #[serde(default)]
+    pub webhook: bool,
+    /// Secret with the key `token`, which requests to the webhook have to send in the header
+    /// `X-Webhook-Token`. Other requests are rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_token_secret: Option<String>,
+    /// Restart the pods of a rolegroup when all of them have synced a new revision, as Odoo
+    /// does not reload Python code. Only used in the mode `continuous` and if the product image
+    /// runs git-sync.
+    #[serde(default)]
+    pub restart_on_change: bool,
+    /// Install the `requirements.txt` files of the addons and of the addons folder with pip
+    /// before Odoo starts. The packages are only installed when a pod starts, so new
+    /// requirements of a synced revision need `restartOnChange`.
+    #[serde(default)]
+    pub install_requirements: bool,
+    /// The roles whose pods sync the addons, e.g. `[webserver, worker]`. Defaults to all roles.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<OdooRole>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<GitSyncSsh>,
     pub git_sync_conf: Option<BTreeMap<String, String>>,
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitCredentialsFormat {
+    /// HTTP basic authentication with the keys `user` and `password`.
+    #[default]
+    Basic,
+    /// A GitLab deploy token with its username in the key `user` and the token in the key
+    /// `token`.
+    GitlabDeployToken,
+    /// A Gitea access token in the key `token`, sent as `Authorization: token` header.
+    GiteaToken,
+    /// A token in the key `token`, sent as `Authorization: Bearer` header, e.g. a GitLab OAuth
+    /// token.
+    BearerToken,
+}
+
+impl GitCredentialsFormat {
+    /// The suffix of the environment variable and the key of the credentials Secret of every
+    /// credential.
+    pub fn credentials(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            GitCredentialsFormat::Basic => &[("USERNAME", "user"), ("PASSWORD", "password")],
+            GitCredentialsFormat::GitlabDeployToken => {
+                &[("USERNAME", "user"), ("PASSWORD", "token")]
+            }
+            GitCredentialsFormat::GiteaToken | GitCredentialsFormat::BearerToken => {
+                &[("TOKEN", "token")]
+            }
+        }
+    }
+
+    /// The HTTP header authenticating with the token. The reference to the environment variable
+    /// is substituted by Kubernetes, so the token does not appear in the pod spec. It must only
+    /// be used in the environment, as git-sync logs its arguments.
+    fn auth_header(&self, env_var_prefix: &str) -> Option<String> {
+        let scheme = match self {
+            GitCredentialsFormat::GiteaToken => "token",
+            GitCredentialsFormat::BearerToken => "Bearer",
+            _ => return None,
+        };
+        Some(format!("Authorization: {scheme} $({env_var_prefix}_TOKEN)"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitSyncMode {
+    /// Sync the repository continuously in a sidecar, so changes are picked up without a
+    /// restart of the pods.
+    #[default]
+    Continuous,
+    /// Clone the repository at `revision` once in an init container. The pods are only changed by
+    /// changing the OdooCluster, e.g. in GitOps flows.
+    Once,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSyncSharedVolume {
+    /// Name of an existing PersistentVolumeClaim with the access mode `ReadWriteMany`.
+    pub claim_name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSyncVerification {
+    /// Secret containing the ASCII-armored public GPG keys of the trusted signers. Every key of
+    /// the Secret is imported.
+    pub trusted_keys_secret: String,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSyncImage {
+    /// The image starting git-sync as its entrypoint, e.g.
+    /// `registry.k8s.io/git-sync/git-sync:v4.2.1`. `version` must match the image.
+    pub image: String,
+    #[serde(default = "default_git_sync_pull_policy")]
+    pub pull_policy: String,
+    /// Secrets to pull the image, added to the pods of the cluster.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_secrets: Option<Vec<LocalObjectReference>>,
+}
+
+fn default_git_sync_pull_policy() -> String {
+    "IfNotPresent".to_string()
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitSyncVersion {
+    #[default]
+    V3,
+    V4,
+}
+
+impl GitSyncVersion {
+    /// The prefix of the environment variables read by git-sync, e.g. of `GITSYNC_PASSWORD`.
+    pub fn env_var_prefix(&self) -> &'static str {
+        match self {
+            GitSyncVersion::V3 => "GIT_SYNC",
+            GitSyncVersion::V4 => "GITSYNC",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "lowercase")]
+pub enum GitSyncSubmodules {
+    /// Check out the submodules and their submodules.
+    Recursive,
+    /// Only check out the submodules of the repository itself.
+    Shallow,
+    /// Do not check out submodules.
+    Off,
+}
+
+/// Authenticate with an SSH key instead of `credentialsSecret`. The repo must be given as SSH
+/// URL then, e.g. `git@github.com:org/addons.git`.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSyncSsh {
+    /// Secret containing the private key under the key `ssh`. If neither `knownHosts` nor
+    /// `knownHostsConfigMap` are given, the host keys are read from its key `known_hosts`.
+    pub key_secret: String,
+    /// Entries of the known_hosts file, e.g. the output of `ssh-keyscan github.com`. Takes
+    /// precedence over `knownHostsConfigMap`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub known_hosts: Option<String>,
+    /// ConfigMap containing the known_hosts file under the key `known_hosts`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub known_hosts_config_map: Option<String>,
+    /// Verify the host key of the git server. Only disable it for testing, as the sync is open
+    /// to man-in-the-middle attacks then.
+    #[serde(default = "default_verify_host_key")]
+    pub verify_host_key: bool,
+}
+
+fn default_verify_host_key() -> bool {
+    true
+}
+
 impl GitSync {
+    /// The command line of git-sync, for a shell. Every argument is quoted.
     pub fn get_args(&self) -> Vec<String> {
+        let mut args = vec!["/stackable/git-sync".to_string()];
+        args.extend(self.get_flags().iter().map(|arg| shell_quote(arg)));
+        args
+    }
+
+    /// The git configuration in the environment of git-sync, which git reads since version 2.31.
+    /// The authentication header is passed this way rather than with `--git-config`, so the
+    /// token is neither in the arguments nor in the log of git-sync.
+    pub fn git_config_env_vars(&self) -> Vec<(String, String)> {
+        let Some(auth_header) = self
+            .credentials_format
+            .auth_header(self.version.env_var_prefix())
+        else {
+            return vec![];
+        };
+        vec![
+            ("GIT_CONFIG_COUNT".to_string(), "1".to_string()),
+            (
+                "GIT_CONFIG_KEY_0".to_string(),
+                "http.extraHeader".to_string(),
+            ),
+            ("GIT_CONFIG_VALUE_0".to_string(), auth_header),
+        ]
+    }
+
+    /// The arguments of git-sync without the binary, for images starting it as entrypoint.
+    pub fn get_flags(&self) -> Vec<String> {
         let mut args: Vec<String> = vec![];
+        let branch = self.branch.clone().unwrap_or_else(|| "main".to_string());
+        let wait = self.wait.unwrap_or(GIT_SYNC_WAIT);
         args.extend(vec![
-            "/stackable/git-sync".to_string(),
             format!("--repo={}", self.repo.clone()),
-            format!(
-                "--branch={}",
-                self.branch.clone().unwrap_or_else(|| "main".to_string())
-            ),
             format!("--depth={}", self.depth.unwrap_or(GIT_SYNC_DEPTH)),
-            format!("--wait={}", self.wait.unwrap_or(GIT_SYNC_WAIT)),
-            format!("--dest={GIT_LINK}"),
             format!("--root={GIT_ROOT}"),
-            format!("--git-config=safe.directory:{GIT_ROOT}"),
         ]);
+        args.push(format!("--git-config=safe.directory:{GIT_ROOT}"));
+        match self.version {
+            GitSyncVersion::V3 => {
+                args.extend(vec![
+                    format!("--branch={branch}"),
+                    format!("--wait={wait}"),
+                    format!("--dest={GIT_LINK}"),
+                ]);
+                if let Some(revision) = self.revision() {
+                    args.push(format!("--rev={revision}"));
+                }
+            }
+            GitSyncVersion::V4 => args.extend(vec![
+                // A branch, a tag or a commit hash
+                format!("--ref={}", self.revision().unwrap_or(&branch)),
+                format!("--period={wait}s"),
+                format!("--link={GIT_LINK}"),
+            ]),
+        }
+        match self.mode {
+            GitSyncMode::Once => args.push("--one-time".to_string()),
+            GitSyncMode::Continuous => args.extend(vec![
+                format!("--http-bind=:{GIT_SYNC_HTTP_PORT}"),
+                "--http-metrics".to_string(),
+            ]),
+        }
+        if self.webhook_enabled() {
+            args.push("--sync-on-signal=SIGHUP".to_string());
+        }
+        // In the mode once, the hook is run after git-sync exited
+        if self.runs_hook() && self.mode == GitSyncMode::Continuous {
+            args.push(format!("--exechook-command={GIT_SYNC_HOOK_SCRIPT}"));
+        }
+        if let Some(submodules) = self.submodules {
+            args.push(format!("--submodules={submodules}"));
+        }
+        if !self.sparse_checkout_patterns().is_empty() {
+            args.push(format!(
+                "--sparse-checkout-file={CONFIG_PATH}/{GIT_SYNC_SPARSE_CHECKOUT_FILE}"
+            ));
+        }
+        if let Some(ssh) = &self.ssh {
+            // git-sync v4 detects SSH from the URL of the repo
+            if self.version == GitSyncVersion::V3 {
+                args.push("--ssh".to_string());
+            }
+            args.push(format!("--ssh-key-file={GIT_SYNC_SSH_DIR}/{GIT_SYNC_SSH_KEY_FILE}"));
+            match ssh.known_hosts_file() {
+                Some(known_hosts_file) => {
+                    args.push(format!("--ssh-known-hosts-file={known_hosts_file}"))
+                }
+                None => args.push("--ssh-known-hosts=false".to_string()),
+            }
+        }
         if let Some(git_sync_conf) = self.git_sync_conf.as_ref() {
             for (key, value) in git_sync_conf {
                 // config options that are internal details have
                 // constant values and will be ignored here
                 if key.eq_ignore_ascii_case("--dest")
+                    || key.eq_ignore_ascii_case("--link")
                     || key.eq_ignore_ascii_case("--root")
                     || key.eq_ignore_ascii_case("--git-config")
+                    || key.eq_ignore_ascii_case("--ssh-key-file")
+                    || key.eq_ignore_ascii_case("--ssh-known-hosts-file")
+                    || key.eq_ignore_ascii_case("--sparse-checkout-file")
+                    || key.eq_ignore_ascii_case("--exechook-command")
+                    || key.eq_ignore_ascii_case("--http-bind")
                 {
                     tracing::warn!("Config option {:?} will be ignored...", key);
+                } else if key.eq_ignore_ascii_case("--rev") {
+                    // Passed as revision, which is the argument --ref of git-sync v4
+                    if self.revision.is_some() {
+                        tracing::warn!("Config option {:?} will be ignored...", key);
+                    }
                 } else {
                     args.push(format!("{key}={value}"));
                 }
@@ -253,16 +1015,245 @@ impl GitSync {
         }
         args
     }
+
+    /// The pinned revision, also read from the deprecated `gitSyncConf` option `--rev`.
+    pub fn revision(&self) -> Option<&String> {
+        self.revision.as_ref().or_else(|| {
+            self.git_sync_conf
+                .as_ref()?
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("--rev"))
+                .map(|(_, value)| value)
+        })
+    }
+
+    /// Checks that the revision is not combined with a branch and set in the mode `once`, where
+    /// the pods would otherwise check out different revisions depending on when they start.
+    pub fn validate_revision(&self) -> Result<(), Error> {
+        // git-sync v3 checks the deprecated --rev out from the branch, so it may be combined
+        ensure!(
+            self.revision.is_none() || self.branch.is_none(),
+            GitSyncBranchAndRevisionSnafu
+        );
+        ensure!(
+            self.mode != GitSyncMode::Once || self.revision().is_some(),
+            GitSyncRevisionMissingSnafu
+        );
+        Ok(())
+    }
+
+    /// The hook verifies and reports the synced revisions. It is a script, so it only runs in the
+    /// product image.
+    pub fn runs_hook(&self) -> bool {
+        self.image.is_none()
+    }
+
+    /// The link to the synced revision which is deployed, relative to `GIT_ROOT`.
+    pub fn deployed_link(&self) -> &'static str {
+        if self.verification.is_some() {
+            GIT_VERIFIED_LINK
+        } else {
+            GIT_LINK
+        }
+    }
+
+    /// The directory of the deployed addons in the Odoo containers.
+    pub fn addons_dir(&self) -> String {
+        let mut dir = format!("{GIT_SYNC_DIR}/{}", self.deployed_link());
+        if let Some(git_folder) = &self.git_folder {
+            dir = format!("{dir}/{}", git_folder.trim_matches('/'));
+        }
+        dir
+    }
+
+    /// Whether the pods mount the revisions synced by the shared Deployment.
+    pub fn uses_shared_volume(&self) -> bool {
+        self.shared_volume.is_some() && self.mode == GitSyncMode::Continuous
+    }
+
+    /// The volume `GIT_CONTENT` the revisions are synced to, the shared claim or an emptyDir.
+    pub fn content_volume(&self, read_only: bool) -> Volume {
+        let mut volume = Volume {
+            name: GIT_CONTENT.to_string(),
+            ..Volume::default()
+        };
+        match &self.shared_volume {
+            Some(shared_volume) if self.uses_shared_volume() => {
+                volume.persistent_volume_claim = Some(PersistentVolumeClaimVolumeSource {
+                    claim_name: shared_volume.claim_name.clone(),
+                    read_only: Some(read_only),
+                });
+            }
+            _ => volume.empty_dir = Some(EmptyDirVolumeSource::default()),
+        }
+        volume
+    }
+
+    pub fn enabled_for(&self, role: &OdooRole) -> bool {
+        self.roles.is_empty() || self.roles.contains(role)
+    }
+
+    pub fn restarts_on_change(&self) -> bool {
+        self.restart_on_change && self.mode == GitSyncMode::Continuous && self.runs_hook()
+    }
+
+    pub fn webhook_enabled(&self) -> bool {
+        self.webhook && self.mode == GitSyncMode::Continuous
+    }
+
+    /// git-sync only serves its health and metrics while it keeps running.
+    pub fn http_enabled(&self) -> bool {
+        self.mode == GitSyncMode::Continuous
+    }
+
+    /// Files stored in the rolegroup ConfigMap, which is mounted to `CONFIG_PATH` in the git-sync
+    /// container.
+    pub fn config_files(&self) -> Vec<(&'static str, String)> {
+        let mut files = vec![];
+        if let Some(known_hosts) = self.ssh.as_ref().and_then(|ssh| ssh.known_hosts.as_ref()) {
+            files.push((GIT_SYNC_KNOWN_HOSTS_FILE, known_hosts.clone()));
+        }
+        let sparse_checkout_patterns = self.sparse_checkout_patterns();
+        if !sparse_checkout_patterns.is_empty() {
+            files.push((
+                GIT_SYNC_SPARSE_CHECKOUT_FILE,
+                sparse_checkout_patterns.join("\n") + "\n",
+            ));
+        }
+        files
+    }
+
+    /// The patterns of the sparse-checkout file. Excluded paths are negated patterns, which
+    /// follow the checked out paths, or all paths if none are given.
+    fn sparse_checkout_patterns(&self) -> Vec<String> {
+        if self.exclude_paths.is_empty() {
+            return self.sparse_checkout_paths.clone();
+        }
+        let mut patterns = if self.sparse_checkout_paths.is_empty() {
+            vec!["/*".to_string()]
+        } else {
+            self.sparse_checkout_paths.clone()
+        };
+        patterns.extend(self.exclude_paths.iter().map(|path| format!("!{path}")));
+        patterns
+    }
+
+    /// The volumes of the SSH key, of the known_hosts ConfigMap, of the hook and of the
+    /// signature verification, mounted by the git-sync container. Known hosts given inline are stored in
+    /// the rolegroup ConfigMap instead.
+    pub fn volumes(&self) -> Vec<Volume> {
+        self.git_sync_volumes()
+            .into_iter()
+            .map(|(volume, _)| volume)
+            .collect()
+    }
+
+    pub fn volume_mounts(&self) -> Vec<VolumeMount> {
+        self.git_sync_volumes()
+            .into_iter()
+            .map(|(volume, mount_path)| VolumeMount {
+                // Only the emptyDir of the hook is written to
+                read_only: Some(volume.empty_dir.is_none()),
+                name: volume.name,
+                mount_path: mount_path.to_string(),
+                ..VolumeMount::default()
+            })
+            .collect()
+    }
+
+    fn git_sync_volumes(&self) -> Vec<(Volume, &'static str)> {
+        let mut volumes = vec![];
+        if let Some(ssh) = &self.ssh {
+            let key_volume = Volume {
+                name: GIT_SYNC_SSH_VOLUME_NAME.to_string(),
+                secret: Some(SecretVolumeSource {
+                    secret_name: Some(ssh.key_secret.clone()),
+                    // ssh refuses private keys which are readable by others
+                    default_mode: Some(0o400),
+                    ..SecretVolumeSource::default()
+                }),
+                ..Volume::default()
+            };
+            volumes.push((key_volume, GIT_SYNC_SSH_DIR));
+            if let (None, Some(config_map)) = (&ssh.known_hosts, &ssh.known_hosts_config_map) {
+                let known_hosts_volume = Volume {
+                    name: GIT_SYNC_KNOWN_HOSTS_VOLUME_NAME.to_string(),
+                    config_map: Some(ConfigMapVolumeSource {
+                        name: Some(config_map.clone()),
+                        ..ConfigMapVolumeSource::default()
+                    }),
+                    ..Volume::default()
+                };
+                volumes.push((known_hosts_volume, GIT_SYNC_KNOWN_HOSTS_DIR));
+            }
+        }
+        if self.runs_hook() {
+            let hooks_volume = Volume {
+                name: GIT_SYNC_HOOKS_VOLUME_NAME.to_string(),
+                empty_dir: Some(EmptyDirVolumeSource::default()),
+                ..Volume::default()
+            };
+            volumes.push((hooks_volume, GIT_SYNC_HOOKS_DIR));
+        }
+        if let Some(verification) = &self.verification {
+            let trusted_keys_volume = Volume {
+                name: GIT_SYNC_TRUSTED_KEYS_VOLUME_NAME.to_string(),
+                secret: Some(SecretVolumeSource {
+                    secret_name: Some(verification.trusted_keys_secret.clone()),
+                    ..SecretVolumeSource::default()
+                }),
+                ..Volume::default()
+            };
+            volumes.push((trusted_keys_volume, GIT_SYNC_TRUSTED_KEYS_DIR));
+        }
+        volumes
+    }
+}
+
+impl GitSyncSsh {
+    /// Path of the known_hosts file in the git-sync container, or `None` if the host key is not
+    /// verified.
+    pub fn known_hosts_file(&self) -> Option<String> {
+        if !self.verify_host_key {
+            None
+        } else if self.known_hosts.is_some() {
+            Some(format!("{CONFIG_PATH}/{GIT_SYNC_KNOWN_HOSTS_FILE}"))
+        } else if self.known_hosts_config_map.is_some() {
+            Some(format!("{GIT_SYNC_KNOWN_HOSTS_DIR}/{GIT_SYNC_KNOWN_HOSTS_FILE}"))
+        } else {
+            Some(format!("{GIT_SYNC_SSH_DIR}/{GIT_SYNC_KNOWN_HOSTS_FILE}"))
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OdooClusterAuthenticationConfig {
     /// Name of the AuthenticationClass used to authenticate the users.
-    /// At the moment only LDAP is supported.
-    /// If not specified the default authentication (AUTH_DB) will be used.
+    /// Deprecated, use `authenticationClasses` instead. If both are given, this class takes
+    /// precedence over the ones in the list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub authentication_class: Option<String>,
 
+    /// Names of the AuthenticationClasses used to authenticate the users, ordered by priority.
+    /// At the moment only LDAP is supported. The first class determines the primary server,
+    /// all further LDAP classes are tried as fallback servers.
+    /// If not specified the default authentication (AUTH_DB) will be used.
+    #[serde(default)]
+    pub authentication_classes: Vec<String>,
+
+    /// Present the certificate issued by the SecretClass used for the LDAP server verification
+    /// as client certificate to the LDAP server (mutual TLS).
+    /// Gets mapped to `AUTH_LDAP_TLS_CERTFILE` and `AUTH_LDAP_TLS_KEYFILE`
+    #[serde(default)]
+    pub ldap_tls_client_certificate: bool,
+
+    /// Allow users stored in the Odoo database to log in if they are not found in (or the
+    /// servers of) the AuthenticationClasses.
+    /// Gets mapped to `AUTH_DB_FALLBACK`
+    #[serde(default)]
+    pub db_fallback: bool,
+
     /// Allow users who are not already in the FAB DB.
     /// Gets mapped to `AUTH_USER_REGISTRATION`
     #[serde(default = "default_user_registration")]
@@ -277,9 +1268,91 @@ pub struct OdooClusterAuthenticationConfig {
     /// Gets mapped to `AUTH_ROLES_SYNC_AT_LOGIN`
     #[serde(default = "default_sync_roles_at")]
     pub sync_roles_at: LdapRolesSyncMoment,
-}
 
-pub fn default_user_registration() -> bool {
+    /// Maps groups of the identity provider (e.g. LDAP group DNs) onto lists of Odoo roles.
+    /// Gets mapped to `AUTH_ROLES_MAPPING`
+    #[serde(default)]
+    pub roles_mapping: BTreeMap<String, Vec<String>>,
+
+    /// Authenticate users with OAuth providers configured from presets.
+    /// Only used if no AuthenticationClass is specified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<OdooOAuthConfig>,
+
+    /// Authenticate users against a SAML identity provider (e.g. ADFS or Okta).
+    /// Only used if no AuthenticationClass is specified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saml: Option<OdooSamlConfig>,
+
+    /// Trust the user name in a header set by an authenticating reverse proxy, e.g. oauth2-proxy.
+    /// Only used if no AuthenticationClass is specified. Requires `web.proxyMode`, and the
+    /// webservers must only be reachable through the proxy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_user: Option<OdooRemoteUserConfig>,
+
+    /// Provide a Kerberos keytab and `krb5.conf` to the webservers, e.g. to authenticate API
+    /// requests with the `kerberos` API auth backend. It is configured here instead of with an
+    /// AuthenticationClass, because the supported AuthenticationClasses have no Kerberos
+    /// provider yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kerberos: Option<OdooKerberosConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooRemoteUserConfig {
+    /// The header containing the name of the authenticated user.
+    #[serde(default = "default_remote_user_header")]
+    pub header: String,
+}
+
+fn default_remote_user_header() -> String {
+    "X-Forwarded-User".to_string()
+}
+
+impl OdooRemoteUserConfig {
+    /// The key of the header in the WSGI environment, e.g. `HTTP_X_FORWARDED_USER`.
+    pub fn wsgi_env_var(&self) -> String {
+        format!("HTTP_{}", self.header.to_uppercase().replace('-', "_"))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooKerberosConfig {
+    /// Name of the SecretClass providing the keytab, which must use the `kerberosKeytab`
+    /// backend of the secret-operator.
+    pub secret_class: String,
+}
+
+impl OdooClusterAuthenticationConfig {
+    /// Returns the names of all referenced AuthenticationClasses in order of priority.
+    pub fn authentication_classes(&self) -> Vec<&String> {
+        self.authentication_class
+            .iter()
+            .chain(self.authentication_classes.iter())
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooAuthorization {
+    /// The OPA discovery ConfigMap and the package containing the `allow` rule.
+    /// Gets mapped to `AUTH_OPA_URL`
+    pub opa: OpaConfig,
+    /// How long authorization decisions are cached.
+    /// Gets mapped to `AUTH_OPA_CACHE_TTL_SECONDS`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_ttl_seconds: Option<u32>,
+    /// How many decisions are cached by each webserver process, the least recently used ones are
+    /// evicted first. Defaults to 10000.
+    /// Gets mapped to `AUTH_OPA_CACHE_MAX_ENTRIES`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_max_entries: Option<u32>,
+}
+
+pub fn default_user_registration() -> bool {
     true
 }
 
@@ -298,6 +1371,30 @@ pub enum LdapRolesSyncMoment {
     Login,
 }
 
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooSamlConfig {
+    /// Where the metadata of the identity provider is retrieved from.
+    pub idp_metadata: SamlIdpMetadata,
+    /// The entity id Odoo uses as service provider.
+    /// Gets mapped to `AUTH_SAML_SP_ENTITY_ID`
+    pub entity_id: String,
+    /// Maps user fields (e.g. `email`, `firstname`) onto SAML attribute names.
+    /// Gets mapped to `AUTH_SAML_ATTRIBUTE_MAPPING`
+    #[serde(default)]
+    pub attribute_mapping: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SamlIdpMetadata {
+    /// URL the identity provider publishes its metadata at.
+    Url(String),
+    /// Name of a Secret containing the identity provider metadata under the key
+    /// `metadata.xml`.
+    Secret(String),
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OdooCredentials {
@@ -335,6 +1432,7 @@ PartialEq,
 Serialize,
 EnumString,
 )]
+#[serde(rename_all = "lowercase")]
 pub enum OdooRole {
     #[strum(serialize = "webserver")]
     Webserver,
@@ -361,6 +1459,22 @@ impl OdooRole {
         }
     }
 
+    /// Whether the role only runs read-only reports on the database replica.
+    pub fn supports_database_replica(&self) -> bool {
+        match &self {
+            OdooRole::Webserver | OdooRole::Worker => true,
+            OdooRole::Scheduler => false,
+        }
+    }
+
+    /// Whether the pods of the role may run as `Deployment`.
+    pub fn supports_deployment(&self) -> bool {
+        match &self {
+            OdooRole::Webserver | OdooRole::Scheduler => true,
+            OdooRole::Worker => false,
+        }
+    }
+
     /// Will be used to expose service ports and - by extension - which roles should be
     /// created as services.
     pub fn get_http_port(&self) -> Option<u16> {
@@ -395,30 +1509,185 @@ impl OdooCluster {
         tmp.iter().flat_map(|v| v.deref().clone()).collect()
     }
 
-    pub fn volume_mounts(&self) -> Vec<VolumeMount> {
+    pub fn volume_mounts(&self, role: &OdooRole) -> Vec<VolumeMount> {
         let tmp = self.spec.cluster_config.volume_mounts.as_ref();
         let mut mounts: Vec<VolumeMount> = tmp.iter().flat_map(|v| v.deref().clone()).collect();
-        if self.git_sync().is_some() {
+        if let Some(git_sync) = self.git_sync_for(role) {
             mounts.push(VolumeMount {
                 name: GIT_CONTENT.into(),
                 mount_path: GIT_SYNC_DIR.into(),
+                read_only: git_sync.uses_shared_volume().then_some(true),
                 ..VolumeMount::default()
             });
         }
         mounts
     }
 
+    /// The number of database connections all pods of the cluster can open at most.
+    pub fn max_database_connections(&self) -> Result<u32, Error> {
+        let mut connections = 0;
+        for role in OdooRole::iter() {
+            let role_groups = match self.get_role(&role) {
+                Some(role) => &role.role_groups,
+                None => continue,
+            };
+            for (role_group, rolegroup) in role_groups {
+                let rolegroup_ref = RoleGroupRef {
+                    cluster: ObjectRef::from_obj(self),
+                    role: role.to_string(),
+                    role_group: role_group.clone(),
+                };
+                let config = self.merged_config(&role, &rolegroup_ref)?;
+                connections += u32::from(rolegroup.replicas.unwrap_or(1))
+                    * config.max_database_connections();
+            }
+        }
+        Ok(connections)
+    }
+
+    /// The modules to update when the product image changes, as argument of `odoo -u`, or `None`
+    /// if they are not updated.
+    pub fn modules_to_upgrade(&self) -> Result<Option<String>, Error> {
+        let cluster_config = &self.spec.cluster_config;
+        match cluster_config.module_upgrade_policy {
+            ModuleUpgradePolicy::None => Ok(None),
+            ModuleUpgradePolicy::All => Ok(Some("all".to_string())),
+            ModuleUpgradePolicy::List => {
+                ensure!(
+                    !cluster_config.upgrade_modules.is_empty(),
+                    UpgradeModulesMissingSnafu
+                );
+                Ok(Some(cluster_config.upgrade_modules.join(",")))
+            }
+        }
+    }
+
+    /// Checks that every source in `addonsPathOrder` is configured and listed only once.
+    pub fn validate_addons_path_order(&self) -> Result<(), Error> {
+        let configured_sources = self.configured_addons_sources();
+        let addons_path_order = &self.spec.cluster_config.addons_path_order;
+        for (index, addons_source) in addons_path_order.iter().enumerate() {
+            ensure!(
+                configured_sources.contains(addons_source),
+                AddonsSourceNotConfiguredSnafu {
+                    addons_source: *addons_source
+                }
+            );
+            ensure!(
+                !addons_path_order[..index].contains(addons_source),
+                AddonsSourceDuplicatedSnafu {
+                    addons_source: *addons_source
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// The directories of the addons in the pods of the role, in the order of `addonsPathOrder`.
+    pub fn addons_path(&self, role: &OdooRole) -> Vec<String> {
+        let cluster_config = &self.spec.cluster_config;
+        let configured_sources = self.configured_addons_sources();
+        let mut sources = cluster_config
+            .addons_path_order
+            .iter()
+            .filter(|addons_source| configured_sources.contains(addons_source))
+            .copied()
+            .collect::<Vec<_>>();
+        for addons_source in configured_sources {
+            if !sources.contains(&addons_source) {
+                sources.push(addons_source);
+            }
+        }
+
+        sources
+            .into_iter()
+            .filter_map(|addons_source| match addons_source {
+                AddonsSource::Git => self
+                    .git_sync_for(role)
+                    .filter(|git_sync| git_sync.git_folder.is_some())
+                    .map(GitSync::addons_dir),
+                AddonsSource::Oci => Some(AddonsOci::addons_dir()),
+                AddonsSource::Bucket => Some(AddonsBucket::addons_dir()),
+                AddonsSource::Pip => Some(pip_addons_dir()),
+            })
+            .collect()
+    }
+
+    /// The addons sources of the cluster in the default order.
+    fn configured_addons_sources(&self) -> Vec<AddonsSource> {
+        let cluster_config = &self.spec.cluster_config;
+        AddonsSource::iter()
+            .filter(|addons_source| match addons_source {
+                AddonsSource::Git => self
+                    .git_sync()
+                    .map_or(false, |git_sync| git_sync.git_folder.is_some()),
+                AddonsSource::Oci => cluster_config.addons_oci.is_some(),
+                AddonsSource::Bucket => cluster_config.addons_bucket.is_some(),
+                AddonsSource::Pip => !cluster_config.addons_pip.is_empty(),
+            })
+            .collect()
+    }
+
+    /// The restart annotations of the cluster which apply to the role, to be set on its pods.
+    pub fn restart_annotations(&self, role: &OdooRole) -> BTreeMap<String, String> {
+        let role_annotation = format!("{RESTART_ANNOTATION}-{role}");
+        self.annotations()
+            .iter()
+            .filter(|(key, _)| *key == RESTART_ANNOTATION || **key == role_annotation)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// The argument enabling the developer mode, if requested.
+    pub fn dev_arg(&self) -> Option<String> {
+        self.spec
+            .cluster_config
+            .dev_mode
+            .as_ref()
+            .and_then(DevMode::dev_arg)
+    }
+
+    pub fn worker_drain_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.spec
+                .cluster_config
+                .worker_drain_timeout_seconds
+                .unwrap_or(600),
+        )
+    }
+
+    /// The deletion policy of the OdooDB belonging to this cluster.
+    pub fn database_deletion_policy(&self) -> odoodb::DeletionPolicy {
+        let cluster_config = &self.spec.cluster_config;
+        cluster_config
+            .persistence
+            .as_ref()
+            .and_then(|persistence| persistence.deletion_policy)
+            .or_else(|| {
+                cluster_config
+                    .database_initialization
+                    .as_ref()
+                    .and_then(|database_initialization| database_initialization.deletion_policy)
+            })
+            .unwrap_or_default()
+    }
+
     pub fn git_sync(&self) -> Option<&GitSync> {
-        let dags_git_sync = &self.spec.cluster_config.dags_git_sync;
-        // dags_git_sync is a list but only the first element is considered
+        let addons_git_sync = &self.spec.cluster_config.addons_git_sync;
+        // addons_git_sync is a list but only the first element is considered
         // (this avoids a later breaking change when all list elements are processed)
-        if dags_git_sync.len() > 1 {
+        if addons_git_sync.len() > 1 {
             tracing::warn!(
                 "{:?} git-sync elements: only first will be considered...",
-                dags_git_sync.len()
+                addons_git_sync.len()
             );
         }
-        dags_git_sync.first()
+        addons_git_sync.first()
+    }
+
+    /// The git-sync settings, if the pods of the role sync the addons.
+    pub fn git_sync_for(&self, role: &OdooRole) -> Option<&GitSync> {
+        self.git_sync().filter(|git_sync| git_sync.enabled_for(role))
     }
 }
 
@@ -477,16 +1746,100 @@ serde(rename_all = "camelCase")
 pub struct OdooConfig {
     #[fragment_attrs(serde(default))]
     pub resources: Resources<OdooStorageConfig, NoRuntimeLimits>,
+    /// A custom log configuration of the `odoo` container is mounted instead of the generated
+    /// one, so its ConfigMap must provide `log_config.py` defining `LOGGING_CONFIG`.
+    /// `logFileMaxSize`, `logFileBackupCount` and `logFormat` do not apply to it.
     #[fragment_attrs(serde(default))]
     pub logging: Logging<Container>,
     #[fragment_attrs(serde(default))]
     pub affinity: StackableAffinity,
+    /// Run reports against `clusterConfig.database.replica`. Only supported by the webserver
+    /// and worker roles, the scheduler always needs the primary.
+    pub use_database_replica: bool,
+    /// Number of Odoo worker processes per pod (`workers`). Odoo runs multi-threaded in a single
+    /// process if not set.
+    pub worker_processes: Option<u16>,
+    /// Number of cron workers per pod (`max_cron_threads`).
+    pub max_cron_threads: Option<u16>,
+    /// Maximum number of database connections of each Odoo process (`db_maxconn`).
+    pub db_maxconn: Option<u16>,
+    /// How changes of the pods are rolled out to this rolegroup.
+    pub rollout_strategy: RolloutStrategy,
+    /// Time in seconds the pods of a `canary` rolegroup must be ready before the other
+    /// rolegroups are rolled out. Defaults to 300.
+    pub canary_soak_time_seconds: u64,
+    /// How the pods of the StatefulSet are replaced when it changes. Defaults to
+    /// `RollingUpdate`.
+    pub update_strategy: Option<UpdateStrategy>,
+    /// Only the pods with an ordinal of at least the partition are updated by a
+    /// `RollingUpdate`, so an update can be staged by lowering it step by step.
+    pub rolling_update_partition: Option<i32>,
+    /// Run the pods as `Deployment` instead of `StatefulSet`, which replaces them by surging
+    /// new pods before stopping the old ones. Only supported by the webserver and scheduler
+    /// roles, the workers are drained by their ordinal. `updateStrategy` and
+    /// `rollingUpdatePartition` do not apply to Deployments.
+    pub workload_type: WorkloadType,
+    /// Whether the pods of the StatefulSet are started and stopped all at once or one after
+    /// another. Defaults to `Parallel`.
+    pub pod_management_policy: PodManagementPolicy,
+    /// Size at which the log files of Odoo are rotated, e.g. `10Mi`. Defaults to `1Mi`. The log
+    /// volume grows with the size of all rotated files.
+    pub log_file_max_size: Option<Quantity>,
+    /// Number of rotated log files of Odoo which are kept. Defaults to 1.
+    pub log_file_backup_count: Option<u16>,
+    /// Format of the log records Odoo writes to the console. The log files are always written
+    /// as JSON. Defaults to `plain`.
+    pub log_format: LogFormat,
 }
 
 impl OdooConfig {
     pub const CREDENTIALS_SECRET_PROPERTY: &'static str = "credentialsSecret";
     pub const GIT_CREDENTIALS_SECRET_PROPERTY: &'static str = "gitCredentialsSecret";
 
+    /// The property of the credentials Secret of the git-sync entry at `index`, which can be
+    /// overridden per rolegroup. The first entry keeps the property without an index.
+    pub fn git_credentials_secret_property(index: usize) -> String {
+        if index == 0 {
+            Self::GIT_CREDENTIALS_SECRET_PROPERTY.to_string()
+        } else {
+            format!("{}{index}", Self::GIT_CREDENTIALS_SECRET_PROPERTY)
+        }
+    }
+
+    /// The number of database connections a single pod can open at most. In multi-processing
+    /// mode every worker, every cron worker and the gevent worker has its own connection pool.
+    pub fn max_database_connections(&self) -> u32 {
+        let db_maxconn = u32::from(self.db_maxconn.unwrap_or(ODOO_DEFAULT_DB_MAXCONN));
+        match self.worker_processes {
+            None | Some(0) => db_maxconn,
+            Some(worker_processes) => {
+                let cron_workers = self
+                    .max_cron_threads
+                    .unwrap_or(ODOO_DEFAULT_MAX_CRON_THREADS);
+                (u32::from(worker_processes) + u32::from(cron_workers) + 1) * db_maxconn
+            }
+        }
+    }
+
+    /// The rotation of the log files of Odoo, if it differs from the default.
+    pub fn log_file_rotation(&self) -> Result<Option<LogFileRotation>, Error> {
+        if self.log_file_max_size.is_none() && self.log_file_backup_count.is_none() {
+            return Ok(None);
+        }
+        let max_file_size = match &self.log_file_max_size {
+            Some(max_size) => {
+                MemoryQuantity::try_from(max_size).context(InvalidLogFileMaxSizeSnafu)?
+            }
+            None => LogFileRotation::DEFAULT.max_file_size,
+        };
+        Ok(Some(LogFileRotation {
+            max_file_size,
+            backup_count: self
+                .log_file_backup_count
+                .unwrap_or(LogFileRotation::DEFAULT.backup_count),
+        }))
+    }
+
     fn default_config(cluster_name: &str, role: &OdooRole) -> OdooConfigFragment {
         let (cpu, memory) = match role {
             OdooRole::Worker => (
@@ -529,10 +1882,111 @@ impl OdooConfig {
             },
             logging: product_logging::spec::default_logging(),
             affinity: get_affinity(cluster_name, role),
+            use_database_replica: Some(false),
+            worker_processes: None,
+            max_cron_threads: None,
+            db_maxconn: None,
+            rollout_strategy: Some(RolloutStrategy::default()),
+            canary_soak_time_seconds: Some(300),
+            update_strategy: None,
+            rolling_update_partition: None,
+            workload_type: Some(WorkloadType::default()),
+            pod_management_policy: Some(PodManagementPolicy::default()),
+            log_file_max_size: None,
+            log_file_backup_count: None,
+            log_format: Some(LogFormat::default()),
+        }
+    }
+}
+
+/// How the log files of Odoo, and the audit log, are rotated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogFileRotation {
+    pub max_file_size: MemoryQuantity,
+    pub backup_count: u16,
+}
+
+impl LogFileRotation {
+    pub const DEFAULT: LogFileRotation = LogFileRotation {
+        max_file_size: MemoryQuantity {
+            value: 1.0,
+            unit: BinaryMultiple::Mebi,
+        },
+        backup_count: 1,
+    };
+
+    /// The size in bytes at which a log file is rotated.
+    pub fn max_bytes(&self) -> u64 {
+        let kibibytes = self.max_file_size.scale_to(BinaryMultiple::Kibi).value;
+        (kibibytes * 1024.0) as u64
+    }
+
+    /// The size of a log file together with all of its rotated files.
+    pub fn files_size(&self) -> MemoryQuantity {
+        MemoryQuantity {
+            value: self.max_file_size.value * f32::from(self.backup_count + 1),
+            unit: self.max_file_size.unit,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RolloutStrategy {
+    /// The rolegroup is rolled out together with the other rolegroups.
+    #[default]
+    Rolling,
+    /// The rolegroup is rolled out first. The other rolegroups are held back until its pods are
+    /// ready for `canarySoakTimeSeconds`.
+    Canary,
+}
+
+impl Atomic for RolloutStrategy {}
+
+/// The `updateStrategy` of the rolegroup StatefulSet.
+#[derive(Clone, Copy, Debug, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum UpdateStrategy {
+    /// The pods are replaced one after another.
+    RollingUpdate,
+    /// The pods are only replaced once they are deleted manually.
+    OnDelete,
+}
+
+impl Atomic for UpdateStrategy {}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum WorkloadType {
+    #[default]
+    StatefulSet,
+    Deployment,
+}
+
+impl Atomic for WorkloadType {}
+
+/// The `podManagementPolicy` of the rolegroup StatefulSet.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize,
+)]
+pub enum PodManagementPolicy {
+    #[default]
+    Parallel,
+    /// A pod is only started once its predecessor is ready, and stopped in reverse order.
+    OrderedReady,
+}
+
+impl Atomic for PodManagementPolicy {}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+    #[default]
+    Plain,
+    /// One JSON object per record, which log collectors can parse without patterns.
+    Json,
+}
+
+impl Atomic for LogFormat {}
+
 impl Configuration for OdooConfigFragment {
     type Configurable = OdooCluster;
 
@@ -546,10 +2000,17 @@ impl Configuration for OdooConfigFragment {
             OdooConfig::CREDENTIALS_SECRET_PROPERTY.to_string(),
             Some(cluster.spec.cluster_config.credentials_secret.clone()),
         );
-        if let Some(git_sync) = &cluster.git_sync() {
+        // Every repository has its own credentials, only passed to its git-sync container
+        for (index, git_sync) in cluster
+            .spec
+            .cluster_config
+            .addons_git_sync
+            .iter()
+            .enumerate()
+        {
             if let Some(credentials_secret) = &git_sync.credentials_secret {
                 env.insert(
-                    OdooConfig::GIT_CREDENTIALS_SECRET_PROPERTY.to_string(),
+                    OdooConfig::git_credentials_secret_property(index),
                     Some(credentials_secret.to_string()),
                 );
             }
@@ -580,6 +2041,194 @@ impl Configuration for OdooConfigFragment {
 pub struct OdooClusterStatus {
     #[serde(default)]
     pub conditions: Vec<ClusterCondition>,
+    /// The last rotation of the secret key managed by the operator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_key: Option<SecretKeyStatus>,
+    /// The product version the database was initialized or last migrated for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_version: Option<String>,
+    /// The database migration to a new product version which holds back the rollout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade: Option<UpgradeStatus>,
+    /// The product image the pods were last rolled out with, so the modules are only updated
+    /// when it changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modules_upgraded_for_image: Option<String>,
+    /// The result of the last connectivity probe of the database server by the operator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_reachable: Option<DatabaseReachableCondition>,
+    /// Set while the checks before the rollout fail, e.g. because the credentials are
+    /// incomplete. Nothing is rolled out until they pass.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preflight_failed: Option<PreflightFailedCondition>,
+    /// The addon revisions synced by git-sync and the failed verifications of downloaded addons,
+    /// by the name of the rolegroup StatefulSet.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub addons: BTreeMap<String, AddonsStatus>,
+    /// The worker rolegroups whose removed pods are still finishing their jobs, by the name of
+    /// the rolegroup StatefulSet.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scaling_down: BTreeMap<String, ScalingDownStatus>,
+    /// Set while the webservers are held back or, after an upgrade of the database, stopped until
+    /// the workers and schedulers run a new image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollout: Option<RolloutStatus>,
+    /// The revisions of the `canary` rolegroups and since when their pods are ready, by the name
+    /// of the rolegroup StatefulSet.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub canaries: BTreeMap<String, CanaryStatus>,
+    /// The replicas of every rolegroup, by role and rolegroup.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub roles: BTreeMap<String, BTreeMap<String, RoleGroupStatus>>,
+    /// The product version all pods were last rolled out with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployed_product_version: Option<String>,
+    /// The generation of the cluster which was last applied completely. The pods may still be
+    /// rolling out, see `deployedProductVersion` and `roles`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+}
+
+impl OdooClusterStatus {
+    /// Whether applying this status would leave `current` as it is. The fields applied separately
+    /// by the database health check and the addons revision report are not compared, nor is the
+    /// `lastUpdateTime` of the conditions, which `compute_conditions` sets on every reconcile.
+    pub fn is_unchanged_from(&self, current: Option<&OdooClusterStatus>) -> bool {
+        let Some(current) = current else {
+            return false;
+        };
+        let applied = OdooClusterStatus {
+            database_reachable: current.database_reachable.clone(),
+            addons: current.addons.clone(),
+            conditions: without_update_times(&self.conditions),
+            ..self.clone()
+        };
+        let current = OdooClusterStatus {
+            conditions: without_update_times(&current.conditions),
+            ..current.clone()
+        };
+        applied == current
+    }
+}
+
+fn without_update_times(conditions: &[ClusterCondition]) -> Vec<ClusterCondition> {
+    conditions
+        .iter()
+        .map(|condition| ClusterCondition {
+            last_update_time: None,
+            ..condition.clone()
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonsStatus {
+    /// The revision synced by all pods of the rolegroup. Unset while the pods have synced
+    /// different revisions, e.g. during a sync, or none at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synced_revision: Option<String>,
+    /// Why the downloaded addons were rejected on a pod of the rolegroup, e.g. a checksum
+    /// mismatch. The pod does not start until the verification succeeds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_failure: Option<String>,
+    /// Whether the health endpoint of git-sync succeeds on all pods of the rolegroup, which it
+    /// does once the last sync succeeded. With `sharedVolume` it is the one of the shared
+    /// git-sync pod. Unset if git-sync does not run continuously.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_sync_up: Option<ClusterConditionStatus>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightFailedCondition {
+    pub last_transition_time: Time,
+    /// Everything which is missing or cannot be resolved.
+    pub failures: Vec<String>,
+}
+
+/// Reported as separate condition, as the database server is not managed by the operator. It is
+/// only updated when the result of the probe changes.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseReachableCondition {
+    pub status: ClusterConditionStatus,
+    pub last_transition_time: Time,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeStatus {
+    pub from_version: String,
+    pub to_version: String,
+    pub condition: UpgradeCondition,
+    pub started_at: Time,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RolloutStatus {
+    /// The product image which is rolled out.
+    pub image: String,
+    pub started_at: Time,
+    /// Set if the database was upgraded for the image, so the webservers are stopped until they
+    /// run it instead of serving the upgraded database with the previous version.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub maintenance: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CanaryStatus {
+    /// The revision of the StatefulSet the pods run.
+    pub revision: String,
+    /// Unset while the pods are not rolled out and ready.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready_since: Option<Time>,
+}
+
+/// The replicas of a rolegroup as last observed by the operator.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleGroupStatus {
+    pub replicas: i32,
+    pub ready_replicas: i32,
+    /// The generation of the StatefulSet or Deployment all pods were last rolled out for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rolled_out_generation: Option<i64>,
+    /// The hash of the rolegroup configuration all pods were last rolled out with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+}
+
+/// Reported while the pods removed by a scale-down are drained.
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScalingDownStatus {
+    /// The replicas which are kept until the removed pods are drained.
+    pub replicas: i32,
+    pub target_replicas: i32,
+    pub started_at: Time,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, JsonSchema, PartialEq, Serialize)]
+pub enum UpgradeCondition {
+    BackingUp,
+    BackupFailed,
+    Upgrading,
+    Failed,
+    UpgradingModules,
+    ModuleUpgradeFailed,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretKeyStatus {
+    /// The `secretKeyGeneration` the current key was generated for.
+    pub generation: u32,
+    pub rotated_at: Time,
 }
 
 impl HasStatusCondition for OdooCluster {
@@ -700,9 +2349,12 @@ pub struct OdooClusterRef {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::odoodb::OdooDB;
-    use crate::OdooCluster;
     use stackable_operator::commons::product_image_selection::ResolvedProductImage;
+    use stackable_operator::status::condition::{
+        compute_conditions, ClusterConditionSet, ClusterConditionType, ConditionBuilder,
+    };
 
     #[test]
     fn test_cluster_config() {
@@ -754,98 +2406,1032 @@ mod tests {
     }
 
     #[test]
-    fn test_git_sync() {
-        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+    fn test_database_deletion_policy() {
+        let mut cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
             "
         apiVersion: odoo.stackable.tech/v1alpha1
         kind: OdooCluster
         metadata:
           name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
         spec:
           image:
             productVersion: 2.6.1
             stackableVersion: 0.0.0-dev
           clusterConfig:
-            executor: CeleryExecutor
-            loadExamples: false
-            exposeConfig: false
             credentialsSecret: simple-odoo-credentials
-            dagsGitSync:
-              - name: git-sync
-                repo: https://github.com/stackabletech/odoo-operator
-                branch: feat/git-sync
-                wait: 20
-                gitSyncConf: {}
-                gitFolder: tests/templates/kuttl/mount-dags-gitsync/dags
-          webservers:
-            roleGroups:
-              default:
-                config: {}
-          workers:
-            roleGroups:
-              default:
-                config: {}
-          schedulers:
-            roleGroups:
-              default:
-                config: {}
+            databaseInitialization:
+              deletionPolicy: Delete
           ",
         )
-            .unwrap();
+        .unwrap();
+        let resolved_odoo_image: ResolvedProductImage = cluster.spec.image.resolve("odoo");
 
-        assert!(cluster.git_sync().is_some(), "git_sync was not Some!");
-        assert_eq!(
-            Some("tests/templates/kuttl/mount-dags-gitsync/dags".to_string()),
-            cluster.git_sync().unwrap().git_folder
-        );
+        assert_eq!(odoodb::DeletionPolicy::Delete, cluster.database_deletion_policy());
+        let odoo_db = OdooDB::for_odoo(&cluster, &resolved_odoo_image).unwrap();
+        assert_eq!(1, odoo_db.owner_references().len());
+
+        cluster.spec.cluster_config.persistence = Some(PersistenceConfig {
+            deletion_policy: Some(odoodb::DeletionPolicy::Retain),
+        });
+        assert_eq!(odoodb::DeletionPolicy::Retain, cluster.database_deletion_policy());
+        let odoo_db = OdooDB::for_odoo(&cluster, &resolved_odoo_image).unwrap();
+        assert!(odoo_db.owner_references().is_empty());
     }
 
     #[test]
-    fn test_git_sync_config() {
-        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+    fn test_audit_logging_installs_module() {
+        let mut cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
             "
         apiVersion: odoo.stackable.tech/v1alpha1
         kind: OdooCluster
         metadata:
           name: odoo
+          namespace: default
         spec:
           image:
             productVersion: 2.6.1
             stackableVersion: 0.0.0-dev
           clusterConfig:
-            executor: CeleryExecutor
-            loadExamples: false
-            exposeConfig: false
             credentialsSecret: simple-odoo-credentials
-            dagsGitSync:
-              - name: git-sync
-                repo: https://github.com/stackabletech/odoo-operator
-                branch: feat/git-sync
-                wait: 20
-                gitSyncConf:
-                  --rev: c63921857618a8c392ad757dda13090fff3d879a
-                gitFolder: tests/templates/kuttl/mount-dags-gitsync/dags
-          webservers:
-            roleGroups:
-              default:
-                config: {}
-          workers:
-            roleGroups:
-              default:
-                config: {}
-          schedulers:
-            roleGroups:
-              default:
-                config: {}
+            databaseInitialization:
+              installModules:
+                - sale
           ",
         )
-            .unwrap();
+        .unwrap();
+        let resolved_odoo_image: ResolvedProductImage = cluster.spec.image.resolve("odoo");
 
-        assert!(cluster
-            .git_sync()
-            .unwrap()
-            .get_args()
+        let odoo_db = OdooDB::for_odoo(&cluster, &resolved_odoo_image).unwrap();
+        assert_eq!(
+            Some(vec!["sale".to_string()]),
+            odoo_db.spec.config.install_modules
+        );
+
+        cluster.spec.cluster_config.audit_logging = Some(OdooAuditLoggingConfig {
+            stream: "odoo-audit".to_string(),
+        });
+        let odoo_db = OdooDB::for_odoo(&cluster, &resolved_odoo_image).unwrap();
+        assert_eq!(
+            Some(vec!["sale".to_string(), odoodb::AUDIT_LOG_MODULE.to_string()]),
+            odoo_db.spec.config.install_modules
+        );
+
+        cluster.spec.cluster_config.database_initialization = None;
+        let odoo_db = OdooDB::for_odoo(&cluster, &resolved_odoo_image).unwrap();
+        assert_eq!(
+            Some(vec![odoodb::AUDIT_LOG_MODULE.to_string()]),
+            odoo_db.spec.config.install_modules
+        );
+    }
+
+    #[test]
+    fn test_validate_api_auth_backend() {
+        let mut cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            apiAuthBackend: kerberos
+          ",
+        )
+        .unwrap();
+        assert!(matches!(
+            cluster.spec.cluster_config.validate_api_auth_backend(),
+            Err(Error::KerberosConfigMissing)
+        ));
+
+        cluster.spec.cluster_config.authentication_config = Some(
+            serde_yaml::from_str(
+                "
+            kerberos:
+              secretClass: kerberos
+            ",
+            )
+            .unwrap(),
+        );
+        assert!(cluster
+            .spec
+            .cluster_config
+            .validate_api_auth_backend()
+            .is_ok());
+
+        cluster.spec.cluster_config.api_auth_backend = ApiAuthBackend::Basic;
+        cluster.spec.cluster_config.authentication_config = None;
+        assert!(cluster
+            .spec
+            .cluster_config
+            .validate_api_auth_backend()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_addons_checksums() {
+        let mut cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            addonsOci:
+              reference: ghcr.io/acme/odoo-addons:1.4.0
+              sha256: E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855
+            addonsBucket:
+              bucket: acme-releases
+              sha256: e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b85; id
+          ",
+        )
+        .unwrap();
+        assert!(matches!(
+            cluster.spec.cluster_config.validate_addons_checksums(),
+            Err(Error::InvalidAddonsChecksum {
+                addons_source: AddonsSource::Bucket,
+                ..
+            })
+        ));
+
+        cluster.spec.cluster_config.addons_bucket = None;
+        assert!(cluster
+            .spec
+            .cluster_config
+            .validate_addons_checksums()
+            .is_ok());
+
+        if let Some(addons_oci) = &mut cluster.spec.cluster_config.addons_oci {
+            addons_oci.sha256 = Some("e3b0c442".to_string());
+        }
+        assert!(matches!(
+            cluster.spec.cluster_config.validate_addons_checksums(),
+            Err(Error::InvalidAddonsChecksum {
+                addons_source: AddonsSource::Oci,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_validate_credentials() {
+        let mut cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig: {}
+          ",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            cluster.spec.cluster_config.validate_credentials(),
+            Err(Error::CredentialsMissing)
+        ));
+
+        cluster.spec.cluster_config.credentials_provider =
+            Some(CredentialsProvider::SecretsStoreCsi {
+                secret_provider_class: "vault-odoo".to_string(),
+            });
+        assert!(cluster.spec.cluster_config.validate_credentials().is_ok());
+
+        cluster.spec.cluster_config.credentials_provider = None;
+        cluster.spec.cluster_config.credentials_secret = "simple-odoo-credentials".to_string();
+        assert!(cluster.spec.cluster_config.validate_credentials().is_ok());
+    }
+
+    #[test]
+    fn test_git_sync() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            executor: CeleryExecutor
+            loadExamples: false
+            exposeConfig: false
+            credentialsSecret: simple-odoo-credentials
+            dagsGitSync:
+              - name: git-sync
+                repo: https://github.com/stackabletech/odoo-operator
+                branch: feat/git-sync
+                wait: 20
+                gitSyncConf: {}
+                gitFolder: tests/templates/kuttl/mount-dags-gitsync/dags
+          webservers:
+            roleGroups:
+              default:
+                config: {}
+          workers:
+            roleGroups:
+              default:
+                config: {}
+          schedulers:
+            roleGroups:
+              default:
+                config: {}
+          ",
+        )
+            .unwrap();
+
+        assert!(cluster.git_sync().is_some(), "git_sync was not Some!");
+        assert_eq!(
+            Some("tests/templates/kuttl/mount-dags-gitsync/dags".to_string()),
+            cluster.git_sync().unwrap().git_folder
+        );
+        let args = cluster.git_sync().unwrap().get_flags();
+        assert!(args.contains(&"--http-bind=:9082".to_string()));
+        assert!(args.contains(&"--http-metrics".to_string()));
+    }
+
+    #[test]
+    fn test_addons_git_sync() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            addonsGitSync:
+              - repo: https://github.com/acme/addons
+                gitFolder: custom
+          webservers:
+            roleGroups:
+              default:
+                config: {}
+          ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("custom".to_string()),
+            cluster.git_sync().unwrap().git_folder
+        );
+        assert_eq!(
+            vec![format!("{GIT_SYNC_DIR}/{GIT_LINK}/custom")],
+            cluster.addons_path(&OdooRole::Webserver)
+        );
+    }
+
+    #[test]
+    fn test_git_sync_config() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            executor: CeleryExecutor
+            loadExamples: false
+            exposeConfig: false
+            credentialsSecret: simple-odoo-credentials
+            dagsGitSync:
+              - name: git-sync
+                repo: https://github.com/stackabletech/odoo-operator
+                branch: feat/git-sync
+                wait: 20
+                gitSyncConf:
+                  --rev: c63921857618a8c392ad757dda13090fff3d879a
+                gitFolder: tests/templates/kuttl/mount-dags-gitsync/dags
+          webservers:
+            roleGroups:
+              default:
+                config: {}
+          workers:
+            roleGroups:
+              default:
+                config: {}
+          schedulers:
+            roleGroups:
+              default:
+                config: {}
+          ",
+        )
+            .unwrap();
+
+        assert!(cluster
+            .git_sync()
+            .unwrap()
+            .get_flags()
             .iter()
             .any(|c| c == "--rev=c63921857618a8c392ad757dda13090fff3d879a"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_git_sync_ssh() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: git@github.com:stackabletech/odoo-operator.git
+            ssh:
+              keySecret: git-ssh-key
+              knownHostsConfigMap: git-known-hosts
+            ",
+        )
+        .unwrap();
+
+        let args = git_sync.get_flags();
+        assert!(args.contains(&"--ssh-key-file=/stackable/gitsync-ssh/ssh".to_string()));
+        assert!(args.contains(
+            &"--ssh-known-hosts-file=/stackable/gitsync-known-hosts/known_hosts".to_string()
+        ));
+        assert_eq!(3, git_sync.volumes().len());
+
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: git@github.com:stackabletech/odoo-operator.git
+            ssh:
+              keySecret: git-ssh-key
+              verifyHostKey: false
+            ",
+        )
+        .unwrap();
+
+        let args = git_sync.get_flags();
+        assert!(args.contains(&"--ssh-known-hosts=false".to_string()));
+        assert!(!args.iter().any(|arg| arg.starts_with("--ssh-known-hosts-file")));
+        assert_eq!(2, git_sync.volumes().len());
+    }
+
+    #[test]
+    fn test_git_sync_once() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/stackabletech/odoo-operator
+            rev: v1.2.0
+            mode: once
+            ",
+        )
+        .unwrap();
+
+        let args = git_sync.get_flags();
+        assert!(args.contains(&"--rev=v1.2.0".to_string()));
+        assert!(args.contains(&"--one-time".to_string()));
+        assert!(!args.iter().any(|arg| arg.starts_with("--http-bind")));
+    }
+
+    #[test]
+    fn test_git_sync_revision() {
+        let mut git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/stackabletech/odoo-operator
+            revision: 0123456789abcdef0123456789abcdef01234567
+            version: v4
+            gitSyncConf:
+              --rev: v1.2.0
+            ",
+        )
+        .unwrap();
+
+        assert!(git_sync.validate_revision().is_ok());
+        let args = git_sync.get_flags();
+        assert!(args.contains(&"--ref=0123456789abcdef0123456789abcdef01234567".to_string()));
+        assert!(!args.iter().any(|arg| arg.starts_with("--rev")));
+
+        git_sync.branch = Some("main".to_string());
+        assert!(git_sync.validate_revision().is_err());
+        git_sync.branch = None;
+        git_sync.revision = None;
+        assert!(git_sync.get_flags().contains(&"--ref=v1.2.0".to_string()));
+        git_sync.git_sync_conf = None;
+        git_sync.mode = GitSyncMode::Once;
+        assert!(git_sync.validate_revision().is_err());
+    }
+
+    #[test]
+    fn test_git_sync_v4() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: git@github.com:stackabletech/odoo-operator.git
+            branch: feat/git-sync
+            wait: 30
+            version: v4
+            ssh:
+              keySecret: git-ssh-key
+            ",
+        )
+        .unwrap();
+
+        let args = git_sync.get_flags();
+        for arg in ["--ref=feat/git-sync", "--period=30s", "--link=current"] {
+            assert!(args.contains(&arg.to_string()), "{arg} is missing");
+        }
+        assert!(!args
+            .iter()
+            .any(|arg| arg.starts_with("--branch") || arg.starts_with("--wait") || arg == "--ssh"));
+    }
+
+    #[test]
+    fn test_git_sync_image() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/stackabletech/odoo-operator
+            version: v4
+            image:
+              image: registry.k8s.io/git-sync/git-sync:v4.2.1
+              pullSecrets:
+                - name: registry-credentials
+            ",
+        )
+        .unwrap();
+
+        let image = git_sync.image.as_ref().unwrap();
+        assert_eq!("IfNotPresent", image.pull_policy);
+        assert_eq!(
+            "--repo=https://github.com/stackabletech/odoo-operator",
+            git_sync.get_flags()[0]
+        );
+        assert_eq!("/stackable/git-sync", git_sync.get_args()[0]);
+    }
+
+    #[test]
+    fn test_git_sync_roles() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/stackabletech/odoo-operator
+            roles:
+              - webserver
+              - worker
+            ",
+        )
+        .unwrap();
+
+        assert!(git_sync.enabled_for(&OdooRole::Webserver));
+        assert!(!git_sync.enabled_for(&OdooRole::Scheduler));
+    }
+
+    #[test]
+    fn test_git_credentials_per_repository() {
+        let cluster: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            addonsGitSync:
+              - repo: https://github.com/acme/private-addons
+                credentialsSecret: acme-git
+              - repo: https://github.com/OCA/server-tools
+              - repo: https://gitlab.example.com/acme/more-addons
+                credentialsSecret: gitlab-deploy-token
+          ",
+        )
+        .unwrap();
+
+        let env = OdooConfigFragment::default()
+            .compute_env(&cluster, "webserver")
+            .unwrap();
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    "credentialsSecret".to_string(),
+                    Some("simple-odoo-credentials".to_string())
+                ),
+                ("gitCredentialsSecret".to_string(), Some("acme-git".to_string())),
+                (
+                    "gitCredentialsSecret2".to_string(),
+                    Some("gitlab-deploy-token".to_string())
+                ),
+            ]),
+            env
+        );
+    }
+
+    #[test]
+    fn test_git_sync_token() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://gitea.example.com/odoo/addons.git
+            credentialsSecret: gitea-token
+            credentialsFormat: giteaToken
+            ",
+        )
+        .unwrap();
+
+        assert!(git_sync
+            .get_flags()
+            .contains(&"--git-config=safe.directory:/tmp/git".to_string()));
+        assert!(!git_sync
+            .get_args()
+            .iter()
+            .any(|arg| arg.contains("GIT_SYNC_TOKEN")));
+        assert_eq!(
+            vec![
+                ("GIT_CONFIG_COUNT".to_string(), "1".to_string()),
+                (
+                    "GIT_CONFIG_KEY_0".to_string(),
+                    "http.extraHeader".to_string()
+                ),
+                (
+                    "GIT_CONFIG_VALUE_0".to_string(),
+                    "Authorization: token $(GIT_SYNC_TOKEN)".to_string()
+                ),
+            ],
+            git_sync.git_config_env_vars()
+        );
+        assert_eq!(
+            &[("TOKEN", "token")],
+            git_sync.credentials_format.credentials()
+        );
+    }
+
+    #[test]
+    fn test_git_sync_args_are_quoted() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/acme/addons.git
+            branch: main; echo injected
+            gitSyncConf:
+              --username: \"acme' $(echo injected)\"
+            ",
+        )
+        .unwrap();
+
+        // git-sync is stubbed to print its arguments
+        let command = format!(
+            "git_sync() {{ printf '%s\\n' \"$@\"; }}\ngit_sync {}",
+            git_sync.get_args()[1..].join(" ")
+        );
+        let output = std::process::Command::new("bash")
+            .args(["-c", &command])
+            .output()
+            .unwrap();
+        assert_eq!(
+            format!("{}\n", git_sync.get_flags().join("\n")),
+            String::from_utf8_lossy(&output.stdout)
+        );
+        assert!(git_sync
+            .get_flags()
+            .contains(&"--username=acme' $(echo injected)".to_string()));
+        assert!(git_sync
+            .get_flags()
+            .contains(&"--branch=main; echo injected".to_string()));
+    }
+
+    #[test]
+    fn test_git_sync_submodules() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/stackabletech/odoo-operator
+            submodules: shallow
+            ",
+        )
+        .unwrap();
+
+        assert!(git_sync
+            .get_flags()
+            .contains(&"--submodules=shallow".to_string()));
+    }
+
+    #[test]
+    fn test_git_sync_sparse_checkout() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/stackabletech/odoo-operator
+            sparseCheckoutPaths:
+              - /addons/sale_extension/
+              - /addons/stock_extension/
+            ",
+        )
+        .unwrap();
+
+        assert!(git_sync
+            .get_flags()
+            .contains(&"--sparse-checkout-file=/stackable/app/config/sparse-checkout".to_string()));
+        assert_eq!(
+            vec![(
+                "sparse-checkout",
+                "/addons/sale_extension/\n/addons/stock_extension/\n".to_string()
+            )],
+            git_sync.config_files()
+        );
+
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/OCA/server-tools
+            excludePaths:
+              - /.github/
+              - tests/
+            ",
+        )
+        .unwrap();
+
+        assert!(git_sync
+            .get_flags()
+            .contains(&"--sparse-checkout-file=/stackable/app/config/sparse-checkout".to_string()));
+        assert_eq!(
+            vec![("sparse-checkout", "/*\n!/.github/\n!tests/\n".to_string())],
+            git_sync.config_files()
+        );
+    }
+
+    #[test]
+    fn test_git_sync_exclude_paths() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/OCA/server-tools
+            sparseCheckoutPaths:
+              - /addons/
+            excludePaths:
+              - tests/
+            ",
+        )
+        .unwrap();
+        let sparse_checkout = git_sync
+            .config_files()
+            .into_iter()
+            .find(|(file_name, _)| *file_name == GIT_SYNC_SPARSE_CHECKOUT_FILE)
+            .map(|(_, content)| content)
+            .unwrap();
+        assert_eq!("/addons/\n!tests/\n", sparse_checkout);
+
+        // Check out a repository with the patterns, as git-sync does
+        let dir = std::env::temp_dir().join(format!("exclude-paths-{}", std::process::id()));
+        let repo = dir.join("repo");
+        for file in [
+            "README.md",
+            "addons/sale/__init__.py",
+            "addons/sale/tests/test_sale.py",
+        ] {
+            let path = repo.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(dir.join("sparse-checkout"), sparse_checkout).unwrap();
+        let output = std::process::Command::new("bash")
+            .args([
+                "-c",
+                "set -euo pipefail
+                cd repo
+                git init -q
+                git add -A
+                git -c user.name=ci -c user.email=ci@example.com commit -qm addons
+                cd ..
+                git clone -q --no-checkout repo checkout
+                cd checkout
+                git sparse-checkout set --no-cone --stdin < ../sparse-checkout
+                git checkout -q
+                git ls-files -t | grep -v '^S ' | cut -c3-",
+            ])
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!(
+            "addons/sale/__init__.py\n",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    #[test]
+    fn test_max_database_connections() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            config:
+              dbMaxconn: 8
+            roleGroups:
+              default:
+                replicas: 2
+                config:
+                  workerProcesses: 4
+          schedulers:
+            roleGroups:
+              default:
+                config:
+                  dbMaxconn: 16
+          ",
+        )
+            .unwrap();
+
+        // 2 * (4 workers + 2 cron workers + 1 gevent worker) * 8 + 16
+        assert_eq!(128, cluster.max_database_connections().unwrap());
+    }
+
+    #[test]
+    fn test_rollout_and_update_strategy() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            config:
+              rollingUpdatePartition: 1
+            roleGroups:
+              canary:
+                config:
+                  rolloutStrategy: canary
+                  canarySoakTimeSeconds: 600
+                  updateStrategy: OnDelete
+                  podManagementPolicy: OrderedReady
+                  logFileMaxSize: 10Mi
+                  logFileBackupCount: 4
+                  logFormat: json
+              default:
+                replicas: 2
+                config:
+                  workloadType: Deployment
+          ",
+        )
+            .unwrap();
+
+        let merged_config = |role_group: &str| {
+            let rolegroup_ref = RoleGroupRef {
+                cluster: ObjectRef::from_obj(&cluster),
+                role: OdooRole::Webserver.to_string(),
+                role_group: role_group.to_string(),
+            };
+            cluster
+                .merged_config(&OdooRole::Webserver, &rolegroup_ref)
+                .unwrap()
+        };
+        let canary = merged_config("canary");
+        assert_eq!(RolloutStrategy::Canary, canary.rollout_strategy);
+        assert_eq!(600, canary.canary_soak_time_seconds);
+        let default = merged_config("default");
+        assert_eq!(RolloutStrategy::Rolling, default.rollout_strategy);
+        assert_eq!(300, default.canary_soak_time_seconds);
+        assert_eq!(Some(UpdateStrategy::OnDelete), canary.update_strategy);
+        assert_eq!(None, default.update_strategy);
+        assert_eq!(Some(1), default.rolling_update_partition);
+        assert_eq!(WorkloadType::StatefulSet, canary.workload_type);
+        assert_eq!(WorkloadType::Deployment, default.workload_type);
+        assert_eq!(
+            PodManagementPolicy::OrderedReady,
+            canary.pod_management_policy
+        );
+        assert_eq!(PodManagementPolicy::Parallel, default.pod_management_policy);
+        let rotation = canary.log_file_rotation().unwrap().unwrap();
+        assert_eq!(10 * 1024 * 1024, rotation.max_bytes());
+        assert_eq!(4, rotation.backup_count);
+        assert_eq!(
+            MemoryQuantity {
+                value: 50.0,
+                unit: BinaryMultiple::Mebi,
+            },
+            rotation.files_size()
+        );
+        assert_eq!(None, default.log_file_rotation().unwrap());
+        assert_eq!(LogFormat::Json, canary.log_format);
+        assert_eq!(LogFormat::Plain, default.log_format);
+    }
+
+    #[test]
+    fn test_modules_to_upgrade() {
+        let mut cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            moduleUpgradePolicy: list
+            upgradeModules:
+              - sale
+              - stock
+          ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("sale,stock".to_string()),
+            cluster.modules_to_upgrade().unwrap()
+        );
+        cluster.spec.cluster_config.upgrade_modules.clear();
+        assert!(cluster.modules_to_upgrade().is_err());
+        cluster.spec.cluster_config.module_upgrade_policy = crate::ModuleUpgradePolicy::None;
+        assert_eq!(None, cluster.modules_to_upgrade().unwrap());
+    }
+
+    #[test]
+    fn test_restart_annotations() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          annotations:
+            odoo.sovrin.cloud/restart: 2024-01-01T00:00:00Z
+            odoo.sovrin.cloud/restart-worker: 2024-01-02T00:00:00Z
+            other: value
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["odoo.sovrin.cloud/restart"],
+            cluster
+                .restart_annotations(&OdooRole::Webserver)
+                .keys()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["odoo.sovrin.cloud/restart", "odoo.sovrin.cloud/restart-worker"],
+            cluster
+                .restart_annotations(&OdooRole::Worker)
+                .keys()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_dev_arg() {
+        let dev_mode: DevMode = serde_yaml::from_str("enabled: true").unwrap();
+        assert_eq!(Some("--dev=reload,qweb,xml".to_string()), dev_mode.dev_arg());
+
+        let dev_mode: DevMode = serde_yaml::from_str(
+            "
+            enabled: true
+            options:
+              - xml
+            ",
+        )
+        .unwrap();
+        assert_eq!(Some("--dev=xml".to_string()), dev_mode.dev_arg());
+
+        let dev_mode: DevMode = serde_yaml::from_str("enabled: false").unwrap();
+        assert_eq!(None, dev_mode.dev_arg());
+    }
+
+    #[test]
+    fn test_status_is_unchanged_from() {
+        let status = OdooClusterStatus {
+            product_version: Some("16.0".to_string()),
+            ..OdooClusterStatus::default()
+        };
+        assert!(!status.is_unchanged_from(None));
+
+        let mut current = status.clone();
+        current.addons.insert(
+            "odoo-webserver-default".to_string(),
+            AddonsStatus::default(),
+        );
+        assert!(status.is_unchanged_from(Some(&current)));
+
+        current.product_version = Some("15.0".to_string());
+        assert!(!status.is_unchanged_from(Some(&current)));
+    }
+
+    #[test]
+    fn test_status_with_recomputed_conditions_is_unchanged() {
+        struct Available(ClusterConditionStatus);
+        impl ConditionBuilder for Available {
+            fn build_conditions(&self) -> ClusterConditionSet {
+                vec![ClusterCondition {
+                    reason: None,
+                    message: None,
+                    status: self.0,
+                    type_: ClusterConditionType::Available,
+                    last_transition_time: None,
+                    last_update_time: None,
+                }]
+                .into()
+            }
+        }
+
+        let mut cluster: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          ",
+        )
+        .unwrap();
+        let mut status = OdooClusterStatus {
+            conditions: compute_conditions(&cluster, &[&Available(ClusterConditionStatus::True)]),
+            ..OdooClusterStatus::default()
+        };
+        status.conditions[0].last_update_time = None;
+        cluster.status = Some(status);
+
+        // Only the lastUpdateTime of the recomputed condition differs
+        let status = OdooClusterStatus {
+            conditions: compute_conditions(&cluster, &[&Available(ClusterConditionStatus::True)]),
+            ..OdooClusterStatus::default()
+        };
+        assert!(status.conditions[0].last_update_time.is_some());
+        assert!(status.is_unchanged_from(cluster.status.as_ref()));
+
+        let status = OdooClusterStatus {
+            conditions: compute_conditions(&cluster, &[&Available(ClusterConditionStatus::False)]),
+            ..OdooClusterStatus::default()
+        };
+        assert!(!status.is_unchanged_from(cluster.status.as_ref()));
+    }
+
+    #[test]
+    fn test_addons_path() {
+        let mut cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            addonsGitSync:
+              - repo: https://github.com/acme/odoo-addons
+                gitFolder: addons
+            addonsBucket:
+              bucket: odoo-addons
+            addonsPathOrder:
+              - bucket
+          ",
+        )
+        .unwrap();
+
+        assert!(cluster.validate_addons_path_order().is_ok());
+        assert_eq!(
+            vec![
+                "/stackable/addons/bucket".to_string(),
+                "/stackable/app/git/current/addons".to_string()
+            ],
+            cluster.addons_path(&OdooRole::Webserver)
+        );
+
+        cluster.spec.cluster_config.addons_path_order = vec![AddonsSource::Oci];
+        assert!(cluster.validate_addons_path_order().is_err());
+        cluster.spec.cluster_config.addons_path_order =
+            vec![AddonsSource::Git, AddonsSource::Git];
+        assert!(cluster.validate_addons_path_order().is_err());
+    }
+}
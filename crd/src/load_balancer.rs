@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::schemars::{self, JsonSchema};
+use std::collections::BTreeMap;
+use strum::Display;
+
+/// Presets for commonly needed load balancer behaviours. They are translated into the
+/// provider-specific annotations on the role `Service`s, so users don't have to know them.
+/// Only used when the `external-stable` listener class is selected. The PROXY protocol is not
+/// offered, as the Odoo webserver cannot accept it.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadBalancerConfig {
+    /// The cloud provider the `LoadBalancer` Service is created with.
+    pub provider: LoadBalancerProvider,
+    /// Idle timeout of connections in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_seconds: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LoadBalancerProvider {
+    Aws,
+    Azure,
+    DigitalOcean,
+    Hetzner,
+}
+
+impl LoadBalancerConfig {
+    /// Returns the `Service` annotations for the configured presets. Presets that are not
+    /// supported by the provider are ignored with a warning.
+    pub fn annotations(&self) -> BTreeMap<String, String> {
+        let mut annotations = BTreeMap::new();
+
+        if let Some(idle_timeout) = self.idle_timeout_seconds {
+            let idle_timeout = match self.provider {
+                LoadBalancerProvider::Aws => Some((
+                    "service.beta.kubernetes.io/aws-load-balancer-connection-idle-timeout",
+                    idle_timeout.to_string(),
+                )),
+                // Azure expects the timeout in minutes
+                LoadBalancerProvider::Azure => Some((
+                    "service.beta.kubernetes.io/azure-load-balancer-tcp-idle-timeout",
+                    ((idle_timeout + 59) / 60).to_string(),
+                )),
+                LoadBalancerProvider::DigitalOcean => Some((
+                    "service.beta.kubernetes.io/do-loadbalancer-http-idle-timeout-seconds",
+                    idle_timeout.to_string(),
+                )),
+                LoadBalancerProvider::Hetzner => None,
+            };
+            match idle_timeout {
+                Some((key, value)) => {
+                    annotations.insert(key.to_string(), value);
+                }
+                None => tracing::warn!(
+                    "An idle timeout is not supported for {} load balancers and will be ignored...",
+                    self.provider
+                ),
+            }
+        }
+
+        annotations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_azure_idle_timeout_in_minutes() {
+        let config = LoadBalancerConfig {
+            provider: LoadBalancerProvider::Azure,
+            idle_timeout_seconds: Some(90),
+        };
+
+        assert_eq!(
+            BTreeMap::from([(
+                "service.beta.kubernetes.io/azure-load-balancer-tcp-idle-timeout".to_string(),
+                "2".to_string()
+            )]),
+            config.annotations()
+        );
+    }
+
+    #[test]
+    fn test_proxy_protocol_is_not_enabled() {
+        // The option was removed, as the webserver cannot accept the PROXY protocol
+        let config: LoadBalancerConfig = serde_yaml::from_str(
+            "
+            provider: digitalOcean
+            proxyProtocol: true
+            idleTimeoutSeconds: 60
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            BTreeMap::from([(
+                "service.beta.kubernetes.io/do-loadbalancer-http-idle-timeout-seconds".to_string(),
+                "60".to_string()
+            )]),
+            config.annotations()
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::schemars::{self, JsonSchema};
+use strum::Display;
+
+pub const OAUTH_CLIENT_ID_KEY: &str = "clientId";
+pub const OAUTH_CLIENT_SECRET_KEY: &str = "clientSecret";
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooOAuthConfig {
+    /// The OAuth providers users can log in with.
+    pub providers: Vec<OAuthProvider>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthProvider {
+    /// The preset which determines the endpoints and scopes of the provider.
+    pub preset: OAuthProviderPreset,
+    /// Name of the Secret containing the client credentials under the keys `clientId` and
+    /// `clientSecret`.
+    pub client_credentials_secret: String,
+    /// The Azure AD tenant. Only used by the `azureAd` preset, defaults to `common`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Display, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "lowercase")]
+pub enum OAuthProviderPreset {
+    Google,
+    Github,
+    AzureAd,
+}
+
+/// The endpoints and scopes a preset expands to.
+pub struct OAuthProviderSettings {
+    /// The name the security manager of Odoo recognizes the provider by, which determines how
+    /// the user info is read from the provider.
+    pub name: &'static str,
+    pub icon: &'static str,
+    pub api_base_url: String,
+    pub access_token_url: String,
+    pub authorize_url: String,
+    pub scope: &'static str,
+}
+
+impl OAuthProvider {
+    pub fn settings(&self) -> OAuthProviderSettings {
+        match self.preset {
+            OAuthProviderPreset::Google => OAuthProviderSettings {
+                name: "google",
+                icon: "fa-google",
+                api_base_url: "https://www.googleapis.com/oauth2/v2/".to_string(),
+                access_token_url: "https://accounts.google.com/o/oauth2/token".to_string(),
+                authorize_url: "https://accounts.google.com/o/oauth2/auth".to_string(),
+                scope: "email profile",
+            },
+            OAuthProviderPreset::Github => OAuthProviderSettings {
+                name: "github",
+                icon: "fa-github",
+                api_base_url: "https://api.github.com".to_string(),
+                access_token_url: "https://github.com/login/oauth/access_token".to_string(),
+                authorize_url: "https://github.com/login/oauth/authorize".to_string(),
+                scope: "read:user user:email",
+            },
+            OAuthProviderPreset::AzureAd => {
+                let tenant_id = self.tenant_id.as_deref().unwrap_or("common");
+                OAuthProviderSettings {
+                    name: "azure",
+                    icon: "fa-windows",
+                    api_base_url: format!("https://login.microsoftonline.com/{tenant_id}/oauth2"),
+                    access_token_url: format!(
+                        "https://login.microsoftonline.com/{tenant_id}/oauth2/token"
+                    ),
+                    authorize_url: format!(
+                        "https://login.microsoftonline.com/{tenant_id}/oauth2/authorize"
+                    ),
+                    scope: "User.read name preferred_username email profile upn",
+                }
+            }
+        }
+    }
+
+    /// The prefix of the environment variables the client credentials are provided in.
+    /// It contains the index of the provider in the list, so that several providers with the
+    /// same preset do not overwrite each other's credentials.
+    pub fn env_var_prefix(&self, index: usize) -> String {
+        format!("OAUTH_{index}_{}", self.preset.to_string().to_uppercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var_prefix() {
+        let oauth: OdooOAuthConfig = serde_yaml::from_str(
+            "
+            providers:
+              - preset: azureAd
+                clientCredentialsSecret: azure-tenant-a
+                tenantId: tenant-a
+              - preset: azureAd
+                clientCredentialsSecret: azure-tenant-b
+                tenantId: tenant-b
+              - preset: github
+                clientCredentialsSecret: github
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["OAUTH_0_AZUREAD", "OAUTH_1_AZUREAD", "OAUTH_2_GITHUB"],
+            oauth
+                .providers
+                .iter()
+                .enumerate()
+                .map(|(index, provider)| provider.env_var_prefix(index))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "https://login.microsoftonline.com/tenant-b/oauth2/token",
+            oauth.providers[1].settings().access_token_url
+        );
+        assert_eq!(
+            vec!["azure", "azure", "github"],
+            oauth
+                .providers
+                .iter()
+                .map(|provider| provider.settings().name)
+                .collect::<Vec<_>>()
+        );
+    }
+}
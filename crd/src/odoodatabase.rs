@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::{
+    k8s_openapi::{apimachinery::pkg::apis::meta::v1::Time, chrono::Utc},
+    kube::CustomResource,
+    schemars::{self, JsonSchema},
+};
+
+pub const AIRFLOW_DATABASE_CONTROLLER_NAME: &str = "odoo-database";
+
+/// A tenant database served by an [`crate::OdooCluster`]. The database is created on the
+/// database server of the cluster and initialized by a Job, which runs once the OdooDatabase is
+/// added.
+#[derive(Clone, CustomResource, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[kube(
+group = "odoo.stackable.tech",
+version = "v1alpha1",
+kind = "OdooDatabase",
+plural = "odoodatabases",
+status = "OdooDatabaseStatus",
+namespaced,
+crates(
+kube_core = "stackable_operator::kube::core",
+k8s_openapi = "stackable_operator::k8s_openapi",
+schemars = "stackable_operator::schemars"
+)
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooDatabaseSpec {
+    /// Name of the OdooCluster in the same namespace the database is served by.
+    pub cluster_name: String,
+    /// Name of the database on the database server of the cluster. It cannot be changed once the
+    /// database was created, and only one OdooDatabase of the cluster may name it.
+    pub database_name: String,
+    /// Odoo modules which are installed when the database is initialized. The `base` module is
+    /// always installed.
+    #[serde(default)]
+    pub install_modules: Vec<String>,
+    /// Load the demo data of the installed modules.
+    #[serde(default)]
+    pub demo: bool,
+    /// The PostgreSQL role owning the database. Defaults to the user of the connection of the
+    /// cluster, which needs the `CREATEDB` privilege and has to be a member of this role.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Drop the database when the OdooDatabase is deleted. The database is kept by default.
+    #[serde(default)]
+    pub drop_on_delete: bool,
+}
+
+impl OdooDatabaseSpec {
+    /// The modules passed to `odoo -i`.
+    pub fn modules(&self) -> Vec<&str> {
+        let mut modules = vec!["base"];
+        modules.extend(
+            self.install_modules
+                .iter()
+                .map(String::as_str)
+                .filter(|module| *module != "base"),
+        );
+        modules
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooDatabaseStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<Time>,
+    pub condition: OdooDatabaseStatusCondition,
+    /// The generation of the OdooDatabase the database was created with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+    /// The name of the database which was created, so it is still dropped if `databaseName` was
+    /// changed afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_name: Option<String>,
+}
+
+impl OdooDatabaseStatus {
+    pub fn new() -> Self {
+        Self {
+            started_at: Some(Time(Utc::now())),
+            condition: OdooDatabaseStatusCondition::Pending,
+            observed_generation: None,
+            database_name: None,
+        }
+    }
+
+    /// The database is created from the spec of the given generation.
+    pub fn creating(&self, generation: Option<i64>, database_name: &str) -> Self {
+        let mut new = self.clone();
+        new.condition = OdooDatabaseStatusCondition::Creating;
+        new.observed_generation = generation;
+        new.database_name = Some(database_name.to_string());
+        new
+    }
+
+    pub fn ready(&self) -> Self {
+        let mut new = self.clone();
+        new.condition = OdooDatabaseStatusCondition::Ready;
+        new
+    }
+
+    pub fn failed(&self) -> Self {
+        let mut new = self.clone();
+        new.condition = OdooDatabaseStatusCondition::Failed;
+        new
+    }
+}
+
+impl OdooDatabaseStatus {
+    /// Whether the spec changed since the database was created, e.g. because modules were
+    /// added, so the creation job has to run again.
+    pub fn outdated(&self, odoo_database: &OdooDatabase) -> bool {
+        match self.condition {
+            OdooDatabaseStatusCondition::Ready | OdooDatabaseStatusCondition::Failed => {
+                self.observed_generation != odoo_database.metadata.generation
+            }
+            OdooDatabaseStatusCondition::Pending | OdooDatabaseStatusCondition::Creating => false,
+        }
+    }
+}
+
+impl Default for OdooDatabaseStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, JsonSchema, PartialEq, Serialize)]
+pub enum OdooDatabaseStatusCondition {
+    Pending,
+    Creating,
+    Ready,
+    Failed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modules() {
+        let odoo_database: OdooDatabase = serde_yaml::from_str(
+            "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooDatabase
+            metadata:
+              name: tenant-a
+            spec:
+              clusterName: odoo
+              databaseName: tenant_a
+              installModules:
+                - sale
+                - base
+                - website
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["base", "sale", "website"],
+            odoo_database.spec.modules()
+        );
+        assert!(!odoo_database.spec.drop_on_delete);
+    }
+
+    #[test]
+    fn test_outdated() {
+        let mut odoo_database: OdooDatabase = serde_yaml::from_str(
+            "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooDatabase
+            metadata:
+              name: tenant-a
+              generation: 1
+            spec:
+              clusterName: odoo
+              databaseName: tenant_a
+            ",
+        )
+        .unwrap();
+
+        let status = OdooDatabaseStatus::new().creating(Some(1), "tenant_a");
+        assert!(!status.outdated(&odoo_database));
+        assert!(!status.ready().outdated(&odoo_database));
+        assert_eq!(Some("tenant_a"), status.ready().database_name.as_deref());
+
+        odoo_database.metadata.generation = Some(2);
+        // The running creation is finished first
+        assert!(!status.outdated(&odoo_database));
+        assert!(status.ready().outdated(&odoo_database));
+        assert!(status.failed().outdated(&odoo_database));
+    }
+}
@@ -1,28 +1,56 @@
-use crate::{build_recommended_labels, OdooCluster};
+use crate::{
+    build_recommended_labels,
+    credentials::{AdminUserConfig, CredentialsProvider},
+    database::{DatabaseProvisioning, OdooDatabaseConfig},
+    image_policy::ImagePolicy,
+    CaBundle, OdooCluster, OdooComplianceConfig,
+};
 
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
 use stackable_operator::{
     builder::ObjectMetaBuilder,
-    commons::product_image_selection::{ProductImage, ResolvedProductImage},
+    commons::{
+        product_image_selection::{ProductImage, ResolvedProductImage},
+        resources::{
+            CpuLimitsFragment, MemoryLimitsFragment, NoRuntimeLimits, NoRuntimeLimitsFragment,
+            NoStorage, NoStorageFragment, Resources, ResourcesFragment,
+        },
+    },
     config::{
         fragment::{self, Fragment, ValidationError},
-        merge::Merge,
+        merge::{Atomic, Merge},
+    },
+    k8s_openapi::{
+        apimachinery::pkg::{api::resource::Quantity, apis::meta::v1::Time},
+        chrono::Utc,
     },
-    k8s_openapi::{apimachinery::pkg::apis::meta::v1::Time, chrono::Utc},
     kube::{CustomResource, ResourceExt},
     product_logging::{self, spec::Logging},
     schemars::{self, JsonSchema},
 };
+use std::time::Duration;
 use strum::{Display, EnumIter};
 
 pub const AIRFLOW_DB_CONTROLLER_NAME: &str = "odoo-db";
+/// Setting this annotation on an OdooDB which is `Ready` or `Failed` runs the initialization
+/// again. The value is recorded in the status, so it has to be changed to trigger another run.
+pub const REINITIALIZE_ANNOTATION: &str = "odoo.sovrin.cloud/reinitialize";
+/// The Odoo module which records the audit events, installed when audit logging is enabled.
+pub const AUDIT_LOG_MODULE: &str = "auditlog";
+
+const RETRY_BACKOFF_BASE: Duration = Duration::from_secs(30);
+const RETRY_BACKOFF_MAX: Duration = Duration::from_secs(30 * 60);
 
 #[derive(Snafu, Debug)]
 #[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[snafu(display("fragment validation failure"))]
     FragmentValidationFailure { source: ValidationError },
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::error::Error,
+    },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -47,7 +75,7 @@ pub enum Container {
     Vector,
 }
 
-#[derive(Clone, Debug, Default, Eq, Fragment, JsonSchema, PartialEq)]
+#[derive(Clone, Debug, Default, Fragment, JsonSchema, PartialEq)]
 #[fragment_attrs(
 derive(
 Clone,
@@ -62,18 +90,76 @@ Serialize
 serde(rename_all = "camelCase")
 )]
 pub struct OdooDbConfig {
+    /// The resources of the init container.
+    #[fragment_attrs(serde(default))]
+    pub resources: Resources<NoStorage, NoRuntimeLimits>,
     #[fragment_attrs(serde(default))]
     pub logging: Logging<Container>,
+    /// What happens to the OdooDB when the OdooCluster is deleted.
+    pub deletion_policy: DeletionPolicy,
+    /// Number of retries before the init job is considered failed. Defaults to 6.
+    pub backoff_limit: Option<i32>,
+    /// Number of times a failed initialization is started again with a new init job. The
+    /// attempts are delayed by an exponential backoff, starting at 30 seconds. Defaults to 3.
+    pub retry_limit: u32,
+    /// Time in seconds after which the init job is stopped and considered failed.
+    pub active_deadline_seconds: Option<i64>,
+    /// Time in seconds after which the finished init job is deleted. It must leave the operator
+    /// enough time to record the result, otherwise the initialization is started again.
+    pub ttl_seconds_after_finished: Option<i32>,
+    /// Odoo modules which are installed when the database is initialized, e.g. `sale` or
+    /// `website`. Modules can still be installed later on from within Odoo.
+    pub install_modules: Vec<String>,
+    /// Neutralizes the database after it is initialized, so that a copy of a production database
+    /// can be used safely for staging. This disables outgoing mail servers, scheduled actions and
+    /// payment providers.
+    pub neutralize: bool,
+    /// Name of a ConfigMap with scripts which are run after the database is initialized, e.g. to
+    /// seed company data. The scripts are run in the order of their names, depending on their
+    /// extension with bash (`.sh`), Python (`.py`) or against the database (`.sql`).
+    pub extra_init_scripts: Option<String>,
 }
 
 impl OdooDbConfig {
     fn default_config() -> OdooDbConfigFragment {
         OdooDbConfigFragment {
+            resources: ResourcesFragment {
+                cpu: CpuLimitsFragment {
+                    min: Some(Quantity("100m".to_owned())),
+                    max: Some(Quantity("400m".to_owned())),
+                },
+                memory: MemoryLimitsFragment {
+                    limit: Some(Quantity("512Mi".to_owned())),
+                    runtime_limits: NoRuntimeLimitsFragment {},
+                },
+                storage: NoStorageFragment {},
+            },
             logging: product_logging::spec::default_logging(),
+            deletion_policy: Some(DeletionPolicy::default()),
+            backoff_limit: None,
+            retry_limit: Some(3),
+            active_deadline_seconds: None,
+            ttl_seconds_after_finished: None,
+            install_modules: Some(Vec::new()),
+            neutralize: Some(false),
+            extra_init_scripts: None,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum DeletionPolicy {
+    /// Keep the OdooDB, so that the initialized database can be reused when the cluster is
+    /// created again.
+    #[default]
+    Retain,
+    /// Delete the OdooDB together with its init job and ConfigMaps when the cluster is deleted.
+    /// The database itself is not dropped.
+    Delete,
+}
+
+impl Atomic for DeletionPolicy {}
+
 #[derive(Clone, CustomResource, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[kube(
 group = "odoo.stackable.tech",
@@ -93,9 +179,23 @@ pub struct OdooDBSpec {
     /// The Odoo image to use
     pub image: ProductImage,
     pub credentials_secret: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_provider: Option<CredentialsProvider>,
+    #[serde(default)]
+    pub admin_user: AdminUserConfig,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub vector_aggregator_config_map_name: Option<String>,
     pub config: OdooDbConfigFragment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_policy: Option<ImagePolicy>,
+    #[serde(default)]
+    pub compliance: OdooComplianceConfig,
+    #[serde(default)]
+    pub database: OdooDatabaseConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_provisioning: Option<DatabaseProvisioning>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<CaBundle>,
 }
 
 impl OdooDB {
@@ -104,37 +204,54 @@ impl OdooDB {
         odoo: &OdooCluster,
         resolved_product_image: &ResolvedProductImage,
     ) -> Result<Self> {
+        let mut metadata = ObjectMetaBuilder::new();
+        metadata
+            .name_and_namespace(odoo)
+            .with_recommended_labels(build_recommended_labels(
+                odoo,
+                AIRFLOW_DB_CONTROLLER_NAME,
+                &resolved_product_image.product_version,
+                "db-initializer",
+                "global",
+            ));
+        // By default the db is deliberately not owned by the cluster so it doesn't get deleted
+        // when the cluster gets deleted. The schema etc. still exists in the database and can be
+        // reused when the cluster is created again.
+        if odoo.database_deletion_policy() == DeletionPolicy::Delete {
+            metadata
+                .ownerreference_from_resource(odoo, None, Some(true))
+                .context(ObjectMissingMetadataForOwnerRefSnafu)?;
+        }
+        let mut config = odoo
+            .spec
+            .cluster_config
+            .database_initialization
+            .clone()
+            .unwrap_or_default();
+        if odoo.spec.cluster_config.audit_logging.is_some() {
+            let install_modules = config.install_modules.get_or_insert_with(Vec::new);
+            if !install_modules.iter().any(|module| module == AUDIT_LOG_MODULE) {
+                install_modules.push(AUDIT_LOG_MODULE.to_string());
+            }
+        }
         Ok(Self {
-            // The db is deliberately not owned by the cluster so it doesn't get deleted when the
-            // cluster gets deleted.  The schema etc. still exists in the database and can be reused
-            // when the cluster is created again.
-            metadata: ObjectMetaBuilder::new()
-                .name_and_namespace(odoo)
-                .with_recommended_labels(build_recommended_labels(
-                    odoo,
-                    AIRFLOW_DB_CONTROLLER_NAME,
-                    &resolved_product_image.product_version,
-                    "db-initializer",
-                    "global",
-                ))
-                .build(),
+            metadata: metadata.build(),
             spec: OdooDBSpec {
                 image: odoo.spec.image.clone(),
                 credentials_secret: odoo.spec.cluster_config.credentials_secret.clone(),
+                credentials_provider: odoo.spec.cluster_config.credentials_provider.clone(),
+                admin_user: odoo.spec.cluster_config.admin_user.clone(),
                 vector_aggregator_config_map_name: odoo
                     .spec
                     .cluster_config
                     .vector_aggregator_config_map_name
                     .clone(),
-                config: OdooDbConfigFragment {
-                    logging: odoo
-                        .spec
-                        .cluster_config
-                        .database_initialization
-                        .clone()
-                        .unwrap_or_default()
-                        .logging,
-                },
+                config,
+                image_policy: odoo.spec.cluster_config.image_policy,
+                compliance: odoo.spec.cluster_config.compliance.clone(),
+                database: odoo.spec.cluster_config.database.clone(),
+                database_provisioning: odoo.spec.cluster_config.database_provisioning.clone(),
+                ca_bundle: odoo.spec.cluster_config.ca_bundle.clone(),
             },
             status: None,
         })
@@ -158,6 +275,14 @@ pub struct OdooDBStatus {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub started_at: Option<Time>,
     pub condition: OdooDBStatusCondition,
+    /// Value of the [`REINITIALIZE_ANNOTATION`] which triggered the current initialization.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reinitialization: Option<String>,
+    /// Number of times the failed initialization was started again.
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_at: Option<Time>,
 }
 
 impl OdooDBStatus {
@@ -165,6 +290,27 @@ impl OdooDBStatus {
         Self {
             started_at: Some(Time(Utc::now())),
             condition: OdooDBStatusCondition::Pending,
+            reinitialization: None,
+            retries: 0,
+            failed_at: None,
+        }
+    }
+
+    /// Returns the value of the [`REINITIALIZE_ANNOTATION`] if it requests a new initialization.
+    pub fn requested_reinitialization<'a>(&self, odoo_db: &'a OdooDB) -> Option<&'a String> {
+        match self.condition {
+            OdooDBStatusCondition::Ready | OdooDBStatusCondition::Failed => odoo_db
+                .annotations()
+                .get(REINITIALIZE_ANNOTATION)
+                .filter(|value| Some(*value) != self.reinitialization.as_ref()),
+            OdooDBStatusCondition::Pending | OdooDBStatusCondition::Initializing => None,
+        }
+    }
+
+    pub fn reinitializing(reinitialization: &str) -> Self {
+        Self {
+            reinitialization: Some(reinitialization.to_string()),
+            ..Self::new()
         }
     }
 
@@ -183,8 +329,50 @@ impl OdooDBStatus {
     pub fn failed(&self) -> Self {
         let mut new = self.clone();
         new.condition = OdooDBStatusCondition::Failed;
+        new.failed_at = Some(Time(Utc::now()));
         new
     }
+
+    /// Starts the current initialization again, without counting it as retry.
+    pub fn pending(&self) -> Self {
+        Self {
+            reinitialization: self.reinitialization.clone(),
+            retries: self.retries,
+            ..Self::new()
+        }
+    }
+
+    /// Starts the initialization again after it failed.
+    pub fn retrying(&self) -> Self {
+        Self {
+            reinitialization: self.reinitialization.clone(),
+            retries: self.retries + 1,
+            ..Self::new()
+        }
+    }
+
+    /// The delay between the failure and the next attempt, doubled with every retry.
+    pub fn retry_backoff(&self) -> Duration {
+        RETRY_BACKOFF_BASE
+            .saturating_mul(2u32.saturating_pow(self.retries))
+            .min(RETRY_BACKOFF_MAX)
+    }
+
+    /// The time to wait until a failed initialization is started again, or `None` if all
+    /// retries are used up.
+    pub fn retry_after(&self, retry_limit: u32) -> Option<Duration> {
+        (self.retries < retry_limit).then(|| self.remaining_retry_backoff())
+    }
+
+    /// The time left until the next attempt may be started.
+    pub fn remaining_retry_backoff(&self) -> Duration {
+        let elapsed = self
+            .failed_at
+            .as_ref()
+            .and_then(|failed_at| (Utc::now() - failed_at.0).to_std().ok())
+            .unwrap_or_default();
+        self.retry_backoff().saturating_sub(elapsed)
+    }
 }
 
 impl Default for OdooDBStatus {
@@ -199,4 +387,86 @@ pub enum OdooDBStatusCondition {
     Initializing,
     Ready,
     Failed,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff() {
+        let status = OdooDBStatus::new().failed();
+        assert_eq!(Duration::from_secs(30), status.retry_backoff());
+        assert!(status.remaining_retry_backoff() <= Duration::from_secs(30));
+
+        let status = status.retrying().failed().retrying().failed();
+        assert_eq!(2, status.retries);
+        assert_eq!(Duration::from_secs(120), status.retry_backoff());
+
+        let status = OdooDBStatus {
+            retries: 10,
+            ..status
+        };
+        assert_eq!(Duration::from_secs(30 * 60), status.retry_backoff());
+    }
+
+    #[test]
+    fn test_requested_reinitialization() {
+        let mut odoo_db: OdooDB = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooDB
+        metadata:
+          name: odoo
+          namespace: default
+          annotations:
+            odoo.sovrin.cloud/reinitialize: \"2023-06-01\"
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          credentialsSecret: simple-odoo-credentials
+          config: {}
+          ",
+        )
+        .unwrap();
+
+        let status = OdooDBStatus::new();
+        assert_eq!(None, status.requested_reinitialization(&odoo_db));
+        assert_eq!(None, status.initializing().requested_reinitialization(&odoo_db));
+        assert_eq!(
+            Some(&"2023-06-01".to_string()),
+            status.ready().requested_reinitialization(&odoo_db)
+        );
+        assert_eq!(
+            Some(&"2023-06-01".to_string()),
+            status.failed().requested_reinitialization(&odoo_db)
+        );
+
+        // The same value does not trigger another initialization
+        let status = OdooDBStatus::reinitializing("2023-06-01").ready();
+        assert_eq!(None, status.requested_reinitialization(&odoo_db));
+
+        odoo_db
+            .annotations_mut()
+            .insert(REINITIALIZE_ANNOTATION.to_string(), "2023-06-02".to_string());
+        assert_eq!(
+            Some(&"2023-06-02".to_string()),
+            status.requested_reinitialization(&odoo_db)
+        );
+    }
+
+    #[test]
+    fn test_pending() {
+        let status = OdooDBStatus {
+            retries: 2,
+            ..OdooDBStatus::reinitializing("2023-06-01")
+        }
+        .initializing();
+
+        let status = status.pending();
+        assert_eq!(OdooDBStatusCondition::Pending, status.condition);
+        assert_eq!(2, status.retries);
+        assert_eq!(Some("2023-06-01".to_string()), status.reinitialization);
+    }
+}
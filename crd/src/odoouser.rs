@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::{
+    k8s_openapi::{apimachinery::pkg::apis::meta::v1::Time, chrono::Utc},
+    kube::CustomResource,
+    schemars::{self, JsonSchema},
+};
+
+pub const AIRFLOW_USER_CONTROLLER_NAME: &str = "odoo-user";
+pub const PASSWORD_SECRET_KEY: &str = "password";
+
+/// A user of an [`crate::OdooCluster`]. The user is created in the database of the cluster by
+/// a Job, which runs again whenever the spec changes. The user is deleted from the database
+/// together with the OdooUser.
+#[derive(Clone, CustomResource, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[kube(
+group = "odoo.stackable.tech",
+version = "v1alpha1",
+kind = "OdooUser",
+plural = "odoousers",
+status = "OdooUserStatus",
+namespaced,
+crates(
+kube_core = "stackable_operator::kube::core",
+k8s_openapi = "stackable_operator::k8s_openapi",
+schemars = "stackable_operator::schemars"
+)
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooUserSpec {
+    /// Name of the OdooCluster in the same namespace the user is created in.
+    pub cluster_name: String,
+    pub login: String,
+    /// The full name of the user. The first word is used as first name, the rest as last name.
+    pub name: String,
+    pub email: String,
+    /// The role assigned to the user, e.g. `Admin`, `User` or `Viewer`.
+    #[serde(default = "default_role")]
+    pub role: String,
+    /// Name of the Secret containing the password of the user under the key `password`.
+    pub password_secret: String,
+}
+
+fn default_role() -> String {
+    "Viewer".to_string()
+}
+
+impl OdooUserSpec {
+    /// Splits the name into first and last name. The first name is reused if the name consists
+    /// of a single word, as both are required.
+    pub fn first_and_last_name(&self) -> (&str, &str) {
+        let name = self.name.trim();
+        match name.split_once(char::is_whitespace) {
+            Some((first_name, last_name)) => (first_name, last_name.trim_start()),
+            None => (name, name),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooUserStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<Time>,
+    pub condition: OdooUserStatusCondition,
+    /// The generation of the OdooUser the user in the database was last created from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+    /// The login the user in the database was last created with. A user with a changed login
+    /// is created anew, so the user with this login is deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub login: Option<String>,
+    /// The resource version of the password Secret the user was last created from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_secret_version: Option<String>,
+}
+
+impl OdooUserStatus {
+    pub fn new() -> Self {
+        Self {
+            started_at: Some(Time(Utc::now())),
+            condition: OdooUserStatusCondition::Pending,
+            observed_generation: None,
+            login: None,
+            password_secret_version: None,
+        }
+    }
+
+    /// The user has to be created again from the changed spec.
+    pub fn pending(&self) -> Self {
+        let mut new = self.clone();
+        new.condition = OdooUserStatusCondition::Pending;
+        new
+    }
+
+    pub fn creating(
+        &self,
+        generation: Option<i64>,
+        login: &str,
+        password_secret_version: Option<String>,
+    ) -> Self {
+        let mut new = self.clone();
+        new.condition = OdooUserStatusCondition::Creating;
+        new.observed_generation = generation;
+        new.login = Some(login.to_string());
+        new.password_secret_version = password_secret_version;
+        new
+    }
+
+    /// Whether the user was created from an older spec than the given generation or from an
+    /// older version of the password Secret.
+    pub fn is_outdated(
+        &self,
+        generation: Option<i64>,
+        password_secret_version: Option<&str>,
+    ) -> bool {
+        matches!(
+            self.condition,
+            OdooUserStatusCondition::Ready | OdooUserStatusCondition::Failed
+        ) && (self.observed_generation != generation
+            || self.password_secret_version.as_deref() != password_secret_version)
+    }
+
+    /// The login of a previously created user which differs from the given one and therefore
+    /// has to be deleted.
+    pub fn previous_login(&self, login: &str) -> Option<&str> {
+        self.login.as_deref().filter(|previous| *previous != login)
+    }
+
+    pub fn ready(&self) -> Self {
+        let mut new = self.clone();
+        new.condition = OdooUserStatusCondition::Ready;
+        new
+    }
+
+    pub fn failed(&self) -> Self {
+        let mut new = self.clone();
+        new.condition = OdooUserStatusCondition::Failed;
+        new
+    }
+}
+
+impl Default for OdooUserStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, JsonSchema, PartialEq, Serialize)]
+pub enum OdooUserStatusCondition {
+    Pending,
+    Creating,
+    Ready,
+    Failed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_and_last_name() {
+        let mut spec = OdooUserSpec {
+            cluster_name: "odoo".to_string(),
+            login: "jdoe".to_string(),
+            name: "Jane  van Doe".to_string(),
+            email: "jane@example.com".to_string(),
+            role: default_role(),
+            password_secret: "jdoe-password".to_string(),
+        };
+        assert_eq!(("Jane", "van Doe"), spec.first_and_last_name());
+
+        spec.name = "Jane".to_string();
+        assert_eq!(("Jane", "Jane"), spec.first_and_last_name());
+    }
+
+    #[test]
+    fn test_is_outdated() {
+        let status = OdooUserStatus::new().creating(Some(1), "jdoe", Some("10".to_string()));
+        // A running Job is awaited before the user is created again
+        assert!(!status.is_outdated(Some(2), Some("10")));
+
+        let status = status.ready();
+        assert!(!status.is_outdated(Some(1), Some("10")));
+        assert!(status.is_outdated(Some(2), Some("10")));
+        assert!(status.failed().is_outdated(Some(2), Some("10")));
+
+        let status = status.pending();
+        assert_eq!(OdooUserStatusCondition::Pending, status.condition);
+        assert!(!status.is_outdated(Some(2), Some("10")));
+    }
+
+    #[test]
+    fn test_changed_password_secret_is_outdated() {
+        let status = OdooUserStatus::new()
+            .creating(Some(1), "jdoe", Some("10".to_string()))
+            .ready();
+        assert!(status.is_outdated(Some(1), Some("11")));
+        // The user is also created again once a missing Secret was created
+        let status = OdooUserStatus::new()
+            .creating(Some(1), "jdoe", None)
+            .ready();
+        assert!(status.is_outdated(Some(1), Some("11")));
+    }
+
+    #[test]
+    fn test_previous_login() {
+        let status = OdooUserStatus::new();
+        assert_eq!(None, status.previous_login("jdoe"));
+
+        let status = status.creating(Some(1), "jdoe", None);
+        assert_eq!(None, status.previous_login("jdoe"));
+        assert_eq!(Some("jdoe"), status.previous_login("jane"));
+    }
+}
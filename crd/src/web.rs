@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use stackable_operator::schemars::{self, JsonSchema};
+use std::collections::BTreeMap;
+
+pub const WEB_BASE_URL_PARAMETER: &str = "web.base.url";
+pub const WEB_BASE_URL_FREEZE_PARAMETER: &str = "web.base.url.freeze";
+pub const REPORT_URL_PARAMETER: &str = "report.url";
+
+/// Settings concerning how the Odoo web interface is reached from the outside.
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooWebConfig {
+    /// The external URL of the web interface. Gets mapped to the (frozen) `web.base.url`
+    /// system parameter so generated links in reports and emails are correct.
+    /// If not specified it is derived from the Ingress host, if an Ingress is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// The URL the report engine uses to fetch assets from the webservers.
+    /// Gets mapped to the `report.url` system parameter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report_url: Option<String>,
+    /// Expose the webservers through an Ingress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingress: Option<OdooIngressConfig>,
+    /// Trust the `X-Forwarded-*` headers set by a reverse proxy in front of the webservers.
+    /// Gets mapped to `ENABLE_PROXY_FIX`
+    #[serde(default)]
+    pub proxy_mode: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdooIngressConfig {
+    /// The host name the Ingress is served on.
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingress_class_name: Option<String>,
+    /// Name of the Secret containing the TLS certificate for the host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_secret_name: Option<String>,
+    /// Redirect all plain HTTP requests to HTTPS.
+    #[serde(default)]
+    pub force_https: bool,
+    /// Additional annotations for the Ingress, e.g. for cert-manager.
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl OdooWebConfig {
+    /// Returns the Odoo system parameters (`ir.config_parameter`) derived from this config.
+    pub fn config_parameters(&self) -> BTreeMap<String, String> {
+        let mut parameters = BTreeMap::new();
+
+        let base_url = self.base_url.clone().or_else(|| {
+            self.ingress.as_ref().map(|ingress| {
+                let scheme = if ingress.force_https || ingress.tls_secret_name.is_some() {
+                    "https"
+                } else {
+                    "http"
+                };
+                format!("{scheme}://{host}", host = ingress.host)
+            })
+        });
+        if let Some(base_url) = base_url {
+            parameters.insert(WEB_BASE_URL_PARAMETER.to_string(), base_url);
+            // Otherwise Odoo overwrites the parameter with the URL an admin logs in from
+            parameters.insert(
+                WEB_BASE_URL_FREEZE_PARAMETER.to_string(),
+                "True".to_string(),
+            );
+        }
+        if let Some(report_url) = &self.report_url {
+            parameters.insert(REPORT_URL_PARAMETER.to_string(), report_url.clone());
+        }
+
+        parameters
+    }
+}
+
+/// Returns a shell command which writes the given system parameters into the Odoo database
+/// using `odoo shell`, or `None` if there is nothing to write.
+///
+/// The parameters are handed to Python as a JSON document, so neither the shell nor Python
+/// interprets quotes or other special characters in the keys and values.
+pub fn set_config_parameters_command(parameters: &BTreeMap<String, String>) -> Option<String> {
+    if parameters.is_empty() {
+        return None;
+    }
+    // Serializing a map of strings cannot fail
+    let parameters_json = serde_json::to_string(parameters).ok()?;
+    // A JSON string is also a valid Python string literal
+    let parameters_literal = serde_json::to_string(&parameters_json).ok()?;
+    let script = format!(
+        "import json
+for key, value in json.loads({parameters_literal}).items():
+    env['ir.config_parameter'].sudo().set_param(key, value)
+env.cr.commit()
+"
+    );
+    Some(format!(
+        "printf '%s' {script} | odoo shell --no-http",
+        script = shell_quote(&script)
+    ))
+}
+
+/// Quotes the given string as a single shell word.
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+impl OdooIngressConfig {
+    /// Returns the annotations for the Ingress including the ones needed for a forced HTTPS
+    /// redirect.
+    pub fn annotations(&self) -> BTreeMap<String, String> {
+        let mut annotations = self.annotations.clone();
+        if self.force_https {
+            annotations.insert(
+                "nginx.ingress.kubernetes.io/force-ssl-redirect".to_string(),
+                "true".to_string(),
+            );
+            annotations.insert(
+                "ingress.kubernetes.io/ssl-redirect".to_string(),
+                "true".to_string(),
+            );
+        }
+        annotations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_from_ingress() {
+        let web = OdooWebConfig {
+            report_url: Some("http://odoo-webserver:8080".to_string()),
+            ingress: Some(OdooIngressConfig {
+                host: "erp.example.com".to_string(),
+                force_https: true,
+                ..OdooIngressConfig::default()
+            }),
+            ..OdooWebConfig::default()
+        };
+
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    REPORT_URL_PARAMETER.to_string(),
+                    "http://odoo-webserver:8080".to_string()
+                ),
+                (
+                    WEB_BASE_URL_PARAMETER.to_string(),
+                    "https://erp.example.com".to_string()
+                ),
+                (
+                    WEB_BASE_URL_FREEZE_PARAMETER.to_string(),
+                    "True".to_string()
+                ),
+            ]),
+            web.config_parameters()
+        );
+    }
+
+    #[test]
+    fn test_set_config_parameters_command_quoting() {
+        assert_eq!(None, set_config_parameters_command(&BTreeMap::new()));
+
+        let command = set_config_parameters_command(&BTreeMap::from([(
+            "mail.catchall.alias".to_string(),
+            r#"it's "$(rm -rf /)" `id` \n"#.to_string(),
+        )]))
+        .unwrap();
+
+        assert_eq!(
+            r#"printf '%s' 'import json
+for key, value in json.loads("{\"mail.catchall.alias\":\"it'\''s \\\"$(rm -rf /)\\\" `id` \\\\n\"}").items():
+    env['\''ir.config_parameter'\''].sudo().set_param(key, value)
+env.cr.commit()
+' | odoo shell --no-http"#,
+            command
+        );
+    }
+}
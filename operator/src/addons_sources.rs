@@ -0,0 +1,236 @@
+//! Provides the addons from archives if requested in `clusterConfig.addonsOci` or
+//! `clusterConfig.addonsBucket`.
+//!
+//! The archives are downloaded by an init container running the client of the source and
+//! unpacked by a second one running the product image, as the clients are not part of it. Every
+//! source has its own download and addons directory, so they can be combined and ordered in the
+//! `addons_path` with `clusterConfig.addonsPathOrder`.
+//!
+//! The checksums and signatures are verified by init containers named with
+//! `ADDONS_VERIFY_CONTAINER_PREFIX`, whose termination messages are reported as
+//! `status.addons.<rolegroup>.verificationFailure` by [`crate::addons_status`].
+//!
+//! The packages of `clusterConfig.addonsPip` are installed by a separate init container into
+//! their own volume, as they are Python packages rather than archives.
+use sovrin_cloud_crd::{
+    addons::{
+        pip_install_command, verify_checksum_command, AddonsBucket, AddonsOci, ADDONS_DIR,
+        ADDONS_DOWNLOAD_DIR, ADDONS_DOWNLOAD_VOLUME_NAME, ADDONS_PIP_DIR, ADDONS_PIP_VOLUME_NAME,
+        ADDONS_VERIFY_CONTAINER_PREFIX, ADDONS_VOLUME_NAME, BUCKET_ACCESS_KEY, BUCKET_SECRET_KEY,
+        REGISTRY_CONFIG_DIR,
+    },
+    OdooCluster,
+};
+use stackable_operator::{
+    builder::{
+        resources::ResourceRequirementsBuilder, ContainerBuilder, PodBuilder, VolumeBuilder,
+    },
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::api::core::v1::{
+        Container, EmptyDirVolumeSource, ResourceRequirements, Volume, VolumeMount,
+    },
+};
+
+use crate::utils::env_var_from_secret;
+
+/// Whether the pods of the cluster unpack addons into `ADDONS_DIR`.
+pub fn provides_addons(odoo: &OdooCluster) -> bool {
+    let cluster_config = &odoo.spec.cluster_config;
+    cluster_config.addons_oci.is_some() || cluster_config.addons_bucket.is_some()
+}
+
+/// Adds the init containers downloading and unpacking the addons and their volumes. The Odoo
+/// containers must mount `ADDONS_VOLUME_NAME` to `ADDONS_DIR`.
+pub fn add_addons_containers(
+    pb: &mut PodBuilder,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+) -> Result<(), stackable_operator::error::Error> {
+    if !provides_addons(odoo) {
+        return Ok(());
+    }
+    let cluster_config = &odoo.spec.cluster_config;
+    let mut verify_commands = vec![];
+    let mut unpack_commands = vec![];
+
+    if let Some(addons_oci) = &cluster_config.addons_oci {
+        let registry_config = addons_oci.volumes();
+        if let Some(cosign) = &addons_oci.cosign {
+            let (key_volume, key_dir) = cosign.volume();
+            let mut verify_container =
+                ContainerBuilder::new(&format!("{ADDONS_VERIFY_CONTAINER_PREFIX}-signature"))?;
+            verify_container
+                .image(&cosign.image)
+                .args(addons_oci.verify_signature_args())
+                .add_env_var("HOME", "/tmp")
+                .add_volume_mount(ADDONS_DOWNLOAD_VOLUME_NAME, ADDONS_DOWNLOAD_DIR)
+                .add_volume_mounts(vec![read_only_mount(&key_volume, key_dir)])
+                .resources(small_resources());
+            if !registry_config.is_empty() {
+                verify_container.add_env_var("DOCKER_CONFIG", REGISTRY_CONFIG_DIR);
+            }
+            for (volume, mount_path) in &registry_config {
+                verify_container.add_volume_mounts(vec![read_only_mount(volume, mount_path)]);
+            }
+            pb.add_init_container(with_logs_as_termination_message(verify_container.build()));
+            pb.add_volume(key_volume);
+        }
+
+        let mut pull_container = ContainerBuilder::new("addons-pull")?;
+        pull_container
+            .image(&addons_oci.image)
+            .command(vec!["/bin/sh".to_string(), "-c".to_string()])
+            .args(vec![addons_oci.pull_command()])
+            .add_volume_mount(ADDONS_DOWNLOAD_VOLUME_NAME, ADDONS_DOWNLOAD_DIR)
+            .resources(small_resources());
+        for (volume, mount_path) in registry_config {
+            pull_container.add_volume_mounts(vec![read_only_mount(&volume, mount_path)]);
+            pb.add_volume(volume);
+        }
+        pb.add_init_container(pull_container.build());
+        if let Some(sha256) = &addons_oci.sha256 {
+            verify_commands.push(verify_checksum_command(&AddonsOci::download_dir(), sha256));
+        }
+        unpack_commands.push(addons_oci.media_type.unpack_command());
+    }
+
+    if let Some(addons_bucket) = &cluster_config.addons_bucket {
+        pb.add_init_container(build_bucket_download_container(addons_bucket)?);
+        if let Some(sha256) = &addons_bucket.sha256 {
+            verify_commands.push(verify_checksum_command(
+                &AddonsBucket::download_dir(),
+                sha256,
+            ));
+        }
+        unpack_commands.push(addons_bucket.unpack_command());
+    }
+
+    if !verify_commands.is_empty() {
+        let verify_container =
+            ContainerBuilder::new(&format!("{ADDONS_VERIFY_CONTAINER_PREFIX}-checksum"))?
+                .image_from_product_image(resolved_product_image)
+                .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+                .args(vec![verify_commands.join("; ")])
+                .add_volume_mount(ADDONS_DOWNLOAD_VOLUME_NAME, ADDONS_DOWNLOAD_DIR)
+                .resources(small_resources())
+                .build();
+        pb.add_init_container(with_logs_as_termination_message(verify_container));
+    }
+
+    // Each unpack command exits the shell on failure
+    pb.add_init_container(
+        ContainerBuilder::new("addons-unpack")?
+            .image_from_product_image(resolved_product_image)
+            .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+            .args(vec![unpack_commands.join("; ")])
+            .add_volume_mount(ADDONS_DOWNLOAD_VOLUME_NAME, ADDONS_DOWNLOAD_DIR)
+            .add_volume_mount(ADDONS_VOLUME_NAME, ADDONS_DIR)
+            .resources(small_resources())
+            .build(),
+    );
+
+    for volume_name in [ADDONS_DOWNLOAD_VOLUME_NAME, ADDONS_VOLUME_NAME] {
+        pb.add_volume(
+            VolumeBuilder::new(volume_name)
+                .empty_dir(EmptyDirVolumeSource::default())
+                .build(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether the pods of the cluster install addons into `ADDONS_PIP_DIR`.
+pub fn installs_pip_addons(odoo: &OdooCluster) -> bool {
+    !odoo.spec.cluster_config.addons_pip.is_empty()
+}
+
+/// Adds the init container installing the packages of `addonsPip` and its volume. The Odoo
+/// containers must mount `ADDONS_PIP_VOLUME_NAME` to `ADDONS_PIP_DIR`.
+pub fn add_pip_container(
+    pb: &mut PodBuilder,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+) -> Result<(), stackable_operator::error::Error> {
+    if !installs_pip_addons(odoo) {
+        return Ok(());
+    }
+    pb.add_init_container(
+        ContainerBuilder::new("addons-pip")?
+            .image_from_product_image(resolved_product_image)
+            .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+            .args(vec![pip_install_command(
+                &odoo.spec.cluster_config.addons_pip,
+            )])
+            .add_volume_mount(ADDONS_PIP_VOLUME_NAME, ADDONS_PIP_DIR)
+            .resources(
+                ResourceRequirementsBuilder::new()
+                    .with_cpu_request("200m")
+                    .with_cpu_limit("1000m")
+                    .with_memory_request("512Mi")
+                    .with_memory_limit("512Mi")
+                    .build(),
+            )
+            .build(),
+    );
+    pb.add_volume(
+        VolumeBuilder::new(ADDONS_PIP_VOLUME_NAME)
+            .empty_dir(EmptyDirVolumeSource::default())
+            .build(),
+    );
+    Ok(())
+}
+
+fn build_bucket_download_container(
+    addons_bucket: &AddonsBucket,
+) -> Result<Container, stackable_operator::error::Error> {
+    let mut download_container = ContainerBuilder::new("addons-download")?;
+    download_container
+        .image(&addons_bucket.image)
+        .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+        .args(vec![addons_bucket.download_command()])
+        .add_env_var("HOME", "/tmp")
+        .add_volume_mount(ADDONS_DOWNLOAD_VOLUME_NAME, ADDONS_DOWNLOAD_DIR)
+        .resources(small_resources());
+    if let Some(region) = &addons_bucket.region {
+        download_container.add_env_var("AWS_DEFAULT_REGION", region);
+    }
+    if let Some(credentials_secret) = &addons_bucket.credentials_secret {
+        download_container.add_env_vars(vec![
+            env_var_from_secret("AWS_ACCESS_KEY_ID", credentials_secret, BUCKET_ACCESS_KEY),
+            env_var_from_secret(
+                "AWS_SECRET_ACCESS_KEY",
+                credentials_secret,
+                BUCKET_SECRET_KEY,
+            ),
+        ]);
+    }
+    Ok(download_container.build())
+}
+
+fn read_only_mount(volume: &Volume, mount_path: &str) -> VolumeMount {
+    VolumeMount {
+        name: volume.name.clone(),
+        mount_path: mount_path.to_string(),
+        read_only: Some(true),
+        ..VolumeMount::default()
+    }
+}
+
+/// The error of a failed container is reported as its termination message, which is shown in the
+/// status of the pod.
+fn with_logs_as_termination_message(container: Container) -> Container {
+    Container {
+        termination_message_policy: Some("FallbackToLogsOnError".to_string()),
+        ..container
+    }
+}
+
+fn small_resources() -> ResourceRequirements {
+    ResourceRequirementsBuilder::new()
+        .with_cpu_request("100m")
+        .with_cpu_limit("400m")
+        .with_memory_request("128Mi")
+        .with_memory_limit("128Mi")
+        .build()
+}
@@ -0,0 +1,776 @@
+//! Reports the addon revisions synced by git-sync as `status.addons`, so it can be confirmed
+//! which revision is live. Every pod is annotated with the revision its git-sync container has
+//! synced. The running container serves it, in the mode once it is the termination message of the
+//! container, see [`crate::git_sync`]. Pods mounting `gitSync.sharedVolume` have the revision of
+//! the shared git-sync pod.
+//!
+//! With `gitSync.restartOnChange`, the workload of a rolegroup is restarted as soon as all of
+//! its pods have synced a new revision. The restarted pods sync the same revision again, so the
+//! restart is only repeated for the next revision.
+//!
+//! Addons downloaded as archives are not synced, but the failures of their verification are
+//! reported, see [`crate::addons_sources`].
+//!
+//! Whether git-sync is up is queried from its health endpoint via the API server, which fails
+//! while the last sync failed. The readiness of the pods is not affected by it.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::Serialize;
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{
+    addons::ADDONS_VERIFY_CONTAINER_PREFIX, AddonsStatus, OdooCluster, OdooRole, APP_NAME,
+    GIT_SYNC_HTTP_PORT, GIT_SYNC_HTTP_PORT_NAME, GIT_SYNC_NAME, GIT_SYNC_REVISION_PORT,
+    GIT_SYNC_REVISION_PORT_NAME, RESTARTED_FOR_REVISION_ANNOTATION, SYNCED_REVISION_ANNOTATION,
+};
+use stackable_operator::{
+    client::Client,
+    k8s_openapi::{
+        api::{
+            apps::v1::{Deployment, StatefulSet},
+            core::v1::{Pod, PodTemplateSpec},
+        },
+        apimachinery::pkg::apis::meta::v1::ObjectMeta,
+        http::Request,
+    },
+    kube::{
+        api::{ListParams, Patch, PatchParams},
+        runtime::reflector::{ObjectRef, Store},
+        Api, ResourceExt,
+    },
+    labels::{APP_COMPONENT_LABEL, APP_INSTANCE_LABEL, APP_NAME_LABEL, APP_ROLE_GROUP_LABEL},
+    role_utils::RoleGroupRef,
+    status::condition::ClusterConditionStatus,
+};
+
+use crate::addons_sources;
+
+const FIELD_MANAGER: &str = "odoo-addons-status";
+const REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to list the pods of the cluster"))]
+    ListPods {
+        source: stackable_operator::kube::Error,
+    },
+    #[snafu(display("failed to annotate the pod [{name}] with the synced revision"))]
+    AnnotatePod {
+        source: stackable_operator::kube::Error,
+        name: String,
+    },
+    #[snafu(display("failed to restart the workload [{name}]"))]
+    RestartWorkload {
+        source: stackable_operator::kube::Error,
+        name: String,
+    },
+    #[snafu(display("failed to apply the addons status"))]
+    ApplyStatus {
+        source: stackable_operator::error::Error,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Only this field is applied, so the status written by the reconciliation is left untouched.
+#[derive(Serialize)]
+struct AddonsStatusPatch<'a> {
+    addons: &'a BTreeMap<String, AddonsStatus>,
+}
+
+/// Only the annotation of the synced revision is patched.
+#[derive(Serialize)]
+struct SyncedRevisionPatch {
+    metadata: ObjectMeta,
+}
+
+/// Only the annotation of the pod template is patched, like `kubectl rollout restart` does.
+#[derive(Serialize)]
+struct RestartPatch {
+    spec: RestartPatchSpec,
+}
+
+#[derive(Serialize)]
+struct RestartPatchSpec {
+    template: PodTemplateSpec,
+}
+
+/// Reports the synced revisions of the clusters in the store until the operator is stopped.
+pub async fn run(client: Client, store: Store<OdooCluster>) {
+    let mut interval = tokio::time::interval(REPORT_INTERVAL);
+    loop {
+        interval.tick().await;
+        futures::future::join_all(
+            store
+                .state()
+                .into_iter()
+                .map(|odoo| report_cluster(&client, odoo)),
+        )
+        .await;
+    }
+}
+
+async fn report_cluster(client: &Client, odoo: Arc<OdooCluster>) {
+    if let Err(error) = report(client, &odoo).await {
+        tracing::warn!(
+            odoo = odoo.name_any(),
+            error = &error as &dyn std::error::Error,
+            "Failed to report the synced addon revisions"
+        );
+    }
+}
+
+async fn report(client: &Client, odoo: &OdooCluster) -> Result<()> {
+    // Dedicated git-sync images do not report the synced revisions, but serve their health
+    if !odoo.git_sync().map_or(false, |git_sync| {
+        git_sync.runs_hook() || git_sync.http_enabled()
+    }) && !addons_sources::provides_addons(odoo)
+    {
+        return Ok(());
+    }
+
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let selector = format!(
+        "{APP_NAME_LABEL}={APP_NAME},{APP_INSTANCE_LABEL}={}",
+        odoo.name_any()
+    );
+    let mut pods = Api::<Pod>::namespaced(client.as_kube_client(), &namespace)
+        .list(&ListParams::default().labels(&selector))
+        .await
+        .context(ListPodsSnafu)?
+        .items;
+
+    annotate_synced_revisions(client, &namespace, &mut pods).await?;
+    let git_sync_ups = probe_git_sync(client, &namespace, &pods).await;
+    let addons = addons_statuses(odoo, &pods, &git_sync_ups);
+    if odoo
+        .git_sync()
+        .map_or(false, |git_sync| git_sync.restarts_on_change())
+    {
+        for (name, status) in &addons {
+            if let Some(revision) = &status.synced_revision {
+                restart_if_changed(client, &namespace, name, revision).await?;
+            }
+        }
+    }
+    if odoo.status.as_ref().map(|status| &status.addons) == Some(&addons) {
+        return Ok(());
+    }
+    client
+        .apply_patch_status(FIELD_MANAGER, odoo, &AddonsStatusPatch { addons: &addons })
+        .await
+        .context(ApplyStatusSnafu)?;
+    Ok(())
+}
+
+/// Restarts the StatefulSet or Deployment of the rolegroup if its pods were not restarted for the
+/// revision yet. This includes the first synced revision, as Odoo may have started before the
+/// addons were synced.
+async fn restart_if_changed(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    revision: &str,
+) -> Result<()> {
+    let statefulsets = Api::<StatefulSet>::namespaced(client.as_kube_client(), namespace);
+    let deployments = Api::<Deployment>::namespaced(client.as_kube_client(), namespace);
+    let (template, is_deployment) = match statefulsets
+        .get_opt(name)
+        .await
+        .context(RestartWorkloadSnafu { name })?
+    {
+        Some(statefulset) => (statefulset.spec.map(|spec| spec.template), false),
+        None => match deployments
+            .get_opt(name)
+            .await
+            .context(RestartWorkloadSnafu { name })?
+        {
+            Some(deployment) => (deployment.spec.map(|spec| spec.template), true),
+            None => return Ok(()),
+        },
+    };
+    let restarted_for_revision = template
+        .and_then(|template| template.metadata)
+        .and_then(|metadata| metadata.annotations)
+        .and_then(|annotations| annotations.get(RESTARTED_FOR_REVISION_ANNOTATION).cloned());
+    if restarted_for_revision.as_deref() == Some(revision) {
+        return Ok(());
+    }
+
+    tracing::info!(
+        workload = name,
+        revision,
+        "Restarting for the synced revision"
+    );
+    let patch = RestartPatch {
+        spec: RestartPatchSpec {
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    annotations: Some(BTreeMap::from([(
+                        RESTARTED_FOR_REVISION_ANNOTATION.to_string(),
+                        revision.to_string(),
+                    )])),
+                    ..ObjectMeta::default()
+                }),
+                spec: None,
+            },
+        },
+    };
+    let patch_params = PatchParams::default();
+    let patch = Patch::Merge(&patch);
+    if is_deployment {
+        deployments
+            .patch(name, &patch_params, &patch)
+            .await
+            .map(|_| ())
+    } else {
+        statefulsets
+            .patch(name, &patch_params, &patch)
+            .await
+            .map(|_| ())
+    }
+    .context(RestartWorkloadSnafu { name })?;
+    Ok(())
+}
+
+/// The status of every rolegroup providing addons. The synced revision is only set if all pods
+/// agree on one. With a shared volume, the pods have the revision of the shared git-sync pod.
+fn addons_statuses(
+    odoo: &OdooCluster,
+    pods: &[Pod],
+    git_sync_ups: &BTreeMap<String, ClusterConditionStatus>,
+) -> BTreeMap<String, AddonsStatus> {
+    let provides_addons = addons_sources::provides_addons(odoo);
+    let shared_pod = odoo
+        .git_sync()
+        .filter(|git_sync| git_sync.uses_shared_volume())
+        .map(|_| {
+            pods.iter().find(|pod| {
+                pod.labels().get(APP_COMPONENT_LABEL).map(String::as_str) == Some(GIT_SYNC_NAME)
+            })
+        });
+    let shared_revision =
+        shared_pod.map(|pod| pod.and_then(|pod| pod.annotations().get(SYNCED_REVISION_ANNOTATION)));
+    let git_sync_up = |pod: &Pod| {
+        git_sync_ups
+            .get(&pod.name_any())
+            .copied()
+            .unwrap_or(ClusterConditionStatus::Unknown)
+    };
+    let shared_git_sync_up =
+        shared_pod.map(|pod| pod.map_or(ClusterConditionStatus::Unknown, git_sync_up));
+    let mut revisions = BTreeMap::<String, BTreeSet<Option<&String>>>::new();
+    let mut git_sync_ups = BTreeMap::<String, ClusterConditionStatus>::new();
+    let mut verification_failures = BTreeMap::<String, Option<String>>::new();
+    for pod in pods {
+        let labels = pod.labels();
+        let (Some(role), Some(role_group)) = (
+            labels.get(APP_COMPONENT_LABEL),
+            labels.get(APP_ROLE_GROUP_LABEL),
+        ) else {
+            continue;
+        };
+        let git_sync = OdooRole::from_str(role)
+            .ok()
+            .and_then(|odoo_role| odoo.git_sync_for(&odoo_role));
+        let syncs_addons = git_sync.is_some();
+        if !syncs_addons && !provides_addons {
+            continue;
+        }
+        let rolegroup = RoleGroupRef {
+            cluster: ObjectRef::from_obj(odoo),
+            role: role.clone(),
+            role_group: role_group.clone(),
+        }
+        .object_name();
+        if syncs_addons {
+            revisions.entry(rolegroup.clone()).or_default().insert(
+                shared_revision
+                    .unwrap_or_else(|| pod.annotations().get(SYNCED_REVISION_ANNOTATION)),
+            );
+        }
+        if git_sync.map_or(false, |git_sync| git_sync.http_enabled()) {
+            let pod_git_sync_up = shared_git_sync_up.unwrap_or_else(|| git_sync_up(pod));
+            let rolegroup_git_sync_up = git_sync_ups
+                .entry(rolegroup.clone())
+                .or_insert(ClusterConditionStatus::True);
+            *rolegroup_git_sync_up = worst(*rolegroup_git_sync_up, pod_git_sync_up);
+        }
+        let failure = verification_failures.entry(rolegroup).or_default();
+        if failure.is_none() && provides_addons {
+            *failure = verification_failure(pod);
+        }
+    }
+
+    verification_failures
+        .into_iter()
+        .map(|(rolegroup, verification_failure)| {
+            let synced_revision = match revisions.remove(&rolegroup) {
+                Some(revisions) if revisions.len() == 1 => {
+                    revisions.into_iter().next().flatten().cloned()
+                }
+                _ => None,
+            };
+            let git_sync_up = git_sync_ups.remove(&rolegroup);
+            (
+                rolegroup,
+                AddonsStatus {
+                    synced_revision,
+                    verification_failure,
+                    git_sync_up,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Annotates the pods with the revision their git-sync container synced last. The pods cannot
+/// patch themselves, they would need to be allowed to patch every pod in the namespace.
+async fn annotate_synced_revisions(
+    client: &Client,
+    namespace: &str,
+    pods: &mut [Pod],
+) -> Result<()> {
+    let mut fetched_revisions = fetch_synced_revisions(client, namespace, pods).await;
+    let api = Api::<Pod>::namespaced(client.as_kube_client(), namespace);
+    for pod in pods {
+        let name = pod.name_any();
+        // The init container of a continuously syncing pod only reported the first revision
+        let revision = if has_port(pod, GIT_SYNC_REVISION_PORT_NAME) {
+            fetched_revisions.remove(&name)
+        } else {
+            terminated_revision(pod)
+        };
+        let Some(revision) = revision else {
+            continue;
+        };
+        if pod.annotations().get(SYNCED_REVISION_ANNOTATION) == Some(&revision) {
+            continue;
+        }
+        let patch = SyncedRevisionPatch {
+            metadata: ObjectMeta {
+                annotations: Some(BTreeMap::from([(
+                    SYNCED_REVISION_ANNOTATION.to_string(),
+                    revision.clone(),
+                )])),
+                ..ObjectMeta::default()
+            },
+        };
+        api.patch(&name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+            .context(AnnotatePodSnafu { name: name.clone() })?;
+        pod.annotations_mut()
+            .insert(SYNCED_REVISION_ANNOTATION.to_string(), revision);
+    }
+    Ok(())
+}
+
+/// Fetches the revision synced last from the running pods serving it, by the name of the pod.
+async fn fetch_synced_revisions(
+    client: &Client,
+    namespace: &str,
+    pods: &[Pod],
+) -> BTreeMap<String, String> {
+    let fetches = pods
+        .iter()
+        .filter(|pod| has_port(pod, GIT_SYNC_REVISION_PORT_NAME) && is_running(pod))
+        .map(|pod| async move {
+            let name = pod.name_any();
+            let request = Request::get(format!(
+                "/api/v1/namespaces/{namespace}/pods/{name}:{GIT_SYNC_REVISION_PORT}/proxy/"
+            ))
+            .body(Vec::new())
+            .ok()?;
+            // Fails until the first revision is synced
+            let revision = client.as_kube_client().request_text(request).await.ok()?;
+            Some((name, revision.trim().to_string()))
+        });
+    futures::future::join_all(fetches)
+        .await
+        .into_iter()
+        .flatten()
+        .filter(|(_, revision)| !revision.is_empty())
+        .collect()
+}
+
+/// The revision a git-sync container of the mode once left as its termination message.
+fn terminated_revision(pod: &Pod) -> Option<String> {
+    let container_statuses = pod.status.as_ref()?.init_container_statuses.as_ref()?;
+    container_statuses
+        .iter()
+        .filter(|status| status.name.starts_with(GIT_SYNC_NAME))
+        .filter_map(|status| status.state.as_ref()?.terminated.as_ref())
+        .filter(|terminated| terminated.exit_code == 0)
+        .find_map(|terminated| {
+            let revision = terminated.message.as_deref()?.trim();
+            (!revision.is_empty()).then(|| revision.to_string())
+        })
+}
+
+/// Queries the health endpoint of the running pods serving one, by the name of the pod.
+async fn probe_git_sync(
+    client: &Client,
+    namespace: &str,
+    pods: &[Pod],
+) -> BTreeMap<String, ClusterConditionStatus> {
+    let probes = pods
+        .iter()
+        .filter(|pod| has_port(pod, GIT_SYNC_HTTP_PORT_NAME) && is_running(pod))
+        .map(|pod| async move {
+            let name = pod.name_any();
+            let up = match Request::get(format!(
+                "/api/v1/namespaces/{namespace}/pods/{name}:{GIT_SYNC_HTTP_PORT}/proxy/"
+            ))
+            .body(Vec::new())
+            {
+                // The endpoint fails while the last sync failed
+                Ok(request) => match client.as_kube_client().request_text(request).await {
+                    Ok(_) => ClusterConditionStatus::True,
+                    Err(_) => ClusterConditionStatus::False,
+                },
+                Err(_) => ClusterConditionStatus::Unknown,
+            };
+            (name, up)
+        });
+    futures::future::join_all(probes)
+        .await
+        .into_iter()
+        .collect()
+}
+
+fn has_port(pod: &Pod, port_name: &str) -> bool {
+    pod.spec
+        .iter()
+        .flat_map(|spec| &spec.containers)
+        .flat_map(|container| container.ports.iter().flatten())
+        .any(|port| port.name.as_deref() == Some(port_name))
+}
+
+fn is_running(pod: &Pod) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.phase.as_deref())
+        == Some("Running")
+}
+
+fn worst(a: ClusterConditionStatus, b: ClusterConditionStatus) -> ClusterConditionStatus {
+    match (a, b) {
+        (ClusterConditionStatus::False, _) | (_, ClusterConditionStatus::False) => {
+            ClusterConditionStatus::False
+        }
+        (ClusterConditionStatus::Unknown, _) | (_, ClusterConditionStatus::Unknown) => {
+            ClusterConditionStatus::Unknown
+        }
+        _ => ClusterConditionStatus::True,
+    }
+}
+
+/// The termination message of the first verification container of the pod which failed. The
+/// last failure is kept while a restarted container is waiting.
+fn verification_failure(pod: &Pod) -> Option<String> {
+    let container_statuses = pod.status.as_ref()?.init_container_statuses.as_ref()?;
+    container_statuses
+        .iter()
+        .filter(|status| status.name.starts_with(ADDONS_VERIFY_CONTAINER_PREFIX))
+        .find_map(|status| {
+            let terminated = match status
+                .state
+                .as_ref()
+                .and_then(|state| state.terminated.as_ref())
+            {
+                Some(terminated) => terminated,
+                None => status.last_state.as_ref()?.terminated.as_ref()?,
+            };
+            if terminated.exit_code == 0 {
+                return None;
+            }
+            let message = terminated
+                .message
+                .as_deref()
+                .or(terminated.reason.as_deref())
+                .unwrap_or_default();
+            Some(format!("{}: {}", status.name, message.trim()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sovrin_cloud_crd::{GitSyncMode, GitSyncSharedVolume};
+
+    fn pod(role: &str, role_group: &str, revision: Option<&str>) -> Pod {
+        Pod {
+            metadata: ObjectMeta {
+                labels: Some(BTreeMap::from([
+                    (APP_COMPONENT_LABEL.to_string(), role.to_string()),
+                    (APP_ROLE_GROUP_LABEL.to_string(), role_group.to_string()),
+                ])),
+                annotations: revision.map(|revision| {
+                    BTreeMap::from([(SYNCED_REVISION_ANNOTATION.to_string(), revision.to_string())])
+                }),
+                ..ObjectMeta::default()
+            },
+            ..Pod::default()
+        }
+    }
+
+    #[test]
+    fn test_verification_failure() {
+        let mut pod = pod("webserver", "default", None);
+        pod.status = Some(serde_yaml::from_str(
+            "
+            initContainerStatuses:
+              - name: addons-pull
+                image: ghcr.io/oras-project/oras:v1.1.0
+                imageID: ''
+                ready: true
+                restartCount: 0
+                state:
+                  terminated:
+                    exitCode: 0
+              - name: addons-verify-checksum
+                image: docker.stackable.tech/stackable/odoo:2.6.1-stackable0.0.0-dev
+                imageID: ''
+                ready: false
+                restartCount: 1
+                state:
+                  waiting:
+                    reason: CrashLoopBackOff
+                lastState:
+                  terminated:
+                    exitCode: 1
+                    message: |
+                      The SHA-256 checksum of /stackable/addons-download/oci/addons.tar.gz is 5d1e2a9, expected c639218
+            ",
+        )
+        .unwrap());
+
+        assert_eq!(
+            Some(
+                "addons-verify-checksum: The SHA-256 checksum of \
+                /stackable/addons-download/oci/addons.tar.gz is 5d1e2a9, expected c639218"
+                    .to_string()
+            ),
+            verification_failure(&pod)
+        );
+    }
+
+    #[test]
+    fn test_synced_revisions() {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooCluster
+            metadata:
+              name: odoo
+              namespace: default
+            spec:
+              image:
+                productVersion: 2.6.1
+                stackableVersion: 0.0.0-dev
+              clusterConfig:
+                credentialsSecret: simple-odoo-credentials
+                addonsGitSync:
+                  - repo: https://github.com/stackabletech/odoo-operator
+                    roles:
+                      - webserver
+            ",
+        )
+        .unwrap();
+        let pods = [
+            pod("webserver", "default", Some("c639218")),
+            pod("webserver", "default", Some("c639218")),
+            pod("webserver", "large", Some("c639218")),
+            pod("webserver", "large", Some("5d1e2a9")),
+            pod("webserver", "small", None),
+            pod("scheduler", "default", None),
+        ];
+
+        let addons = addons_statuses(&odoo, &pods, &BTreeMap::new());
+        assert_eq!(
+            Some("c639218".to_string()),
+            addons["odoo-webserver-default"].synced_revision
+        );
+        assert_eq!(None, addons["odoo-webserver-large"].synced_revision);
+        assert_eq!(None, addons["odoo-webserver-small"].synced_revision);
+        assert!(!addons.contains_key("odoo-scheduler-default"));
+        assert_eq!(
+            Some(ClusterConditionStatus::Unknown),
+            addons["odoo-webserver-default"].git_sync_up
+        );
+
+        odoo.spec.cluster_config.addons_git_sync[0].shared_volume = Some(GitSyncSharedVolume {
+            claim_name: "odoo-addons".to_string(),
+        });
+        let pods = [
+            pod("gitsync", "shared", Some("5d1e2a9")),
+            pod("webserver", "large", None),
+        ];
+        assert_eq!(
+            Some("5d1e2a9".to_string()),
+            addons_statuses(&odoo, &pods, &BTreeMap::new())["odoo-webserver-large"].synced_revision
+        );
+    }
+
+    #[test]
+    fn test_git_sync_up() {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooCluster
+            metadata:
+              name: odoo
+              namespace: default
+            spec:
+              image:
+                productVersion: 2.6.1
+                stackableVersion: 0.0.0-dev
+              clusterConfig:
+                credentialsSecret: simple-odoo-credentials
+                addonsGitSync:
+                  - repo: https://github.com/stackabletech/odoo-operator
+            ",
+        )
+        .unwrap();
+        let named_pod = |name: &str, role: &str, role_group: &str| {
+            let mut pod = pod(role, role_group, None);
+            pod.metadata.name = Some(name.to_string());
+            pod
+        };
+        let pods = [
+            named_pod("odoo-webserver-default-0", "webserver", "default"),
+            named_pod("odoo-webserver-default-1", "webserver", "default"),
+            named_pod("odoo-webserver-large-0", "webserver", "large"),
+            named_pod("odoo-worker-default-0", "worker", "default"),
+        ];
+        let git_sync_ups = BTreeMap::from([
+            (
+                "odoo-webserver-default-0".to_string(),
+                ClusterConditionStatus::True,
+            ),
+            (
+                "odoo-webserver-default-1".to_string(),
+                ClusterConditionStatus::False,
+            ),
+            (
+                "odoo-webserver-large-0".to_string(),
+                ClusterConditionStatus::True,
+            ),
+        ]);
+
+        // A single failed pod fails the rolegroup, pods which were not probed are unknown
+        let addons = addons_statuses(&odoo, &pods, &git_sync_ups);
+        assert_eq!(
+            Some(ClusterConditionStatus::False),
+            addons["odoo-webserver-default"].git_sync_up
+        );
+        assert_eq!(
+            Some(ClusterConditionStatus::True),
+            addons["odoo-webserver-large"].git_sync_up
+        );
+        assert_eq!(
+            Some(ClusterConditionStatus::Unknown),
+            addons["odoo-worker-default"].git_sync_up
+        );
+
+        // The shared git-sync pod counts for all rolegroups
+        odoo.spec.cluster_config.addons_git_sync[0].shared_volume = Some(GitSyncSharedVolume {
+            claim_name: "odoo-addons".to_string(),
+        });
+        let pods = [
+            named_pod("odoo-gitsync-7d9f8", "gitsync", "shared"),
+            named_pod("odoo-webserver-large-0", "webserver", "large"),
+        ];
+        let git_sync_ups = BTreeMap::from([(
+            "odoo-gitsync-7d9f8".to_string(),
+            ClusterConditionStatus::False,
+        )]);
+        assert_eq!(
+            Some(ClusterConditionStatus::False),
+            addons_statuses(&odoo, &pods, &git_sync_ups)["odoo-webserver-large"].git_sync_up
+        );
+
+        // git-sync does not serve its health in the mode once
+        odoo.spec.cluster_config.addons_git_sync[0].mode = GitSyncMode::Once;
+        assert_eq!(
+            None,
+            addons_statuses(&odoo, &pods, &git_sync_ups)["odoo-webserver-large"].git_sync_up
+        );
+    }
+
+    #[test]
+    fn test_has_port() {
+        let mut pod: Pod = serde_yaml::from_str(
+            "
+            metadata:
+              name: odoo-webserver-default-0
+            spec:
+              containers:
+                - name: gitsync-1
+                  ports:
+                    - name: gitsync-metrics
+                      containerPort: 9082
+            status:
+              phase: Running
+            ",
+        )
+        .unwrap();
+        assert!(has_port(&pod, GIT_SYNC_HTTP_PORT_NAME));
+        assert!(!has_port(&pod, GIT_SYNC_REVISION_PORT_NAME));
+        assert!(is_running(&pod));
+
+        pod.status = None;
+        assert!(!is_running(&pod));
+        pod.spec = None;
+        assert!(!has_port(&pod, GIT_SYNC_HTTP_PORT_NAME));
+    }
+
+    #[test]
+    fn test_terminated_revision() {
+        let mut pod = pod("webserver", "default", None);
+        pod.status = serde_yaml::from_str(
+            "
+            initContainerStatuses:
+              - name: gitsync-1
+                image: docker.stackable.tech/stackable/odoo:2.6.1-stackable0.0.0-dev
+                imageID: ''
+                ready: true
+                restartCount: 0
+                state:
+                  terminated:
+                    exitCode: 0
+                    message: 0123abc
+              - name: addons-verify-checksum
+                image: docker.stackable.tech/stackable/odoo:2.6.1-stackable0.0.0-dev
+                imageID: ''
+                ready: true
+                restartCount: 0
+                state:
+                  terminated:
+                    exitCode: 0
+                    message: checksum ok
+            ",
+        )
+        .unwrap();
+        assert_eq!(Some("0123abc".to_string()), terminated_revision(&pod));
+
+        // A failed sync did not report a revision
+        let status = pod.status.as_mut().unwrap();
+        let terminated = status.init_container_statuses.as_mut().unwrap()[0]
+            .state
+            .as_mut()
+            .unwrap()
+            .terminated
+            .as_mut()
+            .unwrap();
+        terminated.exit_code = 1;
+        terminated.message = Some("authentication failed".to_string());
+        assert_eq!(None, terminated_revision(&pod));
+    }
+}
@@ -0,0 +1,397 @@
+//! Skips the server-side apply of objects which did not change since they were last applied, to
+//! reduce the API churn and audit noise of the reconciliations.
+//!
+//! The hash of the desired object is stored in its `APPLIED_HASH_ANNOTATION`. The object is only
+//! applied again if the live object carries another hash or was changed by another field manager
+//! since, so changes made by others are reverted by the next reconciliation.
+//!
+//! `ClusterResources` has to apply every object to record it for the deletion of the orphaned
+//! ones. The objects of the role groups are therefore labeled as managed by
+//! `ROLE_GROUP_CONTROLLER_NAME`, which `ClusterResources` does not clean up, and
+//! `AppliedObjects` deletes their orphans instead. So are the Ingresses of the roles, which
+//! `ClusterResources` does not support at all.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
+
+use fnv::FnvHasher;
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{OdooCluster, APP_NAME, OPERATOR_NAME};
+use stackable_operator::{
+    client::{Client, GetApi},
+    k8s_openapi::{
+        api::{
+            apps::v1::{Deployment, StatefulSet},
+            core::v1::{ConfigMap, Service},
+            networking::v1::Ingress,
+        },
+        apimachinery::pkg::apis::meta::v1::LabelSelector,
+        serde_json,
+    },
+    kube::{Resource, ResourceExt},
+    labels::{APP_INSTANCE_LABEL, APP_MANAGED_BY_LABEL, APP_NAME_LABEL},
+};
+
+/// Labels the objects of the role groups, which are applied through `AppliedObjects`
+pub const ROLE_GROUP_CONTROLLER_NAME: &str = "odoocluster-rolegroup";
+pub const APPLIED_HASH_ANNOTATION: &str = "odoo.sovrin.cloud/applied-hash";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("failed to retrieve the {kind} [{name}]"))]
+    GetObject {
+        source: stackable_operator::error::Error,
+        kind: String,
+        name: String,
+    },
+    #[snafu(display("the {kind} [{name}] does not exist while the reconciliation is paused"))]
+    MissingObject { kind: String, name: String },
+    #[snafu(display("failed to apply the {kind} [{name}]"))]
+    ApplyObject {
+        source: stackable_operator::error::Error,
+        kind: String,
+        name: String,
+    },
+    #[snafu(display("failed to list the {kind}s to delete the orphaned ones"))]
+    ListObjects {
+        source: stackable_operator::error::Error,
+        kind: String,
+    },
+    #[snafu(display("failed to delete the orphaned {kind} [{name}]"))]
+    DeleteObject {
+        source: stackable_operator::error::Error,
+        kind: String,
+        name: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Records the objects of the role groups applied in a reconciliation of an [`OdooCluster`].
+pub struct AppliedObjects {
+    namespace: String,
+    labels: BTreeMap<String, String>,
+    reconciliation_paused: bool,
+    /// The names of the applied objects by kind
+    applied: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl AppliedObjects {
+    pub fn new(odoo: &OdooCluster, namespace: &str) -> Self {
+        AppliedObjects {
+            namespace: namespace.to_string(),
+            labels: role_group_labels(odoo),
+            reconciliation_paused: odoo.spec.cluster_operation.reconciliation_paused,
+            applied: BTreeMap::new(),
+        }
+    }
+
+    /// Records the object, so it is not deleted as orphan.
+    pub fn record<T: Resource<DynamicType = ()>>(&mut self, object: &T) {
+        self.applied
+            .entry(T::kind(&()).into_owned())
+            .or_default()
+            .insert(object.name_any());
+    }
+
+    /// Deletes the objects of the role groups of the cluster which were not recorded, e.g. the
+    /// ones of removed role groups or the Ingresses removed from the spec. Nothing is deleted
+    /// while the reconciliation is paused.
+    pub async fn delete_orphans(&self, client: &Client) -> Result<()> {
+        if self.reconciliation_paused {
+            return Ok(());
+        }
+        self.delete_orphans_of_kind::<Service>(client).await?;
+        self.delete_orphans_of_kind::<ConfigMap>(client).await?;
+        self.delete_orphans_of_kind::<StatefulSet>(client).await?;
+        self.delete_orphans_of_kind::<Deployment>(client).await?;
+        self.delete_orphans_of_kind::<Ingress>(client).await
+    }
+
+    async fn delete_orphans_of_kind<T>(&self, client: &Client) -> Result<()>
+    where
+        T: Clone + Debug + DeserializeOwned + GetApi<Namespace = str> + Resource<DynamicType = ()>,
+    {
+        let kind = T::kind(&()).into_owned();
+        let selector = LabelSelector {
+            match_labels: Some(self.labels.clone()),
+            ..LabelSelector::default()
+        };
+        let objects = client
+            .list_with_label_selector::<T>(&self.namespace, &selector)
+            .await
+            .context(ListObjectsSnafu { kind: &kind })?;
+        let applied = self.applied.get(&kind);
+        for object in objects {
+            if !applied.map_or(false, |applied| applied.contains(&object.name_any())) {
+                client.delete(&object).await.context(DeleteObjectSnafu {
+                    kind: &kind,
+                    name: object.name_any(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Applies the object unless the live object is unchanged and returns it as applied. While the
+/// reconciliation of the cluster is paused, the live object is returned instead.
+pub async fn apply<T>(
+    client: &Client,
+    odoo: &OdooCluster,
+    field_manager_scope: &str,
+    object: &T,
+) -> Result<T>
+where
+    T: Clone
+        + Debug
+        + DeserializeOwned
+        + GetApi<Namespace = str>
+        + Resource<DynamicType = ()>
+        + Serialize,
+{
+    let kind = T::kind(&()).into_owned();
+    let name = object.name_any();
+    let live = client
+        .get_opt::<T>(&name, &object.namespace().unwrap_or_default())
+        .await
+        .context(GetObjectSnafu {
+            kind: &kind,
+            name: &name,
+        })?;
+    if odoo.spec.cluster_operation.reconciliation_paused {
+        // Like ClusterResources, a missing object is an error while the reconciliation is paused
+        return live.context(MissingObjectSnafu { kind, name });
+    }
+
+    let hash = hash(object);
+    if let Some(live) = live.filter(|live| is_unchanged(live, &hash)) {
+        return Ok(live);
+    }
+    let mut object = object.clone();
+    object
+        .annotations_mut()
+        .insert(APPLIED_HASH_ANNOTATION.to_string(), hash);
+    client
+        .apply_patch(field_manager_scope, &object, &object)
+        .await
+        .context(ApplyObjectSnafu { kind, name })
+}
+
+/// The labels of the objects of the role groups of the cluster.
+fn role_group_labels(odoo: &OdooCluster) -> BTreeMap<String, String> {
+    [
+        (APP_NAME_LABEL, APP_NAME.to_string()),
+        (APP_INSTANCE_LABEL, odoo.name_any()),
+        (
+            APP_MANAGED_BY_LABEL,
+            format!("{OPERATOR_NAME}_{ROLE_GROUP_CONTROLLER_NAME}"),
+        ),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value))
+    .collect()
+}
+
+/// Whether the live object was applied with the given hash and not changed by another field
+/// manager afterwards. Changes of the status are ignored.
+fn is_unchanged<T: Resource>(live: &T, hash: &str) -> bool {
+    if live
+        .annotations()
+        .get(APPLIED_HASH_ANNOTATION)
+        .map(String::as_str)
+        != Some(hash)
+    {
+        return false;
+    }
+    let managed_fields = live.meta().managed_fields.as_deref().unwrap_or_default();
+    let annotation_field = format!("f:{APPLIED_HASH_ANNOTATION}");
+    let Some(applied) = managed_fields.iter().find(|entry| {
+        entry
+            .fields_v1
+            .as_ref()
+            .and_then(|fields| fields.0.get("f:metadata"))
+            .and_then(|metadata| metadata.get("f:annotations"))
+            .and_then(|annotations| annotations.get(&annotation_field))
+            .is_some()
+    }) else {
+        return false;
+    };
+    // The times only have a precision of seconds, so a change within the same second counts
+    managed_fields.iter().all(|entry| {
+        std::ptr::eq(entry, applied)
+            || entry.subresource.is_some()
+            || entry.time.as_ref().map(|time| &time.0) < applied.time.as_ref().map(|time| &time.0)
+    })
+}
+
+/// A hash of the object which does not change between Rust versions, as it is stored in the
+/// annotations of the applied objects.
+fn hash<T: Serialize>(object: &T) -> String {
+    let mut hasher = FnvHasher::default();
+    // Serializing the objects of k8s-openapi does not fail
+    serde_json::to_vec(object)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use stackable_operator::{
+        builder::ObjectMetaBuilder,
+        k8s_openapi::apimachinery::pkg::apis::meta::v1::{FieldsV1, ManagedFieldsEntry, Time},
+        k8s_openapi::chrono::{TimeZone, Utc},
+    };
+
+    use super::*;
+
+    fn config_map(value: &str) -> ConfigMap {
+        ConfigMap {
+            metadata: ObjectMetaBuilder::new()
+                .name("odoo")
+                .namespace("default")
+                .build(),
+            data: Some([("key".to_string(), value.to_string())].into()),
+            ..ConfigMap::default()
+        }
+    }
+
+    fn managed_fields_entry(
+        manager: &str,
+        fields: serde_json::Value,
+        seconds: i64,
+        subresource: Option<&str>,
+    ) -> ManagedFieldsEntry {
+        ManagedFieldsEntry {
+            manager: Some(manager.to_string()),
+            fields_v1: Some(FieldsV1(fields)),
+            time: Some(Time(Utc.timestamp_opt(seconds, 0).unwrap())),
+            subresource: subresource.map(str::to_string),
+            ..ManagedFieldsEntry::default()
+        }
+    }
+
+    /// The ConfigMap as applied with the hash of its desired state at the given time
+    fn applied_config_map(value: &str, seconds: i64) -> ConfigMap {
+        let mut config_map = config_map(value);
+        let hash = hash(&config_map);
+        config_map
+            .annotations_mut()
+            .insert(APPLIED_HASH_ANNOTATION.to_string(), hash);
+        config_map.metadata.managed_fields = Some(vec![managed_fields_entry(
+            "odoo.sovrin.cloud_odoocluster",
+            serde_json::json!({
+                "f:metadata": {
+                    "f:annotations": { format!("f:{APPLIED_HASH_ANNOTATION}"): {} }
+                },
+                "f:data": { "f:key": {} }
+            }),
+            seconds,
+            None,
+        )]);
+        config_map
+    }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(&config_map("a")), hash(&config_map("a")));
+        assert_ne!(hash(&config_map("a")), hash(&config_map("b")));
+        assert_eq!(16, hash(&config_map("a")).len());
+    }
+
+    #[test]
+    fn test_unchanged_objects_are_skipped() {
+        let live = applied_config_map("a", 100);
+        assert!(is_unchanged(&live, &hash(&config_map("a"))));
+
+        // Changes of the status by others do not count
+        let mut with_status_change = live.clone();
+        if let Some(managed_fields) = with_status_change.metadata.managed_fields.as_mut() {
+            managed_fields.push(managed_fields_entry(
+                "kube-controller-manager",
+                serde_json::json!({ "f:status": {} }),
+                200,
+                Some("status"),
+            ));
+        }
+        assert!(is_unchanged(&with_status_change, &hash(&config_map("a"))));
+
+        // Changes made before the object was applied were overwritten
+        let mut with_earlier_change = live.clone();
+        if let Some(managed_fields) = with_earlier_change.metadata.managed_fields.as_mut() {
+            managed_fields.push(managed_fields_entry(
+                "kubectl-edit",
+                serde_json::json!({ "f:data": { "f:other": {} } }),
+                50,
+                None,
+            ));
+        }
+        assert!(is_unchanged(&with_earlier_change, &hash(&config_map("a"))));
+    }
+
+    #[test]
+    fn test_changed_objects_are_applied() {
+        let live = applied_config_map("a", 100);
+
+        // The desired object changed
+        assert!(!is_unchanged(&live, &hash(&config_map("b"))));
+
+        // The object was changed by others after it was applied
+        let mut with_later_change = live.clone();
+        if let Some(managed_fields) = with_later_change.metadata.managed_fields.as_mut() {
+            managed_fields.push(managed_fields_entry(
+                "kubectl-edit",
+                serde_json::json!({ "f:data": { "f:key": {} } }),
+                100,
+                None,
+            ));
+        }
+        assert!(!is_unchanged(&with_later_change, &hash(&config_map("a"))));
+
+        // The object was applied before the hash was recorded
+        assert!(!is_unchanged(&config_map("a"), &hash(&config_map("a"))));
+        let mut without_managed_fields = live;
+        without_managed_fields.metadata.managed_fields = None;
+        assert!(!is_unchanged(
+            &without_managed_fields,
+            &hash(&config_map("a"))
+        ));
+    }
+
+    #[test]
+    fn test_record() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          ",
+        )
+        .unwrap();
+
+        let mut applied_objects = AppliedObjects::new(&odoo, "default");
+        applied_objects.record(&config_map("a"));
+        assert_eq!(
+            Some(&BTreeSet::from(["odoo".to_string()])),
+            applied_objects.applied.get("ConfigMap")
+        );
+        assert_eq!(None, applied_objects.applied.get("Service"));
+        assert_eq!(
+            Some(&"odoo.sovrin.cloud_odoocluster-rolegroup".to_string()),
+            applied_objects.labels.get(APP_MANAGED_BY_LABEL)
+        );
+    }
+}
@@ -0,0 +1,538 @@
+//! Dumps the database and snapshots the filestore to S3 before the database is migrated to a
+//! new product version, if requested in `clusterConfig.upgradeBackup`.
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{
+    backup::UpgradeBackupConfig,
+    filestore::{FILESTORE_DIR, FILESTORE_VOLUME_NAME},
+    OdooCluster, AIRFLOW_UID,
+};
+use stackable_operator::{
+    builder::{
+        resources::ResourceRequirementsBuilder, ContainerBuilder, ObjectMetaBuilder,
+        PodSecurityContextBuilder, SecretOperatorVolumeSourceBuilder, VolumeBuilder,
+    },
+    client::Client,
+    commons::{
+        authentication::tls::{CaCert, TlsServerVerification, TlsVerification},
+        product_image_selection::ResolvedProductImage,
+        s3::InlinedS3BucketSpec,
+    },
+    k8s_openapi::api::{
+        batch::v1::{Job, JobSpec},
+        core::v1::{EmptyDirVolumeSource, EnvVar, PodSpec, PodTemplateSpec, ResourceRequirements},
+    },
+    kube::ResourceExt,
+};
+use std::collections::BTreeMap;
+
+use crate::{
+    controller_commons, network_policy,
+    odoo_controller::AIRFLOW_CONTROLLER_NAME,
+    upgrade::add_database_connection,
+    utils::{add_labels, export_env_var_from_file, get_job_state, JobState},
+};
+
+const BACKUP_VOLUME_NAME: &str = "backup";
+const BACKUP_DIR: &str = "/stackable/backup";
+const DATABASE_DUMP_FILE: &str = "/stackable/backup/database.dump";
+const FILESTORE_SNAPSHOT_FILE: &str = "/stackable/backup/filestore.tar.gz";
+const S3_CREDENTIALS_VOLUME_NAME: &str = "s3-credentials";
+const S3_CREDENTIALS_DIR: &str = "/stackable/s3-credentials";
+const S3_TLS_VOLUME_NAME: &str = "s3-tls";
+const S3_TLS_DIR: &str = "/stackable/s3-tls";
+const S3_ENDPOINT_ENV: &str = "S3_ENDPOINT";
+const DATABASE_OBJECT_URI_ENV: &str = "DATABASE_OBJECT_URI";
+const FILESTORE_OBJECT_URI_ENV: &str = "FILESTORE_OBJECT_URI";
+/// pg_dump does not understand the driver part of SQLAlchemy URIs like `postgresql+psycopg2`.
+const STRIP_SQLALCHEMY_DRIVER: &str = r"s/^postgresql\+[a-z0-9]+:/postgresql:/";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to resolve the S3 bucket for the backup"))]
+    ResolveS3Bucket {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("the S3 bucket for the backup has no name"))]
+    S3BucketNameMissing,
+    #[snafu(display("invalid container name"))]
+    InvalidContainerName {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to retrieve the backup job [{name}]"))]
+    GetBackupJob {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to apply the backup job [{name}]"))]
+    ApplyBackupJob {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The locations in the bucket the backup is uploaded to.
+struct BackupObjectUris {
+    database: String,
+    filestore: String,
+}
+
+/// Starts the backup before the upgrade to `to_version` if it was not started yet, and returns
+/// its state.
+pub async fn reconcile_backup(
+    client: &Client,
+    odoo: &OdooCluster,
+    config: &UpgradeBackupConfig,
+    resolved_product_image: &ResolvedProductImage,
+    sa_name: &str,
+    to_version: &str,
+    selected_labels: &BTreeMap<String, String>,
+) -> Result<JobState> {
+    let name = job_name(odoo, to_version);
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    if let Some(job) = client
+        .get_opt::<Job>(&name, &namespace)
+        .await
+        .context(GetBackupJobSnafu { name: &name })?
+    {
+        return Ok(get_job_state(&job));
+    }
+
+    let bucket = config
+        .bucket
+        .resolve(client, &namespace)
+        .await
+        .context(ResolveS3BucketSnafu)?;
+    let bucket_name = bucket
+        .bucket_name
+        .as_ref()
+        .context(S3BucketNameMissingSnafu)?;
+    let object_uris = BackupObjectUris {
+        database: format!(
+            "s3://{bucket_name}/{}",
+            config.object_key(&odoo.name_any(), to_version)
+        ),
+        filestore: format!(
+            "s3://{bucket_name}/{}",
+            config.filestore_object_key(&odoo.name_any(), to_version)
+        ),
+    };
+    tracing::info!(
+        object_uri = object_uris.database,
+        "Backing up the database before the upgrade"
+    );
+
+    let mut job = build_backup_job(
+        odoo,
+        config,
+        &bucket,
+        &object_uris,
+        resolved_product_image,
+        sa_name,
+        &name,
+    )?;
+    add_labels(&mut job, selected_labels);
+    let job = client
+        .apply_patch(AIRFLOW_CONTROLLER_NAME, &job, &job)
+        .await
+        .context(ApplyBackupJobSnafu { name: &name })?;
+    Ok(get_job_state(&job))
+}
+
+fn job_name(odoo: &OdooCluster, to_version: &str) -> String {
+    format!(
+        "{}-backup-{}",
+        odoo.name_any(),
+        to_version.to_lowercase().replace(['.', '_', '+'], "-")
+    )
+}
+
+/// The dump and the filestore snapshot are written by init containers, so the upload only
+/// starts if both succeeded.
+fn build_backup_job(
+    odoo: &OdooCluster,
+    config: &UpgradeBackupConfig,
+    bucket: &InlinedS3BucketSpec,
+    object_uris: &BackupObjectUris,
+    resolved_product_image: &ResolvedProductImage,
+    sa_name: &str,
+    name: &str,
+) -> Result<Job> {
+    let cluster_config = &odoo.spec.cluster_config;
+    let mut volumes = vec![VolumeBuilder::new(BACKUP_VOLUME_NAME)
+        .empty_dir(EmptyDirVolumeSource::default())
+        .build()];
+
+    // Only the schema of the cluster is dumped if the database is shared with other tenants
+    let schema_arg = cluster_config
+        .database
+        .schema
+        .as_ref()
+        .map(|schema| format!(" --schema={schema}"))
+        .unwrap_or_default();
+    let mut dump_commands = vec![if cluster_config.database.assembles_connection_uri() {
+        // pg_dump reads the connection fields from the libpq environment variables
+        format!("pg_dump --format=custom --file={DATABASE_DUMP_FILE}{schema_arg}")
+    } else {
+        format!(
+            "pg_dump --format=custom --file={DATABASE_DUMP_FILE}{schema_arg} \
+            \"$(echo \"$AIRFLOW__CORE__SQL_ALCHEMY_CONN\" | sed -E '{STRIP_SQLALCHEMY_DRIVER}')\""
+        )
+    }];
+    let mut dump_env = Vec::new();
+    let mut dump_container = ContainerBuilder::new("pg-dump").context(InvalidContainerNameSnafu)?;
+    add_database_connection(
+        odoo,
+        &mut dump_container,
+        &mut dump_commands,
+        &mut dump_env,
+        &mut volumes,
+    );
+    dump_container
+        .image(&config.pg_dump_image)
+        .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+        .args(vec![fail_fast_script(&dump_commands)])
+        .add_env_vars(dump_env)
+        .add_volume_mount(BACKUP_VOLUME_NAME, BACKUP_DIR)
+        .resources(resources());
+    let mut init_containers = vec![dump_container.build()];
+
+    let mut upload_env = vec![EnvVar {
+        name: DATABASE_OBJECT_URI_ENV.to_string(),
+        value: Some(object_uris.database.clone()),
+        ..EnvVar::default()
+    }];
+    let mut upload_commands = Vec::new();
+    let mut upload_container =
+        ContainerBuilder::new("upload").context(InvalidContainerNameSnafu)?;
+    if let Some(filestore) = &cluster_config.filestore {
+        volumes.push(filestore.read_only_volume());
+        let mut snapshot_container =
+            ContainerBuilder::new("filestore-snapshot").context(InvalidContainerNameSnafu)?;
+        snapshot_container
+            .image_from_product_image(resolved_product_image)
+            .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+            .args(vec![fail_fast_script(&[format!(
+                "tar --create --gzip --file={FILESTORE_SNAPSHOT_FILE} --directory={FILESTORE_DIR} ."
+            )])])
+            .add_volume_mount(FILESTORE_VOLUME_NAME, FILESTORE_DIR)
+            .add_volume_mount(BACKUP_VOLUME_NAME, BACKUP_DIR)
+            .resources(resources());
+        init_containers.push(snapshot_container.build());
+        upload_env.push(EnvVar {
+            name: FILESTORE_OBJECT_URI_ENV.to_string(),
+            value: Some(object_uris.filestore.clone()),
+            ..EnvVar::default()
+        });
+    }
+
+    let connection = bucket.connection.as_ref();
+    if let Some(credentials) = connection.and_then(|connection| connection.credentials.as_ref()) {
+        volumes.push(credentials.to_volume(S3_CREDENTIALS_VOLUME_NAME));
+        upload_container.add_volume_mount(S3_CREDENTIALS_VOLUME_NAME, S3_CREDENTIALS_DIR);
+        upload_commands.extend([
+            export_env_var_from_file(
+                "AWS_ACCESS_KEY_ID",
+                &format!("{S3_CREDENTIALS_DIR}/accessKey"),
+            ),
+            export_env_var_from_file(
+                "AWS_SECRET_ACCESS_KEY",
+                &format!("{S3_CREDENTIALS_DIR}/secretKey"),
+            ),
+        ]);
+    }
+    let mut aws_args = String::new();
+    if let Some(endpoint) = bucket.endpoint() {
+        upload_env.push(EnvVar {
+            name: S3_ENDPOINT_ENV.to_string(),
+            value: Some(endpoint),
+            ..EnvVar::default()
+        });
+        aws_args.push_str(&format!(" --endpoint-url \"${S3_ENDPOINT_ENV}\""));
+    }
+    match connection
+        .and_then(|connection| connection.tls.as_ref())
+        .map(|tls| &tls.verification)
+    {
+        Some(TlsVerification::None {}) => aws_args.push_str(" --no-verify-ssl"),
+        Some(TlsVerification::Server(TlsServerVerification {
+            ca_cert: CaCert::SecretClass(secret_class),
+        })) => {
+            volumes.push(
+                VolumeBuilder::new(S3_TLS_VOLUME_NAME)
+                    .ephemeral(SecretOperatorVolumeSourceBuilder::new(secret_class).build())
+                    .build(),
+            );
+            upload_container.add_volume_mount(S3_TLS_VOLUME_NAME, S3_TLS_DIR);
+            upload_env.push(EnvVar {
+                name: "AWS_CA_BUNDLE".to_string(),
+                value: Some(format!("{S3_TLS_DIR}/ca.crt")),
+                ..EnvVar::default()
+            });
+        }
+        // The system CAs and the CA bundle of the cluster are trusted anyway
+        Some(TlsVerification::Server(TlsServerVerification {
+            ca_cert: CaCert::WebPki {},
+        }))
+        | None => (),
+    }
+    upload_commands.push(format!(
+        "aws s3 cp{aws_args} {DATABASE_DUMP_FILE} \"${DATABASE_OBJECT_URI_ENV}\""
+    ));
+    if cluster_config.filestore.is_some() {
+        upload_commands.push(format!(
+            "aws s3 cp{aws_args} {FILESTORE_SNAPSHOT_FILE} \"${FILESTORE_OBJECT_URI_ENV}\""
+        ));
+    }
+    upload_container
+        .image(&config.upload_image)
+        .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+        .args(vec![fail_fast_script(&upload_commands)])
+        .add_env_var("HOME", "/tmp")
+        .add_env_vars(upload_env)
+        .add_volume_mount(BACKUP_VOLUME_NAME, BACKUP_DIR)
+        .resources(resources());
+
+    let mut pod_spec = PodSpec {
+        init_containers: Some(init_containers),
+        containers: vec![upload_container.build()],
+        restart_policy: Some("Never".to_string()),
+        service_account: Some(sa_name.to_string()),
+        image_pull_secrets: resolved_product_image.pull_secrets.clone(),
+        security_context: Some(
+            PodSecurityContextBuilder::new()
+                .run_as_user(AIRFLOW_UID)
+                .run_as_group(0)
+                .build(),
+        ),
+        volumes: Some(volumes),
+        ..Default::default()
+    };
+    if let Some(ca_bundle) = &cluster_config.ca_bundle {
+        controller_commons::add_ca_bundle(&mut pod_spec, ca_bundle, resolved_product_image);
+    }
+    controller_commons::apply_compliance(&mut pod_spec, &cluster_config.compliance);
+
+    Ok(Job {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(name)
+            .ownerreference_from_resource(odoo, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            .build(),
+        spec: Some(JobSpec {
+            template: PodTemplateSpec {
+                metadata: Some(network_policy::job_pod_metadata(
+                    odoo,
+                    AIRFLOW_CONTROLLER_NAME,
+                    &resolved_product_image.app_version_label,
+                    "backup",
+                )),
+                spec: Some(pod_spec),
+            },
+            ..Default::default()
+        }),
+        status: None,
+    })
+}
+
+/// Joins the commands to a script which stops at the first failing command.
+fn fail_fast_script(commands: &[String]) -> String {
+    let mut script = vec![String::from("set -euo pipefail")];
+    script.extend_from_slice(commands);
+    script.join("\n")
+}
+
+fn resources() -> ResourceRequirements {
+    ResourceRequirementsBuilder::new()
+        .with_cpu_request("100m")
+        .with_cpu_limit("400m")
+        .with_memory_request("256Mi")
+        .with_memory_limit("256Mi")
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_operator::k8s_openapi::api::core::v1::Container;
+
+    fn odoo() -> OdooCluster {
+        serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            filestore:
+              claimName: odoo-filestore
+            upgradeBackup:
+              bucket:
+                reference: backups
+              prefix: odoo
+          ",
+        )
+        .unwrap()
+    }
+
+    fn object_uris() -> BackupObjectUris {
+        BackupObjectUris {
+            database: "s3://backups/odoo/odoo/pre-upgrade-2.7.0.dump".to_string(),
+            filestore: "s3://backups/odoo/odoo/pre-upgrade-2.7.0.filestore.tar.gz".to_string(),
+        }
+    }
+
+    fn build_job(odoo: &OdooCluster, bucket: &InlinedS3BucketSpec) -> Job {
+        build_backup_job(
+            odoo,
+            odoo.spec.cluster_config.upgrade_backup.as_ref().unwrap(),
+            bucket,
+            &object_uris(),
+            &odoo.spec.image.resolve("odoo"),
+            "odoo-serviceaccount",
+            "odoo-backup-2-7-0",
+        )
+        .unwrap()
+    }
+
+    fn env_var(container: &Container, name: &str) -> Option<String> {
+        container
+            .env
+            .iter()
+            .flatten()
+            .find(|env_var| env_var.name == name)
+            .and_then(|env_var| env_var.value.clone())
+    }
+
+    #[test]
+    fn test_build_backup_job() {
+        let odoo = odoo();
+        let bucket: InlinedS3BucketSpec = serde_yaml::from_str(
+            "
+            bucketName: backups
+            connection:
+              host: minio
+              port: 9000
+              credentials:
+                secretClass: s3-credentials
+              tls:
+                verification:
+                  server:
+                    caCert:
+                      secretClass: minio-tls
+            ",
+        )
+        .unwrap();
+
+        let pod_template = job_pod_template(&build_job(&odoo, &bucket));
+        let pod_spec = pod_template.spec.unwrap();
+        assert_eq!(
+            Some("backup"),
+            pod_template
+                .metadata
+                .unwrap()
+                .labels
+                .unwrap()
+                .get("app.kubernetes.io/component")
+                .map(String::as_str)
+        );
+
+        let init_containers = pod_spec.init_containers.unwrap();
+        assert_eq!(
+            vec!["pg-dump", "filestore-snapshot"],
+            init_containers
+                .iter()
+                .map(|container| container.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        let snapshot_script = &init_containers[1].args.as_ref().unwrap()[0];
+        assert_eq!(
+            "set -euo pipefail\n\
+            tar --create --gzip --file=/stackable/backup/filestore.tar.gz \
+            --directory=/stackable/filestore .",
+            snapshot_script
+        );
+
+        let upload_container = &pod_spec.containers[0];
+        let upload_script = &upload_container.args.as_ref().unwrap()[0];
+        assert!(upload_script.starts_with("set -euo pipefail\n"));
+        assert!(upload_script.contains(
+            "aws s3 cp --endpoint-url \"$S3_ENDPOINT\" /stackable/backup/database.dump \
+            \"$DATABASE_OBJECT_URI\""
+        ));
+        assert!(upload_script.contains(
+            "aws s3 cp --endpoint-url \"$S3_ENDPOINT\" /stackable/backup/filestore.tar.gz \
+            \"$FILESTORE_OBJECT_URI\""
+        ));
+        assert!(!upload_script.contains("s3://"));
+        assert_eq!(
+            Some(object_uris().filestore),
+            env_var(upload_container, FILESTORE_OBJECT_URI_ENV)
+        );
+        assert_eq!(
+            Some("/stackable/s3-tls/ca.crt".to_string()),
+            env_var(upload_container, "AWS_CA_BUNDLE")
+        );
+
+        let volumes = pod_spec.volumes.unwrap();
+        let filestore_volume = volumes
+            .iter()
+            .find(|volume| volume.name == FILESTORE_VOLUME_NAME)
+            .unwrap();
+        assert_eq!(
+            Some(true),
+            filestore_volume
+                .persistent_volume_claim
+                .as_ref()
+                .and_then(|claim| claim.read_only)
+        );
+        assert!(volumes
+            .iter()
+            .any(|volume| volume.name == S3_TLS_VOLUME_NAME && volume.ephemeral.is_some()));
+    }
+
+    #[test]
+    fn test_build_backup_job_without_tls_verification() {
+        let mut odoo = odoo();
+        odoo.spec.cluster_config.filestore = None;
+        let bucket: InlinedS3BucketSpec = serde_yaml::from_str(
+            "
+            bucketName: backups
+            connection:
+              host: minio
+              port: 9000
+              tls:
+                verification:
+                  none: {}
+            ",
+        )
+        .unwrap();
+
+        let pod_spec = job_pod_template(&build_job(&odoo, &bucket)).spec.unwrap();
+        assert_eq!(1, pod_spec.init_containers.unwrap().len());
+        let upload_container = &pod_spec.containers[0];
+        let upload_script = &upload_container.args.as_ref().unwrap()[0];
+        assert!(upload_script.contains("--no-verify-ssl"));
+        assert!(!upload_script.contains("filestore"));
+        assert_eq!(None, env_var(upload_container, "AWS_CA_BUNDLE"));
+    }
+
+    fn job_pod_template(job: &Job) -> PodTemplateSpec {
+        job.spec.as_ref().unwrap().template.clone()
+    }
+}
@@ -0,0 +1,159 @@
+//! Blue/green generations of the webservers, see [`BlueGreenConfig`].
+//!
+//! Both generations are built from the same rolegroups. The StatefulSet and Service of the green
+//! generation are moved to the rolegroup `<rolegroup>-green`, so their selectors do not overlap
+//! with the blue ones, while both share the rolegroup ConfigMap. The pods are labeled with their
+//! color, which the role Service and the preview Service select. A generation which is not
+//! built anymore is deleted as orphaned resource.
+use std::collections::BTreeMap;
+
+use sovrin_cloud_crd::blue_green::{BlueGreenConfig, Color};
+use stackable_operator::{
+    k8s_openapi::api::{apps::v1::StatefulSet, core::v1::Service},
+    kube::ResourceExt,
+    labels::APP_ROLE_GROUP_LABEL,
+};
+
+pub const COLOR_LABEL: &str = "odoo.sovrin.cloud/color";
+
+/// Labels the pods of the rolegroup with the color and moves the green generation to its own
+/// rolegroup.
+pub fn colorize(statefulset: &mut StatefulSet, service: &mut Service, color: Color) {
+    let role_group = statefulset.labels().get(APP_ROLE_GROUP_LABEL).cloned();
+    let colored_role_group = role_group
+        .as_deref()
+        .map(|role_group| color.rolegroup_name(role_group));
+    let rename = |name: &mut Option<String>| {
+        if let Some(name) = name {
+            *name = color.rolegroup_name(name);
+        }
+    };
+    let relabel = |labels: Option<&mut BTreeMap<String, String>>| {
+        if let (Some(labels), Some(colored_role_group)) = (labels, &colored_role_group) {
+            if labels.contains_key(APP_ROLE_GROUP_LABEL) {
+                labels.insert(APP_ROLE_GROUP_LABEL.to_string(), colored_role_group.clone());
+            }
+        }
+    };
+
+    rename(&mut statefulset.metadata.name);
+    relabel(statefulset.metadata.labels.as_mut());
+    if let Some(spec) = statefulset.spec.as_mut() {
+        spec.service_name = color.rolegroup_name(&spec.service_name);
+        relabel(spec.selector.match_labels.as_mut());
+        let template_metadata = spec.template.metadata.get_or_insert_with(Default::default);
+        relabel(template_metadata.labels.as_mut());
+        template_metadata
+            .labels
+            .get_or_insert_with(BTreeMap::new)
+            .insert(COLOR_LABEL.to_string(), color.to_string());
+    }
+
+    rename(&mut service.metadata.name);
+    relabel(service.metadata.labels.as_mut());
+    if let Some(spec) = service.spec.as_mut() {
+        relabel(spec.selector.as_mut());
+    }
+}
+
+/// Restricts the role Service to the active generation.
+pub fn select_active(role_service: &mut Service, blue_green: &BlueGreenConfig) {
+    select_color(role_service, blue_green.active_color);
+}
+
+/// The Service of the generation which is not active, while it runs the preview image.
+pub fn build_preview_service(role_service: &Service, blue_green: &BlueGreenConfig) -> Service {
+    let mut preview_service = role_service.clone();
+    preview_service.metadata.name = Some(format!("{}-preview", role_service.name_any()));
+    // The annotations only configure the load balancer of the role Service
+    preview_service.metadata.annotations = None;
+    if let Some(spec) = preview_service.spec.as_mut() {
+        spec.type_ = Some("ClusterIP".to_string());
+    }
+    select_color(&mut preview_service, blue_green.active_color.other());
+    preview_service
+}
+
+fn select_color(service: &mut Service, color: Color) {
+    if let Some(spec) = service.spec.as_mut() {
+        spec.selector
+            .get_or_insert_with(BTreeMap::new)
+            .insert(COLOR_LABEL.to_string(), color.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stackable_operator::{
+        builder::ObjectMetaBuilder,
+        k8s_openapi::{
+            api::{
+                apps::v1::StatefulSetSpec,
+                core::v1::{PodTemplateSpec, ServiceSpec},
+            },
+            apimachinery::pkg::apis::meta::v1::LabelSelector,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_colorize() {
+        let labels: BTreeMap<String, String> =
+            [(APP_ROLE_GROUP_LABEL.to_string(), "default".to_string())].into();
+        let metadata = ObjectMetaBuilder::new()
+            .name("odoo-webserver-default")
+            .with_labels(labels.clone())
+            .build();
+        let mut statefulset = StatefulSet {
+            metadata: metadata.clone(),
+            spec: Some(StatefulSetSpec {
+                selector: LabelSelector {
+                    match_labels: Some(labels.clone()),
+                    ..LabelSelector::default()
+                },
+                service_name: "odoo-webserver-default".to_string(),
+                template: PodTemplateSpec {
+                    metadata: Some(metadata.clone()),
+                    ..PodTemplateSpec::default()
+                },
+                ..StatefulSetSpec::default()
+            }),
+            ..StatefulSet::default()
+        };
+        let mut service = Service {
+            metadata,
+            spec: Some(ServiceSpec {
+                selector: Some(labels),
+                ..ServiceSpec::default()
+            }),
+            ..Service::default()
+        };
+
+        colorize(&mut statefulset, &mut service, Color::Green);
+
+        assert_eq!("odoo-webserver-default-green", statefulset.name_any());
+        assert_eq!("odoo-webserver-default-green", service.name_any());
+        let spec = statefulset.spec.unwrap();
+        assert_eq!("odoo-webserver-default-green", spec.service_name);
+        let template_labels = spec.template.metadata.unwrap().labels.unwrap();
+        assert_eq!(
+            Some("default-green"),
+            template_labels
+                .get(APP_ROLE_GROUP_LABEL)
+                .map(String::as_str)
+        );
+        assert_eq!(
+            Some("green"),
+            template_labels.get(COLOR_LABEL).map(String::as_str)
+        );
+        assert_eq!(
+            Some("default-green"),
+            service
+                .spec
+                .and_then(|spec| spec.selector)
+                .and_then(|selector| selector.get(APP_ROLE_GROUP_LABEL).cloned())
+                .as_deref()
+        );
+    }
+}
@@ -1,28 +1,81 @@
 use sovrin_cloud_crd::{
-    OdooClusterAuthenticationConfig, OdooConfigOptions, LdapRolesSyncMoment,
+    filestore::FILESTORE_DIR, oauth::OdooOAuthConfig, web::OdooWebConfig, LdapRolesSyncMoment,
+    OdooAuthorization, OdooCluster, OdooClusterAuthenticationConfig, OdooConfig, OdooConfigOptions,
+    OdooRole, OdooSamlConfig, SamlIdpMetadata, SAML_METADATA_DIR, SAML_METADATA_FILE,
 };
 use stackable_operator::commons::authentication::{
     ldap::LdapAuthenticationProvider, tls::TlsVerification, AuthenticationClass,
     AuthenticationClassProvider,
 };
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, path::Path};
 
-pub const PYTHON_IMPORTS: &[&str] = &[
+const PYTHON_IMPORTS: &[&str] = &[
     "import os",
     "from odoo.www.fab_security.manager import (AUTH_DB, AUTH_LDAP, AUTH_OAUTH, AUTH_OID, AUTH_REMOTE_USER)",
+    "AUTH_SAML = 'saml'",
     "basedir = os.path.abspath(os.path.dirname(__file__))",
     "WTF_CSRF_ENABLED = True",
 ];
 
+/// The security manager which asks the Open Policy Agent at `AUTH_OPA_URL` whether the current
+/// user may perform an action on a resource. The decisions are cached for
+/// `AUTH_OPA_CACHE_TTL_SECONDS`, at most `AUTH_OPA_CACHE_MAX_ENTRIES` of them, and access is
+/// denied if OPA cannot be reached.
+const OPA_SECURITY_MANAGER: &[&str] = &[
+    "import collections",
+    "import json",
+    "import threading",
+    "import time",
+    "import urllib.request",
+    "from flask import current_app",
+    "from odoo.www.security import OdooSecurityManager",
+    "class OpaSecurityManager(OdooSecurityManager):",
+    "    opa_decisions = collections.OrderedDict()",
+    "    opa_decisions_lock = threading.Lock()",
+    "    def has_access(self, action_name, resource_name, user=None):",
+    "        user = user or self.current_user",
+    "        if user is None or user.is_anonymous:",
+    "            return False",
+    "        opa_input = {'user': user.username, 'roles': sorted(role.name for role in user.roles), 'action': action_name, 'resource': resource_name}",
+    "        key = json.dumps(opa_input, sort_keys=True)",
+    "        cache_ttl_seconds = current_app.config.get('AUTH_OPA_CACHE_TTL_SECONDS', 0)",
+    "        cache_max_entries = current_app.config.get('AUTH_OPA_CACHE_MAX_ENTRIES', 10000)",
+    "        with self.opa_decisions_lock:",
+    "            cached = self.opa_decisions.pop(key, None)",
+    "            if cached is not None and time.monotonic() - cached[1] < cache_ttl_seconds:",
+    "                self.opa_decisions[key] = cached",
+    "                return cached[0]",
+    "        request = urllib.request.Request(current_app.config['AUTH_OPA_URL'], data=json.dumps({'input': opa_input}).encode(), headers={'Content-Type': 'application/json'})",
+    "        try:",
+    "            with urllib.request.urlopen(request, timeout=5) as response:",
+    "                allowed = json.load(response).get('result') is True",
+    "        except Exception:",
+    "            return False",
+    "        if cache_ttl_seconds > 0 and cache_max_entries > 0:",
+    "            with self.opa_decisions_lock:",
+    "                self.opa_decisions[key] = (allowed, time.monotonic())",
+    "                while len(self.opa_decisions) > cache_max_entries:",
+    "                    self.opa_decisions.popitem(last=False)",
+    "        return allowed",
+];
+
+/// The Python preamble of the webserver configuration, which defines the OPA security manager
+/// if authorization is delegated to OPA.
+pub fn python_imports(authorization: Option<&OdooAuthorization>) -> Vec<&'static str> {
+    let mut imports = PYTHON_IMPORTS.to_vec();
+    if authorization.is_some() {
+        imports.extend(OPA_SECURITY_MANAGER);
+    }
+    imports
+}
+
 pub fn add_odoo_config(
     config: &mut BTreeMap<String, String>,
     authentication_config: Option<&OdooClusterAuthenticationConfig>,
-    authentication_class: Option<&AuthenticationClass>,
+    authentication_classes: &[AuthenticationClass],
 ) {
     if let Some(authentication_config) = authentication_config {
-        if let Some(authentication_class) = authentication_class {
-            append_authentication_config(config, authentication_config, authentication_class);
-        }
+        append_authentication_config(config, authentication_config, authentication_classes);
     }
     if !config.contains_key(&*OdooConfigOptions::AuthType.to_string()) {
         config.insert(
@@ -33,15 +86,142 @@ pub fn add_odoo_config(
     }
 }
 
+pub fn add_web_config(config: &mut BTreeMap<String, String>, web: &OdooWebConfig) {
+    config.insert(
+        OdooConfigOptions::EnableProxyFix.to_string(),
+        web.proxy_mode.to_string(),
+    );
+}
+
+pub fn add_opa_config(
+    config: &mut BTreeMap<String, String>,
+    authorization: &OdooAuthorization,
+    opa_url: &str,
+) {
+    config.insert(
+        OdooConfigOptions::SecurityManagerClass.to_string(),
+        "OpaSecurityManager".to_string(),
+    );
+    config.insert(
+        OdooConfigOptions::AuthOpaUrl.to_string(),
+        opa_url.to_string(),
+    );
+    if let Some(cache_ttl_seconds) = authorization.cache_ttl_seconds {
+        config.insert(
+            OdooConfigOptions::AuthOpaCacheTtlSeconds.to_string(),
+            cache_ttl_seconds.to_string(),
+        );
+    }
+    if let Some(cache_max_entries) = authorization.cache_max_entries {
+        config.insert(
+            OdooConfigOptions::AuthOpaCacheMaxEntries.to_string(),
+            cache_max_entries.to_string(),
+        );
+    }
+}
+
+/// Returns the server options of Odoo for the pods of a role group, named like in `odoo.conf`.
+pub fn odoo_server_options(
+    odoo: &OdooCluster,
+    odoo_role: &OdooRole,
+    config: &OdooConfig,
+) -> BTreeMap<String, String> {
+    let mut options = BTreeMap::new();
+    let addons_path = odoo.addons_path(odoo_role);
+    if !addons_path.is_empty() {
+        options.insert("addons_path".to_string(), addons_path.join(","));
+    }
+    if let Some(replica) = odoo
+        .spec
+        .cluster_config
+        .database
+        .replica
+        .as_ref()
+        .filter(|_| config.use_database_replica)
+    {
+        options.insert("db_replica_host".to_string(), replica.host.clone());
+        options.insert("db_replica_port".to_string(), replica.port.to_string());
+    }
+    for (name, value) in [
+        ("workers", config.worker_processes),
+        ("max_cron_threads", config.max_cron_threads),
+        ("db_maxconn", config.db_maxconn),
+    ] {
+        if let Some(value) = value {
+            options.insert(name.to_string(), value.to_string());
+        }
+    }
+    if odoo.spec.cluster_config.filestore.is_some() {
+        options.insert("data_dir".to_string(), FILESTORE_DIR.to_string());
+    }
+    options
+}
+
+/// Renders the server options as Odoo config file.
+pub fn write_odoo_config(options: &BTreeMap<String, String>) -> String {
+    let mut config = String::from("[options]\n");
+    for (name, value) in options {
+        config.push_str(&format!("{name} = {value}\n"));
+    }
+    config
+}
+
 fn append_authentication_config(
     config: &mut BTreeMap<String, String>,
     authentication_config: &OdooClusterAuthenticationConfig,
-    authentication_class: &AuthenticationClass,
+    authentication_classes: &[AuthenticationClass],
 ) {
-    if let AuthenticationClassProvider::Ldap(ldap) = &authentication_class.spec.provider {
-        append_ldap_config(config, ldap);
+    let ldap_providers = authentication_classes
+        .iter()
+        .filter_map(
+            |authentication_class| match &authentication_class.spec.provider {
+                AuthenticationClassProvider::Ldap(ldap) => Some(ldap),
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    if let Some((ldap, fallback_ldaps)) = ldap_providers.split_first() {
+        append_ldap_config(
+            config,
+            ldap,
+            authentication_config.ldap_tls_client_certificate,
+        );
+        if !fallback_ldaps.is_empty() {
+            config.insert(
+                OdooConfigOptions::AuthLdapFallbackServers.to_string(),
+                format!(
+                    "[{}]",
+                    fallback_ldaps
+                        .iter()
+                        .map(|ldap| fallback_ldap_server_expression(ldap))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            );
+        }
+    } else if let Some(oauth) = &authentication_config.oauth {
+        append_oauth_config(config, oauth);
+    } else if let Some(saml) = &authentication_config.saml {
+        append_saml_config(config, saml);
+    } else if let Some(remote_user) = &authentication_config.remote_user {
+        config.insert(
+            OdooConfigOptions::AuthType.to_string(),
+            "AUTH_REMOTE_USER".into(),
+        );
+        config.insert(
+            OdooConfigOptions::AuthRemoteUserEnvVar.to_string(),
+            remote_user.wsgi_env_var(),
+        );
+    } else {
+        return;
     }
 
+    config.insert(
+        OdooConfigOptions::AuthDbFallback.to_string(),
+        authentication_config.db_fallback.to_string(),
+    );
+
     config.insert(
         OdooConfigOptions::AuthUserRegistration.to_string(),
         authentication_config.user_registration.to_string(),
@@ -54,24 +234,26 @@ fn append_authentication_config(
         OdooConfigOptions::AuthRolesSyncAtLogin.to_string(),
         (authentication_config.sync_roles_at == LdapRolesSyncMoment::Login).to_string(),
     );
+    if !authentication_config.roles_mapping.is_empty() {
+        config.insert(
+            OdooConfigOptions::AuthRolesMapping.to_string(),
+            roles_mapping_expression(&authentication_config.roles_mapping),
+        );
+    }
 }
 
-fn append_ldap_config(config: &mut BTreeMap<String, String>, ldap: &LdapAuthenticationProvider) {
+fn append_ldap_config(
+    config: &mut BTreeMap<String, String>,
+    ldap: &LdapAuthenticationProvider,
+    tls_client_certificate: bool,
+) {
     config.insert(
         OdooConfigOptions::AuthType.to_string(),
         "AUTH_LDAP".into(),
     );
     config.insert(
         OdooConfigOptions::AuthLdapServer.to_string(),
-        format!(
-            "{protocol}{server_hostname}:{server_port}",
-            protocol = match ldap.tls {
-                None => "ldap://",
-                Some(_) => "ldaps://",
-            },
-            server_hostname = ldap.hostname,
-            server_port = ldap.port.unwrap_or_else(|| ldap.default_port()),
-        ),
+        ldap_server_uri(ldap),
     );
     config.insert(
         OdooConfigOptions::AuthLdapSearch.to_string(),
@@ -124,6 +306,19 @@ fn append_ldap_config(config: &mut BTreeMap<String, String>, ldap: &LdapAuthenti
                         false.to_string(),
                     );
                     if let Some(ca_path) = ldap.tls_ca_cert_mount_path() {
+                        if tls_client_certificate {
+                            // The SecretClass volume contains the certificate and key issued
+                            // for the pod next to the CA certificate
+                            let ca_cert_path = Path::new(&ca_path);
+                            config.insert(
+                                OdooConfigOptions::AuthLdapTlsCertfile.to_string(),
+                                ca_cert_path.with_file_name("tls.crt").display().to_string(),
+                            );
+                            config.insert(
+                                OdooConfigOptions::AuthLdapTlsKeyfile.to_string(),
+                                ca_cert_path.with_file_name("tls.key").display().to_string(),
+                            );
+                        }
                         config.insert(
                             OdooConfigOptions::AuthLdapTlsCacertfile.to_string(),
                             ca_path,
@@ -146,9 +341,170 @@ fn append_ldap_config(config: &mut BTreeMap<String, String>, ldap: &LdapAuthenti
     }
 }
 
+/// Renders the roles mapping as Python dictionary, e.g.
+/// `{'cn=admins,ou=groups,dc=example,dc=org': ['Admin']}`
+fn roles_mapping_expression(roles_mapping: &BTreeMap<String, Vec<String>>) -> String {
+    let entries = roles_mapping
+        .iter()
+        .map(|(group, roles)| {
+            let roles = roles
+                .iter()
+                .map(|role| python_string(role))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{}: [{roles}]", python_string(group))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{{{entries}}}")
+}
+
+fn ldap_server_uri(ldap: &LdapAuthenticationProvider) -> String {
+    format!(
+        "{protocol}{server_hostname}:{server_port}",
+        protocol = match ldap.tls {
+            None => "ldap://",
+            Some(_) => "ldaps://",
+        },
+        server_hostname = ldap.hostname,
+        server_port = ldap.port.unwrap_or_else(|| ldap.default_port()),
+    )
+}
+
+/// Renders a fallback LDAP server as Python dictionary with its own bind credentials and TLS
+/// settings, e.g. `{'server': 'ldaps://ldap:636', 'use_tls': True, ...}`
+fn fallback_ldap_server_expression(ldap: &LdapAuthenticationProvider) -> String {
+    let mut entries = vec![
+        format!("'server': '{}'", ldap_server_uri(ldap)),
+        format!("'use_tls': {}", python_bool(ldap.tls.is_some())),
+    ];
+    if let Some(tls) = &ldap.tls {
+        entries.push(format!(
+            "'allow_self_signed': {}",
+            python_bool(matches!(tls.verification, TlsVerification::None {}))
+        ));
+    }
+    if let Some(ca_path) = ldap.tls_ca_cert_mount_path() {
+        entries.push(format!("'tls_cacertfile': '{ca_path}'"));
+    }
+    if let Some((username_path, password_path)) = ldap.bind_credentials_mount_paths() {
+        entries.push(format!("'bind_user': open('{username_path}').read()"));
+        entries.push(format!("'bind_password': open('{password_path}').read()"));
+    }
+    format!("{{{}}}", entries.join(", "))
+}
+
+/// Renders the given string as Python string literal, so that it cannot break out of the
+/// expression it is embedded in.
+fn python_string(value: &str) -> String {
+    let mut literal = String::from("'");
+    for c in value.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '\'' => literal.push_str("\\'"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            c if c.is_control() => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('\'');
+    literal
+}
+
+fn python_bool(value: bool) -> &'static str {
+    if value {
+        "True"
+    } else {
+        "False"
+    }
+}
+
+fn append_oauth_config(config: &mut BTreeMap<String, String>, oauth: &OdooOAuthConfig) {
+    config.insert(
+        OdooConfigOptions::AuthType.to_string(),
+        "AUTH_OAUTH".into(),
+    );
+    let providers = oauth
+        .providers
+        .iter()
+        .enumerate()
+        .map(|(index, provider)| {
+            let settings = provider.settings();
+            let env_var_prefix = provider.env_var_prefix(index);
+            let client_id_env_var = format!("{env_var_prefix}_CLIENT_ID");
+            let client_secret_env_var = format!("{env_var_prefix}_CLIENT_SECRET");
+            format!(
+                "{{'name': {name}, 'icon': {icon}, 'token_key': 'access_token', \
+                'remote_app': {{\
+                'client_id': os.environ[{client_id_env_var}], \
+                'client_secret': os.environ[{client_secret_env_var}], \
+                'api_base_url': {api_base_url}, \
+                'client_kwargs': {{'scope': {scope}}}, \
+                'request_token_url': None, \
+                'access_token_url': {access_token_url}, \
+                'authorize_url': {authorize_url}}}}}",
+                name = python_string(settings.name),
+                icon = python_string(settings.icon),
+                client_id_env_var = python_string(&client_id_env_var),
+                client_secret_env_var = python_string(&client_secret_env_var),
+                api_base_url = python_string(&settings.api_base_url),
+                scope = python_string(settings.scope),
+                access_token_url = python_string(&settings.access_token_url),
+                authorize_url = python_string(&settings.authorize_url),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    config.insert(
+        OdooConfigOptions::OauthProviders.to_string(),
+        format!("[{providers}]"),
+    );
+}
+
+fn append_saml_config(config: &mut BTreeMap<String, String>, saml: &OdooSamlConfig) {
+    config.insert(
+        OdooConfigOptions::AuthType.to_string(),
+        "AUTH_SAML".into(),
+    );
+    match &saml.idp_metadata {
+        SamlIdpMetadata::Url(url) => {
+            config.insert(
+                OdooConfigOptions::AuthSamlIdpMetadataUrl.to_string(),
+                url.clone(),
+            );
+        }
+        SamlIdpMetadata::Secret(_) => {
+            config.insert(
+                OdooConfigOptions::AuthSamlIdpMetadataFile.to_string(),
+                format!("{SAML_METADATA_DIR}/{SAML_METADATA_FILE}"),
+            );
+        }
+    }
+    config.insert(
+        OdooConfigOptions::AuthSamlSpEntityId.to_string(),
+        saml.entity_id.clone(),
+    );
+    config.insert(
+        OdooConfigOptions::AuthSamlAttributeMapping.to_string(),
+        format!(
+            "{{{}}}",
+            saml.attribute_mapping
+                .iter()
+                .map(|(field, attribute)| format!(
+                    "{}: {}",
+                    python_string(field),
+                    python_string(attribute)
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    );
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::config::add_odoo_config;
+    use super::*;
     use crate::OdooCluster;
     use sovrin_cloud_crd::LdapRolesSyncMoment::Registration;
     use sovrin_cloud_crd::{OdooClusterAuthenticationConfig, OdooConfigOptions};
@@ -180,7 +536,7 @@ mod tests {
         add_odoo_config(
             &mut result,
             cluster.spec.cluster_config.authentication_config.as_ref(),
-            None,
+            &[],
         );
         assert_eq!(
             None,
@@ -246,14 +602,22 @@ mod tests {
         add_odoo_config(
             &mut result,
             cluster.spec.cluster_config.authentication_config.as_ref(),
-            Some(&authentication_class),
+            &[authentication_class],
         );
         assert_eq!(
             Some(OdooClusterAuthenticationConfig {
                 authentication_class: Some("odoo-with-ldap-server-veri-tls-ldap".to_string()),
+                authentication_classes: vec![],
+                ldap_tls_client_certificate: false,
+                db_fallback: false,
                 user_registration: true,
                 user_registration_role: "Admin".to_string(),
-                sync_roles_at: Registration
+                sync_roles_at: Registration,
+                roles_mapping: BTreeMap::new(),
+                oauth: None,
+                saml: None,
+                remote_user: None,
+                kerberos: None,
             }),
             cluster.spec.cluster_config.authentication_config
         );
@@ -265,4 +629,449 @@ mod tests {
         );
         println!("{result:#?}");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_opa_authorization() {
+        let authorization: OdooAuthorization = serde_yaml::from_str(
+            "
+            opa:
+              configMapName: opa
+              package: odoo
+            cacheTtlSeconds: 30
+            cacheMaxEntries: 1000
+            ",
+        )
+        .unwrap();
+
+        let mut result = BTreeMap::new();
+        add_opa_config(
+            &mut result,
+            &authorization,
+            "http://opa.default.svc.cluster.local:8081/v1/data/odoo/allow",
+        );
+        assert_eq!(
+            "OpaSecurityManager",
+            result
+                .get(&OdooConfigOptions::SecurityManagerClass.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            "http://opa.default.svc.cluster.local:8081/v1/data/odoo/allow",
+            result
+                .get(&OdooConfigOptions::AuthOpaUrl.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            "30",
+            result
+                .get(&OdooConfigOptions::AuthOpaCacheTtlSeconds.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            "1000",
+            result
+                .get(&OdooConfigOptions::AuthOpaCacheMaxEntries.to_string())
+                .unwrap()
+        );
+
+        // The security manager class is only defined if it is used
+        assert_eq!(PYTHON_IMPORTS.to_vec(), python_imports(None));
+        assert!(python_imports(Some(&authorization))
+            .contains(&"class OpaSecurityManager(OdooSecurityManager):"));
+    }
+
+    #[test]
+    fn test_opa_decisions_are_bounded() {
+        // OPA is stubbed and allows everything, the requested resources are recorded
+        let stubs = "\
+import io, json, sys, types, urllib.request
+flask = types.ModuleType('flask')
+flask.current_app = types.SimpleNamespace(config={
+    'AUTH_OPA_URL': 'http://opa',
+    'AUTH_OPA_CACHE_TTL_SECONDS': 60,
+    'AUTH_OPA_CACHE_MAX_ENTRIES': 2,
+})
+sys.modules['flask'] = flask
+security = types.ModuleType('odoo.www.security')
+security.OdooSecurityManager = object
+sys.modules['odoo.www.security'] = security
+requests = []
+def urlopen(request, timeout):
+    requests.append(json.loads(request.data)['input']['resource'])
+    return io.BytesIO(b'{\"result\": true}')
+urllib.request.urlopen = urlopen
+";
+        let checks = "\
+user = types.SimpleNamespace(username='jane', roles=[], is_anonymous=False)
+manager = OpaSecurityManager()
+for resource in ['a', 'b', 'a', 'c', 'a', 'b']:
+    assert manager.has_access('can_read', resource, user)
+print(len(manager.opa_decisions), ','.join(requests))
+";
+        let output = std::process::Command::new("python3")
+            .args([
+                "-c",
+                &format!("{stubs}{}\n{checks}", OPA_SECURITY_MANAGER.join("\n")),
+            ])
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        // The least recently used decision is evicted first
+        assert_eq!("2 a,b,c,b\n", String::from_utf8_lossy(&output.stdout));
+    }
+
+    #[test]
+    fn test_saml() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            authenticationConfig:
+              saml:
+                idpMetadata:
+                  secret: odoo-saml-metadata
+                entityId: https://odoo.example.com
+                attributeMapping:
+                  email: mail
+                  firstname: givenName
+          ",
+        )
+            .unwrap();
+
+        let mut result = BTreeMap::new();
+        add_odoo_config(
+            &mut result,
+            cluster.spec.cluster_config.authentication_config.as_ref(),
+            &[],
+        );
+        assert_eq!(
+            "AUTH_SAML",
+            result
+                .get(&OdooConfigOptions::AuthType.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            "/stackable/saml/metadata.xml",
+            result
+                .get(&OdooConfigOptions::AuthSamlIdpMetadataFile.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            "{'email': 'mail', 'firstname': 'givenName'}",
+            result
+                .get(&OdooConfigOptions::AuthSamlAttributeMapping.to_string())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_python_string() {
+        assert_eq!("'givenName'", python_string("givenName"));
+        assert_eq!(
+            r"'mail\', os.system(\'id\'), \'\\n\n'",
+            python_string("mail', os.system('id'), '\\n\n")
+        );
+        assert_eq!(r"'\u0000'", python_string("\0"));
+    }
+
+    #[test]
+    fn test_roles_mapping_is_escaped() {
+        let roles_mapping = BTreeMap::from([(
+            "cn=o'brien,ou=groups".to_string(),
+            vec!["Admin".to_string(), "User']}, {'x': ['".to_string()],
+        )]);
+        assert_eq!(
+            r"{'cn=o\'brien,ou=groups': ['Admin', 'User\']}, {\'x\': [\'']}",
+            roles_mapping_expression(&roles_mapping)
+        );
+    }
+
+    #[test]
+    fn test_saml_attribute_mapping_is_escaped() {
+        let saml: OdooSamlConfig = serde_yaml::from_str(
+            "
+            idpMetadata:
+              url: https://idp.example.com/metadata
+            entityId: https://odoo.example.com
+            attributeMapping:
+              email: \"mail'} or __import__('os').system('id') or {'\"
+            ",
+        )
+        .unwrap();
+
+        let mut result = BTreeMap::new();
+        append_saml_config(&mut result, &saml);
+        assert_eq!(
+            r"{'email': 'mail\'} or __import__(\'os\').system(\'id\') or {\''}",
+            result
+                .get(&OdooConfigOptions::AuthSamlAttributeMapping.to_string())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ldap_tls_client_certificate() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            authenticationConfig:
+              authenticationClass: ldap
+              ldapTlsClientCertificate: true
+          ",
+        )
+            .unwrap();
+        let authentication_class: AuthenticationClass = serde_yaml::from_str::<AuthenticationClass>(
+            "
+            apiVersion: authentication.stackable.tech/v1alpha1
+            kind: AuthenticationClass
+            metadata:
+              name: ldap
+            spec:
+              provider:
+                ldap:
+                  hostname: openldap.default.svc.cluster.local
+                  searchBase: ou=users,dc=example,dc=org
+                  tls:
+                    verification:
+                      server:
+                        caCert:
+                          secretClass: openldap-tls
+          ",
+        )
+            .unwrap();
+        let ca_path = match &authentication_class.spec.provider {
+            AuthenticationClassProvider::Ldap(ldap) => ldap.tls_ca_cert_mount_path().unwrap(),
+            _ => unreachable!(),
+        };
+        let certificate_dir = Path::new(&ca_path).parent().unwrap().display().to_string();
+
+        let mut result = BTreeMap::new();
+        add_odoo_config(
+            &mut result,
+            cluster.spec.cluster_config.authentication_config.as_ref(),
+            &[authentication_class],
+        );
+        assert_eq!(
+            &ca_path,
+            result
+                .get(&OdooConfigOptions::AuthLdapTlsCacertfile.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            &format!("{certificate_dir}/tls.crt"),
+            result
+                .get(&OdooConfigOptions::AuthLdapTlsCertfile.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            &format!("{certificate_dir}/tls.key"),
+            result
+                .get(&OdooConfigOptions::AuthLdapTlsKeyfile.to_string())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_oauth_providers_with_same_preset() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            authenticationConfig:
+              oauth:
+                providers:
+                  - preset: azureAd
+                    clientCredentialsSecret: azure-tenant-a
+                    tenantId: tenant-a
+                  - preset: azureAd
+                    clientCredentialsSecret: azure-tenant-b
+                    tenantId: tenant-b
+          ",
+        )
+            .unwrap();
+
+        let mut result = BTreeMap::new();
+        add_odoo_config(
+            &mut result,
+            cluster.spec.cluster_config.authentication_config.as_ref(),
+            &[],
+        );
+        assert_eq!(
+            "AUTH_OAUTH",
+            result
+                .get(&OdooConfigOptions::AuthType.to_string())
+                .unwrap()
+        );
+        let providers = result
+            .get(&OdooConfigOptions::OauthProviders.to_string())
+            .unwrap();
+        for (prefix, tenant_id) in [
+            ("OAUTH_0_AZUREAD", "tenant-a"),
+            ("OAUTH_1_AZUREAD", "tenant-b"),
+        ] {
+            assert!(providers.contains(&format!(
+                "'client_id': os.environ['{prefix}_CLIENT_ID'], \
+                'client_secret': os.environ['{prefix}_CLIENT_SECRET'], \
+                'api_base_url': 'https://login.microsoftonline.com/{tenant_id}/oauth2'"
+            )));
+        }
+        // The security manager reads the user info by the name of the provider
+        assert!(providers.starts_with("[{'name': 'azure', 'icon': 'fa-windows'"));
+    }
+
+    #[test]
+    fn test_oauth_tenant_id_is_escaped() {
+        let oauth: OdooOAuthConfig = serde_yaml::from_str(
+            "
+            providers:
+              - preset: azureAd
+                clientCredentialsSecret: azure
+                tenantId: \"x'}, {'name': 'evil\"
+            ",
+        )
+        .unwrap();
+
+        let mut result = BTreeMap::new();
+        append_oauth_config(&mut result, &oauth);
+        let providers = result
+            .get(&OdooConfigOptions::OauthProviders.to_string())
+            .unwrap();
+        assert!(providers.contains(
+            r"'api_base_url': 'https://login.microsoftonline.com/x\'}, {\'name\': \'evil/oauth2'"
+        ));
+    }
+
+    #[test]
+    fn test_ldap_fallback_servers_and_roles_mapping() {
+        let cluster: OdooCluster = serde_yaml::from_str::<OdooCluster>(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            authenticationConfig:
+              authenticationClasses:
+                - primary-ldap
+                - secondary-ldap
+              dbFallback: true
+              rolesMapping:
+                cn=admins,ou=groups,dc=example,dc=org:
+                  - Admin
+                cn=users,ou=groups,dc=example,dc=org:
+                  - User
+                  - Viewer
+          ",
+        )
+            .unwrap();
+
+        let authentication_classes = [
+            "primary",
+            "secondary
+                  bindCredentials:
+                    secretClass: secondary-ldap-bind
+                  tls:
+                    verification:
+                      server:
+                        caCert:
+                          secretClass: secondary-ldap-tls",
+        ]
+        .map(|name| {
+            serde_yaml::from_str::<AuthenticationClass>(&format!(
+                "
+            apiVersion: authentication.stackable.tech/v1alpha1
+            kind: AuthenticationClass
+            metadata:
+              name: ldap
+            spec:
+              provider:
+                ldap:
+                  searchBase: ou=users,dc=example,dc=org
+                  hostname: {name}"
+            ))
+            .unwrap()
+        });
+        let secondary_ldap = match &authentication_classes[1].spec.provider {
+            AuthenticationClassProvider::Ldap(ldap) => ldap,
+            _ => unreachable!(),
+        };
+        let (username_path, password_path) = secondary_ldap.bind_credentials_mount_paths().unwrap();
+        let ca_path = secondary_ldap.tls_ca_cert_mount_path().unwrap();
+
+        let mut result = BTreeMap::new();
+        add_odoo_config(
+            &mut result,
+            cluster.spec.cluster_config.authentication_config.as_ref(),
+            &authentication_classes,
+        );
+        assert_eq!(
+            "ldap://primary:389",
+            result
+                .get(&OdooConfigOptions::AuthLdapServer.to_string())
+                .unwrap()
+        );
+        // The fallback server brings its own bind credentials and TLS settings
+        assert_eq!(
+            &format!(
+                "[{{'server': 'ldaps://secondary:636', 'use_tls': True, \
+                'allow_self_signed': False, 'tls_cacertfile': '{ca_path}', \
+                'bind_user': open('{username_path}').read(), \
+                'bind_password': open('{password_path}').read()}}]"
+            ),
+            result
+                .get(&OdooConfigOptions::AuthLdapFallbackServers.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            result.get(&OdooConfigOptions::AuthLdapBindUser.to_string())
+        );
+        assert_eq!(
+            "true",
+            result
+                .get(&OdooConfigOptions::AuthDbFallback.to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            "{'cn=admins,ou=groups,dc=example,dc=org': ['Admin'], \
+            'cn=users,ou=groups,dc=example,dc=org': ['User', 'Viewer']}",
+            result
+                .get(&OdooConfigOptions::AuthRolesMapping.to_string())
+                .unwrap()
+        );
+    }
+}
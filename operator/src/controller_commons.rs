@@ -1,7 +1,14 @@
-use sovrin_cloud_crd::MAX_LOG_FILES_SIZE;
+use sovrin_cloud_crd::{
+    CaBundle, OdooComplianceConfig, PodSecurityStandard, MAX_LOG_FILES_SIZE,
+};
 use stackable_operator::{
     builder::VolumeBuilder,
-    k8s_openapi::api::core::v1::{ConfigMapVolumeSource, EmptyDirVolumeSource, Volume},
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::api::core::v1::{
+        Capabilities, ConfigMapVolumeSource, Container, EmptyDirVolumeSource, EnvVar,
+        KeyToPath, PodSecurityContext, PodSpec, SeccompProfile, SecurityContext, Volume, VolumeMount,
+    },
+    memory::MemoryQuantity,
     product_logging::{
         self,
         spec::{
@@ -15,10 +22,31 @@ pub const CONFIG_VOLUME_NAME: &str = "config";
 pub const LOG_CONFIG_VOLUME_NAME: &str = "log-config";
 pub const LOG_VOLUME_NAME: &str = "log";
 
+const CA_BUNDLE_SOURCE_VOLUME_NAME: &str = "ca-bundle-source";
+const CA_BUNDLE_SOURCE_DIR: &str = "/stackable/ca-bundle-source";
+const CA_BUNDLE_SOURCE_FILE: &str = "ca.crt";
+const TRUST_STORE_VOLUME_NAME: &str = "trust-store";
+const TRUST_STORE_DIR: &str = "/stackable/trust-store";
+const SYSTEM_CA_BUNDLE: &str = "/etc/pki/tls/certs/ca-bundle.crt";
+/// Environment variables pointing the TLS clients used by Odoo (OpenSSL, requests, OpenLDAP
+/// and git) to a CA bundle.
+const CA_BUNDLE_ENV_VARS: &[&str] = &[
+    "SSL_CERT_FILE",
+    "REQUESTS_CA_BUNDLE",
+    "LDAPTLS_CACERT",
+    "GIT_SSL_CAINFO",
+];
+
+/// The log volume is sized for `MAX_LOG_FILES_SIZE` and the `extra_log_files_sizes` of log files
+/// which are rotated differently than the default.
 pub fn create_volumes(
     config_map_name: &str,
     log_config: Option<&ContainerLogConfig>,
+    extra_log_files_sizes: &[MemoryQuantity],
 ) -> Vec<Volume> {
+    let mut log_files_sizes = vec![MAX_LOG_FILES_SIZE];
+    log_files_sizes.extend_from_slice(extra_log_files_sizes);
+
     let mut volumes = Vec::new();
 
     volumes.push(
@@ -31,7 +59,7 @@ pub fn create_volumes(
         empty_dir: Some(EmptyDirVolumeSource {
             medium: None,
             size_limit: Some(product_logging::framework::calculate_log_volume_size_limit(
-                &[MAX_LOG_FILES_SIZE],
+                &log_files_sizes,
             )),
         }),
         ..Volume::default()
@@ -64,4 +92,239 @@ pub fn create_volumes(
     }
 
     volumes
-}
\ No newline at end of file
+}
+
+/// Makes all containers of the pod trust the given CA bundle in addition to the system CAs.
+///
+/// An init container appends the bundle to the system CAs of the product image, the result is
+/// shared with all containers and referenced by the environment variables of common TLS clients.
+pub fn add_ca_bundle(
+    pod_spec: &mut PodSpec,
+    ca_bundle: &CaBundle,
+    resolved_product_image: &ResolvedProductImage,
+) {
+    let trust_store_mount = VolumeMount {
+        name: TRUST_STORE_VOLUME_NAME.to_string(),
+        mount_path: TRUST_STORE_DIR.to_string(),
+        ..VolumeMount::default()
+    };
+    let ca_bundle_file = format!("{TRUST_STORE_DIR}/ca-bundle.crt");
+
+    for container in pod_spec
+        .containers
+        .iter_mut()
+        .chain(pod_spec.init_containers.iter_mut().flatten())
+    {
+        container
+            .volume_mounts
+            .get_or_insert_with(Vec::new)
+            .push(trust_store_mount.clone());
+        container
+            .env
+            .get_or_insert_with(Vec::new)
+            .extend(CA_BUNDLE_ENV_VARS.iter().map(|name| EnvVar {
+                name: name.to_string(),
+                value: Some(ca_bundle_file.clone()),
+                ..EnvVar::default()
+            }));
+    }
+
+    pod_spec.init_containers.get_or_insert_with(Vec::new).insert(
+        0,
+        Container {
+            name: "create-trust-store".to_string(),
+            image: Some(resolved_product_image.image.clone()),
+            image_pull_policy: Some(resolved_product_image.image_pull_policy.clone()),
+            command: Some(vec!["/bin/bash".to_string(), "-c".to_string()]),
+            args: Some(vec![format!(
+                "cat {SYSTEM_CA_BUNDLE} {CA_BUNDLE_SOURCE_DIR}/{CA_BUNDLE_SOURCE_FILE} > {ca_bundle_file}"
+            )]),
+            volume_mounts: Some(vec![
+                VolumeMount {
+                    name: CA_BUNDLE_SOURCE_VOLUME_NAME.to_string(),
+                    mount_path: CA_BUNDLE_SOURCE_DIR.to_string(),
+                    read_only: Some(true),
+                    ..VolumeMount::default()
+                },
+                trust_store_mount,
+            ]),
+            ..Container::default()
+        },
+    );
+
+    pod_spec.volumes.get_or_insert_with(Vec::new).extend([
+        // The key is mapped to a fixed file name, so it does not end up in the shell command
+        Volume {
+            name: CA_BUNDLE_SOURCE_VOLUME_NAME.to_string(),
+            config_map: Some(ConfigMapVolumeSource {
+                name: Some(ca_bundle.config_map_name.clone()),
+                items: Some(vec![KeyToPath {
+                    key: ca_bundle.key.clone(),
+                    path: CA_BUNDLE_SOURCE_FILE.to_string(),
+                    mode: None,
+                }]),
+                ..ConfigMapVolumeSource::default()
+            }),
+            ..Volume::default()
+        },
+        VolumeBuilder::new(TRUST_STORE_VOLUME_NAME)
+            .empty_dir(EmptyDirVolumeSource::default())
+            .build(),
+    ]);
+}
+
+/// Adjusts the pod and all of its containers to the given compliance settings.
+///
+/// No ServiceAccount token is mounted unless requested. Unless disabled, all containers drop all capabilities and must not escalate privileges.
+/// The `restricted` Pod Security Standard additionally requires non-root containers with the
+/// `RuntimeDefault` seccomp profile and always implies the container hardening.
+pub fn apply_compliance(pod_spec: &mut PodSpec, compliance: &OdooComplianceConfig) {
+    pod_spec.automount_service_account_token =
+        Some(compliance.automount_service_account_token.unwrap_or(false));
+
+    let restricted = compliance.pod_security_standard == PodSecurityStandard::Restricted;
+    if !restricted && !compliance.harden_containers {
+        return;
+    }
+
+    if restricted {
+        let pod_security_context = pod_spec
+            .security_context
+            .get_or_insert_with(PodSecurityContext::default);
+        pod_security_context.run_as_non_root = Some(true);
+        pod_security_context.seccomp_profile = Some(runtime_default_seccomp_profile());
+    }
+
+    for container in pod_spec
+        .containers
+        .iter_mut()
+        .chain(pod_spec.init_containers.iter_mut().flatten())
+    {
+        let security_context = container
+            .security_context
+            .get_or_insert_with(SecurityContext::default);
+        security_context.allow_privilege_escalation = Some(false);
+        security_context.capabilities = Some(Capabilities {
+            drop: Some(vec!["ALL".to_string()]),
+            add: None,
+        });
+        if restricted {
+            security_context.run_as_non_root = Some(true);
+            security_context.privileged = Some(false);
+            security_context.seccomp_profile = Some(runtime_default_seccomp_profile());
+        }
+    }
+}
+
+fn runtime_default_seccomp_profile() -> SeccompProfile {
+    SeccompProfile {
+        type_: "RuntimeDefault".to_string(),
+        localhost_profile: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_operator::commons::product_image_selection::ProductImage;
+
+    #[test]
+    fn test_add_ca_bundle() {
+        let resolved_product_image = serde_yaml::from_str::<ProductImage>(
+            "
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+            ",
+        )
+        .unwrap()
+        .resolve("odoo");
+        let ca_bundle: CaBundle = serde_yaml::from_str(
+            "
+            configMapName: corporate-ca
+            key: bundle with spaces.pem
+            ",
+        )
+        .unwrap();
+        let mut pod_spec = PodSpec {
+            containers: vec![Container {
+                name: "odoo".to_string(),
+                env: Some(vec![EnvVar {
+                    name: "ODOO_RC".to_string(),
+                    value: Some("/stackable/app/config/odoo.conf".to_string()),
+                    ..EnvVar::default()
+                }]),
+                ..Container::default()
+            }],
+            init_containers: Some(vec![Container {
+                name: "gitsync-1".to_string(),
+                ..Container::default()
+            }]),
+            ..PodSpec::default()
+        };
+
+        add_ca_bundle(&mut pod_spec, &ca_bundle, &resolved_product_image);
+
+        // The trust store is created before any other init container runs
+        let init_containers = pod_spec.init_containers.as_ref().unwrap();
+        assert_eq!(
+            vec!["create-trust-store", "gitsync-1"],
+            init_containers
+                .iter()
+                .map(|container| container.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(vec![
+                "cat /etc/pki/tls/certs/ca-bundle.crt /stackable/ca-bundle-source/ca.crt \
+                 > /stackable/trust-store/ca-bundle.crt"
+                    .to_string()
+            ]),
+            init_containers[0].args
+        );
+        assert_eq!(Some(resolved_product_image.image), init_containers[0].image);
+
+        for container in pod_spec.containers.iter().chain(init_containers) {
+            assert!(container
+                .volume_mounts
+                .iter()
+                .flatten()
+                .any(|mount| mount.name == TRUST_STORE_VOLUME_NAME
+                    && mount.mount_path == TRUST_STORE_DIR));
+            if container.name == "create-trust-store" {
+                continue;
+            }
+            for name in CA_BUNDLE_ENV_VARS {
+                assert!(
+                    container.env.iter().flatten().any(|env_var| &env_var.name == name
+                        && env_var.value.as_deref()
+                            == Some("/stackable/trust-store/ca-bundle.crt")),
+                    "{name} is not set in {}",
+                    container.name
+                );
+            }
+        }
+        // Existing environment variables are kept
+        assert!(pod_spec.containers[0]
+            .env
+            .iter()
+            .flatten()
+            .any(|env_var| env_var.name == "ODOO_RC"));
+
+        let source_volume = pod_spec
+            .volumes
+            .iter()
+            .flatten()
+            .find(|volume| volume.name == CA_BUNDLE_SOURCE_VOLUME_NAME)
+            .and_then(|volume| volume.config_map.clone())
+            .unwrap();
+        assert_eq!(Some("corporate-ca".to_string()), source_volume.name);
+        assert_eq!(
+            Some(vec![KeyToPath {
+                key: "bundle with spaces.pem".to_string(),
+                path: "ca.crt".to_string(),
+                mode: None,
+            }]),
+            source_volume.items
+        );
+    }
+}
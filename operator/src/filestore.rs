@@ -0,0 +1,405 @@
+//! Prepares the PersistentVolumeClaim of the filestore before the cluster is rolled out, if
+//! requested in `clusterConfig.filestore.initialization`.
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{
+    filestore::{
+        FilestoreConfig, FilestoreInitialization, FILESTORE_DIR, FILESTORE_SOURCE_DIR,
+        FILESTORE_SOURCE_VOLUME_NAME, FILESTORE_VOLUME_NAME,
+    },
+    web::shell_quote,
+    OdooCluster, AIRFLOW_UID,
+};
+use stackable_operator::{
+    builder::{ContainerBuilder, ObjectMetaBuilder, PodSecurityContextBuilder},
+    client::Client,
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::api::{
+        batch::v1::{Job, JobSpec},
+        core::v1::{PodSpec, PodTemplateSpec},
+    },
+    kube::ResourceExt,
+};
+use std::collections::BTreeMap;
+
+use crate::{
+    controller_commons, network_policy,
+    odoo_controller::AIRFLOW_CONTROLLER_NAME,
+    utils::{add_labels, delete_job, get_job_state, JobState},
+};
+
+const CONTAINER_NAME: &str = "filestore-init";
+/// Marks the claim as initialized and contains the id of the copied source, so a recreated job
+/// does not copy the same source again.
+const INITIALIZED_MARKER: &str = ".initialized";
+/// The claim and source the job initializes, so that the job is replaced when they change.
+const INITIALIZATION_ANNOTATION: &str = "odoo.sovrin.cloud/filestore-initialization";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("invalid container name"))]
+    InvalidContainerName {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to retrieve the filestore job [{name}]"))]
+    GetFilestoreJob {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to apply the filestore job [{name}]"))]
+    ApplyFilestoreJob {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to delete the outdated filestore job [{name}]"))]
+    DeleteFilestoreJob {
+        source: stackable_operator::kube::Error,
+        name: String,
+    },
+    #[snafu(display("invalid resources of the filestore job"))]
+    InvalidResources {
+        source: stackable_operator::config::fragment::ValidationError,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Starts the initialization of the filestore if it was not started yet, and returns its state.
+/// A job of a previous claim or source is replaced.
+pub async fn reconcile_filestore(
+    client: &Client,
+    odoo: &OdooCluster,
+    config: &FilestoreConfig,
+    initialization: &FilestoreInitialization,
+    resolved_product_image: &ResolvedProductImage,
+    sa_name: &str,
+    selected_labels: &BTreeMap<String, String>,
+) -> Result<JobState> {
+    let name = job_name(odoo);
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    if let Some(job) = client
+        .get_opt::<Job>(&name, &namespace)
+        .await
+        .context(GetFilestoreJobSnafu { name: &name })?
+    {
+        if is_outdated(&job, config, initialization) {
+            tracing::info!(
+                claim = config.claim_name,
+                "The filestore claim or source changed, replacing the initialization job"
+            );
+            delete_job(client, &job)
+                .await
+                .context(DeleteFilestoreJobSnafu { name: &name })?;
+            // The job is owned by the cluster, so its deletion triggers a reconciliation
+            return Ok(JobState::InProgress);
+        }
+        return Ok(get_job_state(&job));
+    }
+
+    tracing::info!(claim = config.claim_name, "Initializing the filestore");
+    let mut job = build_filestore_job(
+        odoo,
+        config,
+        initialization,
+        resolved_product_image,
+        sa_name,
+        &name,
+    )?;
+    add_labels(&mut job, selected_labels);
+    let job = client
+        .apply_patch(AIRFLOW_CONTROLLER_NAME, &job, &job)
+        .await
+        .context(ApplyFilestoreJobSnafu { name: &name })?;
+    Ok(get_job_state(&job))
+}
+
+fn job_name(odoo: &OdooCluster) -> String {
+    format!("{}-filestore-init", odoo.name_any())
+}
+
+fn initialization_id(config: &FilestoreConfig, initialization: &FilestoreInitialization) -> String {
+    format!("{}:{}", config.claim_name, initialization.source_id())
+}
+
+fn is_outdated(
+    job: &Job,
+    config: &FilestoreConfig,
+    initialization: &FilestoreInitialization,
+) -> bool {
+    job.annotations().get(INITIALIZATION_ANNOTATION)
+        != Some(&initialization_id(config, initialization))
+}
+
+/// The commands creating the directory layout of the data directory of Odoo. The job runs as
+/// the Odoo user, so the copied files are owned by it.
+///
+/// The claim is initialized if it has no marker yet or if the marker records another source.
+/// An empty marker was written by an older version of the operator and is left untouched.
+fn initialize_commands(initialization: &FilestoreInitialization) -> Vec<String> {
+    let marker = format!("{FILESTORE_DIR}/{INITIALIZED_MARKER}");
+    let marker_content = shell_quote(&format!("source={}", initialization.source_id()));
+    let mut init_commands = Vec::new();
+    if let Some(source) = &initialization.source {
+        init_commands.push(format!(
+            "cp -a {}/. {FILESTORE_DIR}/",
+            shell_quote(&source.dir())
+        ));
+    }
+    init_commands.extend([
+        format!(
+            "mkdir -p {FILESTORE_DIR}/filestore {FILESTORE_DIR}/sessions {FILESTORE_DIR}/addons"
+        ),
+        format!("printf '%s' {marker_content} > {marker}"),
+    ]);
+    vec![
+        String::from("set -euo pipefail"),
+        format!(
+            "if [ ! -e {marker} ] || \
+            {{ [ -s {marker} ] && [ \"$(cat {marker})\" != {marker_content} ]; }}; \
+            then {}; fi",
+            init_commands.join("; ")
+        ),
+    ]
+}
+
+/// The job runs as the Odoo user, the `fsGroup` gives it write access to the claim.
+fn build_filestore_job(
+    odoo: &OdooCluster,
+    config: &FilestoreConfig,
+    initialization: &FilestoreInitialization,
+    resolved_product_image: &ResolvedProductImage,
+    sa_name: &str,
+    name: &str,
+) -> Result<Job> {
+    let mut volumes = vec![config.volume()];
+    let mut cb = ContainerBuilder::new(CONTAINER_NAME).context(InvalidContainerNameSnafu)?;
+    cb.image_from_product_image(resolved_product_image)
+        .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+        .args(vec![initialize_commands(initialization).join("; ")])
+        .add_volume_mount(FILESTORE_VOLUME_NAME, FILESTORE_DIR)
+        .resources(
+            initialization
+                .resources()
+                .context(InvalidResourcesSnafu)?
+                .into(),
+        );
+    if let Some(source) = &initialization.source {
+        volumes.push(source.volume());
+        cb.add_volume_mount(FILESTORE_SOURCE_VOLUME_NAME, FILESTORE_SOURCE_DIR);
+    }
+
+    let mut pod_spec = PodSpec {
+        containers: vec![cb.build()],
+        restart_policy: Some("Never".to_string()),
+        service_account: Some(sa_name.to_string()),
+        image_pull_secrets: resolved_product_image.pull_secrets.clone(),
+        security_context: Some(
+            PodSecurityContextBuilder::new()
+                .run_as_user(AIRFLOW_UID)
+                .run_as_group(0)
+                .fs_group(1000)
+                .build(),
+        ),
+        volumes: Some(volumes),
+        ..Default::default()
+    };
+    controller_commons::apply_compliance(&mut pod_spec, &odoo.spec.cluster_config.compliance);
+
+    Ok(Job {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(name)
+            .with_annotation(
+                INITIALIZATION_ANNOTATION,
+                initialization_id(config, initialization),
+            )
+            .ownerreference_from_resource(odoo, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            .build(),
+        spec: Some(JobSpec {
+            template: PodTemplateSpec {
+                metadata: Some(network_policy::job_pod_metadata(
+                    odoo,
+                    AIRFLOW_CONTROLLER_NAME,
+                    &resolved_product_image.app_version_label,
+                    "filestore",
+                )),
+                spec: Some(pod_spec),
+            },
+            ..Default::default()
+        }),
+        status: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_operator::k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+
+    fn odoo_and_filestore() -> (OdooCluster, FilestoreConfig) {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooCluster
+            metadata:
+              name: odoo
+              namespace: default
+              uid: 12345678-1234-1234-1234-123456789012
+            spec:
+              image:
+                productVersion: 2.6.1
+                stackableVersion: 0.0.0-dev
+              clusterConfig:
+                credentialsSecret: odoo-credentials
+                compliance:
+                  podSecurityStandard: restricted
+                filestore:
+                  claimName: odoo-filestore
+                  initialization:
+                    source:
+                      claimName: legacy-odoo
+                      path: odoo
+                    resources:
+                      memory:
+                        limit: 1Gi
+            ",
+        )
+        .unwrap();
+        let config = odoo.spec.cluster_config.filestore.clone().unwrap();
+        (odoo, config)
+    }
+
+    fn build_job(odoo: &OdooCluster, config: &FilestoreConfig) -> Job {
+        build_filestore_job(
+            odoo,
+            config,
+            config.initialization.as_ref().unwrap(),
+            &odoo.spec.image.resolve("odoo"),
+            "odoo-serviceaccount",
+            "odoo-filestore-init",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_initialize_commands() {
+        let initialization: FilestoreInitialization = serde_yaml::from_str(
+            "
+            source:
+              claimName: legacy-odoo
+              path: odoo's files
+            ",
+        )
+        .unwrap();
+
+        let commands = initialize_commands(&initialization).join("; ");
+        assert!(commands.starts_with(
+            "set -euo pipefail; if [ ! -e /stackable/filestore/.initialized ] || \
+            { [ -s /stackable/filestore/.initialized ] && \
+            [ \"$(cat /stackable/filestore/.initialized)\" != \
+            'source=legacy-odoo/odoo'\\''s files' ]; }; \
+            then cp -a '/stackable/filestore-source/odoo'\\''s files'/. \
+            /stackable/filestore/; mkdir -p"
+        ));
+        assert!(!commands.contains("chown"));
+    }
+
+    #[test]
+    fn test_initialize_commands_copy_each_source_once() {
+        let dir = std::env::temp_dir().join(format!("filestore-init-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("source/odoo/filestore")).unwrap();
+        std::fs::write(dir.join("source/odoo/filestore/attachment"), "legacy").unwrap();
+        std::fs::create_dir_all(dir.join("filestore")).unwrap();
+
+        let run = |source: Option<&str>| {
+            let initialization = FilestoreInitialization {
+                source: source.map(|path| {
+                    serde_yaml::from_str(&format!("{{claimName: legacy-odoo, path: {path}}}"))
+                        .unwrap()
+                }),
+                ..Default::default()
+            };
+            let source_dir = dir.join("source").display().to_string();
+            let filestore_dir = dir.join("filestore").display().to_string();
+            let script = initialize_commands(&initialization)
+                .join("; ")
+                .replace(FILESTORE_SOURCE_DIR, &source_dir)
+                .replace(FILESTORE_DIR, &filestore_dir);
+            let status = std::process::Command::new("bash")
+                .args(["-c", &script])
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        let attachment = dir.join("filestore/filestore/attachment");
+
+        run(Some("odoo"));
+        assert_eq!("legacy", std::fs::read_to_string(&attachment).unwrap());
+
+        // The same source is not copied over the files changed in the meantime
+        std::fs::write(&attachment, "changed").unwrap();
+        run(Some("odoo"));
+        assert_eq!("changed", std::fs::read_to_string(&attachment).unwrap());
+
+        // A changed source is copied
+        run(Some("/odoo/filestore/.."));
+        assert_eq!("legacy", std::fs::read_to_string(&attachment).unwrap());
+
+        // Claims initialized by an older version of the operator are left untouched
+        std::fs::write(&attachment, "changed").unwrap();
+        std::fs::write(dir.join("filestore").join(INITIALIZED_MARKER), "").unwrap();
+        run(Some("odoo"));
+        assert_eq!("changed", std::fs::read_to_string(&attachment).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_filestore_job_runs_as_odoo_user() {
+        let (odoo, config) = odoo_and_filestore();
+        let job = build_job(&odoo, &config);
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+
+        let security_context = pod_spec.security_context.unwrap();
+        assert_eq!(Some(AIRFLOW_UID), security_context.run_as_user);
+        assert_eq!(Some(1000), security_context.fs_group);
+        // The compliance settings of the cluster apply to the job
+        assert_eq!(Some(true), security_context.run_as_non_root);
+
+        let limits = pod_spec.containers[0]
+            .resources
+            .clone()
+            .unwrap()
+            .limits
+            .unwrap();
+        assert_eq!(Some(&Quantity("1Gi".to_owned())), limits.get("memory"));
+    }
+
+    #[test]
+    fn test_changed_claim_or_source_outdates_the_job() {
+        let (odoo, config) = odoo_and_filestore();
+        let job = build_job(&odoo, &config);
+        let initialization = config.initialization.clone().unwrap();
+        assert!(!is_outdated(&job, &config, &initialization));
+
+        let mut changed_config = config.clone();
+        changed_config.claim_name = "new-odoo-filestore".to_string();
+        assert!(is_outdated(&job, &changed_config, &initialization));
+
+        let mut changed_initialization = initialization.clone();
+        changed_initialization.source = None;
+        assert!(is_outdated(&job, &config, &changed_initialization));
+
+        // Jobs of older versions of the operator have no annotation
+        let mut job = job;
+        job.metadata.annotations = None;
+        assert!(is_outdated(&job, &config, &initialization));
+    }
+}
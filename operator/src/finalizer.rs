@@ -0,0 +1,281 @@
+//! Cleans up the objects which are deliberately not owned by an OdooCluster, so they are not
+//! deleted by the garbage collector:
+//!
+//! * The OdooDB is deleted together with the cluster if the `Delete` deletion policy is selected
+//!   in `clusterConfig.databaseInitialization.deletionPolicy`.
+//! * The ServiceAccount and RoleBinding of the init jobs are shared by all OdooDBs of the
+//!   namespace and deleted together with the last one.
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{
+    odoodb::{DeletionPolicy, OdooDB},
+    OdooCluster,
+};
+use stackable_operator::{
+    client::Client,
+    k8s_openapi::{
+        api::{core::v1::ServiceAccount, rbac::v1::RoleBinding},
+        apimachinery::pkg::apis::meta::v1::ObjectMeta,
+    },
+    kube::{api::ListParams, Api, Resource, ResourceExt},
+};
+use std::fmt::Debug;
+
+use crate::rbac;
+
+pub const CLEANUP_FINALIZER: &str = "odoo.sovrin.cloud/cleanup";
+/// Added by earlier versions only if the OdooDB was deleted together with the cluster
+const LEGACY_ODOO_DB_FINALIZER: &str = "odoo.sovrin.cloud/delete-odoo-db";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to update the finalizers"))]
+    UpdateFinalizers {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to delete the OdooDB"))]
+    DeleteOdooDB {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to list the remaining OdooDBs"))]
+    ListOdooDBs {
+        source: stackable_operator::kube::Error,
+    },
+    #[snafu(display("failed to delete the RBAC objects of the init jobs"))]
+    DeleteInitRbac {
+        source: stackable_operator::error::Error,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Serialize)]
+struct MetadataPatch {
+    metadata: ObjectMeta,
+}
+
+/// Adds the finalizer, replacing the one of earlier versions.
+pub async fn ensure_finalizer(client: &Client, odoo: &OdooCluster) -> Result<()> {
+    match finalizers_with_cleanup(odoo) {
+        Some(finalizers) => patch_finalizers(client, odoo, finalizers).await,
+        None => Ok(()),
+    }
+}
+
+/// Cleans up a cluster which is being deleted according to its deletion policies. The objects
+/// owned by the OdooDB, like the init job, get deleted by the garbage collector.
+pub async fn finalize(client: &Client, odoo: &OdooCluster) -> Result<()> {
+    let Some(finalizers) = finalizers_after_cleanup(odoo) else {
+        return Ok(());
+    };
+
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    if odoo.database_deletion_policy() == DeletionPolicy::Delete {
+        if let Some(odoo_db) = client
+            .get_opt::<OdooDB>(&odoo.name_any(), &namespace)
+            .await
+            .context(DeleteOdooDBSnafu)?
+        {
+            client.delete(&odoo_db).await.context(DeleteOdooDBSnafu)?;
+            tracing::info!("Deleted the OdooDB");
+        }
+    }
+
+    let odoo_dbs = Api::<OdooDB>::namespaced(client.as_kube_client(), &namespace)
+        .list(&ListParams::default())
+        .await
+        .context(ListOdooDBsSnafu)?
+        .items;
+    if !init_rbac_in_use(&odoo_dbs) {
+        delete_init_rbac(client, odoo, &namespace).await?;
+    }
+
+    patch_finalizers(client, odoo, finalizers).await
+}
+
+async fn delete_init_rbac(client: &Client, odoo: &OdooCluster, namespace: &str) -> Result<()> {
+    let (service_account, role_binding) = rbac::build_rbac_resources(odoo, "odoo");
+    if let Some(role_binding) = client
+        .get_opt::<RoleBinding>(&role_binding.name_any(), namespace)
+        .await
+        .context(DeleteInitRbacSnafu)?
+    {
+        client
+            .delete(&role_binding)
+            .await
+            .context(DeleteInitRbacSnafu)?;
+    }
+    if let Some(service_account) = client
+        .get_opt::<ServiceAccount>(&service_account.name_any(), namespace)
+        .await
+        .context(DeleteInitRbacSnafu)?
+    {
+        client
+            .delete(&service_account)
+            .await
+            .context(DeleteInitRbacSnafu)?;
+    }
+    tracing::info!("Deleted the RBAC objects of the init jobs");
+    Ok(())
+}
+
+/// Whether the RBAC objects of the init jobs are still needed by one of the given OdooDBs. An
+/// OdooDB which is being deleted does not need them anymore.
+fn init_rbac_in_use(odoo_dbs: &[OdooDB]) -> bool {
+    odoo_dbs
+        .iter()
+        .any(|odoo_db| odoo_db.metadata.deletion_timestamp.is_none())
+}
+
+/// The finalizers with the cleanup finalizer added, or `None` if it is already the only own one.
+/// It is added regardless of the deletion policies, which are only evaluated on deletion.
+fn finalizers_with_cleanup(odoo: &OdooCluster) -> Option<Vec<String>> {
+    let mut finalizers = without_own_finalizers(odoo);
+    finalizers.push(CLEANUP_FINALIZER.to_string());
+    (finalizers != odoo.finalizers()).then_some(finalizers)
+}
+
+/// The finalizers without the own ones, or `None` if the cluster was already cleaned up.
+fn finalizers_after_cleanup(odoo: &OdooCluster) -> Option<Vec<String>> {
+    let finalizers = without_own_finalizers(odoo);
+    (finalizers.len() != odoo.finalizers().len()).then_some(finalizers)
+}
+
+fn without_own_finalizers(odoo: &OdooCluster) -> Vec<String> {
+    odoo.finalizers()
+        .iter()
+        .filter(|finalizer| {
+            *finalizer != CLEANUP_FINALIZER && *finalizer != LEGACY_ODOO_DB_FINALIZER
+        })
+        .cloned()
+        .collect()
+}
+
+/// Replaces the finalizers of the given object, e.g. of an OdooCluster or an OdooDatabase.
+pub async fn patch_finalizers<T>(client: &Client, obj: &T, finalizers: Vec<String>) -> Result<()>
+where
+    T: Resource<DynamicType = ()> + Clone + Debug + DeserializeOwned,
+{
+    client
+        .merge_patch(obj, finalizers_patch(obj, finalizers))
+        .await
+        .context(UpdateFinalizersSnafu)?;
+    Ok(())
+}
+
+fn finalizers_patch<T: Resource>(obj: &T, finalizers: Vec<String>) -> MetadataPatch {
+    MetadataPatch {
+        metadata: ObjectMeta {
+            finalizers: Some(finalizers),
+            // Makes the patch fail instead of overwriting finalizers added concurrently
+            resource_version: obj.resource_version(),
+            ..ObjectMeta::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_operator::k8s_openapi::{apimachinery::pkg::apis::meta::v1::Time, chrono::Utc};
+
+    fn odoo_cluster(finalizers: &[&str]) -> OdooCluster {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          resourceVersion: \"42\"
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          ",
+        )
+        .unwrap();
+        odoo.metadata.finalizers = Some(finalizers.iter().map(|f| f.to_string()).collect());
+        odoo
+    }
+
+    #[test]
+    fn test_without_own_finalizers() {
+        let odoo = odoo_cluster(&[
+            LEGACY_ODOO_DB_FINALIZER,
+            "example.com/backup",
+            CLEANUP_FINALIZER,
+        ]);
+        assert_eq!(
+            vec!["example.com/backup".to_string()],
+            without_own_finalizers(&odoo)
+        );
+    }
+
+    #[test]
+    fn test_finalizers_with_cleanup() {
+        assert_eq!(
+            Some(vec![CLEANUP_FINALIZER.to_string()]),
+            finalizers_with_cleanup(&odoo_cluster(&[]))
+        );
+        // The finalizer of earlier versions is replaced
+        assert_eq!(
+            Some(vec![
+                "example.com/backup".to_string(),
+                CLEANUP_FINALIZER.to_string()
+            ]),
+            finalizers_with_cleanup(&odoo_cluster(&[
+                LEGACY_ODOO_DB_FINALIZER,
+                "example.com/backup"
+            ]))
+        );
+        assert_eq!(
+            None,
+            finalizers_with_cleanup(&odoo_cluster(&["example.com/backup", CLEANUP_FINALIZER]))
+        );
+    }
+
+    #[test]
+    fn test_finalizers_after_cleanup() {
+        assert_eq!(
+            Some(vec!["example.com/backup".to_string()]),
+            finalizers_after_cleanup(&odoo_cluster(&[CLEANUP_FINALIZER, "example.com/backup"]))
+        );
+        assert_eq!(
+            Some(vec![]),
+            finalizers_after_cleanup(&odoo_cluster(&[LEGACY_ODOO_DB_FINALIZER]))
+        );
+        // Another controller is still cleaning up
+        assert_eq!(
+            None,
+            finalizers_after_cleanup(&odoo_cluster(&["example.com/backup"]))
+        );
+    }
+
+    #[test]
+    fn test_finalizers_patch() {
+        let odoo = odoo_cluster(&[CLEANUP_FINALIZER]);
+        let patch = finalizers_patch(&odoo, vec!["example.com/backup".to_string()]);
+        assert_eq!(
+            Some(vec!["example.com/backup".to_string()]),
+            patch.metadata.finalizers
+        );
+        assert_eq!(Some("42".to_string()), patch.metadata.resource_version);
+    }
+
+    #[test]
+    fn test_init_rbac_in_use() {
+        let odoo = odoo_cluster(&[]);
+        let odoo_db = OdooDB::for_odoo(&odoo, &odoo.spec.image.resolve("odoo")).unwrap();
+        let mut deleted_odoo_db = odoo_db.clone();
+        deleted_odoo_db.metadata.deletion_timestamp = Some(Time(Utc::now()));
+
+        assert!(!init_rbac_in_use(&[]));
+        assert!(!init_rbac_in_use(&[deleted_odoo_db.clone()]));
+        assert!(init_rbac_in_use(&[deleted_odoo_db, odoo_db]));
+    }
+}
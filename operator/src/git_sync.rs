@@ -0,0 +1,441 @@
+//! Extends git-sync in the product image with features it does not offer itself:
+//!
+//! * A hook reports every synced revision. A small HTTP server serves it next to git-sync, or
+//!   it is the termination message in the mode once. The operator annotates the pod with it
+//!   and reports it in the status, see [`crate::addons_status`].
+//! * `gitSync.webhook` lets CI trigger a sync with a POST request. A small HTTP server runs next
+//!   to git-sync and sends it a SIGHUP on every request carrying the token.
+//! * `gitSync.verification` only deploys signed revisions. The hook verifies every synced
+//!   revision, copies it and points the link `verified` to the copy, so the Odoo containers
+//!   never see an unverified revision.
+//! * `gitSync.installRequirements` installs the Python requirements of the addons in an init
+//!   container.
+//! * `gitSync.sharedVolume` syncs once for all pods into a ReadWriteMany claim. The controller
+//!   runs the git-sync container in a Deployment, which the webhook then points to.
+use sovrin_cloud_crd::{
+    build_recommended_labels, GitSync, GitSyncMode, OdooCluster, ADDONS_PACKAGES_DIR, APP_NAME,
+    GIT_LINK, GIT_ROOT, GIT_SYNC_HOOKS_DIR, GIT_SYNC_HOOK_SCRIPT, GIT_SYNC_NAME,
+    GIT_SYNC_REVISION_FILE, GIT_SYNC_REVISION_PORT, GIT_SYNC_TRUSTED_KEYS_DIR, GIT_VERIFIED_LINK,
+};
+use stackable_operator::{
+    builder::ObjectMetaBuilder,
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::api::core::v1::{Service, ServicePort, ServiceSpec},
+    kube::ResourceExt,
+    labels::{APP_COMPONENT_LABEL, APP_INSTANCE_LABEL, APP_NAME_LABEL},
+};
+use std::collections::BTreeMap;
+
+use crate::odoo_controller::AIRFLOW_CONTROLLER_NAME;
+
+pub const WEBHOOK_PORT_NAME: &str = "gitsync-webhook";
+pub const WEBHOOK_PORT: i32 = 9081;
+pub const WEBHOOK_TOKEN_ENV: &str = "GIT_SYNC_WEBHOOK_TOKEN";
+pub const WEBHOOK_TOKEN_SECRET_KEY: &str = "token";
+
+const WEBHOOK_SCRIPT: &str = r#"
+import hmac, http.server, os, signal
+
+token = os.environ["GIT_SYNC_WEBHOOK_TOKEN"].encode()
+
+class Handler(http.server.BaseHTTPRequestHandler):
+    def do_POST(self):
+        if not hmac.compare_digest(self.headers.get("X-Webhook-Token", "").encode(), token):
+            self.send_response(401)
+            self.end_headers()
+            return
+        os.kill(int(os.environ["GIT_SYNC_PID"]), signal.SIGHUP)
+        self.send_response(202)
+        self.end_headers()
+
+http.server.HTTPServer(("", int(os.environ["GIT_SYNC_WEBHOOK_PORT"])), Handler).serve_forever()
+"#;
+
+/// Serves the revision synced last, which the operator fetches through the API server and
+/// annotates the pod with. The pods do not need any permissions for it.
+const REVISION_SERVER_SCRIPT: &str = r#"
+import http.server, os
+
+class Handler(http.server.BaseHTTPRequestHandler):
+    def do_GET(self):
+        try:
+            with open(os.environ["GIT_SYNC_REVISION_FILE"], "rb") as f:
+                revision = f.read()
+        except FileNotFoundError:
+            self.send_response(404)
+            self.end_headers()
+            return
+        self.send_response(200)
+        self.end_headers()
+        self.wfile.write(revision)
+
+    def log_message(self, format, *args):
+        pass
+
+http.server.HTTPServer(("", int(os.environ["GIT_SYNC_REVISION_PORT"])), Handler).serve_forever()
+"#;
+
+/// Runs in the worktree of the synced revision. The verified copies are named after the
+/// revision and the link is relative, so it resolves in the Odoo containers, which mount the
+/// volume elsewhere.
+fn hook_script(git_sync: &GitSync) -> String {
+    let mut lines = vec![
+        "#!/bin/bash".to_string(),
+        "set -e".to_string(),
+        "rev=$(git rev-parse HEAD)".to_string(),
+    ];
+    if git_sync.verification.is_some() {
+        lines.push(format!(
+            r#"git verify-commit HEAD
+cd {GIT_ROOT}
+if [ ! -d .verified-$rev ]; then
+    cp -a "$OLDPWD" .verified-$rev.tmp
+    mv .verified-$rev.tmp .verified-$rev
+fi
+ln -sfn .verified-$rev .verified-link
+mv -Tf .verified-link {GIT_VERIFIED_LINK}
+for dir in .verified-*; do
+    [ "$dir" = .verified-$rev ] || rm -rf "$dir"
+done"#
+        ));
+    }
+    // Replaced at once, so the revision server never reads a partial revision
+    lines.extend([
+        format!("printf '%s' \"$rev\" > {GIT_SYNC_REVISION_FILE}.tmp"),
+        format!("mv -f {GIT_SYNC_REVISION_FILE}.tmp {GIT_SYNC_REVISION_FILE}"),
+    ]);
+    lines.join("\n") + "\n"
+}
+
+/// The shell command of the git-sync container.
+pub fn git_sync_command(git_sync: &GitSync) -> String {
+    let mut lines = vec![];
+    let mut git_sync_command = git_sync.get_args().join(" ");
+    if git_sync.verification.is_some() {
+        // Only the trusted keys are imported, so every good signature is trusted
+        lines.extend([
+            format!("export GNUPGHOME={GIT_SYNC_HOOKS_DIR}/gnupg"),
+            "mkdir -p -m 700 $GNUPGHOME".to_string(),
+            format!("gpg --batch --import {GIT_SYNC_TRUSTED_KEYS_DIR}/*"),
+        ]);
+    }
+    lines.extend([
+        format!(
+            "cat > {GIT_SYNC_HOOK_SCRIPT} <<'EOF'\n{}EOF",
+            hook_script(git_sync)
+        ),
+        format!("chmod +x {GIT_SYNC_HOOK_SCRIPT}"),
+    ]);
+    match git_sync.mode {
+        // The container stops after the sync, so the revision is reported as its termination
+        // message
+        GitSyncMode::Once => {
+            git_sync_command = format!(
+                "{git_sync_command} && cd {GIT_ROOT}/{GIT_LINK} && {GIT_SYNC_HOOK_SCRIPT} && \
+                cp {GIT_SYNC_REVISION_FILE} /dev/termination-log"
+            )
+        }
+        // The container stops with git-sync, the revision server is only a helper
+        GitSyncMode::Continuous => lines.push(format!(
+            "GIT_SYNC_REVISION_FILE={GIT_SYNC_REVISION_FILE} \
+            GIT_SYNC_REVISION_PORT={GIT_SYNC_REVISION_PORT} python3 -c '{REVISION_SERVER_SCRIPT}' &"
+        )),
+    }
+    if git_sync.webhook_enabled() {
+        // The container stops with git-sync, the webhook server is only a helper
+        git_sync_command = format!(
+            "{git_sync_command} & export GIT_SYNC_PID=$!; \
+            GIT_SYNC_WEBHOOK_PORT={WEBHOOK_PORT} python3 -c '{WEBHOOK_SCRIPT}' & \
+            wait $GIT_SYNC_PID"
+        );
+    }
+    lines.push(git_sync_command);
+    lines.join("\n")
+}
+
+/// Installs the `requirements.txt` files of the addons folder and of every addon in it into
+/// `ADDONS_PACKAGES_DIR`, which the Odoo containers add to the `PYTHONPATH`.
+pub fn install_requirements_command(git_sync: &GitSync) -> String {
+    // The deployed link is followed, the revisions themselves are in hidden directories
+    format!(
+        "set -e; find -L {} -maxdepth 2 -name requirements.txt | sort | \
+        while read -r requirements; do \
+        echo \"Installing $requirements\"; \
+        pip install --no-cache-dir --target {ADDONS_PACKAGES_DIR} -r \"$requirements\"; \
+        done",
+        git_sync.addons_dir()
+    )
+}
+
+/// The [`Service`] of the shared git-sync pod, which syncs for all pods of the cluster. `None` if
+/// the webhook is not enabled.
+pub fn build_webhook_service(
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+) -> stackable_operator::error::OperatorResult<Option<Service>> {
+    if !odoo.git_sync().map_or(false, GitSync::webhook_enabled) {
+        return Ok(None);
+    }
+
+    Ok(Some(Service {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(format!("{}-{GIT_SYNC_NAME}", odoo.name_any()))
+            .ownerreference_from_resource(odoo, None, Some(true))?
+            .with_recommended_labels(build_recommended_labels(
+                odoo,
+                AIRFLOW_CONTROLLER_NAME,
+                &resolved_product_image.app_version_label,
+                GIT_SYNC_NAME,
+                "global",
+            ))
+            .build(),
+        spec: Some(ServiceSpec {
+            type_: Some("ClusterIP".to_string()),
+            ports: Some(vec![ServicePort {
+                name: Some(WEBHOOK_PORT_NAME.to_string()),
+                port: WEBHOOK_PORT,
+                protocol: Some("TCP".to_string()),
+                ..ServicePort::default()
+            }]),
+            selector: Some(BTreeMap::from([
+                (APP_NAME_LABEL.to_string(), APP_NAME.to_string()),
+                (APP_INSTANCE_LABEL.to_string(), odoo.name_any()),
+                (APP_COMPONENT_LABEL.to_string(), GIT_SYNC_NAME.to_string()),
+            ])),
+            ..ServiceSpec::default()
+        }),
+        status: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_sync_command() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/stackabletech/odoo-operator
+            webhook: true
+            ",
+        )
+        .unwrap();
+        let command = git_sync_command(&git_sync);
+        assert!(command.contains(
+            "'--exechook-command=/stackable/gitsync-hooks/on-sync.sh' & export GIT_SYNC_PID=$!;"
+        ));
+        assert!(command.ends_with("wait $GIT_SYNC_PID"));
+        assert!(
+            command.contains("\nGIT_SYNC_REVISION_FILE=/stackable/gitsync-hooks/synced-revision")
+        );
+        // The hook only writes the revision, it does not call the API server
+        assert!(command.contains(
+            "\nmv -f /stackable/gitsync-hooks/synced-revision.tmp \
+            /stackable/gitsync-hooks/synced-revision\n"
+        ));
+        assert!(!command.contains("serviceaccount"));
+
+        let git_sync = GitSync {
+            mode: GitSyncMode::Once,
+            ..git_sync
+        };
+        let command = git_sync_command(&git_sync);
+        assert!(command.ends_with(
+            "'--one-time' && cd /tmp/git/current && /stackable/gitsync-hooks/on-sync.sh && \
+            cp /stackable/gitsync-hooks/synced-revision /dev/termination-log"
+        ));
+        assert!(!command.contains("GIT_SYNC_PID"));
+        assert!(!command.contains("GIT_SYNC_REVISION_PORT"));
+    }
+
+    #[test]
+    fn test_revision_server_serves_synced_revision() {
+        use std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream},
+            process::Command,
+            thread,
+            time::Duration,
+        };
+
+        let dir = std::env::temp_dir().join(format!("revision-server-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let revision_file = dir.join("synced-revision");
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut server = Command::new("python3")
+            .args(["-c", REVISION_SERVER_SCRIPT])
+            .env("GIT_SYNC_REVISION_FILE", &revision_file)
+            .env("GIT_SYNC_REVISION_PORT", port.to_string())
+            .spawn()
+            .unwrap();
+        let get = || -> String {
+            let mut stream = (0..50)
+                .find_map(|_| {
+                    TcpStream::connect(("127.0.0.1", port))
+                        .map_err(|_| thread::sleep(Duration::from_millis(100)))
+                        .ok()
+                })
+                .unwrap();
+            write!(stream, "GET / HTTP/1.0\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let not_synced = get();
+        std::fs::write(&revision_file, "0123abc").unwrap();
+        let synced = get();
+        server.kill().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(not_synced.starts_with("HTTP/1.0 404 "), "{not_synced}");
+        assert!(synced.starts_with("HTTP/1.0 200 "), "{synced}");
+        assert!(synced.ends_with("\r\n\r\n0123abc"), "{synced}");
+    }
+
+    #[test]
+    fn test_webhook_requires_token() {
+        use std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream},
+            process::Command,
+            thread,
+            time::Duration,
+        };
+
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        // Stands in for git-sync, which syncs on SIGHUP
+        let mut git_sync = Command::new("bash")
+            .args(["-c", "trap 'exit 0' HUP; while true; do sleep 0.1; done"])
+            .spawn()
+            .unwrap();
+        let mut server = Command::new("python3")
+            .args(["-c", WEBHOOK_SCRIPT])
+            .env("GIT_SYNC_PID", git_sync.id().to_string())
+            .env("GIT_SYNC_WEBHOOK_PORT", port.to_string())
+            .env(WEBHOOK_TOKEN_ENV, "s3cr3t")
+            .spawn()
+            .unwrap();
+        let post = |token: &str| -> String {
+            let mut stream = (0..50)
+                .find_map(|_| {
+                    TcpStream::connect(("127.0.0.1", port))
+                        .map_err(|_| thread::sleep(Duration::from_millis(100)))
+                        .ok()
+                })
+                .unwrap();
+            write!(
+                stream,
+                "POST / HTTP/1.0\r\nX-Webhook-Token: {token}\r\nContent-Length: 0\r\n\r\n"
+            )
+            .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response.lines().next().unwrap_or_default().to_string()
+        };
+
+        let rejected = post("wrong");
+        assert!(git_sync.try_wait().unwrap().is_none());
+        let accepted = post("s3cr3t");
+        let synced = git_sync.wait().unwrap();
+        server.kill().unwrap();
+
+        assert!(rejected.contains(" 401 "), "{rejected}");
+        assert!(accepted.contains(" 202 "), "{accepted}");
+        assert!(synced.success());
+    }
+
+    #[test]
+    fn test_build_webhook_service() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            dagsGitSync:
+              - repo: https://github.com/OCA/server-tools
+                webhook: true
+                webhookTokenSecret: gitsync-webhook
+                sharedVolume:
+                  claimName: addons
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+        let resolved_product_image = odoo.spec.image.resolve("odoo");
+
+        let service = build_webhook_service(&odoo, &resolved_product_image)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some("odoo-gitsync".to_string()), service.metadata.name);
+        let selector = service.spec.unwrap().selector.unwrap();
+        // Only the shared git-sync pod receives the requests
+        assert_eq!(
+            Some(&GIT_SYNC_NAME.to_string()),
+            selector.get(APP_COMPONENT_LABEL)
+        );
+        assert_eq!(Some(&"odoo".to_string()), selector.get(APP_INSTANCE_LABEL));
+    }
+
+    #[test]
+    fn test_install_requirements_command() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/OCA/server-tools
+            gitFolder: /addons/
+            installRequirements: true
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            "set -e; find -L /stackable/app/git/current/addons -maxdepth 2 -name requirements.txt \
+            | sort | while read -r requirements; do echo \"Installing $requirements\"; \
+            pip install --no-cache-dir --target /stackable/addons-packages -r \"$requirements\"; \
+            done",
+            install_requirements_command(&git_sync)
+        );
+    }
+
+    #[test]
+    fn test_git_sync_command_verification() {
+        let git_sync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/stackabletech/odoo-operator
+            rev: v1.2.0
+            mode: once
+            verification:
+              trustedKeysSecret: addon-signers
+            ",
+        )
+        .unwrap();
+        assert_eq!("verified", git_sync.deployed_link());
+
+        let command = git_sync_command(&git_sync);
+        assert!(command.starts_with("export GNUPGHOME=/stackable/gitsync-hooks/gnupg\n"));
+        assert!(command.contains("\ngit verify-commit HEAD\n"));
+        assert!(command.contains("/stackable/gitsync-hooks/synced-revision\nEOF\nchmod +x"));
+        assert!(!command.contains("--exechook-command"));
+    }
+}
@@ -0,0 +1,151 @@
+//! Readiness of the operator, served on `/readyz` by [`crate::http_server`], so the Deployment
+//! of the operator can use probes which check more than a running process.
+//!
+//! The operator is ready once the cache of every controller contains the objects existing in the
+//! API server, and as long as the API server can be reached.
+use std::{
+    fmt::Debug,
+    future::Future,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde::de::DeserializeOwned;
+use stackable_operator::{
+    client::Client,
+    kube::{
+        api::ListParams,
+        runtime::reflector::{ObjectRef, Store},
+        Api, Resource,
+    },
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The caches are registered by [`Health::wait_for_cache`], so the readiness has to be served
+/// after all of them are registered.
+#[derive(Default)]
+pub struct Health {
+    caches: AtomicUsize,
+    synced_caches: AtomicUsize,
+    api_reachable: AtomicBool,
+}
+
+impl Health {
+    pub fn is_ready(&self) -> bool {
+        self.api_reachable.load(Ordering::Relaxed)
+            && self.synced_caches.load(Ordering::Relaxed) >= self.caches.load(Ordering::Relaxed)
+    }
+
+    /// Registers the cache of the store and returns a future which waits until the store
+    /// contains every object listed with `list_params`, which must select the objects watched by
+    /// the store. The objects are listed again on every check, so deleted objects are not waited
+    /// for.
+    pub fn wait_for_cache<K>(
+        self: &Arc<Self>,
+        api: Api<K>,
+        list_params: ListParams,
+        store: Store<K>,
+    ) -> impl Future<Output = ()>
+    where
+        K: Resource + Clone + DeserializeOwned + Debug,
+        K::DynamicType: Clone + Default + Eq + Hash,
+    {
+        self.caches.fetch_add(1, Ordering::Relaxed);
+        self.clone().wait_for_synced_cache(api, list_params, store)
+    }
+
+    async fn wait_for_synced_cache<K>(
+        self: Arc<Self>,
+        api: Api<K>,
+        list_params: ListParams,
+        store: Store<K>,
+    ) where
+        K: Resource + Clone + DeserializeOwned + Debug,
+        K::DynamicType: Clone + Default + Eq + Hash,
+    {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let objects = match api.list_metadata(&list_params).await {
+                Ok(objects) => objects,
+                Err(error) => {
+                    tracing::debug!(
+                        error = &error as &dyn std::error::Error,
+                        "Failed to list the objects of a cache"
+                    );
+                    continue;
+                }
+            };
+            let synced = objects.items.iter().all(|object| {
+                let Some(name) = &object.metadata.name else {
+                    return true;
+                };
+                let mut object_ref = ObjectRef::<K>::new(name);
+                if let Some(namespace) = &object.metadata.namespace {
+                    object_ref = object_ref.within(namespace);
+                }
+                store.get(&object_ref).is_some()
+            });
+            if synced {
+                self.synced_caches.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+
+    /// Checks periodically whether the API server can be reached, until the operator is stopped.
+    pub async fn probe_api(self: Arc<Self>, client: Client) {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let reachable = client.as_kube_client().apiserver_version().await.is_ok();
+            if self.api_reachable.swap(reachable, Ordering::Relaxed) != reachable {
+                tracing::info!(reachable, "The reachability of the API server changed");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_operator::k8s_openapi::api::core::v1::ConfigMap;
+
+    #[test]
+    fn test_is_ready() {
+        let health = Health::default();
+        health.caches.store(2, Ordering::Relaxed);
+        health.api_reachable.store(true, Ordering::Relaxed);
+        health.synced_caches.store(1, Ordering::Relaxed);
+        assert!(!health.is_ready());
+        health.synced_caches.store(2, Ordering::Relaxed);
+        assert!(health.is_ready());
+        health.api_reachable.store(false, Ordering::Relaxed);
+        assert!(!health.is_ready());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_cache_registers_the_cache() {
+        let client = stackable_operator::kube::Client::try_from(
+            stackable_operator::kube::Config::new("http://127.0.0.1:1".parse().unwrap()),
+        )
+        .unwrap();
+        let (store, _writer) = stackable_operator::kube::runtime::reflector::store();
+        let health = Arc::new(Health::default());
+        health.api_reachable.store(true, Ordering::Relaxed);
+        assert!(health.is_ready());
+
+        // The cache counts before the returned future is polled
+        let _wait = health.wait_for_cache(
+            Api::<ConfigMap>::namespaced(client, "default"),
+            ListParams::default(),
+            store,
+        );
+        assert!(!health.is_ready());
+    }
+}
@@ -0,0 +1,119 @@
+//! A minimal HTTP server for the endpoints of the operator itself. Only the path of `GET`
+//! requests is evaluated, so no web framework is needed.
+use std::sync::Arc;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{health::Health, metrics::Metrics};
+
+/// Serves the endpoints until the operator is stopped.
+pub async fn run(port: u16, metrics: Arc<Metrics>, health: Arc<Health>) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            tracing::error!(
+                port,
+                error = &error as &dyn std::error::Error,
+                "Failed to start the HTTP server"
+            );
+            return;
+        }
+    };
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                tracing::warn!(
+                    error = &error as &dyn std::error::Error,
+                    "Failed to accept an HTTP connection"
+                );
+                continue;
+            }
+        };
+        let metrics = metrics.clone();
+        let health = health.clone();
+        tokio::spawn(async move {
+            if let Err(error) = handle(stream, &metrics, &health).await {
+                tracing::debug!(
+                    error = &error as &dyn std::error::Error,
+                    "Failed to answer an HTTP request"
+                );
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, metrics: &Metrics, health: &Health) -> std::io::Result<()> {
+    // The request line is at the start of the first segment
+    let mut buffer = [0; 1024];
+    let len = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..len]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = respond(path, metrics, health);
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+        Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn respond(path: &str, metrics: &Metrics, health: &Health) -> (&'static str, String) {
+    match path.split('?').next().unwrap_or_default() {
+        "/metrics" => ("200 OK", metrics.render()),
+        // The operator is alive as long as it answers
+        "/healthz" => ("200 OK", "ok\n".to_string()),
+        "/readyz" if health.is_ready() => ("200 OK", "ok\n".to_string()),
+        "/readyz" => ("503 Service Unavailable", "Not ready\n".to_string()),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond() {
+        let metrics = Metrics::default();
+        let health = Health::default();
+
+        let (status, body) = respond("/metrics?name[]=up", &metrics, &health);
+        assert_eq!(status, "200 OK");
+        assert_eq!(body, metrics.render());
+        assert_eq!(respond("/healthz", &metrics, &health).0, "200 OK");
+        // The API server was not reached yet
+        assert_eq!(
+            respond("/readyz", &metrics, &health).0,
+            "503 Service Unavailable"
+        );
+        assert_eq!(respond("/", &metrics, &health).0, "404 Not Found");
+    }
+
+    #[tokio::test]
+    async fn test_handle() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle(stream, &Metrics::default(), &Health::default()).await
+        });
+
+        let mut client = TcpStream::connect(address).await.unwrap();
+        client
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap().unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("Content-Length: 3\r\n"), "{response}");
+        assert!(response.ends_with("\r\n\r\nok\n"), "{response}");
+    }
+}
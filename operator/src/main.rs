@@ -1,36 +1,65 @@
 mod utils;
+mod workload;
+mod backup;
+mod blue_green;
 mod rbac;
+mod rollout;
+mod scale_down;
+mod secret_key;
+mod upgrade;
+mod filestore;
 mod odoo_controller;
+mod odoo_database_controller;
 mod odoo_db_controller;
+mod odoo_user_controller;
 mod config;
+mod finalizer;
+mod git_sync;
+mod network_policy;
+mod pgbouncer;
+mod preflight;
 mod controller_commons;
+mod database_health;
+mod health;
+mod http_server;
+mod metrics;
+mod addons_status;
+mod addons_sources;
+mod applied_objects;
 mod product_logging;
 
 
+use crate::health::Health;
+use crate::metrics::{observed, Metrics};
 use crate::odoo_controller::AIRFLOW_CONTROLLER_NAME;
 
 use clap::{crate_description, crate_version, Parser};
 use futures::StreamExt;
 use sovrin_cloud_crd::{
+    cnpg::CnpgCluster,
+    image_policy::ImagePolicy,
+    odoodatabase::{OdooDatabase, AIRFLOW_DATABASE_CONTROLLER_NAME},
     odoodb::{OdooDB, AIRFLOW_DB_CONTROLLER_NAME},
+    odoouser::{OdooUser, AIRFLOW_USER_CONTROLLER_NAME},
     OdooCluster, OdooClusterAuthenticationConfig, APP_NAME, OPERATOR_NAME,
 };
 use stackable_operator::{
     cli::{Command, ProductOperatorRun},
     commons::authentication::AuthenticationClass,
     k8s_openapi::api::{
-        apps::v1::StatefulSet,
+        apps::v1::{Deployment, StatefulSet},
         batch::v1::Job,
-        core::v1::{Secret, Service},
+        core::v1::{ConfigMap, Namespace, Secret, Service},
     },
     kube::{
+        api::ListParams,
         runtime::{reflector::ObjectRef, watcher, Controller},
         ResourceExt,
     },
     logging::controller::report_controller_reconciled,
     CustomResourceExt,
 };
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
 mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
@@ -41,7 +70,57 @@ mod built_info {
 #[clap(about, author)]
 struct Opts {
     #[clap(subcommand)]
-    cmd: Command,
+    cmd: OdooCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum OdooCommand {
+    #[clap(flatten)]
+    Framework(Command<OdooRun>),
+    /// Print the RBAC objects the operator itself needs, e.g. for installs without Helm
+    Rbac(RbacArgs),
+}
+
+#[derive(clap::Args)]
+struct RbacArgs {
+    /// Only grant the namespaced permissions in this namespace instead of cluster-wide
+    #[arg(long)]
+    watch_namespace: Option<String>,
+    /// The ServiceAccount the operator runs as
+    #[arg(long, default_value = "odoo-operator-serviceaccount")]
+    service_account: String,
+    /// The namespace of the operator ServiceAccount
+    #[arg(long, default_value = "default")]
+    service_account_namespace: String,
+}
+
+#[derive(clap::Args)]
+struct OdooRun {
+    #[clap(flatten)]
+    common: ProductOperatorRun,
+    /// Restricts which product images may be deployed: `permissive`, `reject-mutable-tags` or
+    /// `require-digests`. Clusters can only tighten the policy.
+    #[arg(long, env, default_value_t, value_parser = ImagePolicy::from_str)]
+    image_policy: ImagePolicy,
+    /// Runs the Vector agent of the init job as native sidecar container, so the job does not
+    /// depend on the agent being shut down. Requires the `SidecarContainers` feature gate of
+    /// Kubernetes, which is enabled by default since 1.29.
+    #[arg(long, env)]
+    native_sidecars: bool,
+    /// Port of the HTTP server exposing the metrics of the operator on `/metrics` and its health
+    /// on `/healthz` and `/readyz`.
+    #[arg(long, env, default_value_t = 8080)]
+    http_port: u16,
+    /// Only reconciles the OdooClusters, OdooDBs, OdooUsers and OdooDatabases matching this label
+    /// selector, e.g. to shard the clusters across several instances of the operator. The objects
+    /// owned by them are still watched, but their events only reconcile the selected owners.
+    #[arg(long, env)]
+    watch_label_selector: Option<String>,
+    /// Only reconciles the OdooClusters in namespaces matching this label selector, e.g.
+    /// `sovrin.cloud/managed=true`. Clusters are reconciled as soon as their namespace starts
+    /// matching.
+    #[arg(long, env)]
+    watch_namespace_label_selector: Option<String>,
 }
 
 #[tokio::main]
@@ -49,15 +128,39 @@ async fn main() -> anyhow::Result<()> {
     let opts = Opts::parse();
 
     match opts.cmd {
-        Command::Crd => {
+        OdooCommand::Rbac(RbacArgs {
+                              watch_namespace,
+                              service_account,
+                              service_account_namespace,
+                          }) => {
+            print!(
+                "{}",
+                rbac::operator_rbac_manifests(
+                    watch_namespace.as_deref(),
+                    &service_account,
+                    &service_account_namespace,
+                )?
+            );
+        }
+        OdooCommand::Framework(Command::Crd) => {
             OdooCluster::print_yaml_schema()?;
             OdooDB::print_yaml_schema()?;
+            OdooUser::print_yaml_schema()?;
+            OdooDatabase::print_yaml_schema()?;
         }
-        Command::Run(ProductOperatorRun {
-                         product_config,
-                         watch_namespace,
-                         tracing_target,
-                     }) => {
+        OdooCommand::Framework(Command::Run(OdooRun {
+                         common:
+                             ProductOperatorRun {
+                                 product_config,
+                                 watch_namespace,
+                                 tracing_target,
+                             },
+                         image_policy,
+                         native_sidecars,
+                         http_port,
+                         watch_label_selector,
+                         watch_namespace_label_selector,
+                     })) => {
             stackable_operator::logging::initialize_logging(
                 "AIRFLOW_OPERATOR_LOG",
                 APP_NAME,
@@ -78,22 +181,60 @@ async fn main() -> anyhow::Result<()> {
 
             let client =
                 stackable_operator::client::create_client(Some(OPERATOR_NAME.to_string())).await?;
+            let (custom_resource_watcher_config, custom_resource_list_params) =
+                custom_resource_selection(watch_label_selector.as_deref());
+            let metrics = Arc::new(Metrics::default());
+            let health = Arc::new(Health::default());
+            tokio::spawn(health.clone().probe_api(client.clone()));
 
             let odoo_controller_builder = Controller::new(
                 watch_namespace.get_api::<OdooCluster>(&client),
-                watcher::Config::default(),
+                custom_resource_watcher_config.clone(),
             );
 
             let odoo_store_1 = odoo_controller_builder.store();
             let odoo_store_2 = odoo_controller_builder.store();
-            let odoo_controller = odoo_controller_builder
+            let odoo_store_3 = odoo_controller_builder.store();
+            let odoo_store_4 = odoo_controller_builder.store();
+            let odoo_store_5 = odoo_controller_builder.store();
+            tokio::spawn(health.wait_for_cache(
+                watch_namespace.get_api::<OdooCluster>(&client),
+                custom_resource_list_params.clone(),
+                odoo_controller_builder.store(),
+            ));
+            tokio::spawn(database_health::run(
+                client.clone(),
+                odoo_controller_builder.store(),
+            ));
+            tokio::spawn(addons_status::run(
+                client.clone(),
+                odoo_controller_builder.store(),
+            ));
+            // The objects created for a custom resource carry its labels the label selector refers
+            // to, so only the objects of the selected custom resources are watched. The objects
+            // referenced by the custom resources are watched unrestricted.
+            let mut odoo_controller_builder = odoo_controller_builder
                 .owns(
                     watch_namespace.get_api::<Service>(&client),
-                    watcher::Config::default(),
+                    custom_resource_watcher_config.clone(),
                 )
                 .owns(
                     watch_namespace.get_api::<StatefulSet>(&client),
-                    watcher::Config::default(),
+                    custom_resource_watcher_config.clone(),
+                )
+                .owns(
+                    watch_namespace.get_api::<Deployment>(&client),
+                    custom_resource_watcher_config.clone(),
+                )
+                // Reverts edits of the generated configuration
+                .owns(
+                    watch_namespace.get_api::<ConfigMap>(&client),
+                    custom_resource_watcher_config.clone(),
+                )
+                // Rolls out the new version once the upgrade job completed
+                .owns(
+                    watch_namespace.get_api::<Job>(&client),
+                    custom_resource_watcher_config.clone(),
                 )
                 .shutdown_on_signal()
                 .watches(
@@ -112,9 +253,20 @@ async fn main() -> anyhow::Result<()> {
                             .map(|odoo| ObjectRef::from_obj(&*odoo))
                     },
                 )
+                // Rolls out a changed address of the vector aggregator
                 .watches(
-                    watch_namespace.get_api::<OdooDB>(&client),
+                    watch_namespace.get_api::<ConfigMap>(&client),
                     watcher::Config::default(),
+                    move |config_map| {
+                        odoo_controller::clusters_using_vector_aggregator(
+                            odoo_store_5.state(),
+                            &config_map,
+                        )
+                    },
+                )
+                .watches(
+                    watch_namespace.get_api::<OdooDB>(&client),
+                    custom_resource_watcher_config.clone(),
                     move |odoo_db| {
                         odoo_store_2
                             .state()
@@ -125,13 +277,57 @@ async fn main() -> anyhow::Result<()> {
                             })
                             .map(|odoo| ObjectRef::from_obj(&*odoo))
                     },
-                )
+                );
+
+            // CloudNativePG is optional, its Clusters can only be watched if it is installed
+            let cnpg_clusters = watch_namespace.get_api::<CnpgCluster>(&client);
+            if cnpg_clusters
+                .list(&ListParams::default().limit(1))
+                .await
+                .is_ok()
+            {
+                odoo_controller_builder = odoo_controller_builder.watches(
+                    cnpg_clusters,
+                    watcher::Config::default(),
+                    move |cnpg_cluster| {
+                        odoo_store_3
+                            .state()
+                            .into_iter()
+                            .filter(move |odoo| {
+                                odoo.spec.cluster_config.database.cnpg_cluster_ref.as_ref()
+                                    == cnpg_cluster.metadata.name.as_ref()
+                                    && cnpg_cluster.namespace() == odoo.namespace()
+                            })
+                            .map(|odoo| ObjectRef::from_obj(&*odoo))
+                    },
+                );
+            }
+
+            if let Some(label_selector) = &watch_namespace_label_selector {
+                // A namespace which stops matching is reported as deleted
+                odoo_controller_builder = odoo_controller_builder.watches(
+                    client.get_api::<Namespace>(&()),
+                    watcher::Config::default().labels(label_selector),
+                    move |namespace| {
+                        odoo_controller::clusters_in_namespace(odoo_store_4.state(), &namespace)
+                    },
+                );
+            }
+
+            let odoo_controller = odoo_controller_builder
                 .run(
-                    odoo_controller::reconcile_odoo,
+                    observed(
+                        AIRFLOW_CONTROLLER_NAME,
+                        metrics.clone(),
+                        odoo_controller::reconcile_odoo,
+                    ),
                     odoo_controller::error_policy,
                     Arc::new(odoo_controller::Ctx {
                         client: client.clone(),
                         product_config,
+                        image_policy,
+                        watch_label_selector: watch_label_selector.clone(),
+                        namespace_label_selector: watch_namespace_label_selector,
                     }),
                 )
                 .map(|res| {
@@ -144,11 +340,17 @@ async fn main() -> anyhow::Result<()> {
 
             let odoo_db_controller_builder = Controller::new(
                 watch_namespace.get_api::<OdooDB>(&client),
-                watcher::Config::default(),
+                custom_resource_watcher_config.clone(),
             );
 
             let odoo_db_store1 = odoo_db_controller_builder.store();
             let odoo_db_store2 = odoo_db_controller_builder.store();
+            let odoo_db_store3 = odoo_db_controller_builder.store();
+            tokio::spawn(health.wait_for_cache(
+                watch_namespace.get_api::<OdooDB>(&client),
+                custom_resource_list_params.clone(),
+                odoo_db_controller_builder.store(),
+            ));
             let odoo_db_controller = odoo_db_controller_builder
                 .shutdown_on_signal()
                 .watches(
@@ -168,11 +370,21 @@ async fn main() -> anyhow::Result<()> {
                             .map(|odoo_db| ObjectRef::from_obj(&*odoo_db))
                     },
                 )
+                .watches(
+                    watch_namespace.get_api::<ConfigMap>(&client),
+                    watcher::Config::default(),
+                    move |config_map| {
+                        odoo_db_controller::odoo_dbs_using_vector_aggregator(
+                            odoo_db_store3.state(),
+                            &config_map,
+                        )
+                    },
+                )
                 // We have to watch jobs so we can react to finished init jobs
                 // and update our status accordingly
                 .watches(
                     watch_namespace.get_api::<Job>(&client),
-                    watcher::Config::default(),
+                    custom_resource_watcher_config.clone(),
                     move |job| {
                         odoo_db_store2
                             .state()
@@ -185,10 +397,17 @@ async fn main() -> anyhow::Result<()> {
                     },
                 )
                 .run(
-                    odoo_db_controller::reconcile_odoo_db,
+                    observed(
+                        AIRFLOW_DB_CONTROLLER_NAME,
+                        metrics.clone(),
+                        odoo_db_controller::reconcile_odoo_db,
+                    ),
                     odoo_db_controller::error_policy,
                     Arc::new(odoo_db_controller::Ctx {
                         client: client.clone(),
+                        image_policy,
+                        native_sidecars,
+                        watch_label_selector: watch_label_selector.clone(),
                     }),
                 )
                 .map(|res| {
@@ -199,15 +418,150 @@ async fn main() -> anyhow::Result<()> {
                     )
                 });
 
-            futures::stream::select(odoo_controller, odoo_db_controller)
-                .collect::<()>()
-                .await;
+            let odoo_user_controller_builder = Controller::new(
+                watch_namespace.get_api::<OdooUser>(&client),
+                custom_resource_watcher_config.clone(),
+            );
+
+            let odoo_user_store = odoo_user_controller_builder.store();
+            let odoo_user_store_2 = odoo_user_controller_builder.store();
+            tokio::spawn(health.wait_for_cache(
+                watch_namespace.get_api::<OdooUser>(&client),
+                custom_resource_list_params.clone(),
+                odoo_user_controller_builder.store(),
+            ));
+            let odoo_user_controller = odoo_user_controller_builder
+                .shutdown_on_signal()
+                // We have to watch jobs so we can react to finished user creation and deletion
+                // jobs and update our status accordingly
+                .watches(
+                    watch_namespace.get_api::<Job>(&client),
+                    custom_resource_watcher_config.clone(),
+                    move |job| {
+                        odoo_user_store
+                            .state()
+                            .into_iter()
+                            .filter(move |odoo_user| {
+                                (job.name_unchecked() == odoo_user_controller::job_name(odoo_user)
+                                    || job.name_unchecked()
+                                        == odoo_user_controller::delete_job_name(odoo_user))
+                                    && job.namespace() == odoo_user.namespace()
+                            })
+                            .map(|odoo_user| ObjectRef::from_obj(&*odoo_user))
+                    },
+                )
+                // Creates the user again with a changed password
+                .watches(
+                    watch_namespace.get_api::<Secret>(&client),
+                    watcher::Config::default(),
+                    move |secret| {
+                        odoo_user_store_2
+                            .state()
+                            .into_iter()
+                            .filter(move |odoo_user| {
+                                secret.metadata.name.as_ref()
+                                    == Some(&odoo_user.spec.password_secret)
+                                    && secret.namespace() == odoo_user.namespace()
+                            })
+                            .map(|odoo_user| ObjectRef::from_obj(&*odoo_user))
+                    },
+                )
+                .run(
+                    observed(
+                        AIRFLOW_USER_CONTROLLER_NAME,
+                        metrics.clone(),
+                        odoo_user_controller::reconcile_odoo_user,
+                    ),
+                    odoo_user_controller::error_policy,
+                    Arc::new(odoo_user_controller::Ctx {
+                        client: client.clone(),
+                        watch_label_selector: watch_label_selector.clone(),
+                    }),
+                )
+                .map(|res| {
+                    report_controller_reconciled(
+                        &client,
+                        &format!("{AIRFLOW_USER_CONTROLLER_NAME}.{OPERATOR_NAME}"),
+                        &res,
+                    )
+                });
+
+            let odoo_database_controller_builder = Controller::new(
+                watch_namespace.get_api::<OdooDatabase>(&client),
+                custom_resource_watcher_config.clone(),
+            );
+
+            let odoo_database_store = odoo_database_controller_builder.store();
+            tokio::spawn(health.wait_for_cache(
+                watch_namespace.get_api::<OdooDatabase>(&client),
+                custom_resource_list_params.clone(),
+                odoo_database_controller_builder.store(),
+            ));
+            let odoo_database_controller = odoo_database_controller_builder
+                .shutdown_on_signal()
+                // We have to watch jobs so we can react to finished create and drop jobs
+                .watches(
+                    watch_namespace.get_api::<Job>(&client),
+                    custom_resource_watcher_config.clone(),
+                    move |job| {
+                        odoo_database_store
+                            .state()
+                            .into_iter()
+                            .filter(move |odoo_database| {
+                                (job.name_unchecked()
+                                    == odoo_database_controller::create_job_name(odoo_database)
+                                    || job.name_unchecked()
+                                        == odoo_database_controller::drop_job_name(odoo_database))
+                                    && job.namespace() == odoo_database.namespace()
+                            })
+                            .map(|odoo_database| ObjectRef::from_obj(&*odoo_database))
+                    },
+                )
+                .run(
+                    observed(
+                        AIRFLOW_DATABASE_CONTROLLER_NAME,
+                        metrics.clone(),
+                        odoo_database_controller::reconcile_odoo_database,
+                    ),
+                    odoo_database_controller::error_policy,
+                    Arc::new(odoo_database_controller::Ctx {
+                        client: client.clone(),
+                        watch_label_selector,
+                    }),
+                )
+                .map(|res| {
+                    report_controller_reconciled(
+                        &client,
+                        &format!("{AIRFLOW_DATABASE_CONTROLLER_NAME}.{OPERATOR_NAME}"),
+                        &res,
+                    )
+                });
+
+            // Served once the caches of all controllers are registered
+            tokio::spawn(http_server::run(http_port, metrics, health));
+            futures::stream::select(
+                futures::stream::select(odoo_controller, odoo_db_controller),
+                futures::stream::select(odoo_user_controller, odoo_database_controller),
+            )
+            .collect::<()>()
+            .await;
         }
     }
 
     Ok(())
 }
 
+/// The watcher config and the list params selecting the custom resources to reconcile.
+fn custom_resource_selection(label_selector: Option<&str>) -> (watcher::Config, ListParams) {
+    match label_selector {
+        Some(label_selector) => (
+            watcher::Config::default().labels(label_selector),
+            ListParams::default().labels(label_selector),
+        ),
+        None => (watcher::Config::default(), ListParams::default()),
+    }
+}
+
 fn references_authentication_class(
     authentication_config: &Option<OdooClusterAuthenticationConfig>,
     authentication_class: &AuthenticationClass,
@@ -216,6 +570,38 @@ fn references_authentication_class(
 
     authentication_config
         .as_ref()
-        .and_then(|c| c.authentication_class.as_ref())
-        == authentication_class.metadata.name.as_ref()
-}
\ No newline at end of file
+        .map(|c| c.authentication_classes())
+        .unwrap_or_default()
+        .into_iter()
+        .any(|name| Some(name) == authentication_class.metadata.name.as_ref())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_resource_selection() {
+        let (watcher_config, list_params) = custom_resource_selection(Some("shard=a"));
+        assert_eq!(watcher_config.label_selector.as_deref(), Some("shard=a"));
+        assert_eq!(list_params.label_selector.as_deref(), Some("shard=a"));
+
+        let (watcher_config, list_params) = custom_resource_selection(None);
+        assert_eq!(watcher_config.label_selector, None);
+        assert_eq!(list_params.label_selector, None);
+    }
+
+    #[test]
+    fn test_watch_label_selector_argument() {
+        let opts = Opts::try_parse_from([
+            "odoo-operator",
+            "run",
+            "--watch-label-selector",
+            "shard in (a,b)",
+        ])
+        .unwrap();
+        let OdooCommand::Framework(Command::Run(run)) = opts.cmd else {
+            panic!("expected the run command");
+        };
+        assert_eq!(run.watch_label_selector.as_deref(), Some("shard in (a,b)"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Prometheus metrics of the reconciliations of the operator itself, served on `/metrics` by
+//! [`crate::http_server`], so stuck or failing reconciliations can be alerted on.
+//!
+//! The work queue of the controllers is internal to kube-runtime, so the reconciliations in
+//! progress are exposed instead of the queue depth.
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    future::Future,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Instant,
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use stackable_operator::{kube::runtime::controller::Action, logging::controller::ReconcilerError};
+
+/// Upper bounds of the buckets of the reconciliation durations in seconds.
+const DURATION_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+#[derive(Default)]
+pub struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// By controller and result
+    reconciles: BTreeMap<(&'static str, &'static str), u64>,
+    /// By controller and error category
+    errors: BTreeMap<(&'static str, &'static str), u64>,
+    durations: BTreeMap<&'static str, Histogram>,
+    in_progress: BTreeMap<&'static str, i64>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Not cumulative, the observations of every bucket are added up when rendered
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        self.buckets.resize(DURATION_BUCKETS.len(), 0);
+        if let Some(bucket) = DURATION_BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+impl Metrics {
+    /// Runs the reconciliation and records its duration and result.
+    pub async fn observe<T, E: ReconcilerError>(
+        &self,
+        controller: &'static str,
+        reconcile: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        *self.lock().in_progress.entry(controller).or_default() += 1;
+        let start = Instant::now();
+        let result = reconcile.await;
+
+        let mut inner = self.lock();
+        *inner.in_progress.entry(controller).or_default() -= 1;
+        inner
+            .durations
+            .entry(controller)
+            .or_default()
+            .observe(start.elapsed().as_secs_f64());
+        let outcome = match &result {
+            Ok(_) => "success",
+            Err(error) => {
+                *inner
+                    .errors
+                    .entry((controller, error.category()))
+                    .or_default() += 1;
+                "error"
+            }
+        };
+        *inner.reconciles.entry((controller, outcome)).or_default() += 1;
+        result
+    }
+
+    /// The metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let inner = self.lock();
+        let mut out = String::new();
+
+        write_header(
+            &mut out,
+            "odoo_operator_reconciles_total",
+            "counter",
+            "Finished reconciliations by result",
+        );
+        for ((controller, result), value) in &inner.reconciles {
+            let _ = writeln!(
+                out,
+                "odoo_operator_reconciles_total{{controller=\"{controller}\",result=\"{result}\"}} \
+                {value}"
+            );
+        }
+
+        write_header(
+            &mut out,
+            "odoo_operator_reconcile_errors_total",
+            "counter",
+            "Failed reconciliations by error category",
+        );
+        for ((controller, category), value) in &inner.errors {
+            let _ = writeln!(
+                out,
+                "odoo_operator_reconcile_errors_total{{controller=\"{controller}\",\
+                category=\"{category}\"}} {value}"
+            );
+        }
+
+        write_header(
+            &mut out,
+            "odoo_operator_reconcile_duration_seconds",
+            "histogram",
+            "Duration of the reconciliations",
+        );
+        for (controller, histogram) in &inner.durations {
+            let mut cumulative = 0;
+            for (bound, count) in DURATION_BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "odoo_operator_reconcile_duration_seconds_bucket{{controller=\"{controller}\",\
+                    le=\"{bound}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "odoo_operator_reconcile_duration_seconds_bucket{{controller=\"{controller}\",\
+                le=\"+Inf\"}} {}\n\
+                odoo_operator_reconcile_duration_seconds_sum{{controller=\"{controller}\"}} {}\n\
+                odoo_operator_reconcile_duration_seconds_count{{controller=\"{controller}\"}} {}",
+                histogram.count, histogram.sum, histogram.count
+            );
+        }
+
+        write_header(
+            &mut out,
+            "odoo_operator_reconciles_in_progress",
+            "gauge",
+            "Reconciliations currently running",
+        );
+        for (controller, value) in &inner.in_progress {
+            let _ = writeln!(
+                out,
+                "odoo_operator_reconciles_in_progress{{controller=\"{controller}\"}} {value}"
+            );
+        }
+
+        out
+    }
+
+    fn lock(&self) -> MutexGuard<Inner> {
+        // The metrics stay consistent even if a panic poisoned the lock
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Wraps the reconcile function of a controller, so its reconciliations are recorded.
+pub fn observed<K, C, E, Fut>(
+    controller: &'static str,
+    metrics: Arc<Metrics>,
+    reconcile: impl Fn(Arc<K>, Arc<C>) -> Fut,
+) -> impl FnMut(Arc<K>, Arc<C>) -> BoxFuture<'static, Result<Action, E>>
+where
+    E: ReconcilerError + Send + 'static,
+    Fut: Future<Output = Result<Action, E>> + Send + 'static,
+{
+    move |object, ctx| {
+        let metrics = metrics.clone();
+        let reconcile = reconcile(object, ctx);
+        async move { metrics.observe(controller, reconcile).await }.boxed()
+    }
+}
+
+fn write_header(out: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {metric_type}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, snafu::Snafu)]
+    #[snafu(display("failed"))]
+    struct TestError;
+
+    impl ReconcilerError for TestError {
+        fn category(&self) -> &'static str {
+            "TestError"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render() {
+        let metrics = Metrics::default();
+        let _ = metrics
+            .observe("odoocluster", async { Ok::<_, TestError>(()) })
+            .await;
+        let _ = metrics
+            .observe("odoocluster", async { Err::<(), _>(TestError) })
+            .await;
+
+        let rendered = metrics.render();
+        assert!(rendered.contains(
+            "odoo_operator_reconciles_total{controller=\"odoocluster\",result=\"success\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "odoo_operator_reconcile_errors_total{controller=\"odoocluster\",\
+            category=\"TestError\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "odoo_operator_reconcile_duration_seconds_bucket{controller=\"odoocluster\",\
+            le=\"+Inf\"} 2\n"
+        ));
+        assert!(rendered
+            .contains("odoo_operator_reconciles_in_progress{controller=\"odoocluster\"} 0\n"));
+    }
+
+    #[test]
+    fn test_duration_buckets_are_cumulative() {
+        let mut histogram = Histogram::default();
+        for duration in [0.01, 0.3, 0.4, 120.0] {
+            histogram.observe(duration);
+        }
+        let metrics = Metrics::default();
+        metrics.lock().durations.insert("odoocluster", histogram);
+
+        let rendered = metrics.render();
+        for (bound, count) in [("0.05", 1), ("0.25", 1), ("0.5", 3), ("60", 3), ("+Inf", 4)] {
+            assert!(
+                rendered.contains(&format!(
+                    "odoo_operator_reconcile_duration_seconds_bucket{{controller=\"odoocluster\",\
+                    le=\"{bound}\"}} {count}\n"
+                )),
+                "{rendered}"
+            );
+        }
+        assert!(rendered.contains(
+            "odoo_operator_reconcile_duration_seconds_count{controller=\"odoocluster\"} 4\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconciles_in_progress() {
+        let metrics = Arc::new(Metrics::default());
+        let (finish, finished) = tokio::sync::oneshot::channel::<()>();
+        let reconciliation = tokio::spawn({
+            let metrics = metrics.clone();
+            async move {
+                metrics
+                    .observe("odoocluster", async {
+                        finished.await.unwrap();
+                        Ok::<_, TestError>(())
+                    })
+                    .await
+            }
+        });
+        while !metrics
+            .render()
+            .contains("odoo_operator_reconciles_in_progress{controller=\"odoocluster\"} 1\n")
+        {
+            tokio::task::yield_now().await;
+        }
+
+        finish.send(()).unwrap();
+        reconciliation.await.unwrap().unwrap();
+        let rendered = metrics.render();
+        assert!(rendered
+            .contains("odoo_operator_reconciles_in_progress{controller=\"odoocluster\"} 0\n"));
+        assert!(rendered.contains(
+            "odoo_operator_reconciles_total{controller=\"odoocluster\",result=\"success\"} 1\n"
+        ));
+    }
+}
@@ -2,51 +2,94 @@
 use stackable_operator::builder::resources::ResourceRequirementsBuilder;
 use stackable_operator::k8s_openapi::DeepMerge;
 
-use crate::config::{self, PYTHON_IMPORTS};
+use crate::config;
 use crate::controller_commons::{
     self, CONFIG_VOLUME_NAME, LOG_CONFIG_VOLUME_NAME, LOG_VOLUME_NAME,
 };
 use crate::product_logging::{
-    extend_config_map_with_log_config, resolve_vector_aggregator_address,
+    extend_config_map_with_log_config, resolve_custom_log_config,
+    resolve_vector_aggregator_address,
+};
+use crate::applied_objects::{self, AppliedObjects, ROLE_GROUP_CONTROLLER_NAME};
+use crate::{
+    addons_sources, blue_green, filestore, finalizer, git_sync, network_policy, pgbouncer,
+    preflight, rbac, rollout, scale_down, secret_key, upgrade,
+    workload::{DeploymentConditionBuilder, Workload},
+};
+use crate::utils::{
+    self, assemble_connection_uri_command, config_map_hash, env_var_from_secret,
+    export_env_var_from_file, JobState,
 };
-use crate::utils::env_var_from_secret;
 
-use snafu::{OptionExt, ResultExt, Snafu};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
 use sovrin_cloud_crd::odoodb::OdooDBStatus;
 use sovrin_cloud_crd::{
+    addons::{ADDONS_DIR, ADDONS_PIP_DIR, ADDONS_PIP_VOLUME_NAME, ADDONS_VOLUME_NAME},
+    build_recommended_labels,
+    cnpg::{CnpgCluster, CNPG_APP_SECRET_URI_KEY},
+    credentials::{
+        credentials_file, CREDENTIALS_DIR, CREDENTIALS_ENV_VARS, CREDENTIALS_VOLUME_NAME,
+        SECRET_KEY_ENV,
+    },
+    database::SQL_ALCHEMY_CONN_ENV,
+    filestore::{FILESTORE_DIR, FILESTORE_VOLUME_NAME},
+    image_policy::ImagePolicy,
+    oauth::{OAUTH_CLIENT_ID_KEY, OAUTH_CLIENT_SECRET_KEY},
     odoodb::{OdooDB, OdooDBStatusCondition},
-    build_recommended_labels, OdooCluster, OdooConfig, OdooConfigFragment,
-    OdooConfigOptions, OdooRole, Container, AIRFLOW_CONFIG_FILENAME, APP_NAME, CONFIG_PATH,
-    LOG_CONFIG_DIR, OPERATOR_NAME, STACKABLE_LOG_DIR,
+    web, Container, CurrentlySupportedListenerClasses, OdooCluster, OdooConfig, OdooConfigFragment,
+    OdooConfigOptions, OdooRole, AIRFLOW_CONFIG_FILENAME, APP_NAME, CONFIG_PATH, LOG_CONFIG_DIR,
+    ODOO_CONFIG_FILENAME, OPERATOR_NAME, STACKABLE_LOG_DIR,
 };
 use sovrin_cloud_crd::{
-    OdooClusterStatus, AIRFLOW_UID, GIT_CONTENT, GIT_LINK, GIT_ROOT, GIT_SYNC_DIR, GIT_SYNC_NAME,
+    ApiAuthBackend, EgressPolicy, GitSync, GitSyncMode, LogFileRotation, OdooClusterStatus,
+    PreflightFailedCondition, RoleGroupStatus, RolloutStatus, RolloutStrategy, SamlIdpMetadata,
+    ScalingDownStatus, UpdateStrategy, UpgradeCondition, UpgradeStatus, WorkloadType,
+    ADDONS_PACKAGES_DIR, ADDONS_PACKAGES_VOLUME_NAME, AIRFLOW_UID, GIT_CONTENT, GIT_ROOT,
+    GIT_SYNC_DIR, GIT_SYNC_HTTP_PORT, GIT_SYNC_HTTP_PORT_NAME, GIT_SYNC_NAME,
+    GIT_SYNC_REVISION_PORT, GIT_SYNC_REVISION_PORT_NAME, KERBEROS_DIR, SAML_METADATA_DIR,
 };
-use stackable_operator::builder::VolumeBuilder;
+use stackable_operator::builder::{SecretOperatorVolumeSourceBuilder, VolumeBuilder};
 use stackable_operator::k8s_openapi::api::core::v1::EmptyDirVolumeSource;
 use stackable_operator::{
     builder::{
         ConfigMapBuilder, ContainerBuilder, ObjectMetaBuilder, PodBuilder,
         PodSecurityContextBuilder,
     },
+    client::GetApi,
     cluster_resources::{ClusterResourceApplyStrategy, ClusterResources},
     commons::{
         authentication::{AuthenticationClass, AuthenticationClassProvider},
+        opa::OpaApiVersion,
         product_image_selection::ResolvedProductImage,
-        rbac::build_rbac_resources,
     },
     k8s_openapi::{
         api::{
-            apps::v1::{StatefulSet, StatefulSetSpec},
+            apps::v1::{
+                Deployment, DeploymentSpec, DeploymentStrategy, RollingUpdateStatefulSetStrategy,
+                StatefulSet, StatefulSetSpec, StatefulSetUpdateStrategy,
+            },
             core::v1::{
-                ConfigMap, EnvVar, Probe, Service, ServicePort, ServiceSpec, TCPSocketAction,
+                ConfigMap, Container as K8sContainer, EnvVar, Namespace, Probe, Service,
+                ServicePort, ServiceSpec, TCPSocketAction,
             },
+            networking::v1::{
+                HTTPIngressPath, HTTPIngressRuleValue, Ingress, IngressBackend, IngressRule,
+                IngressServiceBackend, IngressSpec, IngressTLS, NetworkPolicy, ServiceBackendPort,
+            },
+            rbac::v1::Role,
+        },
+        apimachinery::pkg::{
+            apis::meta::v1::{LabelSelector, Time},
+            util::intstr::IntOrString,
         },
-        apimachinery::pkg::{apis::meta::v1::LabelSelector, util::intstr::IntOrString},
+        chrono::Utc,
     },
     kube::{
+        api::ListParams,
         runtime::{controller::Action, reflector::ObjectRef},
-        Resource, ResourceExt,
+        Api, Resource, ResourceExt,
     },
     labels::{role_group_selector_labels, role_selector_labels},
     logging::controller::ReconcilerError,
@@ -55,7 +98,7 @@ use stackable_operator::{
         types::PropertyNameKind, ProductConfigManager,
     },
     product_config_utils::{transform_all_roles_to_config, validate_all_roles_and_groups_config},
-    product_logging::{self, spec::Logging},
+    product_logging,
     role_utils::RoleGroupRef,
     status::condition::{
         compute_conditions, operations::ClusterOperationsConditionBuilder,
@@ -64,7 +107,8 @@ use stackable_operator::{
     },
 };
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Debug,
     str::FromStr,
     sync::Arc,
     time::Duration,
@@ -73,13 +117,49 @@ use strum::{EnumDiscriminants, IntoEnumIterator, IntoStaticStr};
 
 pub const AIRFLOW_CONTROLLER_NAME: &str = "odoocluster";
 pub const DOCKER_IMAGE_BASE_NAME: &str = "odoo";
+/// The role group of the shared git-sync Deployment, whose role is `GIT_SYNC_NAME`.
+pub const SHARED_GITSYNC_ROLE_GROUP: &str = "shared";
 
 const METRICS_PORT_NAME: &str = "metrics";
 const METRICS_PORT: i32 = 9102;
+const SAML_METADATA_VOLUME_NAME: &str = "saml-idp-metadata";
+const KERBEROS_VOLUME_NAME: &str = "kerberos";
+const KERBEROS_SERVICE_NAME: &str = "HTTP";
+const KERBEROS_SERVICE_NAMES_ANNOTATION: &str = "secrets.stackable.tech/kerberos.service.names";
+/// Rolls the pods of a rolegroup when its ConfigMap changes
+pub const CONFIG_HASH_ANNOTATION: &str = "odoo.sovrin.cloud/config-hash";
+/// The number of role groups whose objects are applied at the same time
+const MAX_CONCURRENT_ROLE_GROUPS: usize = 8;
+
+/// The credentials read from the credentials Secret or provider, without the connection to the
+/// database. The secret key is omitted if it is managed by the operator.
+pub fn credentials_env_vars(odoo: &OdooCluster) -> impl Iterator<Item = &(&str, &str)> {
+    let secret_key_managed = odoo.spec.cluster_config.secret_key_generation.is_some();
+    CREDENTIALS_ENV_VARS
+        .iter()
+        .filter(move |(var_name, _)| !(secret_key_managed && *var_name == SECRET_KEY_ENV))
+}
+
+/// The objects of a role group, which are all built before any of them is applied.
+struct RoleGroupObjects {
+    rolegroup: RoleGroupRef<OdooCluster>,
+    canary_soak_time: Option<Duration>,
+    workload_type: WorkloadType,
+    service: Service,
+    /// Not built for the inactive blue/green generation, which shares the ConfigMap
+    config_map: Option<ConfigMap>,
+    statefulset: StatefulSet,
+}
 
 pub struct Ctx {
     pub client: stackable_operator::client::Client,
     pub product_config: ProductConfigManager,
+    pub image_policy: ImagePolicy,
+    /// The label selector of the watchers, whose labels are copied from the OdooCluster to the
+    /// Services, ConfigMaps, workloads, Jobs and the OdooDB built for it
+    pub watch_label_selector: Option<String>,
+    /// Only the clusters in namespaces matching this label selector are reconciled
+    pub namespace_label_selector: Option<String>,
 }
 
 #[derive(Snafu, Debug, EnumDiscriminants)]
@@ -88,25 +168,128 @@ pub struct Ctx {
 pub enum Error {
     #[snafu(display("object has no namespace"))]
     ObjectHasNoNamespace,
+    #[snafu(display("failed to check whether the namespace is selected"))]
+    ListNamespaces {
+        source: stackable_operator::kube::Error,
+    },
+    #[snafu(display("the product image violates the image policy"))]
+    ImagePolicyViolation {
+        source: sovrin_cloud_crd::image_policy::Error,
+    },
+    #[snafu(display("invalid addonsPathOrder"))]
+    InvalidAddonsPathOrder { source: sovrin_cloud_crd::Error },
+    #[snafu(display("invalid addons checksum"))]
+    InvalidAddonsChecksum { source: sovrin_cloud_crd::Error },
+    #[snafu(display("invalid credentials configuration"))]
+    InvalidCredentials { source: sovrin_cloud_crd::Error },
+    #[snafu(display("invalid API auth backend"))]
+    InvalidApiAuthBackend { source: sovrin_cloud_crd::Error },
+    #[snafu(display("invalid database configuration"))]
+    InvalidDatabaseConfig { source: sovrin_cloud_crd::Error },
+    #[snafu(display("failed to migrate the database to the new product version"))]
+    Upgrade { source: crate::upgrade::Error },
+    #[snafu(display("failed to run the preflight checks"))]
+    Preflight { source: crate::preflight::Error },
+    #[snafu(display("failed to initialize the filestore"))]
+    Filestore { source: crate::filestore::Error },
+    #[snafu(display("the filestore initialization job failed"))]
+    FilestoreInitializationFailed,
+    #[snafu(display("invalid git-sync revision"))]
+    InvalidGitSyncRevision { source: sovrin_cloud_crd::Error },
+    #[snafu(display("invalid rotation of the log files"))]
+    InvalidLogFileRotation { source: sovrin_cloud_crd::Error },
+    #[snafu(display("the git-sync {feature} cannot be used with a dedicated git-sync image"))]
+    GitSyncFeatureRequiresProductImage { feature: String },
+    #[snafu(display("the git-sync webhook requires gitSync.sharedVolume"))]
+    GitSyncWebhookRequiresSharedVolume,
+    #[snafu(display("the git-sync webhook requires gitSync.webhookTokenSecret"))]
+    GitSyncWebhookTokenSecretMissing,
+    #[snafu(display("failed to build the git-sync webhook Service"))]
+    BuildGitSyncWebhookService {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to apply the git-sync webhook Service"))]
+    ApplyGitSyncWebhookService {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to build the ConfigMap of the shared git-sync Deployment"))]
+    BuildSharedGitSyncConfig {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to apply the ConfigMap of the shared git-sync Deployment"))]
+    ApplySharedGitSyncConfig {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to apply the shared git-sync Deployment"))]
+    ApplySharedGitSync {
+        source: crate::applied_objects::Error,
+    },
+    #[snafu(display("failed to delete the shared git-sync Deployment"))]
+    DeleteSharedGitSync {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to retrieve the CloudNativePG cluster"))]
+    CnpgClusterRetrieval {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to handle the OdooDB finalizer"))]
+    Finalizer { source: crate::finalizer::Error },
+    #[snafu(display("failed to drain the workers before the scale-down"))]
+    ScaleDown { source: crate::scale_down::Error },
+    #[snafu(display("failed to determine the rollout of the webservers"))]
+    Rollout { source: crate::rollout::Error },
     #[snafu(display("object defines no odoo config role"))]
     NoOdooRole,
     #[snafu(display("failed to apply global Service"))]
     ApplyRoleService {
         source: stackable_operator::error::Error,
     },
+    #[snafu(display("failed to apply global Ingress"))]
+    ApplyRoleIngress {
+        source: crate::applied_objects::Error,
+    },
+    #[snafu(display("failed to add the PgBouncer sidecar"))]
+    AddPgBouncer {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to add the containers providing the addons"))]
+    AddAddonsSource {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to resolve the S3 bucket for the upgrade backup"))]
+    ResolveBackupBucket {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to build the egress NetworkPolicy"))]
+    BuildNetworkPolicy {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to apply the egress NetworkPolicy"))]
+    ApplyNetworkPolicy {
+        source: crate::applied_objects::Error,
+    },
+    #[snafu(display("failed to delete the egress NetworkPolicy"))]
+    DeleteNetworkPolicy {
+        source: stackable_operator::error::Error,
+    },
     #[snafu(display("failed to apply Service for {rolegroup}"))]
     ApplyRoleGroupService {
-        source: stackable_operator::error::Error,
+        source: crate::applied_objects::Error,
         rolegroup: RoleGroupRef<OdooCluster>,
     },
     #[snafu(display("failed to apply ConfigMap for {rolegroup}"))]
     ApplyRoleGroupConfig {
-        source: stackable_operator::error::Error,
+        source: crate::applied_objects::Error,
         rolegroup: RoleGroupRef<OdooCluster>,
     },
     #[snafu(display("failed to apply StatefulSet for {rolegroup}"))]
     ApplyRoleGroupStatefulSet {
-        source: stackable_operator::error::Error,
+        source: crate::applied_objects::Error,
+        rolegroup: RoleGroupRef<OdooCluster>,
+    },
+    #[snafu(display("failed to apply Deployment for {rolegroup}"))]
+    ApplyRoleGroupDeployment {
+        source: crate::applied_objects::Error,
         rolegroup: RoleGroupRef<OdooCluster>,
     },
     #[snafu(display("invalid product config"))]
@@ -137,6 +320,14 @@ pub enum Error {
     ApplyServiceAccount {
         source: stackable_operator::error::Error,
     },
+    #[snafu(display("failed to patch role"))]
+    ApplyRole {
+        source: crate::applied_objects::Error,
+    },
+    #[snafu(display("failed to delete the unused role"))]
+    DeleteRole {
+        source: stackable_operator::error::Error,
+    },
     #[snafu(display("failed to patch role binding: {source}"))]
     ApplyRoleBinding {
         source: stackable_operator::error::Error,
@@ -158,6 +349,29 @@ pub enum Error {
         authentication_class_provider: String,
         authentication_class: ObjectRef<AuthenticationClass>,
     },
+    #[snafu(display(
+        "remote user authentication requires proxy mode, please set clusterConfig.web.proxyMode"
+    ))]
+    RemoteUserWithoutProxyMode,
+    #[snafu(display("the role [{role}] does not support the database replica"))]
+    DatabaseReplicaNotSupported { role: String },
+    #[snafu(display("the role [{role}] does not support the workload type Deployment"))]
+    DeploymentNotSupported { role: String },
+    #[snafu(display(
+        "useDatabaseReplica requires a replica, please set clusterConfig.database.replica"
+    ))]
+    DatabaseReplicaMissing,
+    #[snafu(display(
+        "the pods can open up to {required} database connections, but the database only \
+        accepts {max_connections}, please reduce dbMaxconn or workerProcesses"
+    ))]
+    TooManyDatabaseConnections { required: u32, max_connections: u32 },
+    #[snafu(display("failed to reconcile the secret key"))]
+    ReconcileSecretKey { source: secret_key::Error },
+    #[snafu(display("invalid OPA configuration"))]
+    InvalidOpaConfig {
+        source: stackable_operator::error::Error,
+    },
     #[snafu(display("failed to build config file for {rolegroup}"))]
     BuildRoleGroupConfigFile {
         source: FlaskAppConfigWriterError,
@@ -191,10 +405,18 @@ pub enum Error {
     DeleteOrphanedResources {
         source: stackable_operator::error::Error,
     },
+    #[snafu(display("failed to delete the orphaned objects of the role groups"))]
+    DeleteOrphanedRoleGroupObjects {
+        source: crate::applied_objects::Error,
+    },
     #[snafu(display("failed to resolve the Vector aggregator address"))]
     ResolveVectorAggregatorAddress {
         source: crate::product_logging::Error,
     },
+    #[snafu(display("failed to resolve the custom logging configuration"))]
+    ResolveCustomLogConfig {
+        source: crate::product_logging::Error,
+    },
     #[snafu(display("failed to add the logging configuration to the ConfigMap [{cm_name}]"))]
     InvalidLoggingConfig {
         source: crate::product_logging::Error,
@@ -218,8 +440,72 @@ pub async fn reconcile_odoo(odoo: Arc<OdooCluster>, ctx: Arc<Ctx>) -> Result<Act
     tracing::info!("Starting reconcile");
 
     let client = &ctx.client;
+    // The watchers of the created objects are restricted with the label selector as well
+    let selected_labels =
+        utils::selected_labels(ctx.watch_label_selector.as_deref(), odoo.labels());
+    if odoo.metadata.deletion_timestamp.is_some() {
+        finalizer::finalize(client, &odoo)
+            .await
+            .context(FinalizerSnafu)?;
+        return Ok(Action::await_change());
+    }
+    if let Some(label_selector) = &ctx.namespace_label_selector {
+        if !namespace_selected(client, &odoo, label_selector).await? {
+            tracing::debug!("Skipping the cluster, its namespace is not selected");
+            return Ok(Action::await_change());
+        }
+    }
+    finalizer::ensure_finalizer(client, &odoo)
+        .await
+        .context(FinalizerSnafu)?;
+
     let resolved_product_image: ResolvedProductImage =
         odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME);
+    let image_policy = ctx
+        .image_policy
+        .effective(odoo.spec.cluster_config.image_policy);
+    image_policy
+        .validate(&resolved_product_image.image)
+        .context(ImagePolicyViolationSnafu)?;
+    let blue_green_config = odoo.spec.cluster_config.blue_green.as_ref();
+    let preview_image = blue_green_config
+        .and_then(|blue_green| blue_green.preview_image.as_ref())
+        .map(|preview_image| preview_image.resolve(DOCKER_IMAGE_BASE_NAME));
+    if let Some(preview_image) = &preview_image {
+        image_policy
+            .validate(&preview_image.image)
+            .context(ImagePolicyViolationSnafu)?;
+    }
+    odoo.validate_addons_path_order().context(InvalidAddonsPathOrderSnafu)?;
+    odoo.spec
+        .cluster_config
+        .validate_addons_checksums()
+        .context(InvalidAddonsChecksumSnafu)?;
+    odoo.spec
+        .cluster_config
+        .validate_credentials()
+        .context(InvalidCredentialsSnafu)?;
+    odoo.spec
+        .cluster_config
+        .validate_api_auth_backend()
+        .context(InvalidApiAuthBackendSnafu)?;
+    odoo.spec
+        .cluster_config
+        .database
+        .validate()
+        .context(InvalidDatabaseConfigSnafu)?;
+
+    if let Some(cnpg_cluster_name) = &odoo.spec.cluster_config.database.cnpg_cluster_ref {
+        let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+        let cnpg_cluster = client
+            .get_opt::<CnpgCluster>(cnpg_cluster_name, &namespace)
+            .await
+            .context(CnpgClusterRetrievalSnafu)?;
+        if !cnpg_cluster.map_or(false, |cnpg_cluster| cnpg_cluster.is_ready()) {
+            tracing::info!(cnpg_cluster_name, "Waiting for the CloudNativePG cluster to be ready");
+            return Ok(Action::requeue(Duration::from_secs(10)));
+        }
+    }
 
     let cluster_operation_cond_builder =
         ClusterOperationsConditionBuilder::new(&odoo.spec.cluster_operation);
@@ -229,6 +515,7 @@ pub async fn reconcile_odoo(odoo: Arc<OdooCluster>, ctx: Arc<Ctx>) -> Result<Act
         &odoo,
         &resolved_product_image,
         &cluster_operation_cond_builder,
+        &selected_labels,
     )
         .await?
     {
@@ -274,9 +561,10 @@ pub async fn reconcile_odoo(odoo: Arc<OdooCluster>, ctx: Arc<Ctx>) -> Result<Act
         .await
         .context(ResolveVectorAggregatorAddressSnafu)?;
 
-    let authentication_class = match &odoo.spec.cluster_config.authentication_config {
-        Some(authentication_config) => match &authentication_config.authentication_class {
-            Some(authentication_class) => Some(
+    let mut authentication_classes = Vec::new();
+    if let Some(authentication_config) = &odoo.spec.cluster_config.authentication_config {
+        for authentication_class in authentication_config.authentication_classes() {
+            authentication_classes.push(
                 AuthenticationClass::resolve(client, authentication_class)
                     .await
                     .context(AuthenticationClassRetrievalSnafu {
@@ -284,12 +572,55 @@ pub async fn reconcile_odoo(odoo: Arc<OdooCluster>, ctx: Arc<Ctx>) -> Result<Act
                             authentication_class,
                         ),
                     })?,
-            ),
-            None => None,
-        },
+            );
+        }
+    }
+
+    if let Some(authentication_config) = &odoo.spec.cluster_config.authentication_config {
+        // Without a trusted proxy anyone could set the header and log in as any user
+        ensure!(
+            authentication_config.remote_user.is_none()
+                || odoo.spec.cluster_config.web.proxy_mode,
+            RemoteUserWithoutProxyModeSnafu
+        );
+    }
+
+    if let Some(max_connections) = odoo.spec.cluster_config.database.max_connections {
+        // PgBouncer limits the connections to the database itself
+        if pgbouncer::pgbouncer_config(&odoo).is_none() {
+            let required = odoo
+                .max_database_connections()
+                .context(FailedToResolveConfigSnafu)?;
+            ensure!(
+                required <= max_connections,
+                TooManyDatabaseConnectionsSnafu {
+                    required,
+                    max_connections
+                }
+            );
+        }
+    }
+
+    let opa_url = match &odoo.spec.cluster_config.authorization {
+        Some(authorization) => Some(
+            authorization
+                .opa
+                .full_document_url_from_config_map(
+                    client,
+                    odoo.as_ref(),
+                    Some("allow"),
+                    OpaApiVersion::V1,
+                )
+                .await
+                .context(InvalidOpaConfigSnafu)?,
+        ),
         None => None,
     };
 
+    let secret_key_status = secret_key::reconcile_secret_key(client, &odoo)
+        .await
+        .context(ReconcileSecretKeySnafu)?;
+
     let mut cluster_resources = ClusterResources::new(
         APP_NAME,
         OPERATOR_NAME,
@@ -299,33 +630,266 @@ pub async fn reconcile_odoo(odoo: Arc<OdooCluster>, ctx: Arc<Ctx>) -> Result<Act
     )
         .context(CreateClusterResourcesSnafu)?;
 
-    let (rbac_sa, rbac_rolebinding) = build_rbac_resources(
-        odoo.as_ref(),
-        APP_NAME,
-        cluster_resources.get_required_labels(),
-    )
-        .context(BuildRBACObjectsSnafu)?;
+    let (rbac_sa, rbac_role_and_binding) =
+        rbac::build_cluster_rbac_resources(odoo.as_ref(), cluster_resources.get_required_labels())
+            .context(BuildRBACObjectsSnafu)?;
 
     let rbac_sa = cluster_resources
         .add(client, rbac_sa)
         .await
         .context(ApplyServiceAccountSnafu)?;
-    cluster_resources
-        .add(client, rbac_rolebinding)
+    match rbac_role_and_binding {
+        Some((rbac_role, rbac_rolebinding)) => {
+            // Roles are not supported by ClusterResources, they are deleted below instead
+            applied_objects::apply(client, &odoo, AIRFLOW_CONTROLLER_NAME, &rbac_role)
+                .await
+                .context(ApplyRoleSnafu)?;
+            cluster_resources
+                .add(client, rbac_rolebinding)
+                .await
+                .context(ApplyRoleBindingSnafu)?;
+        }
+        None => {
+            // The binding is deleted as orphan, the Role has to be deleted explicitly
+            let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+            if let Some(rbac_role) = client
+                .get_opt::<Role>(&rbac::cluster_role_name(&odoo), &namespace)
+                .await
+                .context(DeleteRoleSnafu)?
+            {
+                client.delete(&rbac_role).await.context(DeleteRoleSnafu)?;
+            }
+        }
+    }
+
+    // The bucket is only needed to open its port in the egress NetworkPolicy
+    let backup_bucket = match &odoo.spec.cluster_config.upgrade_backup {
+        Some(upgrade_backup)
+            if odoo.spec.cluster_config.egress_policy == EgressPolicy::Strict =>
+        {
+            Some(
+                upgrade_backup
+                    .bucket
+                    .resolve(client, &odoo.namespace().context(ObjectHasNoNamespaceSnafu)?)
+                    .await
+                    .context(ResolveBackupBucketSnafu)?,
+            )
+        }
+        _ => None,
+    };
+    match network_policy::build_egress_network_policy(
+        &odoo,
+        &authentication_classes,
+        vector_aggregator_address.as_deref(),
+        opa_url.as_deref(),
+        backup_bucket.as_ref(),
+    )
+    .context(BuildNetworkPolicySnafu)?
+    {
+        Some(network_policy) => {
+            applied_objects::apply(client, &odoo, AIRFLOW_CONTROLLER_NAME, &network_policy)
+                .await
+                .context(ApplyNetworkPolicySnafu)?;
+        }
+        None => {
+            // Lift the restriction if the egress policy was switched back to unrestricted
+            let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+            if let Some(network_policy) = client
+                .get_opt::<NetworkPolicy>(&network_policy::network_policy_name(&odoo), &namespace)
+                .await
+                .context(DeleteNetworkPolicySnafu)?
+            {
+                client
+                    .delete(&network_policy)
+                    .await
+                    .context(DeleteNetworkPolicySnafu)?;
+            }
+        }
+    }
+
+    let preflight_failures = preflight::preflight_failures(client, &odoo)
         .await
-        .context(ApplyRoleBindingSnafu)?;
+        .context(PreflightSnafu)?;
+    if !preflight_failures.is_empty() {
+        tracing::warn!(?preflight_failures, "Preflight checks failed");
+        let last_transition_time = odoo
+            .status
+            .as_ref()
+            .and_then(|status| status.preflight_failed.as_ref())
+            .map(|preflight_failed| preflight_failed.last_transition_time.clone())
+            .unwrap_or_else(|| Time(Utc::now()));
+        let status = OdooClusterStatus {
+            secret_key: secret_key_status,
+            preflight_failed: Some(PreflightFailedCondition {
+                last_transition_time,
+                failures: preflight_failures,
+            }),
+            ..cluster_status(
+                &odoo,
+                compute_conditions(odoo.as_ref(), &[&cluster_operation_cond_builder]),
+            )
+        };
+        apply_status(client, &odoo, &status).await?;
+        // Missing Secrets and DNS records are not watched, so the checks are repeated
+        return Ok(Action::requeue(Duration::from_secs(30)));
+    }
 
-    let mut ss_cond_builder = StatefulSetConditionBuilder::default();
+    if let Some(upgrade) = upgrade::reconcile_upgrade(
+        client,
+        &odoo,
+        &resolved_product_image,
+        &rbac_sa.name_unchecked(),
+        &selected_labels,
+    )
+    .await
+    .context(UpgradeSnafu)?
+    {
+        // The workers and schedulers keep running the previous version until the database is
+        // upgraded, the webservers are stopped while it changes
+        let rollout = if rollout::requires_maintenance(&upgrade.condition) {
+            Some(
+                rollout::stop_webservers(client, &odoo, &resolved_product_image.image)
+                    .await
+                    .context(RolloutSnafu)?,
+            )
+        } else {
+            odoo.status
+                .as_ref()
+                .and_then(|status| status.rollout.clone())
+        };
+        let conditions = compute_conditions(
+            odoo.as_ref(),
+            &[
+                &UpgradeConditionBuilder(&upgrade),
+                &cluster_operation_cond_builder,
+            ],
+        );
+        let status = OdooClusterStatus {
+            secret_key: secret_key_status,
+            upgrade: Some(upgrade),
+            rollout,
+            ..cluster_status(&odoo, conditions)
+        };
+        apply_status(client, &odoo, &status).await?;
+        return Ok(Action::await_change());
+    }
+
+    if let Some(filestore_config) = &odoo.spec.cluster_config.filestore {
+        if let Some(initialization) = &filestore_config.initialization {
+            match filestore::reconcile_filestore(
+                client,
+                &odoo,
+                filestore_config,
+                initialization,
+                &resolved_product_image,
+                &rbac_sa.name_unchecked(),
+                &selected_labels,
+            )
+            .await
+            .context(FilestoreSnafu)?
+            {
+                JobState::Complete => {}
+                // The job is owned by the cluster, so its completion triggers a reconciliation
+                JobState::InProgress => return Ok(Action::await_change()),
+                JobState::Failed => return FilestoreInitializationFailedSnafu.fail(),
+            }
+        }
+    }
+
+    if let Some(mut webhook_service) =
+        git_sync::build_webhook_service(&odoo, &resolved_product_image)
+            .context(BuildGitSyncWebhookServiceSnafu)?
+    {
+        utils::add_labels(&mut webhook_service, &selected_labels);
+        cluster_resources
+            .add(client, webhook_service)
+            .await
+            .context(ApplyGitSyncWebhookServiceSnafu)?;
+    }
+
+    let shared_gitsync_name = format!("{}-{GIT_SYNC_NAME}", odoo.name_any());
+    match odoo.git_sync().filter(|gitsync| gitsync.uses_shared_volume()) {
+        Some(gitsync) => {
+            let mut config_map = build_shared_gitsync_config_map(
+                &odoo,
+                gitsync,
+                &resolved_product_image,
+                &shared_gitsync_name,
+            )?;
+            utils::add_labels(&mut config_map, &selected_labels);
+            cluster_resources
+                .add(client, config_map)
+                .await
+                .context(ApplySharedGitSyncConfigSnafu)?;
+            let mut deployment = build_shared_gitsync_deployment(
+                &odoo,
+                gitsync,
+                &resolved_product_image,
+                &shared_gitsync_name,
+                &rbac_sa.name_unchecked(),
+            )?;
+            utils::add_labels(&mut deployment, &selected_labels);
+            applied_objects::apply(client, &odoo, AIRFLOW_CONTROLLER_NAME, &deployment)
+                .await
+                .context(ApplySharedGitSyncSnafu)?;
+        }
+        None => {
+            // Stop syncing if the pods were switched back to their own git-sync containers
+            let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+            if let Some(deployment) = client
+                .get_opt::<Deployment>(&shared_gitsync_name, &namespace)
+                .await
+                .context(DeleteSharedGitSyncSnafu)?
+            {
+                client
+                    .delete(&deployment)
+                    .await
+                    .context(DeleteSharedGitSyncSnafu)?;
+            }
+        }
+    }
+
+    // Records the objects outside of ClusterResources to delete the orphaned ones
+    let mut applied_objects =
+        AppliedObjects::new(&odoo, &odoo.namespace().context(ObjectHasNoNamespaceSnafu)?);
+    // All objects of the role groups are built before any of them is applied, so an invalid role
+    // group does not leave the cluster partially updated
+    let mut rolegroup_objects = Vec::new();
+    // The webservers of the inactive blue/green generation
+    let mut preview_objects = Vec::new();
 
     for (role_name, role_config) in validated_role_config.iter() {
         // some roles will only run "internally" and do not need to be created as services
         if let Some(resolved_port) = role_port(role_name) {
-            let role_service =
+            let mut role_service =
                 build_role_service(&odoo, &resolved_product_image, role_name, resolved_port)?;
+            utils::add_labels(&mut role_service, &selected_labels);
+            let is_webserver = *role_name == OdooRole::Webserver.to_string();
+            if let Some(blue_green) = blue_green_config.filter(|_| is_webserver) {
+                blue_green::select_active(&mut role_service, blue_green);
+                if preview_image.is_some() {
+                    cluster_resources
+                        .add(
+                            client,
+                            blue_green::build_preview_service(&role_service, blue_green),
+                        )
+                        .await
+                        .context(ApplyRoleServiceSnafu)?;
+                }
+            }
             cluster_resources
                 .add(client, role_service)
                 .await
                 .context(ApplyRoleServiceSnafu)?;
+
+            if let Some(role_ingress) =
+                build_role_ingress(&odoo, &resolved_product_image, role_name, resolved_port)?
+            {
+                let role_ingress = apply_rolegroup_object(client, &odoo, &role_ingress)
+                    .await
+                    .context(ApplyRoleIngressSnafu)?;
+                applied_objects.record(&role_ingress);
+            }
         }
 
         for (rolegroup_name, rolegroup_config) in role_config.iter() {
@@ -340,74 +904,265 @@ pub async fn reconcile_odoo(odoo: Arc<OdooCluster>, ctx: Arc<Ctx>) -> Result<Act
                     role: role_name.to_string(),
                 })?;
 
-            let config = odoo
-                .merged_config(&odoo_role, &rolegroup)
-                .context(FailedToResolveConfigSnafu)?;
+            let config = rolegroup_config(&odoo, &odoo_role, &rolegroup)?;
+            let log_file_rotation = config
+                .log_file_rotation()
+                .context(InvalidLogFileRotationSnafu)?;
 
-            let rg_service =
+            let mut rg_service =
                 build_rolegroup_service(&odoo, &resolved_product_image, &rolegroup)?;
-            cluster_resources.add(client, rg_service).await.context(
-                ApplyRoleGroupServiceSnafu {
-                    rolegroup: rolegroup.clone(),
-                },
-            )?;
-
             let rg_configmap = build_rolegroup_config_map(
                 &odoo,
                 &resolved_product_image,
                 &rolegroup,
                 rolegroup_config,
-                authentication_class.as_ref(),
-                &config.logging,
+                &odoo_role,
+                &config,
+                &authentication_classes,
+                opa_url.as_deref(),
+                log_file_rotation.as_ref(),
                 vector_aggregator_address.as_deref(),
             )?;
-            cluster_resources
-                .add(client, rg_configmap)
-                .await
-                .with_context(|_| ApplyRoleGroupConfigSnafu {
-                    rolegroup: rolegroup.clone(),
-                })?;
-
-            let rg_statefulset = build_server_rolegroup_statefulset(
+            // Changes of the custom log configuration are rolled out like the generated one
+            let custom_log_config =
+                resolve_custom_log_config(client, odoo.as_ref(), &config.logging, &Container::Odoo)
+                    .await
+                    .context(ResolveCustomLogConfigSnafu)?;
+            let config_hash = config_map_hash(
+                &[Some(&rg_configmap), custom_log_config.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>(),
+            );
+            let mut rg_statefulset = build_server_rolegroup_statefulset(
                 &odoo,
                 &resolved_product_image,
                 &odoo_role,
                 &rolegroup,
                 rolegroup_config,
-                authentication_class.as_ref(),
+                &authentication_classes,
                 &rbac_sa.name_unchecked(),
                 &config,
+                &config_hash,
             )?;
-
-            ss_cond_builder.add(
-                cluster_resources
-                    .add(client, rg_statefulset)
-                    .await
-                    .context(ApplyRoleGroupStatefulSetSnafu {
+            if let Some(blue_green) = blue_green_config.filter(|_| odoo_role == OdooRole::Webserver)
+            {
+                if let Some(preview_image) = &preview_image {
+                    let mut preview_service =
+                        build_rolegroup_service(&odoo, preview_image, &rolegroup)?;
+                    let mut preview_statefulset = build_server_rolegroup_statefulset(
+                        &odoo,
+                        preview_image,
+                        &odoo_role,
+                        &rolegroup,
+                        rolegroup_config,
+                        &authentication_classes,
+                        &rbac_sa.name_unchecked(),
+                        &config,
+                        &config_hash,
+                    )?;
+                    blue_green::colorize(
+                        &mut preview_statefulset,
+                        &mut preview_service,
+                        blue_green.active_color.other(),
+                    );
+                    preview_objects.push(RoleGroupObjects {
                         rolegroup: rolegroup.clone(),
-                    })?,
-            );
+                        canary_soak_time: None,
+                        workload_type: config.workload_type,
+                        service: preview_service,
+                        config_map: None,
+                        statefulset: preview_statefulset,
+                    });
+                }
+                blue_green::colorize(
+                    &mut rg_statefulset,
+                    &mut rg_service,
+                    blue_green.active_color,
+                );
+            }
+            let canary_soak_time = (config.rollout_strategy == RolloutStrategy::Canary)
+                .then(|| Duration::from_secs(config.canary_soak_time_seconds));
+            rolegroup_objects.push(RoleGroupObjects {
+                rolegroup,
+                canary_soak_time,
+                workload_type: config.workload_type,
+                service: rg_service,
+                config_map: Some(rg_configmap),
+                statefulset: rg_statefulset,
+            });
         }
     }
 
+    for objects in rolegroup_objects.iter_mut().chain(&mut preview_objects) {
+        utils::add_labels(&mut objects.service, &selected_labels);
+        if let Some(config_map) = &mut objects.config_map {
+            utils::add_labels(config_map, &selected_labels);
+        }
+        utils::add_labels(&mut objects.statefulset, &selected_labels);
+    }
+
+    // The objects are applied concurrently and recorded afterwards
+    let applied_services = stream::iter(rolegroup_objects.iter().chain(&preview_objects))
+        .map(|objects| apply_service_and_config_map(client, &odoo, objects))
+        .buffered(MAX_CONCURRENT_ROLE_GROUPS)
+        .try_collect::<Vec<_>>()
+        .await?;
+    for (rg_service, rg_configmap) in applied_services {
+        applied_objects.record(&rg_service);
+        if let Some(rg_configmap) = rg_configmap {
+            applied_objects.record(&rg_configmap);
+        }
+    }
+
+    let mut ss_cond_builder = StatefulSetConditionBuilder::default();
+    let mut deployment_cond_builder = DeploymentConditionBuilder::default();
+    let mut scaling_down = BTreeMap::new();
+    // The canaries are applied first and the webservers last, so the others can be held back
+    // until they are rolled out. The workloads within each of these waves are applied
+    // concurrently.
+    let (canary_objects, other_objects): (Vec<_>, Vec<_>) = rolegroup_objects
+        .into_iter()
+        .partition(|objects| objects.canary_soak_time.is_some());
+    let (webserver_objects, other_objects): (Vec<_>, Vec<_>) = other_objects
+        .into_iter()
+        .partition(|objects| objects.rolegroup.role == OdooRole::Webserver.to_string());
+    let mut others_rolled_out = true;
+    let mut rollout = None;
+    let mut canaries_soaked = true;
+    let mut canaries = BTreeMap::new();
+    let mut roles = BTreeMap::new();
+    let mut all_rolled_out = true;
+    for wave in [canary_objects, other_objects, webserver_objects] {
+        let applied_workloads = stream::iter(wave)
+            .map(|objects| {
+                apply_rolegroup_workload(client, &odoo, objects, canaries_soaked, others_rolled_out)
+            })
+            .buffered(MAX_CONCURRENT_ROLE_GROUPS)
+            .try_collect::<Vec<_>>()
+            .await?;
+        for applied in applied_workloads {
+            let rolegroup = applied.rolegroup;
+            if let Some(scale_down) = applied.scale_down {
+                scaling_down.insert(applied.workload.name(), scale_down);
+            }
+            if applied.rollout.is_some() {
+                rollout = applied.rollout;
+            }
+            let applied_workload = applied.workload;
+            applied_workload.record_in(&mut applied_objects);
+            add_role_group_status(&mut roles, &odoo, &rolegroup, &applied_workload);
+            all_rolled_out &= applied_workload.is_rolled_out();
+            if rolegroup.role != OdooRole::Webserver.to_string() {
+                others_rolled_out &= applied_workload.is_rolled_out();
+            }
+            if let Some(canary_soak_time) = applied.canary_soak_time {
+                let name = applied_workload.name();
+                let canary = rollout::canary_status(
+                    &odoo,
+                    &name,
+                    applied_workload.revision(),
+                    applied_workload.is_rolled_out(),
+                );
+                canaries_soaked &= rollout::is_soaked(canary.as_ref(), canary_soak_time);
+                if let Some(canary) = canary {
+                    canaries.insert(name, canary);
+                }
+            }
+            applied_workload.add_to(&mut ss_cond_builder, &mut deployment_cond_builder);
+        }
+    }
+    // The webservers of the inactive blue/green generation are never held back
+    let applied_previews = stream::iter(preview_objects)
+        .map(|objects| {
+            let workload = Workload::build(objects.statefulset, objects.workload_type, false);
+            apply_workload_object(client, &odoo, workload, objects.rolegroup)
+        })
+        .buffered(MAX_CONCURRENT_ROLE_GROUPS)
+        .try_collect::<Vec<_>>()
+        .await?;
+    for (rolegroup, applied_workload) in applied_previews {
+        applied_workload.record_in(&mut applied_objects);
+        add_role_group_status(&mut roles, &odoo, &rolegroup, &applied_workload);
+        all_rolled_out &= applied_workload.is_rolled_out();
+        applied_workload.add_to(&mut ss_cond_builder, &mut deployment_cond_builder);
+    }
+
     cluster_resources
         .delete_orphaned_resources(client)
         .await
         .context(DeleteOrphanedResourcesSnafu)?;
+    applied_objects
+        .delete_orphans(client)
+        .await
+        .context(DeleteOrphanedRoleGroupObjectsSnafu)?;
 
+    let conditions = compute_conditions(
+        odoo.as_ref(),
+        &[
+            &ss_cond_builder,
+            &deployment_cond_builder,
+            &cluster_operation_cond_builder,
+            &scale_down::ScalingDownConditionBuilder(&scaling_down),
+        ],
+    );
     let status = OdooClusterStatus {
-        conditions: compute_conditions(
-            odoo.as_ref(),
-            &[&ss_cond_builder, &cluster_operation_cond_builder],
+        secret_key: secret_key_status,
+        product_version: Some(resolved_product_image.product_version.clone()),
+        upgrade: None,
+        modules_upgraded_for_image: Some(resolved_product_image.image.clone()),
+        scaling_down,
+        rollout,
+        canaries,
+        roles,
+        deployed_product_version: deployed_product_version(
+            &odoo,
+            &resolved_product_image,
+            all_rolled_out,
         ),
+        observed_generation: odoo.metadata.generation,
+        ..cluster_status(&odoo, conditions)
     };
 
-    client
-        .apply_patch_status(OPERATOR_NAME, &*odoo, &status)
-        .await
-        .context(ApplyStatusSnafu)?;
+    apply_status(client, &odoo, &status).await?;
 
-    Ok(Action::await_change())
+    if status.scaling_down.is_empty() && status.rollout.is_none() && canaries_soaked {
+        Ok(Action::await_change())
+    } else {
+        // Neither the readiness of the drained pods nor the end of a rollout or soak time is
+        // watched
+        Ok(Action::requeue(Duration::from_secs(10)))
+    }
+}
+
+/// The merged config of the role group, rejected if the role does not support it.
+fn rolegroup_config(
+    odoo: &OdooCluster,
+    odoo_role: &OdooRole,
+    rolegroup: &RoleGroupRef<OdooCluster>,
+) -> Result<OdooConfig> {
+    let config = odoo
+        .merged_config(odoo_role, rolegroup)
+        .context(FailedToResolveConfigSnafu)?;
+    if config.use_database_replica {
+        ensure!(
+            odoo_role.supports_database_replica(),
+            DatabaseReplicaNotSupportedSnafu {
+                role: &rolegroup.role
+            }
+        );
+        ensure!(
+            odoo.spec.cluster_config.database.replica.is_some(),
+            DatabaseReplicaMissingSnafu
+        );
+    }
+    ensure!(
+        config.workload_type == WorkloadType::StatefulSet || odoo_role.supports_deployment(),
+        DeploymentNotSupportedSnafu {
+            role: &rolegroup.role
+        }
+    );
+    Ok(config)
 }
 
 /// The server-role service is the primary endpoint that should be used by clients that do not perform internal load balancing,
@@ -418,17 +1173,17 @@ fn build_role_service(
     role_name: &str,
     port: u16,
 ) -> Result<Service> {
-    let role_svc_name = format!(
-        "{}-{}",
-        odoo
-            .metadata
-            .name
-            .as_ref()
-            .unwrap_or(&APP_NAME.to_string()),
-        role_name
-    );
+    let role_svc_name = role_service_name(odoo, role_name);
     let ports = role_ports(port);
 
+    let cluster_config = &odoo.spec.cluster_config;
+    let annotations = match (&cluster_config.listener_class, &cluster_config.load_balancer) {
+        (CurrentlySupportedListenerClasses::ExternalStable, Some(load_balancer)) => {
+            load_balancer.annotations()
+        }
+        _ => BTreeMap::new(),
+    };
+
     Ok(Service {
         metadata: ObjectMetaBuilder::new()
             .name_and_namespace(odoo)
@@ -442,6 +1197,7 @@ fn build_role_service(
                 role_name,
                 "global",
             ))
+            .with_annotations(annotations)
             .build(),
         spec: Some(ServiceSpec {
             type_: Some(
@@ -459,45 +1215,138 @@ fn build_role_service(
     })
 }
 
-fn role_ports(port: u16) -> Vec<ServicePort> {
-    vec![ServicePort {
-        name: Some(APP_NAME.to_string()),
-        port: port.into(),
-        protocol: Some("TCP".to_string()),
-        ..ServicePort::default()
-    }]
+fn role_service_name(odoo: &OdooCluster, role_name: &str) -> String {
+    format!(
+        "{}-{}",
+        odoo
+            .metadata
+            .name
+            .as_ref()
+            .unwrap_or(&APP_NAME.to_string()),
+        role_name
+    )
 }
 
-fn role_port(role_name: &str) -> Option<u16> {
+/// The role [`Ingress`] exposes the role service under the configured host, if an Ingress is
+/// configured in the cluster spec.
+fn build_role_ingress(
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    role_name: &str,
+    port: u16,
+) -> Result<Option<Ingress>> {
+    let Some(ingress_config) = &odoo.spec.cluster_config.web.ingress else {
+        return Ok(None);
+    };
+    let role_svc_name = role_service_name(odoo, role_name);
+
+    Ok(Some(Ingress {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(&role_svc_name)
+            .ownerreference_from_resource(odoo, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            // Applied through AppliedObjects, which deletes it once it is removed from the spec
+            .with_recommended_labels(build_recommended_labels(
+                odoo,
+                ROLE_GROUP_CONTROLLER_NAME,
+                &resolved_product_image.app_version_label,
+                role_name,
+                "global",
+            ))
+            .with_annotations(ingress_config.annotations())
+            .build(),
+        spec: Some(IngressSpec {
+            ingress_class_name: ingress_config.ingress_class_name.clone(),
+            rules: Some(vec![IngressRule {
+                host: Some(ingress_config.host.clone()),
+                http: Some(HTTPIngressRuleValue {
+                    paths: vec![HTTPIngressPath {
+                        path: Some("/".to_string()),
+                        path_type: "Prefix".to_string(),
+                        backend: IngressBackend {
+                            service: Some(IngressServiceBackend {
+                                name: role_svc_name.clone(),
+                                port: Some(ServiceBackendPort {
+                                    number: Some(port.into()),
+                                    ..ServiceBackendPort::default()
+                                }),
+                            }),
+                            ..IngressBackend::default()
+                        },
+                    }],
+                }),
+            }]),
+            tls: ingress_config
+                .tls_secret_name
+                .as_ref()
+                .map(|tls_secret_name| {
+                    vec![IngressTLS {
+                        hosts: Some(vec![ingress_config.host.clone()]),
+                        secret_name: Some(tls_secret_name.clone()),
+                    }]
+                }),
+            ..IngressSpec::default()
+        }),
+        status: None,
+    }))
+}
+
+fn role_ports(port: u16) -> Vec<ServicePort> {
+    vec![ServicePort {
+        name: Some(APP_NAME.to_string()),
+        port: port.into(),
+        protocol: Some("TCP".to_string()),
+        ..ServicePort::default()
+    }]
+}
+
+fn role_port(role_name: &str) -> Option<u16> {
     OdooRole::from_str(role_name).unwrap().get_http_port()
 }
 
 /// The rolegroup [`ConfigMap`] configures the rolegroup based on the configuration given by the administrator
+#[allow(clippy::too_many_arguments)]
 fn build_rolegroup_config_map(
     odoo: &OdooCluster,
     resolved_product_image: &ResolvedProductImage,
     rolegroup: &RoleGroupRef<OdooCluster>,
     rolegroup_config: &HashMap<PropertyNameKind, BTreeMap<String, String>>,
-    authentication_class: Option<&AuthenticationClass>,
-    logging: &Logging<Container>,
+    odoo_role: &OdooRole,
+    config: &OdooConfig,
+    authentication_classes: &[AuthenticationClass],
+    opa_url: Option<&str>,
+    log_file_rotation: Option<&LogFileRotation>,
     vector_aggregator_address: Option<&str>,
 ) -> Result<ConfigMap, Error> {
-    let mut config = rolegroup_config
+    let mut webserver_config = rolegroup_config
         .get(&PropertyNameKind::File(AIRFLOW_CONFIG_FILENAME.to_string()))
         .cloned()
         .unwrap_or_default();
 
     config::add_odoo_config(
-        &mut config,
+        &mut webserver_config,
         odoo.spec.cluster_config.authentication_config.as_ref(),
-        authentication_class,
+        authentication_classes,
     );
+    config::add_web_config(&mut webserver_config, &odoo.spec.cluster_config.web);
+    if let (Some(authorization), Some(opa_url)) =
+        (&odoo.spec.cluster_config.authorization, opa_url)
+    {
+        config::add_opa_config(&mut webserver_config, authorization, opa_url);
+    }
 
     let mut config_file = Vec::new();
     flask_app_config_writer::write::<OdooConfigOptions, _, _>(
         &mut config_file,
-        config.iter(),
-        PYTHON_IMPORTS,
+        webserver_config.iter(),
+        &config::python_imports(
+            odoo.spec
+                .cluster_config
+                .authorization
+                .as_ref()
+                .filter(|_| opa_url.is_some()),
+        ),
     )
         .with_context(|_| BuildRoleGroupConfigFileSnafu {
             rolegroup: rolegroup.clone(),
@@ -514,7 +1363,7 @@ fn build_rolegroup_config_map(
                 .context(ObjectMissingMetadataForOwnerRefSnafu)?
                 .with_recommended_labels(build_recommended_labels(
                     odoo,
-                    AIRFLOW_CONTROLLER_NAME,
+                    ROLE_GROUP_CONTROLLER_NAME,
                     &resolved_product_image.app_version_label,
                     &rolegroup.role,
                     &rolegroup.role_group,
@@ -524,14 +1373,33 @@ fn build_rolegroup_config_map(
         .add_data(
             AIRFLOW_CONFIG_FILENAME,
             String::from_utf8(config_file).unwrap(),
+        )
+        .add_data(
+            ODOO_CONFIG_FILENAME,
+            config::write_odoo_config(&config::odoo_server_options(odoo, odoo_role, config)),
         );
+    if let Some(pgbouncer_config) = pgbouncer::pgbouncer_config(odoo) {
+        for (file_name, content) in
+            pgbouncer::config_files(pgbouncer_config, odoo.spec.cluster_config.database.tls.as_ref())
+        {
+            cm_builder.add_data(file_name, content);
+        }
+    }
+    if let Some(git_sync) = odoo.git_sync() {
+        for (file_name, content) in git_sync.config_files() {
+            cm_builder.add_data(file_name, content);
+        }
+    }
 
     extend_config_map_with_log_config(
         rolegroup,
         vector_aggregator_address,
-        logging,
+        &config.logging,
         &Container::Odoo,
         &Container::Vector,
+        odoo.spec.cluster_config.audit_logging.as_ref(),
+        log_file_rotation,
+        config.log_format,
         &mut cm_builder,
     )
         .context(InvalidLoggingConfigSnafu {
@@ -560,6 +1428,27 @@ fn build_rolegroup_service(
         ..Default::default()
     }];
 
+    if pgbouncer::pgbouncer_config(odoo).is_some() {
+        ports.push(ServicePort {
+            name: Some(pgbouncer::PGBOUNCER_METRICS_PORT_NAME.into()),
+            port: pgbouncer::PGBOUNCER_METRICS_PORT,
+            protocol: Some("TCP".to_string()),
+            ..Default::default()
+        });
+    }
+    // The shared git-sync pod is not part of the rolegroup
+    let runs_gitsync_sidecar = OdooRole::from_str(&rolegroup.role)
+        .ok()
+        .and_then(|odoo_role| odoo.git_sync_for(&odoo_role))
+        .map_or(false, |gitsync| gitsync.http_enabled() && !gitsync.uses_shared_volume());
+    if runs_gitsync_sidecar {
+        ports.push(ServicePort {
+            name: Some(GIT_SYNC_HTTP_PORT_NAME.into()),
+            port: GIT_SYNC_HTTP_PORT,
+            protocol: Some("TCP".to_string()),
+            ..Default::default()
+        });
+    }
     if let Some(http_port) = role_port(&rolegroup.role) {
         ports.append(&mut role_ports(http_port));
     }
@@ -572,7 +1461,7 @@ fn build_rolegroup_service(
             .context(ObjectMissingMetadataForOwnerRefSnafu)?
             .with_recommended_labels(build_recommended_labels(
                 odoo,
-                AIRFLOW_CONTROLLER_NAME,
+                ROLE_GROUP_CONTROLLER_NAME,
                 &resolved_product_image.app_version_label,
                 &rolegroup.role,
                 &rolegroup.role_group,
@@ -607,9 +1496,10 @@ fn build_server_rolegroup_statefulset(
     odoo_role: &OdooRole,
     rolegroup_ref: &RoleGroupRef<OdooCluster>,
     rolegroup_config: &HashMap<PropertyNameKind, BTreeMap<String, String>>,
-    authentication_class: Option<&AuthenticationClass>,
+    authentication_classes: &[AuthenticationClass],
     sa_name: &str,
     config: &OdooConfig,
+    config_hash: &str,
 ) -> Result<StatefulSet> {
     let role = odoo
         .get_role(odoo_role)
@@ -618,10 +1508,52 @@ fn build_server_rolegroup_statefulset(
 
     let rolegroup = role.role_groups.get(&rolegroup_ref.role_group);
 
-    let commands = odoo_role.get_commands();
+    let mut commands = odoo_role.get_commands();
+    if odoo_role == &OdooRole::Webserver {
+        if let Some(set_parameters) =
+            web::set_config_parameters_command(&odoo.spec.cluster_config.web.config_parameters())
+        {
+            // The system parameters must be written before the webserver is started. The
+            // commands are joined with "; ", so the container is stopped explicitly if they
+            // could not be written.
+            commands.insert(commands.len() - 1, format!("{set_parameters} || exit 1"));
+        }
+    }
+    if let Some(dev_arg) = odoo.dev_arg() {
+        if let Some(start_command) = commands.last_mut() {
+            start_command.push_str(&format!(" {dev_arg}"));
+        }
+    }
+    if *odoo_role == OdooRole::Worker {
+        if let Some(start_command) = commands.last_mut() {
+            *start_command = scale_down::drain_command(start_command);
+        }
+    }
+    let pgbouncer_config = pgbouncer::pgbouncer_config(odoo);
+    if pgbouncer_config.is_some() {
+        commands.insert(0, pgbouncer::rewrite_connection_uri_command());
+    }
+    if odoo.spec.cluster_config.database.assembles_connection_uri() {
+        commands.insert(0, assemble_connection_uri_command());
+    }
+    if odoo.spec.cluster_config.credentials_provider.is_some() {
+        let exports = credentials_env_vars(odoo)
+            .chain(odoo.spec.cluster_config.database.connection_credentials())
+            .map(|(var_name, key)| export_env_var_from_file(var_name, &credentials_file(key)));
+        commands.splice(0..0, exports);
+    }
 
     let mut pb = PodBuilder::new();
     pb.metadata_builder(|m| {
+        m.with_annotation(CONFIG_HASH_ANNOTATION, config_hash)
+            .with_annotations(odoo.restart_annotations(odoo_role));
+        if let Some(generation) = odoo.spec.cluster_config.secret_key_generation {
+            // Rolls the pods once the secret key is rotated
+            m.with_annotation(
+                secret_key::SECRET_KEY_GENERATION_ANNOTATION,
+                generation.to_string(),
+            );
+        }
         m.with_recommended_labels(build_recommended_labels(
             odoo,
             AIRFLOW_CONTROLLER_NAME,
@@ -644,13 +1576,22 @@ fn build_server_rolegroup_statefulset(
     let mut odoo_container = ContainerBuilder::new(&Container::Odoo.to_string())
         .context(InvalidContainerNameSnafu)?;
 
-    if let Some(authentication_class) = authentication_class {
+    let mut authentication_volume_names = BTreeSet::new();
+    for authentication_class in authentication_classes {
         add_authentication_volumes_and_volume_mounts(
             authentication_class,
+            &mut authentication_volume_names,
             &mut odoo_container,
             &mut pb,
         )?;
     }
+    if authentication_classes.is_empty() {
+        add_saml_volumes_and_volume_mounts(odoo, &mut odoo_container, &mut pb);
+    }
+    // Only the webservers serve the API, which is authenticated with Kerberos
+    if odoo_role == &OdooRole::Webserver {
+        add_kerberos_volumes_and_env(odoo, &rolegroup_ref.role, &mut odoo_container, &mut pb);
+    }
 
     odoo_container
         .image_from_product_image(resolved_product_image)
@@ -663,6 +1604,8 @@ fn build_server_rolegroup_statefulset(
         .get(&PropertyNameKind::Env)
         .iter()
         .flat_map(|env_vars| env_vars.iter())
+        // The git credentials are only passed to the git-sync containers
+        .filter(|(k, _)| !k.starts_with(OdooConfig::GIT_CREDENTIALS_SECRET_PROPERTY))
         .map(|(k, v)| EnvVar {
             name: k.clone(),
             value: Some(v.clone()),
@@ -671,17 +1614,57 @@ fn build_server_rolegroup_statefulset(
         .collect::<Vec<_>>();
 
     // mapped environment variables
-    let env_mapped = build_mapped_envs(odoo, rolegroup_config);
+    let env_mapped = build_mapped_envs(odoo, odoo_role, rolegroup_config);
 
     odoo_container.add_env_vars(env_config);
     odoo_container.add_env_vars(env_mapped);
-    odoo_container.add_env_vars(build_static_envs());
+    let installs_requirements = odoo
+        .git_sync_for(odoo_role)
+        .map_or(false, |gitsync| gitsync.install_requirements);
+    let mut python_paths = vec![];
+    if installs_requirements {
+        python_paths.push(ADDONS_PACKAGES_DIR);
+    }
+    if addons_sources::installs_pip_addons(odoo) {
+        python_paths.push(ADDONS_PIP_DIR);
+    }
+    odoo_container.add_env_vars(build_static_envs(
+        &odoo.spec.cluster_config.api_auth_backend,
+        &python_paths,
+    ));
 
-    let volume_mounts = odoo.volume_mounts();
+    let volume_mounts = odoo.volume_mounts(odoo_role);
     odoo_container.add_volume_mounts(volume_mounts);
     odoo_container.add_volume_mount(CONFIG_VOLUME_NAME, CONFIG_PATH);
     odoo_container.add_volume_mount(LOG_CONFIG_VOLUME_NAME, LOG_CONFIG_DIR);
     odoo_container.add_volume_mount(LOG_VOLUME_NAME, STACKABLE_LOG_DIR);
+    if installs_requirements {
+        odoo_container.add_volume_mount(ADDONS_PACKAGES_VOLUME_NAME, ADDONS_PACKAGES_DIR);
+    }
+    if addons_sources::provides_addons(odoo) {
+        odoo_container.add_volume_mount(ADDONS_VOLUME_NAME, ADDONS_DIR);
+    }
+    if addons_sources::installs_pip_addons(odoo) {
+        odoo_container.add_volume_mount(ADDONS_PIP_VOLUME_NAME, ADDONS_PIP_DIR);
+    }
+    if let Some(database_tls) = &odoo.spec.cluster_config.database.tls {
+        pb.add_volumes(database_tls.volumes());
+        // Otherwise PgBouncer connects to the database via TLS
+        if pgbouncer_config.is_none() {
+            odoo_container.add_volume_mounts(database_tls.volume_mounts());
+            odoo_container.add_env_vars(database_tls.env_vars());
+        }
+    }
+    if let Some(credentials_provider) = &odoo.spec.cluster_config.credentials_provider {
+        pb.add_volume(credentials_provider.volume());
+        odoo_container.add_volume_mount(CREDENTIALS_VOLUME_NAME, CREDENTIALS_DIR);
+    }
+    if let Some(filestore_config) = &odoo.spec.cluster_config.filestore {
+        pb.add_volume(filestore_config.volume());
+        odoo_container.add_volume_mount(FILESTORE_VOLUME_NAME, FILESTORE_DIR);
+    }
+    // The server options rendered into the ConfigMap
+    odoo_container.add_env_var("ODOO_RC", format!("{CONFIG_PATH}/{ODOO_CONFIG_FILENAME}"));
 
     if let Some(resolved_port) = odoo_role.get_http_port() {
         let probe = Probe {
@@ -694,9 +1677,23 @@ fn build_server_rolegroup_statefulset(
             ..Probe::default()
         };
         odoo_container.readiness_probe(probe.clone());
-        odoo_container.liveness_probe(probe);
+        if odoo.dev_arg().is_some() {
+            // The server restarts on its own when the addons change, which must not be
+            // mistaken for a hanging server
+            odoo_container.liveness_probe(Probe {
+                failure_threshold: Some(30),
+                ..probe
+            });
+        } else {
+            odoo_container.liveness_probe(probe);
+        }
         odoo_container.add_container_port("http", resolved_port.into());
     }
+    if *odoo_role == OdooRole::Worker {
+        odoo_container.readiness_probe(scale_down::drained_probe());
+        odoo_container.add_volume_mount(scale_down::DRAIN_VOLUME_NAME, scale_down::DRAIN_DIR);
+        pb.add_volume(scale_down::drain_volume());
+    }
 
     pb.add_container(odoo_container.build());
 
@@ -717,36 +1714,110 @@ fn build_server_rolegroup_statefulset(
         .build();
     pb.add_container(metrics_container);
 
+    if let Some(pgbouncer_config) = pgbouncer_config {
+        pgbouncer::add_pgbouncer_containers(
+            &mut pb,
+            odoo,
+            pgbouncer_config,
+            resolved_product_image,
+            CONFIG_VOLUME_NAME,
+        )
+        .context(AddPgBouncerSnafu)?;
+    }
+
     pb.add_volumes(odoo.volumes());
+    // The audit log is rotated like the log of Odoo
+    let log_files_size = config
+        .log_file_rotation()
+        .context(InvalidLogFileRotationSnafu)?
+        .map(|log_file_rotation| log_file_rotation.files_size());
+    let log_files_count = if odoo.spec.cluster_config.audit_logging.is_some() {
+        2
+    } else {
+        1
+    };
+    let extra_log_files_sizes = log_files_size
+        .map(|log_files_size| vec![log_files_size; log_files_count])
+        .unwrap_or_default();
     pb.add_volumes(controller_commons::create_volumes(
         &rolegroup_ref.object_name(),
         config.logging.containers.get(&Container::Odoo),
+        &extra_log_files_sizes,
     ));
 
-    if let Some(gitsync) = odoo.git_sync() {
-        let gitsync_container = ContainerBuilder::new(&format!("{}-{}", GIT_SYNC_NAME, 1))
-            .context(InvalidContainerNameSnafu)?
-            .add_env_vars(build_gitsync_envs(rolegroup_config))
-            .image_from_product_image(resolved_product_image)
-            .command(vec!["/bin/bash".to_string(), "-c".to_string()])
-            .args(vec![gitsync.get_args().join(" ")])
-            .add_volume_mount(GIT_CONTENT, GIT_ROOT)
-            .resources(
-                ResourceRequirementsBuilder::new()
-                    .with_cpu_request("100m")
-                    .with_cpu_limit("200m")
-                    .with_memory_request("64Mi")
-                    .with_memory_limit("64Mi")
-                    .build(),
-            )
-            .build();
+    addons_sources::add_addons_containers(&mut pb, odoo, resolved_product_image)
+        .context(AddAddonsSourceSnafu)?;
+    addons_sources::add_pip_container(&mut pb, odoo, resolved_product_image)
+        .context(AddAddonsSourceSnafu)?;
 
-        pb.add_volume(
-            VolumeBuilder::new(GIT_CONTENT)
-                .empty_dir(EmptyDirVolumeSource::default())
-                .build(),
-        );
-        pb.add_container(gitsync_container);
+    if let Some(gitsync) = odoo.git_sync_for(odoo_role) {
+        gitsync
+            .validate_revision()
+            .context(InvalidGitSyncRevisionSnafu)?;
+        if gitsync.uses_shared_volume() {
+            // Synced by the shared Deployment
+            pb.add_volume(gitsync.content_volume(true));
+        } else {
+            // Only the first entry is synced so far
+            let index = 0;
+            let credentials_secret = git_credentials_secret(rolegroup_config, index);
+            let gitsync_container = build_gitsync_container(
+                gitsync,
+                &format!("{}-{}", GIT_SYNC_NAME, index + 1),
+                resolved_product_image,
+                credentials_secret,
+            )?;
+
+            pb.add_volume(gitsync.content_volume(false));
+            pb.add_volumes(gitsync.volumes());
+            match gitsync.mode {
+                GitSyncMode::Continuous => {
+                    if gitsync.install_requirements {
+                        // The requirements are installed before the sidecar starts, so the first
+                        // revision is synced by an init container
+                        let initial_sync = GitSync {
+                            mode: GitSyncMode::Once,
+                            webhook: false,
+                            ..gitsync.clone()
+                        };
+                        pb.add_init_container(build_gitsync_container(
+                            &initial_sync,
+                            &format!("{GIT_SYNC_NAME}-init"),
+                            resolved_product_image,
+                            credentials_secret,
+                        )?);
+                    }
+                    pb.add_container(gitsync_container)
+                }
+                GitSyncMode::Once => pb.add_init_container(gitsync_container),
+            };
+        }
+
+        if gitsync.install_requirements {
+            pb.add_init_container(
+                ContainerBuilder::new("addons-requirements")
+                    .context(InvalidContainerNameSnafu)?
+                    .image_from_product_image(resolved_product_image)
+                    .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+                    .args(vec![git_sync::install_requirements_command(gitsync)])
+                    .add_volume_mount(GIT_CONTENT, GIT_SYNC_DIR)
+                    .add_volume_mount(ADDONS_PACKAGES_VOLUME_NAME, ADDONS_PACKAGES_DIR)
+                    .resources(
+                        ResourceRequirementsBuilder::new()
+                            .with_cpu_request("200m")
+                            .with_cpu_limit("1000m")
+                            .with_memory_request("512Mi")
+                            .with_memory_limit("512Mi")
+                            .build(),
+                    )
+                    .build(),
+            );
+            pb.add_volume(
+                VolumeBuilder::new(ADDONS_PACKAGES_VOLUME_NAME)
+                    .empty_dir(EmptyDirVolumeSource::default())
+                    .build(),
+            );
+        }
     }
 
     if config.logging.enable_vector_agent {
@@ -765,10 +1836,37 @@ fn build_server_rolegroup_statefulset(
     }
 
     let mut pod_template = pb.build_template();
+    if let Some(pod_spec) = pod_template.spec.as_mut() {
+        if let Some(pull_secrets) = odoo
+            .git_sync_for(odoo_role)
+            .and_then(|gitsync| gitsync.image.as_ref())
+            .and_then(|image| image.pull_secrets.as_ref())
+        {
+            pod_spec
+                .image_pull_secrets
+                .get_or_insert_with(Vec::new)
+                .extend(pull_secrets.iter().cloned());
+        }
+        if let Some(ca_bundle) = &odoo.spec.cluster_config.ca_bundle {
+            controller_commons::add_ca_bundle(pod_spec, ca_bundle, resolved_product_image);
+        }
+    }
     pod_template.merge_from(role.config.pod_overrides.clone());
     if let Some(rolegroup) = rolegroup {
         pod_template.merge_from(rolegroup.config.pod_overrides.clone());
     }
+    // Applied after the pod overrides, so they cannot weaken it and containers added by them
+    // are covered as well
+    if let Some(pod_spec) = pod_template.spec.as_mut() {
+        let compliance = &odoo.spec.cluster_config.compliance;
+        controller_commons::apply_compliance(pod_spec, compliance);
+        if compliance.automount_service_account_token.is_none()
+            && odoo.spec.cluster_config.executor.as_deref() == Some(rbac::KUBERNETES_EXECUTOR)
+        {
+            // The KubernetesExecutor launches the task pods itself
+            pod_spec.automount_service_account_token = Some(true);
+        }
+    }
 
     Ok(StatefulSet {
         metadata: ObjectMetaBuilder::new()
@@ -778,7 +1876,7 @@ fn build_server_rolegroup_statefulset(
             .context(ObjectMissingMetadataForOwnerRefSnafu)?
             .with_recommended_labels(build_recommended_labels(
                 odoo,
-                AIRFLOW_CONTROLLER_NAME,
+                ROLE_GROUP_CONTROLLER_NAME,
                 &resolved_product_image.app_version_label,
                 &rolegroup_ref.role,
                 &rolegroup_ref.role_group,
@@ -786,7 +1884,7 @@ fn build_server_rolegroup_statefulset(
             .with_label("restarter.stackable.tech/enabled", "true")
             .build(),
         spec: Some(StatefulSetSpec {
-            pod_management_policy: Some("Parallel".to_string()),
+            pod_management_policy: Some(config.pod_management_policy.to_string()),
             replicas: rolegroup.and_then(|rg| rg.replicas).map(i32::from),
             selector: LabelSelector {
                 match_labels: Some(role_group_selector_labels(
@@ -799,16 +1897,36 @@ fn build_server_rolegroup_statefulset(
             },
             service_name: rolegroup_ref.object_name(),
             template: pod_template,
+            update_strategy: build_update_strategy(config),
             ..StatefulSetSpec::default()
         }),
         status: None,
     })
 }
 
+/// The update strategy configured for the rolegroup, if it differs from the default.
+fn build_update_strategy(config: &OdooConfig) -> Option<StatefulSetUpdateStrategy> {
+    let rolling_update = config
+        .rolling_update_partition
+        .map(|partition| RollingUpdateStatefulSetStrategy {
+            partition: Some(partition),
+            ..RollingUpdateStatefulSetStrategy::default()
+        });
+    if config.update_strategy.is_none() && rolling_update.is_none() {
+        return None;
+    }
+    let update_strategy = config.update_strategy.unwrap_or(UpdateStrategy::RollingUpdate);
+    Some(StatefulSetUpdateStrategy {
+        type_: Some(update_strategy.to_string()),
+        rolling_update: rolling_update.filter(|_| update_strategy == UpdateStrategy::RollingUpdate),
+    })
+}
+
 /// This builds a collection of environment variables some require some minimal mapping,
 /// such as executor type, contents of the secret etc.
 fn build_mapped_envs(
     odoo: &OdooCluster,
+    odoo_role: &OdooRole,
     rolegroup_config: &HashMap<PropertyNameKind, BTreeMap<String, String>>,
 ) -> Vec<EnvVar> {
     let secret_prop = rolegroup_config
@@ -816,41 +1934,61 @@ fn build_mapped_envs(
         .and_then(|vars| vars.get(OdooConfig::CREDENTIALS_SECRET_PROPERTY));
 
     let mut env = secret_prop
+        // Credentials from a provider are exported by the start command instead
+        .filter(|_| odoo.spec.cluster_config.credentials_provider.is_none())
         .map(|secret| {
-            vec![
-                // The secret key is used to run the webserver flask app and also used to authorize
-                // requests to Celery workers when logs are retrieved.
-                env_var_from_secret(
-                    "AIRFLOW__WEBSERVER__SECRET_KEY",
-                    secret,
-                    "connections.secretKey",
-                ),
-                env_var_from_secret(
-                    "AIRFLOW__CORE__SQL_ALCHEMY_CONN",
-                    secret,
-                    "connections.sqlalchemyDatabaseUri",
-                ),
-                env_var_from_secret(
-                    "AIRFLOW__CELERY__RESULT_BACKEND",
-                    secret,
-                    "connections.celeryResultBackend",
-                ),
-                env_var_from_secret(
-                    "AIRFLOW__CELERY__BROKER_URL",
-                    secret,
-                    "connections.celeryBrokerUrl",
-                ),
-            ]
+            let database = &odoo.spec.cluster_config.database;
+            let connection_secret = database.connection_secret(secret);
+            credentials_env_vars(odoo)
+                .map(|(var_name, key)| env_var_from_secret(var_name, secret, key))
+                .chain(
+                    database
+                        .connection_credentials()
+                        .iter()
+                        .map(|(var_name, key)| {
+                            env_var_from_secret(var_name, connection_secret, key)
+                        }),
+                )
+                .collect::<Vec<_>>()
         })
         .unwrap_or_default();
+    env.extend(odoo.spec.cluster_config.database.connection_env_vars());
 
-    if let Some(git_sync) = &odoo.git_sync() {
-        if let Some(dags_folder) = &git_sync.git_folder {
-            env.push(EnvVar {
-                name: "AIRFLOW__CORE__DAGS_FOLDER".into(),
-                value: Some(format!("{GIT_SYNC_DIR}/{GIT_LINK}/{dags_folder}")),
-                ..Default::default()
-            })
+    if odoo.spec.cluster_config.secret_key_generation.is_some() {
+        env.push(env_var_from_secret(
+            SECRET_KEY_ENV,
+            &secret_key::secret_key_secret_name(odoo),
+            secret_key::SECRET_KEY_KEY,
+        ));
+    }
+
+    if let Some(cnpg_app_secret) = odoo.spec.cluster_config.database.cnpg_app_secret() {
+        env.push(env_var_from_secret(
+            SQL_ALCHEMY_CONN_ENV,
+            &cnpg_app_secret,
+            CNPG_APP_SECRET_URI_KEY,
+        ));
+    }
+
+    if let Some(oauth) = odoo
+        .spec
+        .cluster_config
+        .authentication_config
+        .as_ref()
+        .and_then(|authentication_config| authentication_config.oauth.as_ref())
+    {
+        for (index, provider) in oauth.providers.iter().enumerate() {
+            let env_var_prefix = provider.env_var_prefix(index);
+            env.push(env_var_from_secret(
+                &format!("{env_var_prefix}_CLIENT_ID"),
+                &provider.client_credentials_secret,
+                OAUTH_CLIENT_ID_KEY,
+            ));
+            env.push(env_var_from_secret(
+                &format!("{env_var_prefix}_CLIENT_SECRET"),
+                &provider.client_credentials_secret,
+                OAUTH_CLIENT_SECRET_KEY,
+            ));
         }
     }
 
@@ -887,30 +2025,259 @@ fn build_mapped_envs(
     env
 }
 
-fn build_gitsync_envs(
+/// The credentials Secret of the git-sync entry at `index`, including the overrides of the
+/// rolegroup.
+fn git_credentials_secret(
     rolegroup_config: &HashMap<PropertyNameKind, BTreeMap<String, String>>,
-) -> Vec<EnvVar> {
-    let mut env = vec![];
-    if let Some(git_secret) = rolegroup_config
+    index: usize,
+) -> Option<&str> {
+    rolegroup_config
         .get(&PropertyNameKind::Env)
-        .and_then(|vars| vars.get(OdooConfig::GIT_CREDENTIALS_SECRET_PROPERTY))
+        .and_then(|vars| vars.get(&OdooConfig::git_credentials_secret_property(index)))
+        .map(String::as_str)
+}
+
+/// `credentials_secret` overrides the `credentialsSecret` of the git-sync settings, e.g. from the
+/// environment overrides of a rolegroup.
+fn build_gitsync_container(
+    gitsync: &GitSync,
+    name: &str,
+    resolved_product_image: &ResolvedProductImage,
+    credentials_secret: Option<&str>,
+) -> Result<K8sContainer> {
+    let mut gitsync_container = ContainerBuilder::new(name).context(InvalidContainerNameSnafu)?;
+    if !gitsync.config_files().is_empty() {
+        gitsync_container.add_volume_mount(CONFIG_VOLUME_NAME, CONFIG_PATH);
+    }
+    if gitsync.webhook_enabled() {
+        // A request only reaches one pod, so only the shared pod can sync for all of them
+        ensure!(
+            gitsync.uses_shared_volume(),
+            GitSyncWebhookRequiresSharedVolumeSnafu
+        );
+        ensure!(
+            gitsync.webhook_token_secret.is_some(),
+            GitSyncWebhookTokenSecretMissingSnafu
+        );
+        gitsync_container.add_container_port(git_sync::WEBHOOK_PORT_NAME, git_sync::WEBHOOK_PORT);
+    }
+    if gitsync.http_enabled() {
+        // Not probed for readiness, a failed sync must not take the pod out of service
+        gitsync_container.add_container_port(GIT_SYNC_HTTP_PORT_NAME, GIT_SYNC_HTTP_PORT);
+    }
+    if gitsync.runs_hook() && gitsync.mode == GitSyncMode::Continuous {
+        gitsync_container.add_container_port(GIT_SYNC_REVISION_PORT_NAME, GIT_SYNC_REVISION_PORT);
+    }
+    if let Some(image) = &gitsync.image {
+        // Both are implemented by scripts, which only the product image can run
+        ensure!(
+            !gitsync.webhook_enabled(),
+            GitSyncFeatureRequiresProductImageSnafu { feature: "webhook" }
+        );
+        ensure!(
+            gitsync.verification.is_none(),
+            GitSyncFeatureRequiresProductImageSnafu {
+                feature: "verification"
+            }
+        );
+        // The image starts git-sync as its entrypoint
+        gitsync_container
+            .image(&image.image)
+            .image_pull_policy(&image.pull_policy)
+            .args(gitsync.get_flags());
+    } else {
+        gitsync_container
+            .image_from_product_image(resolved_product_image)
+            .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+            .args(vec![git_sync::git_sync_command(gitsync)]);
+    }
+    Ok(gitsync_container
+        .add_env_vars(build_gitsync_envs(gitsync, credentials_secret))
+        .add_volume_mount(GIT_CONTENT, GIT_ROOT)
+        .add_volume_mounts(gitsync.volume_mounts())
+        .resources(
+            ResourceRequirementsBuilder::new()
+                .with_cpu_request("100m")
+                .with_cpu_limit("200m")
+                .with_memory_request("64Mi")
+                .with_memory_limit("64Mi")
+                .build(),
+        )
+        .build())
+}
+
+/// The files of the git-sync settings, mounted by the shared Deployment.
+fn build_shared_gitsync_config_map(
+    odoo: &OdooCluster,
+    gitsync: &GitSync,
+    resolved_product_image: &ResolvedProductImage,
+    name: &str,
+) -> Result<ConfigMap> {
+    let mut cm_builder = ConfigMapBuilder::new();
+    cm_builder.metadata(
+        ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(name)
+            .ownerreference_from_resource(odoo, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            .with_recommended_labels(build_recommended_labels(
+                odoo,
+                AIRFLOW_CONTROLLER_NAME,
+                &resolved_product_image.app_version_label,
+                GIT_SYNC_NAME,
+                SHARED_GITSYNC_ROLE_GROUP,
+            ))
+            .build(),
+    );
+    for (file_name, content) in gitsync.config_files() {
+        cm_builder.add_data(file_name, content);
+    }
+    cm_builder.build().context(BuildSharedGitSyncConfigSnafu)
+}
+
+/// The single git-sync pod writing to the shared claim. It is recreated instead of rolled, so
+/// two pods never sync into the same directory.
+fn build_shared_gitsync_deployment(
+    odoo: &OdooCluster,
+    gitsync: &GitSync,
+    resolved_product_image: &ResolvedProductImage,
+    name: &str,
+    sa_name: &str,
+) -> Result<Deployment> {
+    let mut pb = PodBuilder::new();
+    pb.metadata_builder(|m| {
+        m.with_recommended_labels(build_recommended_labels(
+            odoo,
+            AIRFLOW_CONTROLLER_NAME,
+            &resolved_product_image.app_version_label,
+            GIT_SYNC_NAME,
+            SHARED_GITSYNC_ROLE_GROUP,
+        ))
+    })
+    .image_pull_secrets_from_product_image(resolved_product_image)
+    .service_account_name(sa_name)
+    .security_context(
+        PodSecurityContextBuilder::new()
+            .run_as_user(AIRFLOW_UID)
+            .run_as_group(0)
+            .fs_group(1000)
+            .build(),
+    )
+    .add_container(build_gitsync_container(
+        gitsync,
+        GIT_SYNC_NAME,
+        resolved_product_image,
+        gitsync.credentials_secret.as_deref(),
+    )?)
+    .add_volume(gitsync.content_volume(false))
+    .add_volumes(gitsync.volumes());
+    if !gitsync.config_files().is_empty() {
+        pb.add_volume(
+            VolumeBuilder::new(CONFIG_VOLUME_NAME)
+                .with_config_map(name)
+                .build(),
+        );
+    }
+
+    let mut pod_template = pb.build_template();
+    if let Some(pod_spec) = pod_template.spec.as_mut() {
+        if let Some(pull_secrets) = gitsync
+            .image
+            .as_ref()
+            .and_then(|image| image.pull_secrets.as_ref())
+        {
+            pod_spec
+                .image_pull_secrets
+                .get_or_insert_with(Vec::new)
+                .extend(pull_secrets.iter().cloned());
+        }
+        controller_commons::apply_compliance(pod_spec, &odoo.spec.cluster_config.compliance);
+    }
+
+    Ok(Deployment {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(name)
+            .ownerreference_from_resource(odoo, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            .with_recommended_labels(build_recommended_labels(
+                odoo,
+                AIRFLOW_CONTROLLER_NAME,
+                &resolved_product_image.app_version_label,
+                GIT_SYNC_NAME,
+                SHARED_GITSYNC_ROLE_GROUP,
+            ))
+            .build(),
+        spec: Some(DeploymentSpec {
+            replicas: Some(1),
+            selector: LabelSelector {
+                match_labels: Some(role_group_selector_labels(
+                    odoo,
+                    APP_NAME,
+                    GIT_SYNC_NAME,
+                    SHARED_GITSYNC_ROLE_GROUP,
+                )),
+                ..LabelSelector::default()
+            },
+            strategy: Some(DeploymentStrategy {
+                type_: Some("Recreate".to_string()),
+                ..DeploymentStrategy::default()
+            }),
+            template: pod_template,
+            ..DeploymentSpec::default()
+        }),
+        status: None,
+    })
+}
+
+fn build_gitsync_envs(gitsync: &GitSync, credentials_secret: Option<&str>) -> Vec<EnvVar> {
+    let mut env = vec![];
+    if let Some(token_secret) = gitsync
+        .webhook_token_secret
+        .as_deref()
+        .filter(|_| gitsync.webhook_enabled())
     {
-        env.push(env_var_from_secret("GIT_SYNC_USERNAME", git_secret, "user"));
         env.push(env_var_from_secret(
-            "GIT_SYNC_PASSWORD",
-            git_secret,
-            "password",
+            git_sync::WEBHOOK_TOKEN_ENV,
+            token_secret,
+            git_sync::WEBHOOK_TOKEN_SECRET_KEY,
         ));
     }
+    if let Some(git_secret) = credentials_secret {
+        let prefix = gitsync.version.env_var_prefix();
+        env.extend(
+            gitsync
+                .credentials_format
+                .credentials()
+                .iter()
+                .map(|(suffix, key)| {
+                    env_var_from_secret(&format!("{prefix}_{suffix}"), git_secret, key)
+                }),
+        );
+        // After the credentials, which the values reference
+        env.extend(
+            gitsync
+                .git_config_env_vars()
+                .into_iter()
+                .map(|(name, value)| EnvVar {
+                    name,
+                    value: Some(value),
+                    ..EnvVar::default()
+                }),
+        );
+    }
 
     env
 }
 
-fn build_static_envs() -> Vec<EnvVar> {
+/// `python_paths` are added to the `PYTHONPATH` after the log configuration.
+fn build_static_envs(api_auth_backend: &ApiAuthBackend, python_paths: &[&str]) -> Vec<EnvVar> {
+    let mut python_path = vec![LOG_CONFIG_DIR];
+    python_path.extend(python_paths);
     [
         EnvVar {
             name: "PYTHONPATH".into(),
-            value: Some(LOG_CONFIG_DIR.into()),
+            value: Some(python_path.join(":")),
             ..Default::default()
         },
         EnvVar {
@@ -934,11 +2301,10 @@ fn build_static_envs() -> Vec<EnvVar> {
             ..Default::default()
         },
         // Authentication for the API is handled separately to the Web Authentication.
-        // Basic authentication is used by the integration tests.
         // The default is to deny all requests to the API.
         EnvVar {
             name: "AIRFLOW__API__AUTH_BACKEND".into(),
-            value: Some("odoo.api.auth.backend.basic_auth".into()),
+            value: Some(api_auth_backend.module().into()),
             ..Default::default()
         },
     ]
@@ -949,14 +2315,26 @@ pub fn error_policy(_obj: Arc<OdooCluster>, _error: &Error, _ctx: Arc<Ctx>) -> A
     Action::requeue(Duration::from_secs(5))
 }
 
+/// Adds the volumes of the given AuthenticationClass which are not yet contained in
+/// `volume_names`. The volumes are named after the referenced SecretClasses, so
+/// AuthenticationClasses sharing a SecretClass also share the volume.
 fn add_authentication_volumes_and_volume_mounts(
     authentication_class: &AuthenticationClass,
+    volume_names: &mut BTreeSet<String>,
     cb: &mut ContainerBuilder,
     pb: &mut PodBuilder,
 ) -> Result<()> {
     match &authentication_class.spec.provider {
         AuthenticationClassProvider::Ldap(ldap) => {
-            ldap.add_volumes_and_mounts(pb, vec![cb]);
+            let (volumes, volume_mounts) = ldap.volumes_and_mounts();
+            let volumes = volumes
+                .into_iter()
+                .filter(|volume| volume_names.insert(volume.name.clone()))
+                .collect::<Vec<_>>();
+            cb.add_volume_mounts(volume_mounts.into_iter().filter(|volume_mount| {
+                volumes.iter().any(|volume| volume.name == volume_mount.name)
+            }));
+            pb.add_volumes(volumes);
             Ok(())
         }
         _ => AuthenticationClassProviderNotSupportedSnafu {
@@ -969,31 +2347,123 @@ fn add_authentication_volumes_and_volume_mounts(
     }
 }
 
-/// Return true if the controller should wait for the DB to be set up.
-///
-/// As a side-effect, the Odoo cluster status is updated as long as the controller waits
-/// for the DB to come up.
-///
-/// Having the DB set up by a Job managed by a different controller has it's own
-/// set of problems as described here: <https://github.com/stackabletech/superset-operator/issues/351>.
-/// The Superset operator uses the same pattern as implemented here for setting up the DB.
-///
-/// When the ticket above is implemented, this function will most likely be removed completely.
-async fn wait_for_db_and_update_status(
-    client: &stackable_operator::client::Client,
+fn add_saml_volumes_and_volume_mounts(
     odoo: &OdooCluster,
-    resolved_product_image: &ResolvedProductImage,
-    cluster_operation_condition_builder: &ClusterOperationsConditionBuilder<'_>,
-) -> Result<bool> {
-    // ensure admin user has been set up on the odoo database
-    let odoo_db = OdooDB::for_odoo(odoo, resolved_product_image)
-        .context(CreateOdooDBObjectSnafu)?;
-    client
-        .apply_patch(AIRFLOW_CONTROLLER_NAME, &odoo_db, &odoo_db)
-        .await
-        .context(ApplyOdooDBSnafu)?;
+    cb: &mut ContainerBuilder,
+    pb: &mut PodBuilder,
+) {
+    if let Some(SamlIdpMetadata::Secret(metadata_secret)) = odoo
+        .spec
+        .cluster_config
+        .authentication_config
+        .as_ref()
+        .and_then(|authentication_config| authentication_config.saml.as_ref())
+        .map(|saml| &saml.idp_metadata)
+    {
+        pb.add_volume(
+            VolumeBuilder::new(SAML_METADATA_VOLUME_NAME)
+                .with_secret(metadata_secret, false)
+                .build(),
+        );
+        cb.add_volume_mount(SAML_METADATA_VOLUME_NAME, SAML_METADATA_DIR);
+    }
+}
 
-    let odoo_db = client
+/// Mounts the keytab and `krb5.conf` provided by the secret-operator and points the Kerberos
+/// libraries at them. The principal is issued for the role Service.
+fn add_kerberos_volumes_and_env(
+    odoo: &OdooCluster,
+    role_name: &str,
+    cb: &mut ContainerBuilder,
+    pb: &mut PodBuilder,
+) {
+    let kerberos = match odoo
+        .spec
+        .cluster_config
+        .authentication_config
+        .as_ref()
+        .and_then(|authentication_config| authentication_config.kerberos.as_ref())
+    {
+        Some(kerberos) => kerberos,
+        None => return,
+    };
+
+    let role_svc_name = role_service_name(odoo, role_name);
+    let mut volume_source = SecretOperatorVolumeSourceBuilder::new(&kerberos.secret_class)
+        .with_service_scope(&role_svc_name)
+        .build();
+    if let Some(metadata) = volume_source
+        .volume_claim_template
+        .as_mut()
+        .and_then(|template| template.metadata.as_mut())
+    {
+        metadata.annotations.get_or_insert_with(BTreeMap::new).insert(
+            KERBEROS_SERVICE_NAMES_ANNOTATION.to_string(),
+            KERBEROS_SERVICE_NAME.to_string(),
+        );
+    }
+    pb.add_volume(
+        VolumeBuilder::new(KERBEROS_VOLUME_NAME)
+            .ephemeral(volume_source)
+            .build(),
+    );
+    cb.add_volume_mount(KERBEROS_VOLUME_NAME, KERBEROS_DIR);
+
+    let namespace = odoo.namespace().unwrap_or_default();
+    cb.add_env_vars(vec![
+        EnvVar {
+            name: "KRB5_CONFIG".into(),
+            value: Some(format!("{KERBEROS_DIR}/krb5.conf")),
+            ..Default::default()
+        },
+        EnvVar {
+            name: "KRB5_CLIENT_KTNAME".into(),
+            value: Some(format!("{KERBEROS_DIR}/keytab")),
+            ..Default::default()
+        },
+        EnvVar {
+            name: "AIRFLOW__KERBEROS__KEYTAB".into(),
+            value: Some(format!("{KERBEROS_DIR}/keytab")),
+            ..Default::default()
+        },
+        EnvVar {
+            name: "AIRFLOW__KERBEROS__PRINCIPAL".into(),
+            value: Some(format!(
+                "{KERBEROS_SERVICE_NAME}/{role_svc_name}.{namespace}.svc.cluster.local"
+            )),
+            ..Default::default()
+        },
+    ]);
+}
+
+/// Return true if the controller should wait for the DB to be set up.
+///
+/// As a side-effect, the Odoo cluster status is updated as long as the controller waits
+/// for the DB to come up.
+///
+/// Having the DB set up by a Job managed by a different controller has it's own
+/// set of problems as described here: <https://github.com/stackabletech/superset-operator/issues/351>.
+/// The Superset operator uses the same pattern as implemented here for setting up the DB.
+///
+/// When the ticket above is implemented, this function will most likely be removed completely.
+async fn wait_for_db_and_update_status(
+    client: &stackable_operator::client::Client,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    cluster_operation_condition_builder: &ClusterOperationsConditionBuilder<'_>,
+    selected_labels: &BTreeMap<String, String>,
+) -> Result<bool> {
+    // ensure admin user has been set up on the odoo database
+    let mut odoo_db = OdooDB::for_odoo(odoo, resolved_product_image)
+        .context(CreateOdooDBObjectSnafu)?;
+    // The OdooDB controller only reconciles the OdooDBs selected by its label selector
+    utils::add_labels(&mut odoo_db, selected_labels);
+    client
+        .apply_patch(AIRFLOW_CONTROLLER_NAME, &odoo_db, &odoo_db)
+        .await
+        .context(ApplyOdooDBSnafu)?;
+
+    let odoo_db = client
         .get::<OdooDB>(
             &odoo.name_unchecked(),
             odoo
@@ -1011,21 +2481,291 @@ async fn wait_for_db_and_update_status(
     // has a ready condition.
     let db_cond_builder = DbConditionBuilder(odoo_db.status);
     if bool::from(&db_cond_builder) {
-        let status = OdooClusterStatus {
-            conditions: compute_conditions(
+        let status = cluster_status(
+            odoo,
+            compute_conditions(
                 odoo,
                 &[&db_cond_builder, cluster_operation_condition_builder],
             ),
-        };
+        );
 
-        client
-            .apply_patch_status(OPERATOR_NAME, odoo, &status)
-            .await
-            .context(ApplyStatusSnafu)?;
+        apply_status(client, odoo, &status).await?;
     }
     Ok(bool::from(&db_cond_builder))
 }
 
+/// Whether the namespace of the cluster matches the label selector. The selector is evaluated by
+/// the API server, so it supports the same syntax as the namespace watcher.
+async fn namespace_selected(
+    client: &stackable_operator::client::Client,
+    odoo: &OdooCluster,
+    label_selector: &str,
+) -> Result<bool> {
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let namespaces = Api::<Namespace>::all(client.as_kube_client())
+        .list_metadata(&namespace_list_params(&namespace, label_selector))
+        .await
+        .context(ListNamespacesSnafu)?;
+    Ok(!namespaces.items.is_empty())
+}
+
+/// Lists the given namespace only if it matches the label selector.
+fn namespace_list_params(namespace: &str, label_selector: &str) -> ListParams {
+    ListParams::default()
+        .labels(label_selector)
+        .fields(&format!("metadata.name={namespace}"))
+}
+
+/// The clusters to reconcile when the given vector aggregator discovery ConfigMap changes.
+pub fn clusters_using_vector_aggregator(
+    clusters: Vec<Arc<OdooCluster>>,
+    config_map: &ConfigMap,
+) -> Vec<ObjectRef<OdooCluster>> {
+    clusters
+        .into_iter()
+        .filter(|odoo| {
+            odoo.spec
+                .cluster_config
+                .vector_aggregator_config_map_name
+                .as_ref()
+                == config_map.metadata.name.as_ref()
+                && config_map.namespace() == odoo.namespace()
+        })
+        .map(|odoo| ObjectRef::from_obj(&*odoo))
+        .collect()
+}
+
+/// The clusters to reconcile when the given namespace starts or stops matching the namespace
+/// label selector.
+pub fn clusters_in_namespace(
+    clusters: Vec<Arc<OdooCluster>>,
+    namespace: &Namespace,
+) -> Vec<ObjectRef<OdooCluster>> {
+    clusters
+        .into_iter()
+        .filter(|odoo| odoo.namespace() == namespace.metadata.name)
+        .map(|odoo| ObjectRef::from_obj(&*odoo))
+        .collect()
+}
+
+/// The product version all pods run, which is only updated once all workloads are rolled out.
+fn deployed_product_version(
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    all_rolled_out: bool,
+) -> Option<String> {
+    if all_rolled_out {
+        Some(resolved_product_image.product_version.clone())
+    } else {
+        odoo.status
+            .as_ref()
+            .and_then(|status| status.deployed_product_version.clone())
+    }
+}
+
+/// Adds the replicas of the applied workload to the status of the roles.
+fn add_role_group_status(
+    roles: &mut BTreeMap<String, BTreeMap<String, RoleGroupStatus>>,
+    odoo: &OdooCluster,
+    rolegroup: &RoleGroupRef<OdooCluster>,
+    workload: &Workload,
+) {
+    let role_group = workload
+        .role_group()
+        .unwrap_or_else(|| rolegroup.role_group.clone());
+    let previous = odoo
+        .status
+        .as_ref()
+        .and_then(|status| status.roles.get(&rolegroup.role))
+        .and_then(|role| role.get(&role_group));
+    let status = workload.role_group_status(previous);
+    roles
+        .entry(rolegroup.role.clone())
+        .or_default()
+        .insert(role_group, status);
+}
+
+/// Applies the Service and the ConfigMap of the role group.
+async fn apply_service_and_config_map(
+    client: &stackable_operator::client::Client,
+    odoo: &OdooCluster,
+    objects: &RoleGroupObjects,
+) -> Result<(Service, Option<ConfigMap>)> {
+    let rolegroup = &objects.rolegroup;
+    let service = apply_rolegroup_object(client, odoo, &objects.service)
+        .await
+        .context(ApplyRoleGroupServiceSnafu {
+            rolegroup: rolegroup.clone(),
+        })?;
+    let config_map = match &objects.config_map {
+        Some(config_map) => Some(
+            apply_rolegroup_object(client, odoo, config_map)
+                .await
+                .with_context(|_| ApplyRoleGroupConfigSnafu {
+                    rolegroup: rolegroup.clone(),
+                })?,
+        ),
+        None => None,
+    };
+    Ok((service, config_map))
+}
+
+/// A workload as applied together with the decisions taken for it.
+struct AppliedRoleGroupWorkload {
+    rolegroup: RoleGroupRef<OdooCluster>,
+    canary_soak_time: Option<Duration>,
+    scale_down: Option<ScalingDownStatus>,
+    rollout: Option<RolloutStatus>,
+    workload: Workload,
+}
+
+/// Decides whether the workload of the role group is scaled down or held back and applies it.
+/// The non-canaries are held back until the `canaries_soaked` and the webservers until the
+/// `others_rolled_out`.
+async fn apply_rolegroup_workload(
+    client: &stackable_operator::client::Client,
+    odoo: &OdooCluster,
+    objects: RoleGroupObjects,
+    canaries_soaked: bool,
+    others_rolled_out: bool,
+) -> Result<AppliedRoleGroupWorkload> {
+    let RoleGroupObjects {
+        rolegroup,
+        canary_soak_time,
+        workload_type,
+        mut statefulset,
+        ..
+    } = objects;
+    let mut scale_down = None;
+    if rolegroup.role == OdooRole::Worker.to_string() {
+        scale_down = scale_down::reconcile_scale_down(client, odoo, &statefulset)
+            .await
+            .context(ScaleDownSnafu)?;
+        if let Some(scale_down) = &scale_down {
+            // The pods are kept until they are drained
+            if let Some(spec) = statefulset.spec.as_mut() {
+                spec.replicas = Some(scale_down.replicas);
+            }
+        }
+    }
+    let mut held_back = false;
+    let mut rollout = None;
+    if canary_soak_time.is_none() && !canaries_soaked {
+        held_back = true;
+    } else if canary_soak_time.is_none() && rolegroup.role == OdooRole::Webserver.to_string() {
+        rollout = rollout::webserver_rollout(
+            client,
+            odoo,
+            &statefulset,
+            workload_type,
+            others_rolled_out,
+        )
+        .await
+        .context(RolloutSnafu)?;
+        held_back = rollout.is_some();
+    }
+    let mut workload = Workload::build(statefulset, workload_type, held_back);
+    if rollout
+        .as_ref()
+        .map_or(false, |rollout| rollout.maintenance)
+    {
+        // The webservers stay stopped until they run the image the database was upgraded for
+        workload.stop();
+    }
+    let (rolegroup, workload) = apply_workload_object(client, odoo, workload, rolegroup).await?;
+    Ok(AppliedRoleGroupWorkload {
+        rolegroup,
+        canary_soak_time,
+        scale_down,
+        rollout,
+        workload,
+    })
+}
+
+/// Applies the workload of the role group.
+async fn apply_workload_object(
+    client: &stackable_operator::client::Client,
+    odoo: &OdooCluster,
+    mut workload: Workload,
+    rolegroup: RoleGroupRef<OdooCluster>,
+) -> Result<(RoleGroupRef<OdooCluster>, Workload)> {
+    if odoo.spec.cluster_operation.stopped {
+        workload.stop();
+    }
+    let workload = match workload {
+        Workload::StatefulSet(statefulset) => Workload::StatefulSet(
+            apply_rolegroup_object(client, odoo, &statefulset)
+                .await
+                .context(ApplyRoleGroupStatefulSetSnafu {
+                    rolegroup: rolegroup.clone(),
+                })?,
+        ),
+        Workload::Deployment(deployment) => Workload::Deployment(
+            apply_rolegroup_object(client, odoo, &deployment)
+                .await
+                .context(ApplyRoleGroupDeploymentSnafu {
+                    rolegroup: rolegroup.clone(),
+                })?,
+        ),
+    };
+    Ok((rolegroup, workload))
+}
+
+/// Applies the object of a role group. The field manager of `ClusterResources` is kept, which
+/// applied these objects before, so it still owns the fields which are removed from the object.
+async fn apply_rolegroup_object<T>(
+    client: &stackable_operator::client::Client,
+    odoo: &OdooCluster,
+    object: &T,
+) -> Result<T, applied_objects::Error>
+where
+    T: Clone
+        + Debug
+        + DeserializeOwned
+        + GetApi<Namespace = str>
+        + Resource<DynamicType = ()>
+        + Serialize,
+{
+    applied_objects::apply(
+        client,
+        odoo,
+        &format!("{OPERATOR_NAME}_{AIRFLOW_CONTROLLER_NAME}"),
+        object,
+    )
+    .await
+}
+
+/// The status of the cluster with the given conditions, which keeps the other fields of the
+/// current status. The fields applied separately are left out, and a passed preflight is cleared.
+fn cluster_status(odoo: &OdooCluster, conditions: Vec<ClusterCondition>) -> OdooClusterStatus {
+    OdooClusterStatus {
+        conditions,
+        // Applied separately by the database health check
+        database_reachable: None,
+        preflight_failed: None,
+        // Applied separately by the addons revision report
+        addons: BTreeMap::new(),
+        ..odoo.status.clone().unwrap_or_default()
+    }
+}
+
+/// Patches the status only if it changed, so the resourceVersion of the cluster is not bumped and
+/// its watchers are not woken up by every reconcile.
+async fn apply_status(
+    client: &stackable_operator::client::Client,
+    odoo: &OdooCluster,
+    status: &OdooClusterStatus,
+) -> Result<()> {
+    if status.is_unchanged_from(odoo.status.as_ref()) {
+        return Ok(());
+    }
+    client
+        .apply_patch_status(OPERATOR_NAME, odoo, status)
+        .await
+        .context(ApplyStatusSnafu)?;
+    Ok(())
+}
+
 struct DbConditionBuilder(Option<OdooDBStatus>);
 impl ConditionBuilder for DbConditionBuilder {
     fn build_conditions(&self) -> ClusterConditionSet {
@@ -1064,6 +2804,50 @@ impl ConditionBuilder for DbConditionBuilder {
     }
 }
 
+struct UpgradeConditionBuilder<'a>(&'a UpgradeStatus);
+impl ConditionBuilder for UpgradeConditionBuilder<'_> {
+    fn build_conditions(&self) -> ClusterConditionSet {
+        let upgrade = self.0;
+        let message = match upgrade.condition {
+            UpgradeCondition::BackingUp => format!(
+                "Backing up the Odoo database before migrating it to {}",
+                upgrade.to_version
+            ),
+            UpgradeCondition::BackupFailed => format!(
+                "Backing up the Odoo database failed, it is not migrated to {}.",
+                upgrade.to_version
+            ),
+            UpgradeCondition::Upgrading => format!(
+                "Migrating the Odoo database from {} to {}",
+                upgrade.from_version, upgrade.to_version
+            ),
+            UpgradeCondition::Failed => format!(
+                "Migrating the Odoo database from {} to {} failed.",
+                upgrade.from_version, upgrade.to_version
+            ),
+            UpgradeCondition::UpgradingModules => format!(
+                "Upgrading the Odoo modules for the new image of {}",
+                upgrade.to_version
+            ),
+            UpgradeCondition::ModuleUpgradeFailed => format!(
+                "Upgrading the Odoo modules for the new image of {} failed.",
+                upgrade.to_version
+            ),
+        };
+
+        let cond = ClusterCondition {
+            reason: None,
+            message: Some(message),
+            status: ClusterConditionStatus::False,
+            type_: ClusterConditionType::Available,
+            last_transition_time: None,
+            last_update_time: None,
+        };
+
+        vec![cond].into()
+    }
+}
+
 /// Evaluates to true if the DB is not ready yet (the controller needs to wait).
 /// Otherwise false.
 impl From<&DbConditionBuilder> for bool {
@@ -1078,4 +2862,1838 @@ impl From<&DbConditionBuilder> for bool {
             true
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sovrin_cloud_crd::GitSyncSharedVolume;
+    use stackable_operator::{
+        commons::product_image_selection::ProductImage,
+        k8s_openapi::{api::core::v1::PodSpec, apimachinery::pkg::apis::meta::v1::ObjectMeta},
+        memory::{BinaryMultiple, MemoryQuantity},
+    };
+
+    /// Builds the StatefulSet of the `default` rolegroup of the given role.
+    fn build_statefulset(odoo: &OdooCluster, odoo_role: OdooRole) -> StatefulSet {
+        let rolegroup_ref = RoleGroupRef {
+            cluster: ObjectRef::from_obj(odoo),
+            role: odoo_role.to_string(),
+            role_group: "default".to_string(),
+        };
+        let config = odoo.merged_config(&odoo_role, &rolegroup_ref).unwrap();
+        build_server_rolegroup_statefulset(
+            odoo,
+            &odoo.spec.image.resolve("odoo"),
+            &odoo_role,
+            &rolegroup_ref,
+            &HashMap::new(),
+            &[],
+            "odoo-serviceaccount",
+            &config,
+            "hash",
+        )
+        .unwrap()
+    }
+
+    /// Builds the ConfigMap of the `default` rolegroup of the given role.
+    fn build_config_map(odoo: &OdooCluster, odoo_role: OdooRole) -> ConfigMap {
+        let rolegroup_ref = RoleGroupRef {
+            cluster: ObjectRef::from_obj(odoo),
+            role: odoo_role.to_string(),
+            role_group: "default".to_string(),
+        };
+        let config = odoo.merged_config(&odoo_role, &rolegroup_ref).unwrap();
+        build_rolegroup_config_map(
+            odoo,
+            &odoo.spec.image.resolve("odoo"),
+            &rolegroup_ref,
+            &HashMap::new(),
+            &odoo_role,
+            &config,
+            &[],
+            None,
+            config.log_file_rotation().unwrap().as_ref(),
+            Some("vector-aggregator:6000"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rolegroup_config() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            config:
+              workloadType: Deployment
+            roleGroups:
+              default:
+                replicas: 1
+          schedulers:
+            config:
+              useDatabaseReplica: true
+            roleGroups:
+              default:
+                replicas: 1
+          workers:
+            roleGroups:
+              default:
+                replicas: 1
+              deployment:
+                replicas: 1
+                config:
+                  workloadType: Deployment
+          ",
+        )
+        .unwrap();
+        let rolegroup = |odoo_role: OdooRole, role_group: &str| RoleGroupRef {
+            cluster: ObjectRef::from_obj(&odoo),
+            role: odoo_role.to_string(),
+            role_group: role_group.to_string(),
+        };
+
+        let config = rolegroup_config(
+            &odoo,
+            &OdooRole::Webserver,
+            &rolegroup(OdooRole::Webserver, "default"),
+        )
+        .unwrap();
+        assert_eq!(WorkloadType::Deployment, config.workload_type);
+        assert!(rolegroup_config(
+            &odoo,
+            &OdooRole::Worker,
+            &rolegroup(OdooRole::Worker, "default")
+        )
+        .is_ok());
+        // Only the invalid role group is rejected, before any object of the cluster is applied
+        assert!(matches!(
+            rolegroup_config(
+                &odoo,
+                &OdooRole::Worker,
+                &rolegroup(OdooRole::Worker, "deployment")
+            ),
+            Err(Error::DeploymentNotSupported { role }) if role == "worker"
+        ));
+        assert!(matches!(
+            rolegroup_config(
+                &odoo,
+                &OdooRole::Scheduler,
+                &rolegroup(OdooRole::Scheduler, "default")
+            ),
+            Err(Error::DatabaseReplicaNotSupported { role }) if role == "scheduler"
+        ));
+    }
+
+    #[test]
+    fn test_clusters_using_vector_aggregator() {
+        let odoo = |namespace: &str, vector_aggregator: &str| {
+            let odoo: OdooCluster = serde_yaml::from_str(&format!(
+                "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooCluster
+            metadata:
+              name: odoo-{vector_aggregator}
+              namespace: {namespace}
+            spec:
+              image:
+                productVersion: 2.6.1
+                stackableVersion: 0.0.0-dev
+              clusterConfig:
+                credentialsSecret: simple-odoo-credentials
+                vectorAggregatorConfigMapName: {vector_aggregator}
+              "
+            ))
+            .unwrap();
+            Arc::new(odoo)
+        };
+        let config_map = ConfigMap {
+            metadata: ObjectMeta {
+                name: Some("vector-aggregator".to_string()),
+                namespace: Some("default".to_string()),
+                ..ObjectMeta::default()
+            },
+            ..ConfigMap::default()
+        };
+
+        assert_eq!(
+            vec![ObjectRef::new("odoo-vector-aggregator").within("default")],
+            clusters_using_vector_aggregator(
+                vec![
+                    odoo("default", "vector-aggregator"),
+                    odoo("default", "other-aggregator"),
+                    odoo("team-a", "vector-aggregator"),
+                ],
+                &config_map
+            )
+        );
+    }
+
+    #[test]
+    fn test_namespace_selection() {
+        let list_params = namespace_list_params("team-a", "sovrin.cloud/managed=true");
+        assert_eq!(
+            Some("sovrin.cloud/managed=true"),
+            list_params.label_selector.as_deref()
+        );
+        assert_eq!(
+            Some("metadata.name=team-a"),
+            list_params.field_selector.as_deref()
+        );
+
+        let odoo = |namespace: &str| {
+            let mut odoo: OdooCluster = serde_yaml::from_str(
+                "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooCluster
+            metadata:
+              name: odoo
+            spec:
+              image:
+                productVersion: 2.6.1
+                stackableVersion: 0.0.0-dev
+              clusterConfig:
+                credentialsSecret: simple-odoo-credentials
+              ",
+            )
+            .unwrap();
+            odoo.metadata.namespace = Some(namespace.to_string());
+            Arc::new(odoo)
+        };
+        let namespace = Namespace {
+            metadata: ObjectMeta {
+                name: Some("team-a".to_string()),
+                ..ObjectMeta::default()
+            },
+            ..Namespace::default()
+        };
+        assert_eq!(
+            vec![ObjectRef::new("odoo").within("team-a")],
+            clusters_in_namespace(vec![odoo("team-a"), odoo("team-b")], &namespace)
+        );
+    }
+
+    #[test]
+    fn test_update_strategy() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            config:
+              rollingUpdatePartition: 1
+            roleGroups:
+              default:
+                replicas: 2
+          schedulers:
+            config:
+              updateStrategy: OnDelete
+              rollingUpdatePartition: 1
+            roleGroups:
+              default:
+                replicas: 1
+          workers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+        let update_strategy = |odoo_role| {
+            build_statefulset(&odoo, odoo_role)
+                .spec
+                .unwrap()
+                .update_strategy
+        };
+
+        assert_eq!(
+            Some(StatefulSetUpdateStrategy {
+                type_: Some("RollingUpdate".to_string()),
+                rolling_update: Some(RollingUpdateStatefulSetStrategy {
+                    partition: Some(1),
+                    ..RollingUpdateStatefulSetStrategy::default()
+                }),
+            }),
+            update_strategy(OdooRole::Webserver)
+        );
+        // The partition only applies to rolling updates
+        assert_eq!(
+            Some(StatefulSetUpdateStrategy {
+                type_: Some("OnDelete".to_string()),
+                rolling_update: None,
+            }),
+            update_strategy(OdooRole::Scheduler)
+        );
+        // The default of Kubernetes is kept
+        assert_eq!(None, update_strategy(OdooRole::Worker));
+    }
+
+    #[test]
+    fn test_pod_management_policy() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          schedulers:
+            config:
+              podManagementPolicy: OrderedReady
+            roleGroups:
+              default:
+                replicas: 2
+          ",
+        )
+        .unwrap();
+        let pod_management_policy = |odoo_role| {
+            build_statefulset(&odoo, odoo_role)
+                .spec
+                .unwrap()
+                .pod_management_policy
+        };
+
+        assert_eq!(
+            Some("Parallel".to_string()),
+            pod_management_policy(OdooRole::Webserver)
+        );
+        assert_eq!(
+            Some("OrderedReady".to_string()),
+            pod_management_policy(OdooRole::Scheduler)
+        );
+    }
+
+    #[test]
+    fn test_deployed_product_version() {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.7.2
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          status:
+            deployedProductVersion: 2.6.1
+          ",
+        )
+        .unwrap();
+        let resolved_product_image = odoo.spec.image.resolve("odoo");
+
+        // Some pods still run the previous version
+        assert_eq!(
+            Some("2.6.1".to_string()),
+            deployed_product_version(&odoo, &resolved_product_image, false)
+        );
+        assert_eq!(
+            Some("2.7.2".to_string()),
+            deployed_product_version(&odoo, &resolved_product_image, true)
+        );
+        odoo.status = None;
+        assert_eq!(
+            None,
+            deployed_product_version(&odoo, &resolved_product_image, false)
+        );
+    }
+
+    #[test]
+    fn test_cluster_status_keeps_the_current_status() {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.7.2
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+        status:
+          modulesUpgradedForImage: odoo:2.6.1
+          databaseReachable:
+            status: \"True\"
+            lastTransitionTime: 2024-01-01T00:00:00Z
+          preflightFailed:
+            lastTransitionTime: 2024-01-01T00:00:00Z
+            failures:
+              - Secret simple-odoo-credentials is missing
+          rollout:
+            image: odoo:2.7.2
+            startedAt: 2024-01-01T00:00:00Z
+            maintenance: true
+          observedGeneration: 3
+          ",
+        )
+        .unwrap();
+
+        let status = cluster_status(&odoo, Vec::new());
+        assert_eq!(
+            Some("odoo:2.6.1"),
+            status.modules_upgraded_for_image.as_deref()
+        );
+        assert!(status.rollout.unwrap().maintenance);
+        assert_eq!(Some(3), status.observed_generation);
+        // Applied separately or only kept while the preflight checks fail
+        assert_eq!(None, status.database_reachable);
+        assert_eq!(None, status.preflight_failed);
+
+        odoo.status = None;
+        assert_eq!(
+            OdooClusterStatus::default(),
+            cluster_status(&odoo, Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_role_group_status_by_role() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            roleGroups:
+              default:
+                replicas: 2
+          workers:
+            roleGroups:
+              default:
+                replicas: 3
+          status:
+            roles:
+              webserver:
+                default:
+                  replicas: 2
+                  readyReplicas: 2
+                  rolledOutGeneration: 7
+          ",
+        )
+        .unwrap();
+
+        let mut roles = BTreeMap::new();
+        for odoo_role in [OdooRole::Webserver, OdooRole::Worker] {
+            let rolegroup = RoleGroupRef {
+                cluster: ObjectRef::from_obj(&odoo),
+                role: odoo_role.to_string(),
+                role_group: "default".to_string(),
+            };
+            // Not rolled out yet, as the StatefulSets have no status
+            let workload = Workload::build(
+                build_statefulset(&odoo, odoo_role),
+                WorkloadType::StatefulSet,
+                false,
+            );
+            add_role_group_status(&mut roles, &odoo, &rolegroup, &workload);
+        }
+
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    "webserver".to_string(),
+                    BTreeMap::from([(
+                        "default".to_string(),
+                        RoleGroupStatus {
+                            replicas: 2,
+                            ready_replicas: 0,
+                            rolled_out_generation: Some(7),
+                            config_hash: None,
+                        }
+                    )])
+                ),
+                (
+                    "worker".to_string(),
+                    BTreeMap::from([(
+                        "default".to_string(),
+                        RoleGroupStatus {
+                            replicas: 3,
+                            ready_replicas: 0,
+                            rolled_out_generation: None,
+                            config_hash: None,
+                        }
+                    )])
+                ),
+            ]),
+            roles
+        );
+    }
+
+    #[test]
+    fn test_database_replica_options() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            database:
+              replica:
+                host: postgresql-ro
+                port: 5433
+          webservers:
+            config:
+              useDatabaseReplica: true
+            roleGroups:
+              default:
+                replicas: 1
+          schedulers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        assert_eq!(
+            Some("[options]\ndb_replica_host = postgresql-ro\ndb_replica_port = 5433\n"),
+            config_map
+                .data
+                .as_ref()
+                .unwrap()
+                .get(ODOO_CONFIG_FILENAME)
+                .map(String::as_str)
+        );
+        let config_map = build_config_map(&odoo, OdooRole::Scheduler);
+        assert_eq!(
+            Some("[options]\n"),
+            config_map
+                .data
+                .as_ref()
+                .unwrap()
+                .get(ODOO_CONFIG_FILENAME)
+                .map(String::as_str)
+        );
+
+        // Odoo reads the options from the mounted ConfigMap
+        let statefulset = build_statefulset(&odoo, OdooRole::Webserver);
+        let container = &statefulset.spec.unwrap().template.spec.unwrap().containers[0];
+        assert_eq!(
+            Some("/stackable/app/config/odoo.conf"),
+            container
+                .env
+                .iter()
+                .flatten()
+                .find(|env_var| env_var.name == "ODOO_RC")
+                .and_then(|env_var| env_var.value.as_deref())
+        );
+        assert!(container
+            .volume_mounts
+            .iter()
+            .flatten()
+            .any(|mount| mount.name == CONFIG_VOLUME_NAME && mount.mount_path == CONFIG_PATH));
+    }
+
+    #[test]
+    fn test_worker_options() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            config:
+              dbMaxconn: 8
+            roleGroups:
+              default:
+                replicas: 1
+                config:
+                  workerProcesses: 4
+                  maxCronThreads: 1
+          ",
+        )
+        .unwrap();
+
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        assert_eq!(
+            Some("[options]\ndb_maxconn = 8\nmax_cron_threads = 1\nworkers = 4\n"),
+            config_map
+                .data
+                .as_ref()
+                .unwrap()
+                .get(ODOO_CONFIG_FILENAME)
+                .map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn test_filestore_options() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            filestore:
+              claimName: odoo-filestore
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        assert_eq!(
+            Some("[options]\ndata_dir = /stackable/filestore\n"),
+            config_map
+                .data
+                .as_ref()
+                .unwrap()
+                .get(ODOO_CONFIG_FILENAME)
+                .map(String::as_str)
+        );
+
+        let statefulset = build_statefulset(&odoo, OdooRole::Webserver);
+        let pod_spec = statefulset.spec.unwrap().template.spec.unwrap();
+        assert!(pod_spec.containers[0]
+            .volume_mounts
+            .iter()
+            .flatten()
+            .any(|mount| mount.name == FILESTORE_VOLUME_NAME
+                && mount.mount_path == FILESTORE_DIR));
+        assert_eq!(
+            Some("odoo-filestore".to_string()),
+            pod_spec
+                .volumes
+                .iter()
+                .flatten()
+                .find(|volume| volume.name == FILESTORE_VOLUME_NAME)
+                .and_then(|volume| volume.persistent_volume_claim.as_ref())
+                .map(|claim| claim.claim_name.clone())
+        );
+    }
+
+    #[test]
+    fn test_addons_path_options() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            addonsGitSync:
+              - repo: https://github.com/acme/addons
+                gitFolder: /custom/
+            addonsOci:
+              reference: registry.example.com/acme/addons:1.0.0
+            addonsPathOrder:
+              - oci
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        assert_eq!(
+            Some(
+                "[options]\n\
+                addons_path = /stackable/addons/oci,/stackable/app/git/current/custom\n"
+            ),
+            config_map
+                .data
+                .as_ref()
+                .unwrap()
+                .get(ODOO_CONFIG_FILENAME)
+                .map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn test_audit_log_config() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            auditLogging:
+              stream: compliance
+          webservers:
+            config:
+              logging:
+                enableVectorAgent: true
+              logFileMaxSize: 10Mi
+              logFileBackupCount: 3
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        let data = config_map.data.unwrap();
+
+        let log_config = data.get("log_config.py").unwrap();
+        assert!(log_config.contains("'filename': '/stackable/log/odoo/audit.json'"));
+        assert!(log_config.contains(&format!("'maxBytes': {},", 10 * 1024 * 1024)));
+        assert!(log_config.contains("'backupCount': 3,"));
+        assert!(log_config.contains("LOGGING_CONFIG['loggers']['odoo.addons.auditlog']"));
+
+        let vector_config = data
+            .get(stackable_operator::product_logging::framework::VECTOR_CONFIG_FILE)
+            .unwrap();
+        assert!(vector_config.contains("[sources.files_audit]"));
+        assert!(vector_config.contains(".stream = \"compliance\""));
+        assert!(vector_config.contains("address = \"vector-aggregator:6000\""));
+    }
+
+    #[test]
+    fn test_log_file_rotation() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            config:
+              logFileMaxSize: 10Mi
+              logFileBackupCount: 3
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        let log_config = config_map.data.unwrap().remove("log_config.py").unwrap();
+        assert!(log_config.contains("'filename': '/stackable/log/odoo/odoo.py.json'"));
+        assert!(log_config.contains(&format!("'maxBytes': {},", 10 * 1024 * 1024)));
+        assert!(log_config.contains("'backupCount': 3,"));
+
+        // The log volume also holds the log file and its three rotated files
+        let statefulset = build_statefulset(&odoo, OdooRole::Webserver);
+        let log_volume = statefulset
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap()
+            .volumes
+            .unwrap()
+            .into_iter()
+            .find(|volume| volume.name == LOG_VOLUME_NAME)
+            .unwrap();
+        let log_files_sizes = [
+            sovrin_cloud_crd::MAX_LOG_FILES_SIZE,
+            MemoryQuantity {
+                value: 40.0,
+                unit: BinaryMultiple::Mebi,
+            },
+        ];
+        assert_eq!(
+            Some(product_logging::framework::calculate_log_volume_size_limit(
+                &log_files_sizes
+            )),
+            log_volume.empty_dir.unwrap().size_limit
+        );
+    }
+
+    #[test]
+    fn test_default_log_file_rotation() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        let log_config = config_map.data.unwrap().remove("log_config.py").unwrap();
+        assert!(log_config.contains(&format!("'maxBytes': {},", 1024 * 1024)));
+        assert!(log_config.contains("'backupCount': 1,"));
+    }
+
+    #[test]
+    fn test_custom_log_config() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            config:
+              logging:
+                containers:
+                  odoo:
+                    custom:
+                      configMap: odoo-log-config
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        // The custom log configuration is mounted instead of the generated one
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        assert!(!config_map.data.unwrap().contains_key("log_config.py"));
+
+        let statefulset = build_statefulset(&odoo, OdooRole::Webserver);
+        let log_config_volume = statefulset
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap()
+            .volumes
+            .unwrap()
+            .into_iter()
+            .find(|volume| volume.name == LOG_CONFIG_VOLUME_NAME)
+            .unwrap();
+        assert_eq!(
+            Some("odoo-log-config"),
+            log_config_volume.config_map.unwrap().name.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_log_format() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            config:
+              logFormat: json
+            roleGroups:
+              default:
+                replicas: 1
+          workers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let json_formatter = "LOGGING_CONFIG['handlers']['console']['formatter'] = 'json'";
+
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        let log_config = config_map.data.unwrap().remove("log_config.py").unwrap();
+        assert!(log_config.contains(json_formatter));
+
+        // The console log of the workers stays plain, the log files are JSON regardless
+        let config_map = build_config_map(&odoo, OdooRole::Worker);
+        let log_config = config_map.data.unwrap().remove("log_config.py").unwrap();
+        assert!(!log_config.contains(json_formatter));
+        assert!(log_config.contains("'formatter': 'json',"));
+    }
+
+    #[test]
+    fn test_compliance_applies_to_pod_overrides() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            compliance:
+              podSecurityStandard: restricted
+          webservers:
+            podOverrides:
+              spec:
+                containers:
+                  - name: odoo
+                    securityContext:
+                      privileged: true
+                      allowPrivilegeEscalation: true
+                  - name: sidecar
+                    image: busybox
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let pod_spec = build_statefulset(&odoo, OdooRole::Webserver)
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+
+        assert_eq!(
+            Some(true),
+            pod_spec
+                .security_context
+                .as_ref()
+                .and_then(|security_context| security_context.run_as_non_root)
+        );
+        for name in ["odoo", "sidecar"] {
+            let security_context = pod_spec
+                .containers
+                .iter()
+                .find(|container| container.name == name)
+                .and_then(|container| container.security_context.clone())
+                .unwrap();
+            assert_eq!(Some(false), security_context.privileged, "{name}");
+            assert_eq!(
+                Some(false),
+                security_context.allow_privilege_escalation,
+                "{name}"
+            );
+            assert_eq!(Some(true), security_context.run_as_non_root, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_authentication_volumes_are_unique() {
+        let authentication_classes = ["primary", "secondary"].map(|name| {
+            serde_yaml::from_str::<AuthenticationClass>(&format!(
+                "
+            apiVersion: authentication.stackable.tech/v1alpha1
+            kind: AuthenticationClass
+            metadata:
+              name: {name}-ldap
+            spec:
+              provider:
+                ldap:
+                  hostname: {name}
+                  searchBase: ou=users,dc=example,dc=org
+                  bindCredentials:
+                    secretClass: ldap-bind
+                  tls:
+                    verification:
+                      server:
+                        caCert:
+                          secretClass: {name}-ldap-tls
+          "
+            ))
+            .unwrap()
+        });
+
+        let mut volume_names = BTreeSet::new();
+        let mut cb = ContainerBuilder::new("odoo").unwrap();
+        let mut pb = PodBuilder::new();
+        for authentication_class in &authentication_classes {
+            add_authentication_volumes_and_volume_mounts(
+                authentication_class,
+                &mut volume_names,
+                &mut cb,
+                &mut pb,
+            )
+            .unwrap();
+        }
+
+        let volumes = pb
+            .build_template()
+            .spec
+            .and_then(|spec| spec.volumes)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|volume| volume.name)
+            .collect::<Vec<_>>();
+        let volume_mounts = cb
+            .build()
+            .volume_mounts
+            .unwrap_or_default()
+            .into_iter()
+            .map(|volume_mount| volume_mount.name)
+            .collect::<Vec<_>>();
+
+        // The shared bind credentials are only mounted once, the CA certificates once per class
+        assert_eq!(3, volumes.len());
+        assert_eq!(
+            volumes.iter().collect::<BTreeSet<_>>(),
+            volume_mounts.iter().collect::<BTreeSet<_>>()
+        );
+        assert_eq!(volumes.len(), volume_mounts.len());
+        assert_eq!(volume_names, volumes.into_iter().collect());
+    }
+
+    #[test]
+    fn test_gitsync_webhook_container() {
+        let gitsync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://github.com/OCA/server-tools
+            webhook: true
+            ",
+        )
+        .unwrap();
+        let resolved_product_image = serde_yaml::from_str::<ProductImage>(
+            "
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+            ",
+        )
+        .unwrap()
+        .resolve("odoo");
+        let build = |gitsync: &GitSync| {
+            build_gitsync_container(gitsync, GIT_SYNC_NAME, &resolved_product_image, None)
+        };
+
+        assert!(matches!(
+            build(&gitsync),
+            Err(Error::GitSyncWebhookRequiresSharedVolume)
+        ));
+        let gitsync = GitSync {
+            shared_volume: Some(GitSyncSharedVolume {
+                claim_name: "addons".to_string(),
+            }),
+            ..gitsync
+        };
+        assert!(matches!(
+            build(&gitsync),
+            Err(Error::GitSyncWebhookTokenSecretMissing)
+        ));
+
+        let gitsync = GitSync {
+            webhook_token_secret: Some("gitsync-webhook".to_string()),
+            ..gitsync
+        };
+        let container = build(&gitsync).unwrap();
+        let token = container
+            .env
+            .unwrap_or_default()
+            .into_iter()
+            .find(|env| env.name == git_sync::WEBHOOK_TOKEN_ENV)
+            .and_then(|env| env.value_from)
+            .and_then(|value_from| value_from.secret_key_ref)
+            .unwrap();
+        assert_eq!(Some("gitsync-webhook".to_string()), token.name);
+        assert_eq!("token", token.key);
+        assert!(container
+            .ports
+            .unwrap_or_default()
+            .iter()
+            .any(|port| port.container_port == git_sync::WEBHOOK_PORT));
+    }
+
+    #[test]
+    fn test_git_credentials_only_in_gitsync_container() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            addonsGitSync:
+              - repo: https://github.com/acme/private-addons
+                credentialsSecret: acme-git
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+        let rolegroup_ref = RoleGroupRef {
+            cluster: ObjectRef::from_obj(&odoo),
+            role: OdooRole::Webserver.to_string(),
+            role_group: "default".to_string(),
+        };
+        let config = odoo
+            .merged_config(&OdooRole::Webserver, &rolegroup_ref)
+            .unwrap();
+        // As computed by `OdooConfigFragment::compute_env`, with the override of the rolegroup
+        let rolegroup_config = HashMap::from([(
+            PropertyNameKind::Env,
+            BTreeMap::from([
+                (
+                    OdooConfig::CREDENTIALS_SECRET_PROPERTY.to_string(),
+                    "simple-odoo-credentials".to_string(),
+                ),
+                (
+                    OdooConfig::git_credentials_secret_property(0),
+                    "acme-git-staging".to_string(),
+                ),
+            ]),
+        )]);
+        let pod_spec = build_server_rolegroup_statefulset(
+            &odoo,
+            &odoo.spec.image.resolve("odoo"),
+            &OdooRole::Webserver,
+            &rolegroup_ref,
+            &rolegroup_config,
+            &[],
+            "odoo-serviceaccount",
+            &config,
+            "hash",
+        )
+        .unwrap()
+        .spec
+        .unwrap()
+        .template
+        .spec
+        .unwrap();
+        let env_of = |name: &str| {
+            pod_spec
+                .containers
+                .iter()
+                .find(|container| container.name == name)
+                .and_then(|container| container.env.clone())
+                .unwrap_or_default()
+        };
+
+        let odoo_env = env_of(&Container::Odoo.to_string());
+        assert!(!odoo_env.is_empty());
+        assert!(!odoo_env.iter().any(|env| env
+            .name
+            .starts_with(OdooConfig::GIT_CREDENTIALS_SECRET_PROPERTY)));
+        let gitsync_secrets = env_of("gitsync-1")
+            .into_iter()
+            .filter_map(|env| env.value_from?.secret_key_ref?.name)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            BTreeSet::from(["acme-git-staging".to_string()]),
+            gitsync_secrets
+        );
+    }
+
+    #[test]
+    fn test_git_token_is_only_in_the_environment() {
+        let gitsync: GitSync = serde_yaml::from_str(
+            "
+            repo: https://gitea.example.com/odoo/addons.git
+            credentialsFormat: giteaToken
+            ",
+        )
+        .unwrap();
+
+        let env = build_gitsync_envs(&gitsync, Some("gitea-token"))
+            .into_iter()
+            .map(|env| env.name)
+            .collect::<Vec<_>>();
+        // Kubernetes only substitutes variables defined before
+        assert_eq!(
+            vec![
+                "GIT_SYNC_TOKEN",
+                "GIT_CONFIG_COUNT",
+                "GIT_CONFIG_KEY_0",
+                "GIT_CONFIG_VALUE_0"
+            ],
+            env
+        );
+        assert!(!git_sync::git_sync_command(&gitsync).contains("GIT_SYNC_TOKEN"));
+        assert!(build_gitsync_envs(&gitsync, None).is_empty());
+    }
+
+    #[test]
+    fn test_gitsync_metrics_port() {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            addonsGitSync:
+              - repo: https://github.com/OCA/server-tools
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+        let rolegroup_ref = RoleGroupRef {
+            cluster: ObjectRef::from_obj(&odoo),
+            role: OdooRole::Webserver.to_string(),
+            role_group: "default".to_string(),
+        };
+        let serves_metrics = |odoo: &OdooCluster| {
+            let service =
+                build_rolegroup_service(odoo, &odoo.spec.image.resolve("odoo"), &rolegroup_ref)
+                    .unwrap();
+            let service_port = service
+                .spec
+                .unwrap()
+                .ports
+                .unwrap_or_default()
+                .iter()
+                .any(|port| {
+                    port.name.as_deref() == Some(GIT_SYNC_HTTP_PORT_NAME)
+                        && port.port == GIT_SYNC_HTTP_PORT
+                });
+            let container_port = build_statefulset(odoo, OdooRole::Webserver)
+                .spec
+                .unwrap()
+                .template
+                .spec
+                .unwrap()
+                .containers
+                .iter()
+                .flat_map(|container| container.ports.iter().flatten())
+                .any(|port| port.container_port == GIT_SYNC_HTTP_PORT);
+            (service_port, container_port)
+        };
+
+        assert_eq!((true, true), serves_metrics(&odoo));
+
+        // git-sync stops after the first sync
+        odoo.spec.cluster_config.addons_git_sync[0].mode = GitSyncMode::Once;
+        odoo.spec.cluster_config.addons_git_sync[0].revision = Some("v1.2.0".to_string());
+        assert_eq!((false, false), serves_metrics(&odoo));
+
+        // The shared git-sync pod is not selected by the rolegroup Service
+        odoo.spec.cluster_config.addons_git_sync[0].mode = GitSyncMode::Continuous;
+        odoo.spec.cluster_config.addons_git_sync[0].shared_volume = Some(GitSyncSharedVolume {
+            claim_name: "addons".to_string(),
+        });
+        assert_eq!((false, false), serves_metrics(&odoo));
+    }
+
+    #[test]
+    fn test_gitsync_revision_is_fetched_by_the_operator() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            addonsGitSync:
+              - repo: https://github.com/OCA/server-tools
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let pod_spec = build_statefulset(&odoo, OdooRole::Webserver)
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        let gitsync_container = pod_spec
+            .containers
+            .iter()
+            .find(|container| container.name.starts_with(GIT_SYNC_NAME))
+            .unwrap();
+        let revision_port = gitsync_container
+            .ports
+            .iter()
+            .flatten()
+            .find(|port| port.name.as_deref() == Some(GIT_SYNC_REVISION_PORT_NAME))
+            .unwrap();
+        assert_eq!(GIT_SYNC_REVISION_PORT, revision_port.container_port);
+        // The pods neither patch themselves nor need a token of the API server
+        assert!(gitsync_container
+            .env
+            .iter()
+            .flatten()
+            .all(|env| env.name != "POD_NAME"));
+        assert_eq!(Some(false), pod_spec.automount_service_account_token);
+    }
+
+    #[test]
+    fn test_role_ingress_is_managed_by_applied_objects() {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            web:
+              ingress:
+                host: erp.example.com
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+        let resolved_product_image = odoo.spec.image.resolve("odoo");
+
+        // AppliedObjects deletes it as orphan once it is removed from the spec
+        let ingress = build_role_ingress(&odoo, &resolved_product_image, "webserver", 8080)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Some("odoo.sovrin.cloud_odoocluster-rolegroup"),
+            ingress
+                .labels()
+                .get("app.kubernetes.io/managed-by")
+                .map(String::as_str)
+        );
+
+        odoo.spec.cluster_config.web.ingress = None;
+        assert_eq!(
+            None,
+            build_role_ingress(&odoo, &resolved_product_image, "webserver", 8080).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_webserver_stops_if_system_parameters_are_not_written() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            web:
+              baseUrl: https://erp.example.com
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let pod_spec = build_statefulset(&odoo, OdooRole::Webserver)
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        let odoo_container = pod_spec
+            .containers
+            .iter()
+            .find(|container| container.name == "odoo")
+            .unwrap();
+        let command = &odoo_container.args.as_ref().unwrap()[1];
+        assert!(
+            command.contains("' | odoo shell --no-http || exit 1; odoo webserver"),
+            "{command}"
+        );
+    }
+
+    #[test]
+    fn test_role_group_objects_are_managed_by_applied_objects() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        // ClusterResources would delete the role group objects it did not apply itself
+        let managed_by = Some("odoo.sovrin.cloud_odoocluster-rolegroup");
+        let statefulset = build_statefulset(&odoo, OdooRole::Webserver);
+        assert_eq!(
+            managed_by,
+            statefulset
+                .labels()
+                .get("app.kubernetes.io/managed-by")
+                .map(String::as_str)
+        );
+        let config_map = build_config_map(&odoo, OdooRole::Webserver);
+        assert_eq!(
+            managed_by,
+            config_map
+                .labels()
+                .get("app.kubernetes.io/managed-by")
+                .map(String::as_str)
+        );
+        let rolegroup_ref = RoleGroupRef {
+            cluster: ObjectRef::from_obj(&odoo),
+            role: OdooRole::Webserver.to_string(),
+            role_group: "default".to_string(),
+        };
+        let service =
+            build_rolegroup_service(&odoo, &odoo.spec.image.resolve("odoo"), &rolegroup_ref)
+                .unwrap();
+        assert_eq!(
+            managed_by,
+            service
+                .labels()
+                .get("app.kubernetes.io/managed-by")
+                .map(String::as_str)
+        );
+
+        // The pods are not cleaned up as orphans, so they keep the labels of the cluster
+        assert_eq!(
+            Some("odoo.sovrin.cloud_odoocluster"),
+            statefulset
+                .spec
+                .unwrap()
+                .template
+                .metadata
+                .unwrap()
+                .labels
+                .unwrap()
+                .get("app.kubernetes.io/managed-by")
+                .map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn test_pods_are_annotated_with_the_config_hash() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        // A changed hash changes the pod template, which rolls the pods
+        let statefulset = build_statefulset(&odoo, OdooRole::Webserver);
+        assert_eq!(
+            Some("hash"),
+            statefulset
+                .spec
+                .unwrap()
+                .template
+                .metadata
+                .unwrap()
+                .annotations
+                .unwrap()
+                .get(CONFIG_HASH_ANNOTATION)
+                .map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn test_config_maps_are_controlled_by_the_cluster() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            dagsGitSync:
+              - repo: https://github.com/OCA/server-tools
+                sharedVolume:
+                  claimName: addons
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+        let shared_gitsync_config_map = build_shared_gitsync_config_map(
+            &odoo,
+            odoo.git_sync().unwrap(),
+            &odoo.spec.image.resolve("odoo"),
+            "odoo-gitsync",
+        )
+        .unwrap();
+
+        // The controller owner reference maps edits of the ConfigMaps to the cluster
+        for config_map in [
+            build_config_map(&odoo, OdooRole::Webserver),
+            shared_gitsync_config_map,
+        ] {
+            let owner_reference = config_map
+                .metadata
+                .owner_references
+                .into_iter()
+                .flatten()
+                .find(|owner_reference| owner_reference.controller == Some(true))
+                .unwrap();
+            assert_eq!("OdooCluster", owner_reference.kind);
+            assert_eq!("odoo", owner_reference.name);
+            assert_eq!("12345678-1234-1234-1234-123456789012", owner_reference.uid);
+        }
+    }
+
+    #[test]
+    fn test_gitsync_shared_volume() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            dagsGitSync:
+              - repo: https://github.com/OCA/server-tools
+                sharedVolume:
+                  claimName: addons
+          webservers:
+            roleGroups:
+              default:
+                replicas: 2
+          ",
+        )
+        .unwrap();
+        let claim_of = |pod_spec: &PodSpec| {
+            pod_spec
+                .volumes
+                .iter()
+                .flatten()
+                .find(|volume| volume.name == GIT_CONTENT)
+                .and_then(|volume| volume.persistent_volume_claim.clone())
+                .unwrap()
+        };
+
+        // The pods only mount the claim, they do not sync themselves
+        let pod_spec = build_statefulset(&odoo, OdooRole::Webserver)
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        let claim = claim_of(&pod_spec);
+        assert_eq!("addons", claim.claim_name);
+        assert_eq!(Some(true), claim.read_only);
+        assert!(!pod_spec
+            .containers
+            .iter()
+            .chain(pod_spec.init_containers.iter().flatten())
+            .any(|container| container.name.starts_with(GIT_SYNC_NAME)));
+        let git_content_mount = pod_spec
+            .containers
+            .iter()
+            .flat_map(|container| container.volume_mounts.iter().flatten())
+            .find(|volume_mount| volume_mount.name == GIT_CONTENT)
+            .unwrap();
+        assert_eq!(Some(true), git_content_mount.read_only);
+
+        // A single pod writes to the claim
+        let deployment_spec = build_shared_gitsync_deployment(
+            &odoo,
+            odoo.git_sync().unwrap(),
+            &odoo.spec.image.resolve("odoo"),
+            "odoo-gitsync",
+            "odoo-serviceaccount",
+        )
+        .unwrap()
+        .spec
+        .unwrap();
+        assert_eq!(Some(1), deployment_spec.replicas);
+        assert_eq!(
+            Some("Recreate".to_string()),
+            deployment_spec.strategy.unwrap().type_
+        );
+        let pod_spec = deployment_spec.template.spec.unwrap();
+        assert_eq!(Some(false), claim_of(&pod_spec).read_only);
+        assert_eq!(
+            vec![GIT_SYNC_NAME.to_string()],
+            pod_spec
+                .containers
+                .iter()
+                .map(|container| container.name.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_kerberos_only_on_webservers() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            apiAuthBackend: kerberos
+            authenticationConfig:
+              kerberos:
+                secretClass: kerberos
+          webservers:
+            roleGroups:
+              default:
+                replicas: 1
+          workers:
+            roleGroups:
+              default:
+                replicas: 1
+          ",
+        )
+        .unwrap();
+
+        let pod_spec = build_statefulset(&odoo, OdooRole::Webserver)
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        let kerberos_volume = pod_spec
+            .volumes
+            .unwrap_or_default()
+            .into_iter()
+            .find(|volume| volume.name == KERBEROS_VOLUME_NAME)
+            .unwrap();
+        let volume_claim_metadata = kerberos_volume
+            .ephemeral
+            .and_then(|ephemeral| ephemeral.volume_claim_template)
+            .and_then(|template| template.metadata)
+            .unwrap();
+        assert_eq!(
+            Some("kerberos"),
+            volume_claim_metadata
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get("secrets.stackable.tech/class"))
+                .map(String::as_str)
+        );
+        assert_eq!(
+            Some("HTTP"),
+            volume_claim_metadata
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get(KERBEROS_SERVICE_NAMES_ANNOTATION))
+                .map(String::as_str)
+        );
+        let odoo_container = pod_spec
+            .containers
+            .iter()
+            .find(|container| container.name == "odoo")
+            .unwrap();
+        let principal = odoo_container
+            .env
+            .iter()
+            .flatten()
+            .find(|env_var| env_var.name == "AIRFLOW__KERBEROS__PRINCIPAL")
+            .and_then(|env_var| env_var.value.clone());
+        assert_eq!(
+            Some("HTTP/odoo-webserver.default.svc.cluster.local".to_string()),
+            principal
+        );
+
+        let pod_spec = build_statefulset(&odoo, OdooRole::Worker)
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        assert!(!pod_spec
+            .volumes
+            .unwrap_or_default()
+            .iter()
+            .any(|volume| volume.name == KERBEROS_VOLUME_NAME));
+        assert!(!pod_spec
+            .containers
+            .iter()
+            .flat_map(|container| container.env.iter().flatten())
+            .any(|env_var| env_var.name == "KRB5_CONFIG"));
+    }
+}
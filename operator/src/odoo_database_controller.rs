@@ -0,0 +1,868 @@
+//! Creates and initializes the tenant databases described by [`OdooDatabase`]s on the database
+//! server of their OdooCluster, and drops them again if requested.
+use crate::controller_commons;
+use crate::finalizer;
+use crate::network_policy;
+use crate::odoo_controller::DOCKER_IMAGE_BASE_NAME;
+use crate::rbac;
+use crate::upgrade::add_database_connection;
+use crate::utils::{self, assemble_connection_uri_command, delete_job, get_job_state, JobState};
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{
+    odoodatabase::{
+        OdooDatabase, OdooDatabaseStatus, OdooDatabaseStatusCondition,
+        AIRFLOW_DATABASE_CONTROLLER_NAME,
+    },
+    OdooCluster, AIRFLOW_UID,
+};
+use stackable_operator::{
+    builder::{
+        resources::ResourceRequirementsBuilder, ContainerBuilder, ObjectMetaBuilder,
+        PodSecurityContextBuilder,
+    },
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::api::{
+        batch::v1::{Job, JobSpec},
+        core::v1::{EnvVar, PodSpec, PodTemplateSpec},
+    },
+    kube::{
+        api::ListParams,
+        runtime::{controller::Action, reflector::ObjectRef},
+        ResourceExt,
+    },
+    logging::controller::ReconcilerError,
+};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use strum::{EnumDiscriminants, IntoStaticStr};
+
+pub const ODOO_DATABASE_FINALIZER: &str = "odoo.sovrin.cloud/drop-database";
+
+const CONTAINER_NAME: &str = "odoo-database";
+
+/// The interval in which the deletion of the previous creation job is checked.
+const CREATE_JOB_DELETION_REQUEUE: Duration = Duration::from_secs(5);
+
+/// Creates the tenant database if it does not exist yet. The connection of the cluster is used,
+/// so its user needs the `CREATEDB` privilege.
+const CREATE_DATABASE_SCRIPT: &str = r#"
+import os
+import sys
+import psycopg2
+from psycopg2 import sql
+from sqlalchemy.engine import make_url
+
+url = make_url(os.environ["AIRFLOW__CORE__SQL_ALCHEMY_CONN"])
+conn = psycopg2.connect(
+    host=url.host,
+    port=url.port or 5432,
+    dbname=url.database,
+    user=url.username,
+    password=url.password,
+)
+conn.autocommit = True
+cur = conn.cursor()
+database = os.environ["TENANT_DATABASE"]
+if database == url.database:
+    sys.exit("The tenant database must not be the database of the cluster")
+owner = os.environ.get("TENANT_DATABASE_OWNER") or url.username
+cur.execute("SELECT 1 FROM pg_database WHERE datname = %s", (database,))
+if cur.fetchone() is None:
+    print("Creating database " + database)
+    cur.execute(
+        sql.SQL("CREATE DATABASE {} OWNER {}").format(
+            sql.Identifier(database), sql.Identifier(owner)
+        )
+    )
+"#;
+
+/// Drops the tenant database, terminating the remaining connections.
+const DROP_DATABASE_SCRIPT: &str = r#"
+import os
+import sys
+import psycopg2
+from psycopg2 import sql
+from sqlalchemy.engine import make_url
+
+url = make_url(os.environ["AIRFLOW__CORE__SQL_ALCHEMY_CONN"])
+conn = psycopg2.connect(
+    host=url.host,
+    port=url.port or 5432,
+    dbname=url.database,
+    user=url.username,
+    password=url.password,
+)
+conn.autocommit = True
+database = os.environ["TENANT_DATABASE"]
+if database == url.database:
+    sys.exit("The tenant database must not be the database of the cluster")
+print("Dropping database " + database)
+conn.cursor().execute(
+    sql.SQL("DROP DATABASE IF EXISTS {} WITH (FORCE)").format(sql.Identifier(database))
+)
+"#;
+
+pub struct Ctx {
+    pub client: stackable_operator::client::Client,
+    /// Restricts the watchers, so the create and drop Jobs carry the labels of the OdooDatabase
+    /// which it selects
+    pub watch_label_selector: Option<String>,
+}
+
+#[derive(Snafu, Debug, EnumDiscriminants)]
+#[strum_discriminants(derive(IntoStaticStr))]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to get OdooCluster {}", odoo))]
+    GetOdooCluster {
+        source: stackable_operator::error::Error,
+        odoo: ObjectRef<OdooCluster>,
+    },
+    #[snafu(display("failed to apply Job for {}", odoo_database))]
+    ApplyJob {
+        source: stackable_operator::error::Error,
+        odoo_database: ObjectRef<OdooDatabase>,
+    },
+    #[snafu(display("failed to update status"))]
+    ApplyStatus {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to handle the finalizer"))]
+    Finalizer { source: crate::finalizer::Error },
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("invalid container name"))]
+    InvalidContainerName {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("database state is 'creating' but failed to find job {}", job))]
+    GetCreateDatabaseJob {
+        source: stackable_operator::error::Error,
+        job: ObjectRef<Job>,
+    },
+    #[snafu(display("failed to delete the creation job {}", job))]
+    DeleteCreateDatabaseJob {
+        source: stackable_operator::kube::Error,
+        job: ObjectRef<Job>,
+    },
+    #[snafu(display(
+        "the database [{database_name}] is the database of the cluster and cannot be managed as \
+        tenant database"
+    ))]
+    PrimaryDatabase { database_name: String },
+    #[snafu(display(
+        "the database [{created}] was created for this OdooDatabase, databaseName cannot be \
+        changed to [{database_name}]"
+    ))]
+    DatabaseNameChanged {
+        database_name: String,
+        created: String,
+    },
+    #[snafu(display("failed to list the OdooDatabases to find other claims of the database"))]
+    ListOdooDatabases {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("the database [{database_name}] is already managed by {odoo_database}"))]
+    DuplicateDatabase {
+        database_name: String,
+        odoo_database: ObjectRef<OdooDatabase>,
+    },
+    #[snafu(display("failed to retrieve the drop job {}", job))]
+    GetDropDatabaseJob {
+        source: stackable_operator::error::Error,
+        job: ObjectRef<Job>,
+    },
+    #[snafu(display(
+        "failed to drop the database, remove the finalizer {ODOO_DATABASE_FINALIZER} to delete \
+        the OdooDatabase anyway"
+    ))]
+    DropDatabaseFailed,
+}
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl ReconcilerError for Error {
+    fn category(&self) -> &'static str {
+        ErrorDiscriminants::from(self).into()
+    }
+}
+
+pub async fn reconcile_odoo_database(
+    odoo_database: Arc<OdooDatabase>,
+    ctx: Arc<Ctx>,
+) -> Result<Action> {
+    tracing::info!("Starting reconcile");
+
+    let client = &ctx.client;
+    let namespace = odoo_database
+        .namespace()
+        .context(ObjectHasNoNamespaceSnafu)?;
+    // Otherwise the restricted watcher would miss the jobs
+    let selected_labels =
+        utils::selected_labels(ctx.watch_label_selector.as_deref(), odoo_database.labels());
+
+    if odoo_database.metadata.deletion_timestamp.is_some() {
+        return finalize(client, &odoo_database, &namespace, &selected_labels).await;
+    }
+    ensure_finalizer(client, &odoo_database).await?;
+    validate_database_name_unchanged(&odoo_database)?;
+
+    if let Some(s) = odoo_database
+        .status
+        .as_ref()
+        .filter(|s| s.outdated(&odoo_database))
+    {
+        // The spec changed after the database was created, so the creation job runs again
+        let job_name = create_job_name(&odoo_database);
+        let job = client.get_opt::<Job>(&job_name, &namespace).await.context(
+            GetCreateDatabaseJobSnafu {
+                job: ObjectRef::<Job>::new(&job_name).within(&namespace),
+            },
+        )?;
+        if let Some(job) = job {
+            delete_job(client, &job)
+                .await
+                .context(DeleteCreateDatabaseJobSnafu {
+                    job: ObjectRef::from_obj(&job),
+                })?;
+            return Ok(Action::requeue(CREATE_JOB_DELETION_REQUEUE));
+        }
+        tracing::info!("Updating the database after a change of the spec");
+        client
+            .apply_patch_status(
+                AIRFLOW_DATABASE_CONTROLLER_NAME,
+                &*odoo_database,
+                &OdooDatabaseStatus {
+                    observed_generation: s.observed_generation,
+                    database_name: s.database_name.clone(),
+                    ..OdooDatabaseStatus::new()
+                },
+            )
+            .await
+            .context(ApplyStatusSnafu)?;
+        return Ok(Action::await_change());
+    }
+
+    if let Some(ref s) = odoo_database.status {
+        match s.condition {
+            OdooDatabaseStatusCondition::Pending => {
+                let odoo = get_odoo_cluster(client, &odoo_database, &namespace).await?;
+                validate_database_name(&odoo_database, &odoo)?;
+                let claims = other_claims(client, &odoo_database, &namespace).await?;
+                // The first OdooDatabase naming the database keeps it
+                if let Some(other) = claims.iter().find(|other| is_older(other, &odoo_database)) {
+                    return DuplicateDatabaseSnafu {
+                        database_name: &odoo_database.spec.database_name,
+                        odoo_database: ObjectRef::from_obj(other),
+                    }
+                    .fail();
+                }
+                let resolved_product_image: ResolvedProductImage =
+                    odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME);
+
+                let mut job =
+                    build_create_database_job(&odoo_database, &odoo, &resolved_product_image)?;
+                utils::add_labels(&mut job, &selected_labels);
+                client
+                    .apply_patch(AIRFLOW_DATABASE_CONTROLLER_NAME, &job, &job)
+                    .await
+                    .context(ApplyJobSnafu {
+                        odoo_database: ObjectRef::from_obj(&*odoo_database),
+                    })?;
+                client
+                    .apply_patch_status(
+                        AIRFLOW_DATABASE_CONTROLLER_NAME,
+                        &*odoo_database,
+                        &s.creating(
+                            odoo_database.metadata.generation,
+                            &odoo_database.spec.database_name,
+                        ),
+                    )
+                    .await
+                    .context(ApplyStatusSnafu)?;
+            }
+            OdooDatabaseStatusCondition::Creating => {
+                let job_name = create_job_name(&odoo_database);
+                let job = client.get::<Job>(&job_name, &namespace).await.context(
+                    GetCreateDatabaseJobSnafu {
+                        job: ObjectRef::<Job>::new(&job_name).within(&namespace),
+                    },
+                )?;
+
+                let new_status = match get_job_state(&job) {
+                    JobState::Complete => Some(s.ready()),
+                    JobState::Failed => Some(s.failed()),
+                    JobState::InProgress => None,
+                };
+
+                if let Some(ns) = new_status {
+                    client
+                        .apply_patch_status(AIRFLOW_DATABASE_CONTROLLER_NAME, &*odoo_database, &ns)
+                        .await
+                        .context(ApplyStatusSnafu)?;
+                }
+            }
+            OdooDatabaseStatusCondition::Ready => (),
+            OdooDatabaseStatusCondition::Failed => (),
+        }
+    } else {
+        client
+            .apply_patch_status(
+                AIRFLOW_DATABASE_CONTROLLER_NAME,
+                &*odoo_database,
+                &OdooDatabaseStatus::new(),
+            )
+            .await
+            .context(ApplyStatusSnafu)?;
+    }
+
+    Ok(Action::await_change())
+}
+
+pub fn create_job_name(odoo_database: &OdooDatabase) -> String {
+    format!("{}-create-database", odoo_database.name_unchecked())
+}
+
+pub fn drop_job_name(odoo_database: &OdooDatabase) -> String {
+    format!("{}-drop-database", odoo_database.name_unchecked())
+}
+
+async fn get_odoo_cluster(
+    client: &stackable_operator::client::Client,
+    odoo_database: &OdooDatabase,
+    namespace: &str,
+) -> Result<OdooCluster> {
+    client
+        .get::<OdooCluster>(&odoo_database.spec.cluster_name, namespace)
+        .await
+        .context(GetOdooClusterSnafu {
+            odoo: ObjectRef::<OdooCluster>::new(&odoo_database.spec.cluster_name).within(namespace),
+        })
+}
+
+/// The database of the cluster itself must neither be initialized again nor dropped. It can
+/// only be detected here if the cluster names it, the jobs check it against the connection as
+/// well.
+fn validate_database_name(odoo_database: &OdooDatabase, odoo: &OdooCluster) -> Result<()> {
+    let database_name = &odoo_database.spec.database_name;
+    ensure!(
+        odoo.spec.cluster_config.database.database_name.as_ref() != Some(database_name),
+        PrimaryDatabaseSnafu { database_name }
+    );
+    Ok(())
+}
+
+/// A changed `databaseName` is rejected, as the created database would otherwise be left behind.
+fn validate_database_name_unchanged(odoo_database: &OdooDatabase) -> Result<()> {
+    let database_name = &odoo_database.spec.database_name;
+    let created = created_database_name(odoo_database);
+    ensure!(
+        created == database_name.as_str(),
+        DatabaseNameChangedSnafu {
+            database_name,
+            created,
+        }
+    );
+    Ok(())
+}
+
+/// The name of the database which was created for the OdooDatabase, or is going to be created.
+fn created_database_name(odoo_database: &OdooDatabase) -> &str {
+    odoo_database
+        .status
+        .as_ref()
+        .and_then(|status| status.database_name.as_deref())
+        .unwrap_or(&odoo_database.spec.database_name)
+}
+
+/// The other OdooDatabases in the namespace which name the same database of the cluster.
+async fn other_claims(
+    client: &stackable_operator::client::Client,
+    odoo_database: &OdooDatabase,
+    namespace: &str,
+) -> Result<Vec<OdooDatabase>> {
+    let odoo_databases = client
+        .list::<OdooDatabase>(namespace, &ListParams::default())
+        .await
+        .context(ListOdooDatabasesSnafu)?;
+    Ok(odoo_databases
+        .into_iter()
+        .filter(|other| claims_same_database(odoo_database, other))
+        .collect())
+}
+
+fn claims_same_database(odoo_database: &OdooDatabase, other: &OdooDatabase) -> bool {
+    other.uid() != odoo_database.uid()
+        && other.spec.cluster_name == odoo_database.spec.cluster_name
+        && created_database_name(other) == created_database_name(odoo_database)
+}
+
+/// Whether the OdooDatabase was created before the other one. The names order OdooDatabases
+/// created within the same second.
+fn is_older(odoo_database: &OdooDatabase, other: &OdooDatabase) -> bool {
+    let creation = |odoo_database: &OdooDatabase| {
+        (
+            odoo_database
+                .metadata
+                .creation_timestamp
+                .as_ref()
+                .map(|time| time.0),
+            odoo_database.name_any(),
+        )
+    };
+    creation(odoo_database) < creation(other)
+}
+
+fn has_finalizer(odoo_database: &OdooDatabase) -> bool {
+    odoo_database
+        .finalizers()
+        .iter()
+        .any(|finalizer| finalizer == ODOO_DATABASE_FINALIZER)
+}
+
+/// Adds the finalizer if the database should be dropped together with the OdooDatabase and
+/// removes it again if `dropOnDelete` was disabled.
+async fn ensure_finalizer(
+    client: &stackable_operator::client::Client,
+    odoo_database: &OdooDatabase,
+) -> Result<()> {
+    let has_finalizer = has_finalizer(odoo_database);
+    if odoo_database.spec.drop_on_delete && !has_finalizer {
+        let mut finalizers = odoo_database.finalizers().to_vec();
+        finalizers.push(ODOO_DATABASE_FINALIZER.to_string());
+        finalizer::patch_finalizers(client, odoo_database, finalizers)
+            .await
+            .context(FinalizerSnafu)?;
+    } else if !odoo_database.spec.drop_on_delete && has_finalizer {
+        remove_finalizer(client, odoo_database).await?;
+    }
+    Ok(())
+}
+
+async fn remove_finalizer(
+    client: &stackable_operator::client::Client,
+    odoo_database: &OdooDatabase,
+) -> Result<()> {
+    let finalizers = odoo_database
+        .finalizers()
+        .iter()
+        .filter(|finalizer| *finalizer != ODOO_DATABASE_FINALIZER)
+        .cloned()
+        .collect();
+    finalizer::patch_finalizers(client, odoo_database, finalizers)
+        .await
+        .context(FinalizerSnafu)
+}
+
+/// Drops the database with a Job before the OdooDatabase is deleted. The finalizer is only
+/// removed once the Job completed. A database which another OdooDatabase still names is kept.
+async fn finalize(
+    client: &stackable_operator::client::Client,
+    odoo_database: &OdooDatabase,
+    namespace: &str,
+    selected_labels: &BTreeMap<String, String>,
+) -> Result<Action> {
+    if !has_finalizer(odoo_database) {
+        return Ok(Action::await_change());
+    }
+
+    let job_name = drop_job_name(odoo_database);
+    let job =
+        client
+            .get_opt::<Job>(&job_name, namespace)
+            .await
+            .context(GetDropDatabaseJobSnafu {
+                job: ObjectRef::<Job>::new(&job_name).within(namespace),
+            })?;
+    match job.as_ref().map(get_job_state) {
+        None => {
+            let claims = other_claims(client, odoo_database, namespace).await?;
+            if claims
+                .iter()
+                .any(|other| other.metadata.deletion_timestamp.is_none())
+            {
+                tracing::info!(
+                    database = created_database_name(odoo_database),
+                    "Keeping the database, which another OdooDatabase names"
+                );
+                remove_finalizer(client, odoo_database).await?;
+                return Ok(Action::await_change());
+            }
+            let odoo = get_odoo_cluster(client, odoo_database, namespace).await?;
+            validate_database_name(odoo_database, &odoo)?;
+            let resolved_product_image: ResolvedProductImage =
+                odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME);
+            let mut job = build_drop_database_job(odoo_database, &odoo, &resolved_product_image)?;
+            utils::add_labels(&mut job, selected_labels);
+            client
+                .apply_patch(AIRFLOW_DATABASE_CONTROLLER_NAME, &job, &job)
+                .await
+                .context(ApplyJobSnafu {
+                    odoo_database: ObjectRef::from_obj(odoo_database),
+                })?;
+        }
+        Some(JobState::InProgress) => (),
+        Some(JobState::Complete) => remove_finalizer(client, odoo_database).await?,
+        Some(JobState::Failed) => return DropDatabaseFailedSnafu.fail(),
+    }
+
+    Ok(Action::await_change())
+}
+
+fn build_create_database_job(
+    odoo_database: &OdooDatabase,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+) -> Result<Job> {
+    let spec = &odoo_database.spec;
+    let mut install =
+        String::from("odoo -d \"$TENANT_DATABASE\" -i \"$TENANT_MODULES\" --stop-after-init");
+    if !spec.demo {
+        install.push_str(" --without-demo=all");
+    }
+
+    let mut env = vec![
+        plain_env_var("TENANT_DATABASE", &spec.database_name),
+        plain_env_var("TENANT_MODULES", &spec.modules().join(",")),
+    ];
+    if let Some(owner) = &spec.owner {
+        env.push(plain_env_var("TENANT_DATABASE_OWNER", owner));
+    }
+
+    build_job(
+        odoo_database,
+        odoo,
+        resolved_product_image,
+        create_job_name(odoo_database),
+        vec![format!("python3 -c '{CREATE_DATABASE_SCRIPT}'"), install],
+        env,
+    )
+}
+
+fn build_drop_database_job(
+    odoo_database: &OdooDatabase,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+) -> Result<Job> {
+    build_job(
+        odoo_database,
+        odoo,
+        resolved_product_image,
+        drop_job_name(odoo_database),
+        vec![format!("python3 -c '{DROP_DATABASE_SCRIPT}'")],
+        vec![plain_env_var(
+            "TENANT_DATABASE",
+            created_database_name(odoo_database),
+        )],
+    )
+}
+
+/// The tenant database and the modules are passed as environment variables, so they never get
+/// interpreted by the shell. The pods run with the service account of the cluster.
+fn build_job(
+    odoo_database: &OdooDatabase,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    name: String,
+    database_commands: Vec<String>,
+    mut env: Vec<EnvVar>,
+) -> Result<Job> {
+    let cluster_config = &odoo.spec.cluster_config;
+
+    let mut commands = Vec::new();
+    let mut cb = ContainerBuilder::new(CONTAINER_NAME).context(InvalidContainerNameSnafu)?;
+    let mut volumes = Vec::new();
+    add_database_connection(odoo, &mut cb, &mut commands, &mut env, &mut volumes);
+    if cluster_config.database.assembles_connection_uri() {
+        commands.push(assemble_connection_uri_command());
+    }
+    commands.insert(0, "set -euo pipefail".to_string());
+    commands.extend(database_commands);
+
+    cb.image_from_product_image(resolved_product_image)
+        .command(vec!["/bin/bash".to_string()])
+        .args(vec![String::from("-c"), commands.join("; ")])
+        .add_env_vars(env)
+        .resources(
+            ResourceRequirementsBuilder::new()
+                .with_cpu_request("100m")
+                .with_cpu_limit("400m")
+                .with_memory_request("512Mi")
+                .with_memory_limit("512Mi")
+                .build(),
+        );
+
+    let mut pod_spec = PodSpec {
+        containers: vec![cb.build()],
+        restart_policy: Some("Never".to_string()),
+        service_account: Some(rbac::cluster_service_account_name(odoo)),
+        image_pull_secrets: resolved_product_image.pull_secrets.clone(),
+        security_context: Some(
+            PodSecurityContextBuilder::new()
+                .run_as_user(AIRFLOW_UID)
+                .run_as_group(0)
+                .build(),
+        ),
+        volumes: Some(volumes),
+        ..Default::default()
+    };
+    if let Some(ca_bundle) = &cluster_config.ca_bundle {
+        controller_commons::add_ca_bundle(&mut pod_spec, ca_bundle, resolved_product_image);
+    }
+    controller_commons::apply_compliance(&mut pod_spec, &cluster_config.compliance);
+
+    Ok(Job {
+        metadata: ObjectMetaBuilder::new()
+            .name(name)
+            .namespace_opt(odoo_database.namespace())
+            .ownerreference_from_resource(odoo_database, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            .build(),
+        spec: Some(JobSpec {
+            template: PodTemplateSpec {
+                metadata: Some(network_policy::job_pod_metadata(
+                    odoo,
+                    AIRFLOW_DATABASE_CONTROLLER_NAME,
+                    &resolved_product_image.app_version_label,
+                    "database",
+                )),
+                spec: Some(pod_spec),
+            },
+            ..Default::default()
+        }),
+        status: None,
+    })
+}
+
+fn plain_env_var(name: &str, value: &str) -> EnvVar {
+    EnvVar {
+        name: name.to_string(),
+        value: Some(value.to_string()),
+        ..Default::default()
+    }
+}
+
+pub fn error_policy(_obj: Arc<OdooDatabase>, _error: &Error, _ctx: Arc<Ctx>) -> Action {
+    Action::requeue(Duration::from_secs(5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_operator::k8s_openapi::api::core::v1::Container;
+
+    fn odoo() -> OdooCluster {
+        serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            database:
+              databaseName: odoo
+          ",
+        )
+        .unwrap()
+    }
+
+    fn odoo_database(database_name: &str) -> OdooDatabase {
+        serde_yaml::from_str(&format!(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooDatabase
+        metadata:
+          name: tenant-a
+          namespace: default
+          uid: 0ea5c2b9-4a7c-4b94-9a5b-3c1f8d7e2a11
+        spec:
+          clusterName: odoo
+          databaseName: {database_name}
+          installModules:
+            - sale; reboot
+          "
+        ))
+        .unwrap()
+    }
+
+    fn env_var(container: &Container, name: &str) -> Option<String> {
+        container
+            .env
+            .iter()
+            .flatten()
+            .find(|env_var| env_var.name == name)
+            .and_then(|env_var| env_var.value.clone())
+    }
+
+    #[test]
+    fn test_build_create_database_job() {
+        let odoo = odoo();
+        let odoo_database = odoo_database("tenant_a");
+
+        let job = build_create_database_job(
+            &odoo_database,
+            &odoo,
+            &odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("tenant-a-create-database".to_string()),
+            job.metadata.name
+        );
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        assert_eq!(
+            Some("odoo-serviceaccount".to_string()),
+            pod_spec.service_account
+        );
+        let container = &pod_spec.containers[0];
+        let script = &container.args.as_ref().unwrap()[1];
+        assert!(script.ends_with(
+            "odoo -d \"$TENANT_DATABASE\" -i \"$TENANT_MODULES\" --stop-after-init \
+            --without-demo=all"
+        ));
+        assert!(!script.contains("reboot"));
+        assert_eq!(
+            Some("tenant_a".to_string()),
+            env_var(container, "TENANT_DATABASE")
+        );
+        assert_eq!(
+            Some("base,sale; reboot".to_string()),
+            env_var(container, "TENANT_MODULES")
+        );
+    }
+
+    #[test]
+    fn test_build_drop_database_job() {
+        let odoo = odoo();
+        let odoo_database = odoo_database("tenant_a");
+
+        let job = build_drop_database_job(
+            &odoo_database,
+            &odoo,
+            &odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("tenant-a-drop-database".to_string()),
+            job.metadata.name
+        );
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        assert_eq!(
+            Some("odoo-serviceaccount".to_string()),
+            pod_spec.service_account
+        );
+        let script = &pod_spec.containers[0].args.as_ref().unwrap()[1];
+        assert!(script.contains("DROP DATABASE IF EXISTS"));
+        assert!(script.contains("if database == url.database:"));
+    }
+
+    #[test]
+    fn test_create_database_job_fails_fast() {
+        let odoo = odoo();
+        let job = build_create_database_job(
+            &odoo_database("tenant_a"),
+            &odoo,
+            &odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME),
+        )
+        .unwrap();
+
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        let script = &pod_spec.containers[0].args.as_ref().unwrap()[1];
+        assert!(script.starts_with("set -euo pipefail; "));
+
+        // The modules are not installed if the database could not be created
+        let output = std::process::Command::new("bash")
+            .args([
+                "-c",
+                &format!(
+                    "python3() {{ case \"$2\" in *\"CREATE DATABASE\"*) return 1;; esac; }}\n\
+                    odoo() {{ echo \"odoo $*\"; }}\n\
+                    {script}"
+                ),
+            ])
+            .env("TENANT_DATABASE", "tenant_a")
+            .env("TENANT_MODULES", "base")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!("", String::from_utf8_lossy(&output.stdout));
+    }
+
+    #[test]
+    fn test_database_name_cannot_be_changed() {
+        let odoo = odoo();
+        let mut odoo_database = odoo_database("tenant_b");
+        assert!(validate_database_name_unchanged(&odoo_database).is_ok());
+
+        odoo_database.status = Some(OdooDatabaseStatus::new().creating(Some(1), "tenant_a"));
+        assert!(matches!(
+            validate_database_name_unchanged(&odoo_database),
+            Err(Error::DatabaseNameChanged { .. })
+        ));
+
+        // The created database is dropped, not the renamed one
+        let job = build_drop_database_job(
+            &odoo_database,
+            &odoo,
+            &odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME),
+        )
+        .unwrap();
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        assert_eq!(
+            Some("tenant_a".to_string()),
+            env_var(&pod_spec.containers[0], "TENANT_DATABASE")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_databases() {
+        let odoo_database_named = |name: &str, database_name: &str, created_at: &str| {
+            let mut odoo_database = odoo_database(database_name);
+            odoo_database.metadata.name = Some(name.to_string());
+            odoo_database.metadata.uid = Some(format!("uid-{name}"));
+            odoo_database.metadata.creation_timestamp =
+                Some(serde_yaml::from_str(created_at).unwrap());
+            odoo_database
+        };
+        let tenant_a = odoo_database_named("tenant-a", "tenant", "2024-01-01T00:00:00Z");
+        let tenant_b = odoo_database_named("tenant-b", "tenant", "2024-01-02T00:00:00Z");
+        let tenant_c = odoo_database_named("tenant-c", "tenant_c", "2024-01-01T00:00:00Z");
+
+        assert!(claims_same_database(&tenant_a, &tenant_b));
+        assert!(!claims_same_database(&tenant_a, &tenant_a));
+        assert!(!claims_same_database(&tenant_a, &tenant_c));
+
+        let mut other_cluster = tenant_b.clone();
+        other_cluster.spec.cluster_name = "other".to_string();
+        assert!(!claims_same_database(&tenant_a, &other_cluster));
+
+        // The first OdooDatabase keeps the database
+        assert!(is_older(&tenant_a, &tenant_b));
+        assert!(!is_older(&tenant_b, &tenant_a));
+        let same_time = odoo_database_named("tenant-b", "tenant", "2024-01-01T00:00:00Z");
+        assert!(is_older(&tenant_a, &same_time));
+    }
+
+    #[test]
+    fn test_validate_database_name() {
+        let odoo = odoo();
+
+        assert!(validate_database_name(&odoo_database("tenant_a"), &odoo).is_ok());
+        assert!(matches!(
+            validate_database_name(&odoo_database("odoo"), &odoo),
+            Err(Error::PrimaryDatabase { .. })
+        ));
+    }
+}
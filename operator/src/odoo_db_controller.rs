@@ -5,28 +5,45 @@ use crate::controller_commons::{CONFIG_VOLUME_NAME, LOG_CONFIG_VOLUME_NAME, LOG_
 use crate::product_logging::{
     extend_config_map_with_log_config, resolve_vector_aggregator_address,
 };
-use crate::utils::{env_var_from_secret, get_job_state, JobState};
-use crate::{controller_commons, rbac};
+use crate::utils::{
+    self, assemble_connection_uri_command, delete_job, env_var_from_secret,
+    export_env_var_from_file, get_job_state, JobState,
+};
+use crate::{controller_commons, network_policy, rbac};
 
 use snafu::{OptionExt, ResultExt, Snafu};
 use sovrin_cloud_crd::{
+    cnpg::CNPG_APP_SECRET_URI_KEY,
+    credentials::{
+        credentials_file, CREDENTIALS_DIR, CREDENTIALS_ENV_VARS, CREDENTIALS_VOLUME_NAME,
+    },
+    database::SQL_ALCHEMY_CONN_ENV,
+    image_policy::ImagePolicy,
     odoodb::{
         OdooDB, OdooDBStatus, OdooDBStatusCondition, OdooDbConfig, Container,
         AIRFLOW_DB_CONTROLLER_NAME,
     },
-    AIRFLOW_UID, LOG_CONFIG_DIR, STACKABLE_LOG_DIR,
+    LogFormat, AIRFLOW_UID, LOG_CONFIG_DIR, OPERATOR_NAME, STACKABLE_LOG_DIR,
 };
 
 use stackable_operator::{
-    builder::{ConfigMapBuilder, ContainerBuilder, ObjectMetaBuilder, PodSecurityContextBuilder},
+    builder::{
+        ConfigMapBuilder, ContainerBuilder, ObjectMetaBuilder, PodSecurityContextBuilder,
+        VolumeBuilder,
+    },
+    client::Client,
     commons::product_image_selection::ResolvedProductImage,
     k8s_openapi::api::{
         batch::v1::{Job, JobSpec},
         core::v1::{ConfigMap, EnvVar, PodSpec, PodTemplateSpec, Secret},
     },
     kube::{
-        runtime::{controller::Action, reflector::ObjectRef},
-        ResourceExt,
+        runtime::{
+            controller::Action,
+            events::{Event, EventType, Recorder, Reporter},
+            reflector::ObjectRef,
+        },
+        Resource, ResourceExt,
     },
     logging::controller::ReconcilerError,
     product_logging::{self, spec::Logging},
@@ -35,8 +52,88 @@ use stackable_operator::{
 use std::{sync::Arc, time::Duration};
 use strum::{EnumDiscriminants, IntoStaticStr};
 
+/// The credentials of the admin user, only read if the admin user is created.
+const ADMIN_USER_CREDENTIALS_ENV_VARS: &[(&str, &str)] = &[
+    ("ADMIN_USERNAME", "adminUser.username"),
+    ("ADMIN_FIRSTNAME", "adminUser.firstname"),
+    ("ADMIN_LASTNAME", "adminUser.lastname"),
+    ("ADMIN_EMAIL", "adminUser.email"),
+    ("ADMIN_PASSWORD", "adminUser.password"),
+];
+
+/// Creates the role and the database from the connection URI if they do not exist yet. The
+/// superuser connects to the `postgres` database on the same server.
+const PROVISION_DATABASE_SCRIPT: &str = r#"
+import os
+import psycopg2
+from psycopg2 import sql
+from sqlalchemy.engine import make_url
+
+url = make_url(os.environ["AIRFLOW__CORE__SQL_ALCHEMY_CONN"])
+conn = psycopg2.connect(
+    host=url.host,
+    port=url.port or 5432,
+    dbname="postgres",
+    user=os.environ["DATABASE_SUPERUSER_USERNAME"],
+    password=os.environ["DATABASE_SUPERUSER_PASSWORD"],
+)
+conn.autocommit = True
+cur = conn.cursor()
+cur.execute("SELECT 1 FROM pg_roles WHERE rolname = %s", (url.username,))
+if cur.fetchone() is None:
+    print("Creating role " + url.username)
+    cur.execute(
+        sql.SQL("CREATE ROLE {} LOGIN PASSWORD %s").format(sql.Identifier(url.username)),
+        (url.password,),
+    )
+cur.execute("SELECT 1 FROM pg_database WHERE datname = %s", (url.database,))
+if cur.fetchone() is None:
+    print("Creating database " + url.database)
+    cur.execute(
+        sql.SQL("CREATE DATABASE {} OWNER {}").format(
+            sql.Identifier(url.database), sql.Identifier(url.username)
+        )
+    )
+"#;
+
+/// Creates the schema of a tenant in a shared database if it does not exist yet.
+const CREATE_SCHEMA_SCRIPT: &str = r#"
+import os
+import psycopg2
+from psycopg2 import sql
+from sqlalchemy.engine import make_url
+
+url = make_url(os.environ["AIRFLOW__CORE__SQL_ALCHEMY_CONN"])
+conn = psycopg2.connect(
+    host=url.host,
+    port=url.port or 5432,
+    dbname=url.database,
+    user=url.username,
+    password=url.password,
+)
+conn.autocommit = True
+conn.cursor().execute(
+    sql.SQL("CREATE SCHEMA IF NOT EXISTS {}").format(
+        sql.Identifier(os.environ["DATABASE_SCHEMA"])
+    )
+)
+"#;
+
+/// The interval in which the deletion of the previous init job is checked.
+const INIT_JOB_DELETION_REQUEUE: Duration = Duration::from_secs(5);
+
+const INIT_SCRIPTS_VOLUME_NAME: &str = "init-scripts";
+const INIT_SCRIPTS_DIR: &str = "/stackable/init-scripts";
+const INSTALL_MODULES_ENV: &str = "INSTALL_MODULES";
+
+
 pub struct Ctx {
     pub client: stackable_operator::client::Client,
+    pub image_policy: ImagePolicy,
+    pub native_sidecars: bool,
+    /// Restricts the watchers, so the initialization Job and its ConfigMap carry the selected
+    /// labels of the OdooDB
+    pub watch_label_selector: Option<String>,
 }
 
 #[derive(Snafu, Debug, EnumDiscriminants)]
@@ -45,6 +142,12 @@ pub struct Ctx {
 pub enum Error {
     #[snafu(display("object has no namespace"))]
     ObjectHasNoNamespace,
+    #[snafu(display("the product image violates the image policy"))]
+    ImagePolicyViolation {
+        source: sovrin_cloud_crd::image_policy::Error,
+    },
+    #[snafu(display("invalid database configuration"))]
+    InvalidDatabaseConfig { source: sovrin_cloud_crd::Error },
     #[snafu(display("failed to apply Job for {}", odoo_db))]
     ApplyJob {
         source: stackable_operator::error::Error,
@@ -63,6 +166,14 @@ pub enum Error {
         source: stackable_operator::error::Error,
         init_job: ObjectRef<Job>,
     },
+    #[snafu(display("failed to delete the previous initialization job"))]
+    DeleteInitializationJob {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to delete the previous initialization job"))]
+    DeleteInitializationJobRequest {
+        source: stackable_operator::kube::Error,
+    },
     #[snafu(display("Failed to check whether the secret ({}) exists", secret))]
     SecretCheck {
         source: stackable_operator::error::Error,
@@ -105,6 +216,8 @@ pub enum Error {
         source: crate::product_logging::Error,
         cm_name: String,
     },
+    #[snafu(display("failed to serialize the init job"))]
+    SerializeJob { source: serde_yaml::Error },
 }
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -121,6 +234,15 @@ pub async fn reconcile_odoo_db(odoo_db: Arc<OdooDB>, ctx: Arc<Ctx>) -> Result<Ac
     let namespace = odoo_db.namespace().context(ObjectHasNoNamespaceSnafu)?;
     let resolved_product_image: ResolvedProductImage =
         odoo_db.spec.image.resolve(DOCKER_IMAGE_BASE_NAME);
+    ctx.image_policy
+        .effective(odoo_db.spec.image_policy)
+        .validate(&resolved_product_image.image)
+        .context(ImagePolicyViolationSnafu)?;
+    odoo_db
+        .spec
+        .database
+        .validate()
+        .context(InvalidDatabaseConfigSnafu)?;
 
     let (rbac_sa, rbac_rolebinding) = rbac::build_rbac_resources(odoo_db.as_ref(), "odoo");
     client
@@ -139,17 +261,59 @@ pub async fn reconcile_odoo_db(odoo_db: Arc<OdooDB>, ctx: Arc<Ctx>) -> Result<Ac
         .with_context(|_| ApplyRoleBindingSnafu {
             name: rbac_rolebinding.name_unchecked(),
         })?;
+    if let Some(reinitialization) = odoo_db
+        .status
+        .as_ref()
+        .and_then(|s| s.requested_reinitialization(&odoo_db))
+    {
+        if !delete_init_job(client, &odoo_db, &namespace).await? {
+            return Ok(Action::requeue(INIT_JOB_DELETION_REQUEUE));
+        }
+        tracing::info!(reinitialization, "Reinitializing the database");
+        publish_event(
+            client,
+            &odoo_db,
+            init_event(
+                EventType::Normal,
+                "Reinitializing",
+                format!("Reinitialization {reinitialization} was requested"),
+            ),
+        )
+        .await;
+        client
+            .apply_patch_status(
+                AIRFLOW_DB_CONTROLLER_NAME,
+                &*odoo_db,
+                &OdooDBStatus::reinitializing(reinitialization),
+            )
+            .await
+            .context(ApplyStatusSnafu)?;
+        return Ok(Action::await_change());
+    }
+
     if let Some(ref s) = odoo_db.status {
         match s.condition {
             OdooDBStatusCondition::Pending => {
-                // This is easier to use than `get_opt` and having an Error variant for "Secret does not exist"
-                let _secret = client
-                    .get::<Secret>(&odoo_db.spec.credentials_secret, &namespace)
-                    .await
-                    .context(SecretCheckSnafu {
-                        secret: ObjectRef::<Secret>::new(&odoo_db.spec.credentials_secret)
-                            .within(&namespace),
-                    })?;
+                // Credentials from a provider are only available inside the pods
+                if odoo_db.spec.credentials_provider.is_none() {
+                    // This is easier to use than `get_opt` and having an Error variant for "Secret does not exist"
+                    let _secret = client
+                        .get::<Secret>(&odoo_db.spec.credentials_secret, &namespace)
+                        .await
+                        .context(SecretCheckSnafu {
+                            secret: ObjectRef::<Secret>::new(&odoo_db.spec.credentials_secret)
+                                .within(&namespace),
+                        })?;
+                }
+                if let Some(database_provisioning) = &odoo_db.spec.database_provisioning {
+                    let superuser_secret = &database_provisioning.superuser_secret;
+                    client
+                        .get::<Secret>(superuser_secret, &namespace)
+                        .await
+                        .context(SecretCheckSnafu {
+                            secret: ObjectRef::<Secret>::new(superuser_secret).within(&namespace),
+                        })?;
+                }
 
                 let vector_aggregator_address = resolve_vector_aggregator_address(
                     client,
@@ -163,11 +327,15 @@ pub async fn reconcile_odoo_db(odoo_db: Arc<OdooDB>, ctx: Arc<Ctx>) -> Result<Ac
                     .merged_config()
                     .context(FailedToResolveConfigSnafu)?;
 
-                let config_map = build_config_map(
+                // The watchers of the created objects are restricted with the label selector
+                let selected_labels =
+                    utils::selected_labels(ctx.watch_label_selector.as_deref(), odoo_db.labels());
+                let mut config_map = build_config_map(
                     &odoo_db,
                     &config.logging,
                     vector_aggregator_address.as_deref(),
                 )?;
+                utils::add_labels(&mut config_map, &selected_labels);
                 client
                     .apply_patch(AIRFLOW_DB_CONTROLLER_NAME, &config_map, &config_map)
                     .await
@@ -175,19 +343,36 @@ pub async fn reconcile_odoo_db(odoo_db: Arc<OdooDB>, ctx: Arc<Ctx>) -> Result<Ac
                         name: config_map.name_any(),
                     })?;
 
-                let job = build_init_job(
+                let mut job = build_init_job(
                     &odoo_db,
                     &resolved_product_image,
                     &rbac_sa.name_unchecked(),
                     &config,
                     &config_map.name_unchecked(),
+                    ctx.native_sidecars,
                 )?;
+                utils::add_labels(&mut job, &selected_labels);
+                let patch = if ctx.native_sidecars {
+                    native_sidecar_patch(&job)?
+                } else {
+                    serde_yaml::to_value(&job).context(SerializeJobSnafu)?
+                };
                 client
-                    .apply_patch(AIRFLOW_DB_CONTROLLER_NAME, &job, &job)
+                    .apply_patch(AIRFLOW_DB_CONTROLLER_NAME, &job, &patch)
                     .await
                     .context(ApplyJobSnafu {
                         odoo_db: ObjectRef::from_obj(&*odoo_db),
                     })?;
+                publish_event(
+                    client,
+                    &odoo_db,
+                    init_event(
+                        EventType::Normal,
+                        "InitStarted",
+                        format!("Started the init job {}", job.name_any()),
+                    ),
+                )
+                .await;
                 // The job is started, update status to reflect new state
                 client
                     .apply_patch_status(AIRFLOW_DB_CONTROLLER_NAME, &*odoo_db, &s.initializing())
@@ -198,27 +383,59 @@ pub async fn reconcile_odoo_db(odoo_db: Arc<OdooDB>, ctx: Arc<Ctx>) -> Result<Ac
                 // In here, check the associated job that is running.
                 // If it is still running, do nothing. If it completed, set status to ready, if it failed, set status to failed.
                 let job_name = odoo_db.job_name();
-                let job = client.get::<Job>(&job_name, &namespace).await.context(
+                let job = client.get_opt::<Job>(&job_name, &namespace).await.context(
                     GetInitializationJobSnafu {
                         init_job: ObjectRef::<Job>::new(&job_name).within(&namespace),
                     },
                 )?;
 
-                let new_status = match get_job_state(&job) {
-                    JobState::Complete => Some(s.ready()),
-                    JobState::Failed => Some(s.failed()),
-                    JobState::InProgress => None,
-                };
-
-                if let Some(ns) = new_status {
+                if let Some((new_status, event)) =
+                    init_job_transition(s, job.as_ref(), &job_name)
+                {
+                    publish_event(client, &odoo_db, event).await;
                     client
-                        .apply_patch_status(AIRFLOW_DB_CONTROLLER_NAME, &*odoo_db, &ns)
+                        .apply_patch_status(AIRFLOW_DB_CONTROLLER_NAME, &*odoo_db, &new_status)
                         .await
                         .context(ApplyStatusSnafu)?;
                 }
             }
             OdooDBStatusCondition::Ready => (),
-            OdooDBStatusCondition::Failed => (),
+            OdooDBStatusCondition::Failed => {
+                let config = odoo_db
+                    .merged_config()
+                    .context(FailedToResolveConfigSnafu)?;
+                if let Some(remaining_backoff) = s.retry_after(config.retry_limit) {
+                    if !remaining_backoff.is_zero() {
+                        return Ok(Action::requeue(remaining_backoff));
+                    }
+                    if !delete_init_job(client, &odoo_db, &namespace).await? {
+                        return Ok(Action::requeue(INIT_JOB_DELETION_REQUEUE));
+                    }
+                    tracing::info!(
+                        retry = s.retries + 1,
+                        retry_limit = config.retry_limit,
+                        "Retrying the failed initialization"
+                    );
+                    publish_event(
+                        client,
+                        &odoo_db,
+                        init_event(
+                            EventType::Normal,
+                            "InitRetried",
+                            format!(
+                                "Retrying the initialization ({} of {})",
+                                s.retries + 1,
+                                config.retry_limit
+                            ),
+                        ),
+                    )
+                    .await;
+                    client
+                        .apply_patch_status(AIRFLOW_DB_CONTROLLER_NAME, &*odoo_db, &s.retrying())
+                        .await
+                        .context(ApplyStatusSnafu)?;
+                }
+            }
         }
     } else {
         // Status is none => initialize the status object as "Provisioned"
@@ -232,51 +449,230 @@ pub async fn reconcile_odoo_db(odoo_db: Arc<OdooDB>, ctx: Arc<Ctx>) -> Result<Ac
     Ok(Action::await_change())
 }
 
+/// The status following the state of the init job and the Event telling about it, or `None`
+/// while the job is still running.
+fn init_job_transition(
+    status: &OdooDBStatus,
+    job: Option<&Job>,
+    job_name: &str,
+) -> Option<(OdooDBStatus, Event)> {
+    match job.map(get_job_state) {
+        // The job was deleted before its result was recorded, e.g. by a too short
+        // `ttlSecondsAfterFinished`, so the initialization is started again.
+        None => {
+            tracing::warn!(job_name, "The init job is missing, starting it again");
+            Some((
+                status.pending(),
+                init_event(
+                    EventType::Warning,
+                    "InitJobMissing",
+                    format!("The init job {job_name} is missing, starting it again"),
+                ),
+            ))
+        }
+        Some(JobState::Complete) => Some((
+            status.ready(),
+            init_event(
+                EventType::Normal,
+                "InitSucceeded",
+                format!("The init job {job_name} completed"),
+            ),
+        )),
+        Some(JobState::Failed) => Some((
+            status.failed(),
+            init_event(
+                EventType::Warning,
+                "InitFailed",
+                format!("The init job {job_name} failed"),
+            ),
+        )),
+        Some(JobState::InProgress) => None,
+    }
+}
+
+fn init_event(type_: EventType, reason: &str, note: String) -> Event {
+    Event {
+        type_,
+        reason: reason.to_string(),
+        note: Some(note),
+        action: "Initialize".to_string(),
+        secondary: None,
+    }
+}
+
+fn event_reporter() -> Reporter {
+    Reporter {
+        controller: format!("{AIRFLOW_DB_CONTROLLER_NAME}.{OPERATOR_NAME}"),
+        instance: None,
+    }
+}
+
+/// Publishes an Event on the OdooDB, so the initialization can be followed with
+/// `kubectl describe`. Failures are only logged, as the Events are merely informational.
+async fn publish_event(client: &Client, odoo_db: &OdooDB, event: Event) {
+    let reason = event.reason.clone();
+    let recorder = Recorder::new(
+        client.as_kube_client(),
+        event_reporter(),
+        odoo_db.object_ref(&()),
+    );
+    if let Err(error) = recorder.publish(event).await {
+        tracing::warn!(
+            reason,
+            error = &error as &dyn std::error::Error,
+            "Failed to publish an Event"
+        );
+    }
+}
+
+/// The finished job would not run again, so it is deleted together with its pods before the
+/// initialization is started anew. Returns whether the job is gone, otherwise the caller has to
+/// wait for the deletion before the job is created again.
+async fn delete_init_job(client: &Client, odoo_db: &OdooDB, namespace: &str) -> Result<bool> {
+    match client
+        .get_opt::<Job>(&odoo_db.job_name(), namespace)
+        .await
+        .context(DeleteInitializationJobSnafu)?
+    {
+        Some(job) => {
+            delete_job(client, &job)
+                .await
+                .context(DeleteInitializationJobRequestSnafu)?;
+            Ok(false)
+        }
+        None => Ok(true),
+    }
+}
+
 fn build_init_job(
     odoo_db: &OdooDB,
     resolved_product_image: &ResolvedProductImage,
     sa_name: &str,
     config: &OdooDbConfig,
     config_map_name: &str,
+    native_sidecars: bool,
 ) -> Result<Job> {
-    let commands = vec![
+    let admin_user = &odoo_db.spec.admin_user;
+    let mut commands = vec![
         String::from("odoo db init"),
         String::from("odoo db upgrade"),
-        String::from(
+    ];
+    if admin_user.create {
+        commands.push(String::from(
             "odoo users create \
                     --username \"$ADMIN_USERNAME\" \
                     --firstname \"$ADMIN_FIRSTNAME\" \
                     --lastname \"$ADMIN_LASTNAME\" \
                     --email \"$ADMIN_EMAIL\" \
                     --password \"$ADMIN_PASSWORD\" \
-                    --role \"Admin\"",
-        ),
-        product_logging::framework::shutdown_vector_command(STACKABLE_LOG_DIR),
-    ];
+                    --role \"$ADMIN_ROLE\"",
+        ));
+    }
 
+    // Runs after the database is initialized, so that the init scripts can rely on the modules
+    let mut post_init_commands = Vec::new();
+    if !config.install_modules.is_empty() {
+        // The module names are passed as env var, so they are not interpreted by the shell
+        post_init_commands.push(format!(
+            "odoo -i \"${INSTALL_MODULES_ENV}\" --stop-after-init"
+        ));
+    }
+    if config.neutralize {
+        post_init_commands.push(String::from("odoo neutralize"));
+    }
+    if config.extra_init_scripts.is_some() {
+        post_init_commands.push(run_init_scripts_command());
+    }
+    commands.splice(1..1, post_init_commands);
+    if odoo_db.spec.database.schema.is_some() {
+        commands.insert(0, format!("python3 -c '{CREATE_SCHEMA_SCRIPT}'"));
+    }
+    if odoo_db.spec.database_provisioning.is_some() {
+        commands.insert(0, format!("python3 -c '{PROVISION_DATABASE_SCRIPT}'"));
+    }
+
+    if odoo_db.spec.database.assembles_connection_uri() {
+        commands.insert(0, assemble_connection_uri_command());
+    }
+
+    let database = &odoo_db.spec.database;
     let secret = &odoo_db.spec.credentials_secret;
+    let connection_secret = database.connection_secret(secret);
+    let cnpg_app_secret = database.cnpg_app_secret();
 
-    let env = vec![
-        env_var_from_secret(
-            "AIRFLOW__WEBSERVER__SECRET_KEY",
-            secret,
-            "connections.secretKey",
-        ),
-        env_var_from_secret(
-            "AIRFLOW__CORE__SQL_ALCHEMY_CONN",
-            secret,
-            "connections.sqlalchemyDatabaseUri",
-        ),
-        env_var_from_secret(
-            "AIRFLOW__CELERY__RESULT_BACKEND",
-            secret,
-            "connections.celeryResultBackend",
-        ),
-        env_var_from_secret("ADMIN_USERNAME", secret, "adminUser.username"),
-        env_var_from_secret("ADMIN_FIRSTNAME", secret, "adminUser.firstname"),
-        env_var_from_secret("ADMIN_LASTNAME", secret, "adminUser.lastname"),
-        env_var_from_secret("ADMIN_EMAIL", secret, "adminUser.email"),
-        env_var_from_secret("ADMIN_PASSWORD", secret, "adminUser.password"),
+    let admin_user_credentials = if admin_user.create {
+        ADMIN_USER_CREDENTIALS_ENV_VARS
+    } else {
+        &[]
+    };
+
+    let mut env = database.connection_env_vars();
+    if let Some(schema) = &database.schema {
+        env.push(EnvVar {
+            name: "DATABASE_SCHEMA".into(),
+            value: Some(schema.clone()),
+            ..Default::default()
+        });
+    }
+    if !config.install_modules.is_empty() {
+        env.push(EnvVar {
+            name: INSTALL_MODULES_ENV.into(),
+            value: Some(config.install_modules.join(",")),
+            ..Default::default()
+        });
+    }
+    if admin_user.create {
+        env.push(EnvVar {
+            name: "ADMIN_ROLE".into(),
+            value: Some(admin_user.role.clone()),
+            ..Default::default()
+        });
+    }
+    if odoo_db.spec.credentials_provider.is_some() {
+        let exports = CREDENTIALS_ENV_VARS
+            .iter()
+            .chain(admin_user_credentials)
+            .chain(database.connection_credentials())
+            .map(|(var_name, key)| export_env_var_from_file(var_name, &credentials_file(key)));
+        commands.splice(0..0, exports);
+    } else {
+        env.extend(
+            CREDENTIALS_ENV_VARS
+                .iter()
+                .chain(admin_user_credentials)
+                .map(|(var_name, key)| env_var_from_secret(var_name, secret, key)),
+        );
+        env.extend(
+            database
+                .connection_credentials()
+                .iter()
+                .map(|(var_name, key)| env_var_from_secret(var_name, connection_secret, key)),
+        );
+    }
+    if let Some(cnpg_app_secret) = &cnpg_app_secret {
+        env.push(env_var_from_secret(
+            SQL_ALCHEMY_CONN_ENV,
+            cnpg_app_secret,
+            CNPG_APP_SECRET_URI_KEY,
+        ));
+    }
+
+    if let Some(database_provisioning) = &odoo_db.spec.database_provisioning {
+        env.extend([
+            env_var_from_secret(
+                "DATABASE_SUPERUSER_USERNAME",
+                &database_provisioning.superuser_secret,
+                "username",
+            ),
+            env_var_from_secret(
+                "DATABASE_SUPERUSER_PASSWORD",
+                &database_provisioning.superuser_secret,
+                "password",
+            ),
+        ]);
+    }
+
+    env.extend([
         EnvVar {
             name: "PYTHONPATH".into(),
             value: Some(LOG_CONFIG_DIR.into()),
@@ -287,7 +683,7 @@ fn build_init_job(
             value: Some("log_config.LOGGING_CONFIG".into()),
             ..Default::default()
         },
-    ];
+    ]);
 
     let mut containers = Vec::new();
 
@@ -296,28 +692,47 @@ fn build_init_job(
 
     cb.image_from_product_image(resolved_product_image)
         .command(vec!["/bin/bash".to_string()])
-        .args(vec![String::from("-c"), commands.join("; ")])
+        // A native sidecar is stopped by the kubelet once the init container terminated
+        .args(vec![String::from("-c"), init_script(&commands, !native_sidecars)])
         .add_env_vars(env)
         .add_volume_mount(LOG_CONFIG_VOLUME_NAME, LOG_CONFIG_DIR)
         .add_volume_mount(LOG_VOLUME_NAME, STACKABLE_LOG_DIR)
-        .resources(
-            ResourceRequirementsBuilder::new()
-                .with_cpu_request("100m")
-                .with_cpu_limit("400m")
-                .with_memory_request("512Mi")
-                .with_memory_limit("512Mi")
-                .build(),
-        );
+        .resources(config.resources.clone().into());
 
-    let volumes = controller_commons::create_volumes(
+    let mut volumes = controller_commons::create_volumes(
         config_map_name,
         config.logging.containers.get(&Container::OdooInitDb),
+        &[],
     );
 
+    if let Some(extra_init_scripts) = &config.extra_init_scripts {
+        volumes.push(
+            VolumeBuilder::new(INIT_SCRIPTS_VOLUME_NAME)
+                .with_config_map(extra_init_scripts)
+                .build(),
+        );
+        cb.add_volume_mount(INIT_SCRIPTS_VOLUME_NAME, INIT_SCRIPTS_DIR);
+    }
+    if let Some(credentials_provider) = &odoo_db.spec.credentials_provider {
+        volumes.push(credentials_provider.volume());
+        cb.add_volume_mount(CREDENTIALS_VOLUME_NAME, CREDENTIALS_DIR);
+    }
+    if let Some(database_tls) = &odoo_db.spec.database.tls {
+        volumes.extend(database_tls.volumes());
+        cb.add_volume_mounts(database_tls.volume_mounts());
+        cb.add_env_vars(database_tls.env_vars());
+    }
+
     containers.push(cb.build());
 
+    let mut init_containers = Vec::new();
     if config.logging.enable_vector_agent {
-        containers.push(product_logging::framework::vector_container(
+        let vector_containers = if native_sidecars {
+            &mut init_containers
+        } else {
+            &mut containers
+        };
+        vector_containers.push(product_logging::framework::vector_container(
             resolved_product_image,
             CONFIG_VOLUME_NAME,
             LOG_VOLUME_NAME,
@@ -331,26 +746,37 @@ fn build_init_job(
         ));
     }
 
-    let pod = PodTemplateSpec {
-        metadata: Some(
-            ObjectMetaBuilder::new()
-                .name(format!("{}-init", odoo_db.name_unchecked()))
+    let mut pod_spec = PodSpec {
+        containers,
+        init_containers: (!init_containers.is_empty()).then_some(init_containers),
+        restart_policy: Some("Never".to_string()),
+        service_account: Some(sa_name.to_string()),
+        image_pull_secrets: resolved_product_image.pull_secrets.clone(),
+        security_context: Some(
+            PodSecurityContextBuilder::new()
+                .run_as_user(AIRFLOW_UID)
+                .run_as_group(0)
                 .build(),
         ),
-        spec: Some(PodSpec {
-            containers,
-            restart_policy: Some("Never".to_string()),
-            service_account: Some(sa_name.to_string()),
-            image_pull_secrets: resolved_product_image.pull_secrets.clone(),
-            security_context: Some(
-                PodSecurityContextBuilder::new()
-                    .run_as_user(AIRFLOW_UID)
-                    .run_as_group(0)
-                    .build(),
-            ),
-            volumes: Some(volumes),
-            ..Default::default()
-        }),
+        volumes: Some(volumes),
+        ..Default::default()
+    };
+    if let Some(ca_bundle) = &odoo_db.spec.ca_bundle {
+        controller_commons::add_ca_bundle(&mut pod_spec, ca_bundle, resolved_product_image);
+    }
+    controller_commons::apply_compliance(&mut pod_spec, &odoo_db.spec.compliance);
+
+    // The OdooDB is named after the cluster, so the pod gets the labels of the cluster
+    let mut pod_metadata = network_policy::job_pod_metadata(
+        odoo_db,
+        AIRFLOW_DB_CONTROLLER_NAME,
+        &resolved_product_image.app_version_label,
+        "db-initializer",
+    );
+    pod_metadata.name = Some(format!("{}-init", odoo_db.name_unchecked()));
+    let pod = PodTemplateSpec {
+        metadata: Some(pod_metadata),
+        spec: Some(pod_spec),
     };
 
     let job = Job {
@@ -362,6 +788,9 @@ fn build_init_job(
             .build(),
         spec: Some(JobSpec {
             template: pod,
+            backoff_limit: config.backoff_limit,
+            active_deadline_seconds: config.active_deadline_seconds,
+            ttl_seconds_after_finished: config.ttl_seconds_after_finished,
             ..Default::default()
         }),
         status: None,
@@ -370,6 +799,68 @@ fn build_init_job(
     Ok(job)
 }
 
+/// Serializes the init job with the Vector init container turned into a native sidecar. The
+/// `restartPolicy` of containers is not known to the Kubernetes API version of the operator, so
+/// it is added to the serialized job.
+fn native_sidecar_patch(job: &Job) -> Result<serde_yaml::Value> {
+    let mut patch = serde_yaml::to_value(job).context(SerializeJobSnafu)?;
+    let vector_container_name = Container::Vector.to_string();
+    if let Some(init_containers) = patch
+        .get_mut("spec")
+        .and_then(|spec| spec.get_mut("template"))
+        .and_then(|template| template.get_mut("spec"))
+        .and_then(|pod_spec| pod_spec.get_mut("initContainers"))
+        .and_then(|init_containers| init_containers.as_sequence_mut())
+    {
+        for container in init_containers.iter_mut().filter(|container| {
+            container.get("name").and_then(|name| name.as_str())
+                == Some(vector_container_name.as_str())
+        }) {
+            if let Some(container) = container.as_mapping_mut() {
+                container.insert("restartPolicy".into(), "Always".into());
+            }
+        }
+    }
+    Ok(patch)
+}
+
+/// Joins the commands to a script which stops at the first failing command, so that a failed
+/// initialization fails the job. The Vector agent is shut down on exit, whether the commands
+/// succeeded or not.
+fn init_script(commands: &[String], shutdown_vector: bool) -> String {
+    let mut script = vec![String::from("set -euo pipefail")];
+    if shutdown_vector {
+        script.push(format!(
+            "shutdown_vector() {{\n    {}\n}}\ntrap shutdown_vector EXIT",
+            product_logging::framework::shutdown_vector_command(STACKABLE_LOG_DIR)
+        ));
+    }
+    script.extend_from_slice(commands);
+    script.join("\n")
+}
+
+/// Runs the init scripts in the order of their names and stops at the first failing one, so
+/// the job fails instead of marking the database as initialized.
+fn run_init_scripts_command() -> String {
+    format!(
+        r#"for script in "{INIT_SCRIPTS_DIR}"/*; do
+    [ -f "$script" ] || continue
+    name="$(basename "$script")"
+    echo "Running init script $name"
+    case "$name" in
+        *.sh) bash "$script" ;;
+        *.py) python3 "$script" ;;
+        *.sql) python3 -c 'import os, sys
+from sqlalchemy import create_engine
+engine = create_engine(os.environ["AIRFLOW__CORE__SQL_ALCHEMY_CONN"])
+with engine.begin() as conn, open(sys.argv[1]) as script:
+    conn.exec_driver_sql(script.read())' "$script" ;;
+        *) echo "Skipping $name, the extension is not supported" ;;
+    esac || {{ echo "Init script $name failed"; exit 1; }}
+done"#
+    )
+}
+
 fn build_config_map(
     odoo_db: &OdooDB,
     logging: &Logging<Container>,
@@ -398,6 +889,9 @@ fn build_config_map(
         logging,
         &Container::OdooInitDb,
         &Container::Vector,
+        None,
+        None,
+        LogFormat::default(),
         &mut cm_builder,
     )
         .context(InvalidLoggingConfigSnafu {
@@ -409,6 +903,508 @@ fn build_config_map(
         .context(BuildConfigSnafu { name: cm_name })
 }
 
+/// The OdooDBs to reconcile when the given vector aggregator discovery ConfigMap changes.
+pub fn odoo_dbs_using_vector_aggregator(
+    odoo_dbs: Vec<Arc<OdooDB>>,
+    config_map: &ConfigMap,
+) -> Vec<ObjectRef<OdooDB>> {
+    odoo_dbs
+        .into_iter()
+        .filter(|odoo_db| {
+            odoo_db.spec.vector_aggregator_config_map_name.as_ref()
+                == config_map.metadata.name.as_ref()
+                && config_map.namespace() == odoo_db.namespace()
+        })
+        .map(|odoo_db| ObjectRef::from_obj(&*odoo_db))
+        .collect()
+}
+
 pub fn error_policy(_obj: Arc<OdooDB>, _error: &Error, _ctx: Arc<Ctx>) -> Action {
     Action::requeue(Duration::from_secs(5))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_operator::k8s_openapi::{
+        apimachinery::pkg::{
+            api::resource::Quantity,
+            apis::meta::v1::{ObjectMeta, Time},
+        },
+        chrono::{self, Utc},
+    };
+
+    /// Builds the OdooDB `odoo` with the given YAML lines appended to its spec.
+    fn odoo_db(spec: &str) -> OdooDB {
+        serde_yaml::from_str(&format!(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooDB
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 0ea5c2b9-4a7c-4b94-9a5b-3c1f8d7e2a11
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          credentialsSecret: simple-odoo-credentials
+{spec}"
+        ))
+        .unwrap()
+    }
+
+    fn init_job(odoo_db: &OdooDB, native_sidecars: bool) -> Job {
+        let resolved_product_image = odoo_db.spec.image.resolve(DOCKER_IMAGE_BASE_NAME);
+        let config = odoo_db.merged_config().unwrap();
+        build_init_job(
+            odoo_db,
+            &resolved_product_image,
+            "odoo-serviceaccount",
+            &config,
+            "odoo-init-db",
+            native_sidecars,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_odoo_dbs_using_vector_aggregator() {
+        let odoo_db = |name: &str, vector_aggregator: &str| {
+            Arc::new(
+                serde_yaml::from_str::<OdooDB>(&format!(
+                    "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooDB
+            metadata:
+              name: {name}
+              namespace: default
+            spec:
+              image:
+                productVersion: 2.6.1
+                stackableVersion: 0.0.0-dev
+              credentialsSecret: simple-odoo-credentials
+              vectorAggregatorConfigMapName: {vector_aggregator}
+              "
+                ))
+                .unwrap(),
+            )
+        };
+        let config_map = ConfigMap {
+            metadata: ObjectMeta {
+                name: Some("vector-aggregator".to_string()),
+                namespace: Some("default".to_string()),
+                ..ObjectMeta::default()
+            },
+            ..ConfigMap::default()
+        };
+
+        assert_eq!(
+            vec![ObjectRef::new("odoo").within("default")],
+            odoo_dbs_using_vector_aggregator(
+                vec![
+                    odoo_db("odoo", "vector-aggregator"),
+                    odoo_db("other", "other-aggregator"),
+                ],
+                &config_map
+            )
+        );
+    }
+
+    #[test]
+    fn test_init_job_restricted_pod_security_standard() {
+        let odoo_db = odoo_db(
+            "
+          config:
+            logging:
+              enableVectorAgent: true
+          compliance:
+            podSecurityStandard: restricted
+          ",
+        );
+
+        let job = init_job(&odoo_db, false);
+
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        assert_eq!(
+            Some(true),
+            pod_spec.security_context.unwrap().run_as_non_root
+        );
+        assert_eq!(2, pod_spec.containers.len());
+        for container in pod_spec.containers {
+            let security_context = container.security_context.unwrap();
+            assert_eq!(Some(false), security_context.allow_privilege_escalation);
+            assert_eq!(Some(true), security_context.run_as_non_root);
+            assert_eq!(
+                "RuntimeDefault",
+                security_context.seccomp_profile.unwrap().type_
+            );
+            assert_eq!(
+                Some(vec!["ALL".to_string()]),
+                security_context.capabilities.unwrap().drop
+            );
+        }
+    }
+
+    #[test]
+    fn test_init_job_hardened_by_default() {
+        let odoo_db = odoo_db(
+            "
+          config: {}
+          ",
+        );
+
+        let job = init_job(&odoo_db, false);
+
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        assert_eq!(Some(false), pod_spec.automount_service_account_token);
+        assert_eq!(None, pod_spec.security_context.unwrap().run_as_non_root);
+        for container in pod_spec.containers {
+            let security_context = container.security_context.unwrap();
+            assert_eq!(Some(false), security_context.allow_privilege_escalation);
+            assert_eq!(None, security_context.run_as_non_root);
+            assert_eq!(
+                Some(vec!["ALL".to_string()]),
+                security_context.capabilities.unwrap().drop
+            );
+        }
+    }
+
+    #[test]
+    fn test_init_job_provisions_database() {
+        let odoo_db = odoo_db(
+            "
+          config: {}
+          databaseProvisioning:
+            superuserSecret: postgresql-superuser
+          ",
+        );
+
+        let job = init_job(&odoo_db, false);
+
+        let container = job.spec.unwrap().template.spec.unwrap().containers.remove(0);
+        let script = &container.args.unwrap()[1];
+        let (_, commands) = script.split_once("trap shutdown_vector EXIT\n").unwrap();
+        assert!(commands.starts_with("python3 -c"));
+        assert!(script.find("CREATE DATABASE") < script.find("odoo db init"));
+        let superuser_secrets = container
+            .env
+            .unwrap()
+            .into_iter()
+            .filter(|env| env.name.starts_with("DATABASE_SUPERUSER_"))
+            .filter_map(|env| env.value_from?.secret_key_ref?.name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["postgresql-superuser"; 2], superuser_secrets);
+    }
+
+    #[test]
+    fn test_init_script_fails_fast() {
+        for commands in [
+            vec!["echo first", "false", "echo never"],
+            vec!["echo first", "false | cat", "echo never"],
+            vec!["echo first", "echo \"$UNDEFINED_VARIABLE\"", "echo never"],
+        ] {
+            let commands = commands.into_iter().map(String::from).collect::<Vec<_>>();
+            let output = std::process::Command::new("bash")
+                .args(["-c", &init_script(&commands, false)])
+                .output()
+                .unwrap();
+            assert!(!output.status.success(), "{commands:?}");
+            assert_eq!("first\n", String::from_utf8_lossy(&output.stdout));
+        }
+
+        let script = init_script(&["odoo db init".to_string()], true);
+        let (prologue, commands) = script.split_once("trap shutdown_vector EXIT\n").unwrap();
+        assert!(prologue.starts_with("set -euo pipefail\n"));
+        assert!(prologue.contains(&product_logging::framework::shutdown_vector_command(
+            STACKABLE_LOG_DIR
+        )));
+        assert_eq!("odoo db init", commands);
+    }
+
+    #[test]
+    fn test_run_init_scripts_command() {
+        let dir = std::env::temp_dir().join(format!("init-scripts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, content) in [
+            ("01-first.sh", "echo first"),
+            ("02-failing.sh", "exit 3"),
+            ("03-never.sh", "echo never"),
+        ] {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+
+        let command = run_init_scripts_command().replace(INIT_SCRIPTS_DIR, dir.to_str().unwrap());
+        let output = std::process::Command::new("bash")
+            .args(["-c", &command])
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!output.status.success());
+        assert_eq!(
+            "Running init script 01-first.sh\n\
+            first\n\
+            Running init script 02-failing.sh\n\
+            Init script 02-failing.sh failed\n",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    #[test]
+    fn test_failed_init_job_is_retried() {
+        let job: Job = serde_yaml::from_str(
+            "
+        apiVersion: batch/v1
+        kind: Job
+        metadata:
+          name: odoo
+          namespace: default
+        status:
+          conditions:
+            - type: Failed
+              status: \"True\"
+              reason: BackoffLimitExceeded
+          ",
+        )
+        .unwrap();
+        assert!(matches!(get_job_state(&job), JobState::Failed));
+
+        let status = OdooDBStatus::new().initializing().failed();
+        assert_eq!(OdooDBStatusCondition::Failed, status.condition);
+        // The first retry waits for the backoff
+        assert!(!status.retry_after(3).unwrap().is_zero());
+
+        let status = OdooDBStatus {
+            failed_at: Some(Time(Utc::now() - chrono::Duration::hours(1))),
+            ..status
+        };
+        assert_eq!(Some(Duration::ZERO), status.retry_after(3));
+        let status = status.retrying();
+        assert_eq!(OdooDBStatusCondition::Pending, status.condition);
+        assert_eq!(1, status.retries);
+
+        // The retries are exhausted
+        let status = OdooDBStatus {
+            retries: 3,
+            ..status.initializing().failed()
+        };
+        assert_eq!(None, status.retry_after(3));
+    }
+
+    #[test]
+    fn test_init_job_transition_events() {
+        let job_with_condition = |type_: &str| -> Job {
+            serde_yaml::from_str(&format!(
+                "
+        apiVersion: batch/v1
+        kind: Job
+        metadata:
+          name: odoo-db-init
+          namespace: default
+        status:
+          conditions:
+            - type: {type_}
+              status: \"True\"
+          "
+            ))
+            .unwrap()
+        };
+        let status = OdooDBStatus::new().initializing();
+
+        let (new_status, event) =
+            init_job_transition(&status, Some(&job_with_condition("Complete")), "odoo-db-init")
+                .unwrap();
+        assert_eq!(OdooDBStatusCondition::Ready, new_status.condition);
+        assert!(matches!(event.type_, EventType::Normal));
+        assert_eq!("InitSucceeded", event.reason);
+        assert_eq!("Initialize", event.action);
+
+        let (new_status, event) =
+            init_job_transition(&status, Some(&job_with_condition("Failed")), "odoo-db-init")
+                .unwrap();
+        assert_eq!(OdooDBStatusCondition::Failed, new_status.condition);
+        assert!(matches!(event.type_, EventType::Warning));
+        assert_eq!("InitFailed", event.reason);
+        assert_eq!(
+            Some("The init job odoo-db-init failed".to_string()),
+            event.note
+        );
+
+        let (new_status, event) = init_job_transition(&status, None, "odoo-db-init").unwrap();
+        assert_eq!(OdooDBStatusCondition::Pending, new_status.condition);
+        assert!(matches!(event.type_, EventType::Warning));
+        assert_eq!("InitJobMissing", event.reason);
+
+        let running: Job = serde_yaml::from_str(
+            "
+        apiVersion: batch/v1
+        kind: Job
+        metadata:
+          name: odoo-db-init
+          namespace: default
+        status:
+          active: 1
+          ",
+        )
+        .unwrap();
+        assert!(init_job_transition(&status, Some(&running), "odoo-db-init").is_none());
+
+        assert_eq!(
+            format!("{AIRFLOW_DB_CONTROLLER_NAME}.{OPERATOR_NAME}"),
+            event_reporter().controller
+        );
+    }
+
+    #[test]
+    fn test_init_job_tuning() {
+        let odoo_db = odoo_db(
+            "
+          config:
+            backoffLimit: 2
+            ttlSecondsAfterFinished: 3600
+          ",
+        );
+
+        let job = init_job(&odoo_db, false);
+
+        let job_spec = job.spec.unwrap();
+        assert_eq!(Some(2), job_spec.backoff_limit);
+        assert_eq!(None, job_spec.active_deadline_seconds);
+        assert_eq!(Some(3600), job_spec.ttl_seconds_after_finished);
+    }
+
+    #[test]
+    fn test_init_job_installs_modules() {
+        let odoo_db = odoo_db(
+            "
+          config:
+            installModules:
+              - sale
+              - website
+              - website; reboot
+          ",
+        );
+
+        let job = init_job(&odoo_db, false);
+
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        let container = &pod_spec.containers[0];
+        let args = container.args.as_ref().unwrap();
+        assert!(args[1].contains(
+            "odoo db init\nodoo -i \"$INSTALL_MODULES\" --stop-after-init\nodoo db upgrade\n"
+        ));
+        assert!(!args[1].contains("reboot"));
+        assert_eq!(
+            Some("sale,website,website; reboot"),
+            container
+                .env
+                .iter()
+                .flatten()
+                .find(|env_var| env_var.name == INSTALL_MODULES_ENV)
+                .and_then(|env_var| env_var.value.as_deref())
+        );
+    }
+
+    #[test]
+    fn test_init_job_neutralizes() {
+        let odoo_db = odoo_db(
+            "
+          config:
+            neutralize: true
+          ",
+        );
+
+        let job = init_job(&odoo_db, false);
+
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        let script = &pod_spec.containers[0].args.as_ref().unwrap()[1];
+        let (_, commands) = script.split_once("trap shutdown_vector EXIT\n").unwrap();
+        assert!(commands.starts_with("odoo db init\nodoo neutralize\nodoo db upgrade\n"));
+
+        // A failing neutralization must fail the job before the database is used any further
+        let output = std::process::Command::new("bash")
+            .args([
+                "-c",
+                &format!(
+                    "set -euo pipefail\n\
+                    odoo() {{ echo \"odoo $1\"; [ \"$1\" != neutralize ]; }}\n\
+                    {commands}"
+                ),
+            ])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(
+            "odoo db\nodoo neutralize\n",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    #[test]
+    fn test_init_job_native_vector_sidecar() {
+        let odoo_db = odoo_db(
+            "
+          config:
+            logging:
+              enableVectorAgent: true
+          ",
+        );
+
+        let job = init_job(&odoo_db, true);
+
+        let patch = native_sidecar_patch(&job).unwrap();
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        assert_eq!(1, pod_spec.containers.len());
+        assert!(!pod_spec.containers[0].args.as_ref().unwrap()[1].contains("shutdown"));
+        assert!(pod_spec.containers[0].args.as_ref().unwrap()[1]
+            .starts_with("set -euo pipefail\n"));
+        assert_eq!(
+            Some("Always"),
+            patch["spec"]["template"]["spec"]["initContainers"][0]["restartPolicy"].as_str()
+        );
+    }
+
+    #[test]
+    fn test_init_job_without_admin_user() {
+        let odoo_db = odoo_db(
+            "
+          adminUser:
+            create: false
+          config: {}
+          ",
+        );
+
+        let job = init_job(&odoo_db, false);
+
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        let container = &pod_spec.containers[0];
+        assert!(!container.args.as_ref().unwrap()[1].contains("odoo users create"));
+        assert!(!container
+            .env
+            .as_ref()
+            .unwrap()
+            .iter()
+            .any(|env| env.name.starts_with("ADMIN_")));
+    }
+
+    #[test]
+    fn test_init_job_resources() {
+        let odoo_db = odoo_db(
+            "
+          config:
+            resources:
+              memory:
+                limit: 2Gi
+          ",
+        );
+
+        let job = init_job(&odoo_db, false);
+
+        let pod_spec = job.spec.unwrap().template.spec.unwrap();
+        let resources = pod_spec.containers[0].resources.clone().unwrap();
+        let limits = resources.limits.unwrap();
+        assert_eq!(Some(&Quantity("2Gi".to_string())), limits.get("memory"));
+        assert_eq!(Some(&Quantity("400m".to_string())), limits.get("cpu"));
+    }
+}
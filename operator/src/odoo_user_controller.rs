@@ -0,0 +1,681 @@
+use crate::controller_commons;
+use crate::finalizer;
+use crate::network_policy;
+use crate::odoo_controller::DOCKER_IMAGE_BASE_NAME;
+use crate::upgrade::add_database_connection;
+use crate::utils::{
+    self, assemble_connection_uri_command, delete_job, env_var_from_secret, get_job_state, JobState,
+};
+
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{
+    odoouser::{
+        OdooUser, OdooUserStatus, OdooUserStatusCondition, AIRFLOW_USER_CONTROLLER_NAME,
+        PASSWORD_SECRET_KEY,
+    },
+    OdooCluster, AIRFLOW_UID,
+};
+use stackable_operator::{
+    builder::{
+        resources::ResourceRequirementsBuilder, ContainerBuilder, ObjectMetaBuilder,
+        PodSecurityContextBuilder,
+    },
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::api::{
+        batch::v1::{Job, JobSpec},
+        core::v1::{EnvVar, PodSpec, PodTemplateSpec, Secret},
+    },
+    kube::{
+        runtime::{controller::Action, reflector::ObjectRef},
+        ResourceExt,
+    },
+    logging::controller::ReconcilerError,
+};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use strum::{EnumDiscriminants, IntoStaticStr};
+
+pub const ODOO_USER_FINALIZER: &str = "odoo.sovrin.cloud/delete-user";
+
+const CONTAINER_NAME: &str = "odoo-user";
+
+/// Deletes the user with the login in the given environment variable if it exists. The CLI
+/// cannot change an existing user, so it is deleted and created again if the OdooUser changed.
+fn delete_user_if_exists_command(login_env_var: &str) -> String {
+    format!(
+        "if odoo users list --output json | python3 -c \
+        'import json, os, sys; \
+        sys.exit(not any(user[\"username\"] == os.environ[\"{login_env_var}\"] \
+        for user in json.load(sys.stdin)))'; \
+        then odoo users delete --username \"${login_env_var}\"; fi"
+    )
+}
+
+pub struct Ctx {
+    pub client: stackable_operator::client::Client,
+    /// Restricts the watchers, so the Jobs which create and delete the user carry the selected
+    /// labels of the OdooUser
+    pub watch_label_selector: Option<String>,
+}
+
+#[derive(Snafu, Debug, EnumDiscriminants)]
+#[strum_discriminants(derive(IntoStaticStr))]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to get OdooCluster {}", odoo))]
+    GetOdooCluster {
+        source: stackable_operator::error::Error,
+        odoo: ObjectRef<OdooCluster>,
+    },
+    #[snafu(display("the OdooCluster {odoo} does not exist"))]
+    OdooClusterMissing { odoo: ObjectRef<OdooCluster> },
+    #[snafu(display("failed to apply Job for {}", odoo_user))]
+    ApplyJob {
+        source: stackable_operator::error::Error,
+        odoo_user: ObjectRef<OdooUser>,
+    },
+    #[snafu(display("failed to update status"))]
+    ApplyStatus {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("invalid container name"))]
+    InvalidContainerName {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to retrieve the password Secret {}", secret))]
+    GetPasswordSecret {
+        source: stackable_operator::error::Error,
+        secret: ObjectRef<Secret>,
+    },
+    #[snafu(display("failed to retrieve the job {}", job))]
+    GetJob {
+        source: stackable_operator::error::Error,
+        job: ObjectRef<Job>,
+    },
+    #[snafu(display("failed to delete the job {}", job))]
+    DeleteJob {
+        source: stackable_operator::kube::Error,
+        job: ObjectRef<Job>,
+    },
+    #[snafu(display("failed to handle the finalizer"))]
+    Finalizer { source: crate::finalizer::Error },
+    #[snafu(display(
+        "failed to delete the user, remove the finalizer {ODOO_USER_FINALIZER} to delete the \
+        OdooUser anyway"
+    ))]
+    DeleteUserFailed,
+}
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl ReconcilerError for Error {
+    fn category(&self) -> &'static str {
+        ErrorDiscriminants::from(self).into()
+    }
+}
+
+pub async fn reconcile_odoo_user(odoo_user: Arc<OdooUser>, ctx: Arc<Ctx>) -> Result<Action> {
+    tracing::info!("Starting reconcile");
+
+    let client = &ctx.client;
+    let namespace = odoo_user.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    // The watchers of the jobs are restricted with the label selector as well
+    let selected_labels =
+        utils::selected_labels(ctx.watch_label_selector.as_deref(), odoo_user.labels());
+
+    if odoo_user.metadata.deletion_timestamp.is_some() {
+        return finalize(client, &odoo_user, &namespace, &selected_labels).await;
+    }
+    ensure_finalizer(client, &odoo_user).await?;
+
+    if let Some(ref s) = odoo_user.status {
+        match s.condition {
+            OdooUserStatusCondition::Pending => {
+                // The Job of the previous spec has to be gone before it is created again
+                let job_name = job_name(&odoo_user);
+                if let Some(job) = get_job(client, &job_name, &namespace).await? {
+                    delete_job(client, &job).await.context(DeleteJobSnafu {
+                        job: ObjectRef::from_obj(&job),
+                    })?;
+                    return Ok(Action::requeue(Duration::from_secs(5)));
+                }
+
+                let odoo = get_odoo_cluster(client, &odoo_user, &namespace)
+                    .await?
+                    .context(OdooClusterMissingSnafu {
+                        odoo: ObjectRef::<OdooCluster>::new(&odoo_user.spec.cluster_name)
+                            .within(&namespace),
+                    })?;
+                let resolved_product_image: ResolvedProductImage =
+                    odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME);
+
+                let password_secret_version =
+                    get_password_secret_version(client, &odoo_user, &namespace).await?;
+                let mut job = build_create_user_job(&odoo_user, &odoo, &resolved_product_image)?;
+                utils::add_labels(&mut job, &selected_labels);
+                client
+                    .apply_patch(AIRFLOW_USER_CONTROLLER_NAME, &job, &job)
+                    .await
+                    .context(ApplyJobSnafu {
+                        odoo_user: ObjectRef::from_obj(&*odoo_user),
+                    })?;
+                client
+                    .apply_patch_status(
+                        AIRFLOW_USER_CONTROLLER_NAME,
+                        &*odoo_user,
+                        &s.creating(
+                            odoo_user.metadata.generation,
+                            &odoo_user.spec.login,
+                            password_secret_version,
+                        ),
+                    )
+                    .await
+                    .context(ApplyStatusSnafu)?;
+            }
+            OdooUserStatusCondition::Creating => {
+                let job_name = job_name(&odoo_user);
+                let job = get_job(client, &job_name, &namespace).await?;
+
+                if let Some(ns) = create_job_transition(s, job.as_ref(), &job_name) {
+                    client
+                        .apply_patch_status(AIRFLOW_USER_CONTROLLER_NAME, &*odoo_user, &ns)
+                        .await
+                        .context(ApplyStatusSnafu)?;
+                }
+            }
+            OdooUserStatusCondition::Ready | OdooUserStatusCondition::Failed => {
+                let password_secret_version =
+                    get_password_secret_version(client, &odoo_user, &namespace).await?;
+                if s.is_outdated(
+                    odoo_user.metadata.generation,
+                    password_secret_version.as_deref(),
+                ) {
+                    client
+                        .apply_patch_status(AIRFLOW_USER_CONTROLLER_NAME, &*odoo_user, &s.pending())
+                        .await
+                        .context(ApplyStatusSnafu)?;
+                }
+            }
+        }
+    } else {
+        client
+            .apply_patch_status(
+                AIRFLOW_USER_CONTROLLER_NAME,
+                &*odoo_user,
+                &OdooUserStatus::new(),
+            )
+            .await
+            .context(ApplyStatusSnafu)?;
+    }
+
+    Ok(Action::await_change())
+}
+
+/// Determines the status after the state of the create Job changed, or `None` while the Job is
+/// still running.
+fn create_job_transition(
+    status: &OdooUserStatus,
+    job: Option<&Job>,
+    job_name: &str,
+) -> Option<OdooUserStatus> {
+    match job.map(get_job_state) {
+        // The Job was deleted before its result was recorded, e.g. by a too short
+        // `ttlSecondsAfterFinished`, so the user is created again.
+        None => {
+            tracing::warn!(job_name, "The create job is missing, starting it again");
+            Some(status.pending())
+        }
+        Some(JobState::Complete) => Some(status.ready()),
+        Some(JobState::Failed) => Some(status.failed()),
+        Some(JobState::InProgress) => None,
+    }
+}
+
+pub fn job_name(odoo_user: &OdooUser) -> String {
+    format!("{}-create-user", odoo_user.name_unchecked())
+}
+
+pub fn delete_job_name(odoo_user: &OdooUser) -> String {
+    format!("{}-delete-user", odoo_user.name_unchecked())
+}
+
+async fn get_odoo_cluster(
+    client: &stackable_operator::client::Client,
+    odoo_user: &OdooUser,
+    namespace: &str,
+) -> Result<Option<OdooCluster>> {
+    client
+        .get_opt::<OdooCluster>(&odoo_user.spec.cluster_name, namespace)
+        .await
+        .context(GetOdooClusterSnafu {
+            odoo: ObjectRef::<OdooCluster>::new(&odoo_user.spec.cluster_name).within(namespace),
+        })
+}
+
+/// Returns the resource version of the password Secret, so that a changed password is detected,
+/// or `None` if the Secret does not exist (yet).
+async fn get_password_secret_version(
+    client: &stackable_operator::client::Client,
+    odoo_user: &OdooUser,
+    namespace: &str,
+) -> Result<Option<String>> {
+    let secret = client
+        .get_opt::<Secret>(&odoo_user.spec.password_secret, namespace)
+        .await
+        .context(GetPasswordSecretSnafu {
+            secret: ObjectRef::<Secret>::new(&odoo_user.spec.password_secret).within(namespace),
+        })?;
+    Ok(secret.and_then(|secret| secret.metadata.resource_version))
+}
+
+async fn get_job(
+    client: &stackable_operator::client::Client,
+    job_name: &str,
+    namespace: &str,
+) -> Result<Option<Job>> {
+    client
+        .get_opt::<Job>(job_name, namespace)
+        .await
+        .context(GetJobSnafu {
+            job: ObjectRef::<Job>::new(job_name).within(namespace),
+        })
+}
+
+fn has_finalizer(odoo_user: &OdooUser) -> bool {
+    odoo_user
+        .finalizers()
+        .iter()
+        .any(|finalizer| finalizer == ODOO_USER_FINALIZER)
+}
+
+async fn ensure_finalizer(
+    client: &stackable_operator::client::Client,
+    odoo_user: &OdooUser,
+) -> Result<()> {
+    if !has_finalizer(odoo_user) {
+        let mut finalizers = odoo_user.finalizers().to_vec();
+        finalizers.push(ODOO_USER_FINALIZER.to_string());
+        finalizer::patch_finalizers(client, odoo_user, finalizers)
+            .await
+            .context(FinalizerSnafu)?;
+    }
+    Ok(())
+}
+
+async fn remove_finalizer(
+    client: &stackable_operator::client::Client,
+    odoo_user: &OdooUser,
+) -> Result<()> {
+    let finalizers = odoo_user
+        .finalizers()
+        .iter()
+        .filter(|finalizer| *finalizer != ODOO_USER_FINALIZER)
+        .cloned()
+        .collect();
+    finalizer::patch_finalizers(client, odoo_user, finalizers)
+        .await
+        .context(FinalizerSnafu)
+}
+
+/// Deletes the user from the database with a Job before the OdooUser is deleted. The finalizer
+/// is only removed once the Job completed, or right away if the cluster is already gone.
+async fn finalize(
+    client: &stackable_operator::client::Client,
+    odoo_user: &OdooUser,
+    namespace: &str,
+    selected_labels: &BTreeMap<String, String>,
+) -> Result<Action> {
+    if !has_finalizer(odoo_user) {
+        return Ok(Action::await_change());
+    }
+
+    let job = get_job(client, &delete_job_name(odoo_user), namespace).await?;
+    match job.as_ref().map(get_job_state) {
+        None => match get_odoo_cluster(client, odoo_user, namespace).await? {
+            Some(odoo) => {
+                let resolved_product_image: ResolvedProductImage =
+                    odoo.spec.image.resolve(DOCKER_IMAGE_BASE_NAME);
+                let mut job = build_delete_user_job(odoo_user, &odoo, &resolved_product_image)?;
+                utils::add_labels(&mut job, selected_labels);
+                client
+                    .apply_patch(AIRFLOW_USER_CONTROLLER_NAME, &job, &job)
+                    .await
+                    .context(ApplyJobSnafu {
+                        odoo_user: ObjectRef::from_obj(odoo_user),
+                    })?;
+            }
+            // The user was deleted together with the database of the cluster
+            None => remove_finalizer(client, odoo_user).await?,
+        },
+        Some(JobState::InProgress) => (),
+        Some(JobState::Complete) => remove_finalizer(client, odoo_user).await?,
+        Some(JobState::Failed) => return DeleteUserFailedSnafu.fail(),
+    }
+
+    Ok(Action::await_change())
+}
+
+fn build_create_user_job(
+    odoo_user: &OdooUser,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+) -> Result<Job> {
+    let spec = &odoo_user.spec;
+    let (first_name, last_name) = spec.first_and_last_name();
+
+    let previous_login = odoo_user
+        .status
+        .as_ref()
+        .and_then(|status| status.previous_login(&spec.login));
+
+    // The user data is passed as environment variables, so it never gets interpreted by the shell
+    let mut commands = Vec::new();
+    let mut env = Vec::new();
+    // A user with a changed login would otherwise be left behind
+    if let Some(previous_login) = previous_login {
+        commands.push(delete_user_if_exists_command("USER_PREVIOUS_LOGIN"));
+        env.push(plain_env_var("USER_PREVIOUS_LOGIN", previous_login));
+    }
+    commands.extend([
+        delete_user_if_exists_command("USER_LOGIN"),
+        String::from(
+            "odoo users create \
+                --username \"$USER_LOGIN\" \
+                --firstname \"$USER_FIRSTNAME\" \
+                --lastname \"$USER_LASTNAME\" \
+                --email \"$USER_EMAIL\" \
+                --password \"$USER_PASSWORD\" \
+                --role \"$USER_ROLE\"",
+        ),
+    ]);
+
+    env.extend([
+        plain_env_var("USER_LOGIN", &spec.login),
+        plain_env_var("USER_FIRSTNAME", first_name),
+        plain_env_var("USER_LASTNAME", last_name),
+        plain_env_var("USER_EMAIL", &spec.email),
+        plain_env_var("USER_ROLE", &spec.role),
+        env_var_from_secret("USER_PASSWORD", &spec.password_secret, PASSWORD_SECRET_KEY),
+    ]);
+
+    build_user_job(
+        odoo_user,
+        odoo,
+        resolved_product_image,
+        job_name(odoo_user),
+        &commands,
+        env,
+    )
+}
+
+fn build_delete_user_job(
+    odoo_user: &OdooUser,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+) -> Result<Job> {
+    let mut commands = vec![delete_user_if_exists_command("USER_LOGIN")];
+    let mut env = vec![plain_env_var("USER_LOGIN", &odoo_user.spec.login)];
+    // The login was changed but the user was not created again yet
+    if let Some(previous_login) = odoo_user
+        .status
+        .as_ref()
+        .and_then(|status| status.previous_login(&odoo_user.spec.login))
+    {
+        commands.push(delete_user_if_exists_command("USER_PREVIOUS_LOGIN"));
+        env.push(plain_env_var("USER_PREVIOUS_LOGIN", previous_login));
+    }
+
+    build_user_job(
+        odoo_user,
+        odoo,
+        resolved_product_image,
+        delete_job_name(odoo_user),
+        &commands,
+        env,
+    )
+}
+
+/// Builds a Job which runs the given commands against the database of the cluster and stops at
+/// the first failing one.
+fn build_user_job(
+    odoo_user: &OdooUser,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    name: String,
+    user_commands: &[String],
+    mut env: Vec<EnvVar>,
+) -> Result<Job> {
+    let cluster_config = &odoo.spec.cluster_config;
+
+    let mut commands = Vec::new();
+    let mut cb = ContainerBuilder::new(CONTAINER_NAME).context(InvalidContainerNameSnafu)?;
+    let mut volumes = Vec::new();
+    add_database_connection(odoo, &mut cb, &mut commands, &mut env, &mut volumes);
+    if cluster_config.database.assembles_connection_uri() {
+        commands.push(assemble_connection_uri_command());
+    }
+    commands.insert(0, "set -euo pipefail".to_string());
+    commands.extend(user_commands.iter().cloned());
+
+    cb.image_from_product_image(resolved_product_image)
+        .command(vec!["/bin/bash".to_string()])
+        .args(vec![String::from("-c"), commands.join("; ")])
+        .add_env_vars(env)
+        .resources(
+            ResourceRequirementsBuilder::new()
+                .with_cpu_request("100m")
+                .with_cpu_limit("400m")
+                .with_memory_request("512Mi")
+                .with_memory_limit("512Mi")
+                .build(),
+        );
+
+    let mut pod_spec = PodSpec {
+        containers: vec![cb.build()],
+        restart_policy: Some("Never".to_string()),
+        image_pull_secrets: resolved_product_image.pull_secrets.clone(),
+        security_context: Some(
+            PodSecurityContextBuilder::new()
+                .run_as_user(AIRFLOW_UID)
+                .run_as_group(0)
+                .build(),
+        ),
+        volumes: Some(volumes),
+        ..Default::default()
+    };
+    if let Some(ca_bundle) = &cluster_config.ca_bundle {
+        controller_commons::add_ca_bundle(&mut pod_spec, ca_bundle, resolved_product_image);
+    }
+    controller_commons::apply_compliance(&mut pod_spec, &cluster_config.compliance);
+
+    Ok(Job {
+        metadata: ObjectMetaBuilder::new()
+            .name(name)
+            .namespace_opt(odoo_user.namespace())
+            .ownerreference_from_resource(odoo_user, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            .build(),
+        spec: Some(JobSpec {
+            template: PodTemplateSpec {
+                metadata: Some(network_policy::job_pod_metadata(
+                    odoo,
+                    AIRFLOW_USER_CONTROLLER_NAME,
+                    &resolved_product_image.app_version_label,
+                    "user",
+                )),
+                spec: Some(pod_spec),
+            },
+            ..Default::default()
+        }),
+        status: None,
+    })
+}
+
+fn plain_env_var(name: &str, value: &str) -> EnvVar {
+    EnvVar {
+        name: name.to_string(),
+        value: Some(value.to_string()),
+        ..Default::default()
+    }
+}
+
+pub fn error_policy(_obj: Arc<OdooUser>, _error: &Error, _ctx: Arc<Ctx>) -> Action {
+    Action::requeue(Duration::from_secs(5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn odoo_user_and_cluster() -> (OdooUser, OdooCluster) {
+        let odoo_user = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooUser
+        metadata:
+          name: jdoe
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          clusterName: odoo
+          login: jdoe
+          name: Jane Doe
+          email: jane@example.com
+          passwordSecret: jdoe-password
+          ",
+        )
+        .unwrap();
+        let odoo = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+          ",
+        )
+        .unwrap();
+        (odoo_user, odoo)
+    }
+
+    fn container(job: &Job) -> stackable_operator::k8s_openapi::api::core::v1::Container {
+        job.spec
+            .as_ref()
+            .and_then(|spec| spec.template.spec.as_ref())
+            .map(|pod_spec| pod_spec.containers[0].clone())
+            .unwrap()
+    }
+
+    fn env_var_names(job: &Job) -> Vec<String> {
+        container(job)
+            .env
+            .unwrap_or_default()
+            .into_iter()
+            .map(|env_var| env_var.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_create_user_job() {
+        let (odoo_user, odoo) = odoo_user_and_cluster();
+        let job = build_create_user_job(&odoo_user, &odoo, &odoo.spec.image.resolve("odoo"))
+            .unwrap();
+
+        assert_eq!("jdoe-create-user", job.name_any());
+        assert_eq!(1, job.owner_references().len());
+
+        let script = container(&job).args.unwrap()[1].clone();
+        assert!(script.starts_with("set -euo pipefail; "));
+        // An existing user is replaced, so changes of the OdooUser are applied
+        let delete = script.find("odoo users delete").unwrap();
+        let create = script.find("odoo users create").unwrap();
+        assert!(delete < create);
+        // The user data is never part of the script
+        assert!(!script.contains("jane@example.com"));
+
+        let env_var_names = env_var_names(&job);
+        for name in [
+            "USER_LOGIN",
+            "USER_FIRSTNAME",
+            "USER_LASTNAME",
+            "USER_EMAIL",
+            "USER_ROLE",
+            "USER_PASSWORD",
+        ] {
+            assert!(env_var_names.contains(&name.to_string()), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_delete_user_job() {
+        let (odoo_user, odoo) = odoo_user_and_cluster();
+        let job = build_delete_user_job(&odoo_user, &odoo, &odoo.spec.image.resolve("odoo"))
+            .unwrap();
+
+        assert_eq!("jdoe-delete-user", job.name_any());
+
+        let script = container(&job).args.unwrap()[1].clone();
+        assert!(script.starts_with("set -euo pipefail; "));
+        assert!(script.ends_with(&delete_user_if_exists_command("USER_LOGIN")));
+        assert!(!script.contains("odoo users create"));
+
+        let env_var_names = env_var_names(&job);
+        assert!(env_var_names.contains(&"USER_LOGIN".to_string()));
+        assert!(!env_var_names.contains(&"USER_PASSWORD".to_string()));
+        assert!(!env_var_names.contains(&"USER_PREVIOUS_LOGIN".to_string()));
+    }
+
+    #[test]
+    fn test_changed_login_deletes_the_previous_user() {
+        let (mut odoo_user, odoo) = odoo_user_and_cluster();
+        let status = OdooUserStatus::new().creating(Some(1), "jane", None);
+        odoo_user.status = Some(status.ready());
+        let resolved_product_image = odoo.spec.image.resolve("odoo");
+
+        let job = build_create_user_job(&odoo_user, &odoo, &resolved_product_image).unwrap();
+        let script = container(&job).args.unwrap()[1].clone();
+        let delete_previous = script
+            .find(&delete_user_if_exists_command("USER_PREVIOUS_LOGIN"))
+            .unwrap();
+        let create = script.find("odoo users create").unwrap();
+        assert!(delete_previous < create);
+        let env = container(&job).env.unwrap();
+        assert!(env.contains(&plain_env_var("USER_PREVIOUS_LOGIN", "jane")));
+
+        let job = build_delete_user_job(&odoo_user, &odoo, &resolved_product_image).unwrap();
+        let script = container(&job).args.unwrap()[1].clone();
+        assert!(script.contains(&delete_user_if_exists_command("USER_PREVIOUS_LOGIN")));
+        assert!(env_var_names(&job).contains(&"USER_PREVIOUS_LOGIN".to_string()));
+
+        // Nothing is deleted twice once the user was created with the current login
+        let status = OdooUserStatus::new().creating(Some(2), "jdoe", None);
+        odoo_user.status = Some(status.ready());
+        let job = build_create_user_job(&odoo_user, &odoo, &resolved_product_image).unwrap();
+        assert!(!env_var_names(&job).contains(&"USER_PREVIOUS_LOGIN".to_string()));
+    }
+
+    #[test]
+    fn test_missing_create_job_creates_the_user_again() {
+        let status = OdooUserStatus::new().creating(Some(1), "jdoe", None);
+
+        let new_status = create_job_transition(&status, None, "jdoe-create-user").unwrap();
+        assert_eq!(OdooUserStatusCondition::Pending, new_status.condition);
+
+        // A running Job is awaited
+        let job = Job::default();
+        assert_eq!(
+            None,
+            create_job_transition(&status, Some(&job), "jdoe-create-user")
+        );
+    }
+}
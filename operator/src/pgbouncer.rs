@@ -0,0 +1,309 @@
+//! Runs PgBouncer as sidecar of the Odoo containers if requested in
+//! `clusterConfig.connectionPooling.pgbouncer`.
+//!
+//! The connection URI is only known inside the pods, so an init container derives the database
+//! server and the user list from it. The Odoo containers rewrite the URI to point to the local
+//! PgBouncer before they start.
+use sovrin_cloud_crd::{
+    cnpg::CNPG_APP_SECRET_URI_KEY,
+    credentials::{credentials_file, CREDENTIALS_DIR, CREDENTIALS_VOLUME_NAME},
+    database::{DatabaseTlsConfig, PgBouncerConfig, SQL_ALCHEMY_CONN_ENV},
+    OdooCluster,
+};
+use stackable_operator::{
+    builder::{
+        resources::ResourceRequirementsBuilder, ContainerBuilder, PodBuilder, VolumeBuilder,
+    },
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::api::core::v1::{EmptyDirVolumeSource, ResourceRequirements},
+};
+
+use crate::utils::{
+    assemble_connection_uri_command, env_var_from_secret, export_env_var_from_command,
+    export_env_var_from_file,
+};
+
+pub const PGBOUNCER_INI: &str = "pgbouncer.ini";
+pub const PGBOUNCER_HBA: &str = "pgbouncer_hba.conf";
+pub const PGBOUNCER_METRICS_PORT_NAME: &str = "pgbouncer-metrics";
+pub const PGBOUNCER_METRICS_PORT: i32 = 9127;
+
+const PGBOUNCER_PORT: u16 = 6432;
+const PGBOUNCER_CONFIG_DIR: &str = "/stackable/pgbouncer-config";
+const PGBOUNCER_GENERATED_VOLUME_NAME: &str = "pgbouncer";
+const PGBOUNCER_GENERATED_DIR: &str = "/stackable/pgbouncer";
+/// Only allowed to read the statistics from the admin console, without a password.
+const STATS_USER: &str = "pgbouncer_stats";
+
+/// Writes the database server and the credentials from the connection URI in the format of
+/// PgBouncer.
+const GENERATE_CONFIG_SCRIPT: &str = r#"
+import os
+from sqlalchemy.engine import make_url
+
+url = make_url(os.environ["AIRFLOW__CORE__SQL_ALCHEMY_CONN"])
+connect_query = ""
+if os.environ.get("DATABASE_SCHEMA"):
+    # The script is passed in single quotes, so they are written as chr(39). The schema is
+    # validated to be a lowercase identifier, it is quoted nevertheless.
+    connect_query = " connect_query=%sSET search_path TO \"%s\"%s" % (
+        chr(39), os.environ["DATABASE_SCHEMA"], chr(39)
+    )
+with open(os.path.join(os.environ["PGBOUNCER_DIR"], "databases.ini"), "w") as f:
+    f.write("[databases]\n* = host=%s port=%s%s\n" % (url.host, url.port or 5432, connect_query))
+with open(os.path.join(os.environ["PGBOUNCER_DIR"], "userlist.txt"), "w") as f:
+    f.write("\"%s\" \"%s\"\n" % (url.username, url.password))
+    f.write("\"%s\" \"\"\n" % os.environ["PGBOUNCER_STATS_USER"])
+"#;
+
+pub fn pgbouncer_config(odoo: &OdooCluster) -> Option<&PgBouncerConfig> {
+    odoo.spec
+        .cluster_config
+        .connection_pooling
+        .as_ref()
+        .and_then(|connection_pooling| connection_pooling.pgbouncer.as_ref())
+}
+
+/// The static part of the PgBouncer configuration, stored in the rolegroup ConfigMap.
+pub fn config_files(
+    config: &PgBouncerConfig,
+    database_tls: Option<&DatabaseTlsConfig>,
+) -> Vec<(&'static str, String)> {
+    let mut ini = vec![
+        "[pgbouncer]".to_string(),
+        "listen_addr = 127.0.0.1".to_string(),
+        format!("listen_port = {PGBOUNCER_PORT}"),
+        "auth_type = hba".to_string(),
+        format!("auth_hba_file = {PGBOUNCER_CONFIG_DIR}/{PGBOUNCER_HBA}"),
+        format!("auth_file = {PGBOUNCER_GENERATED_DIR}/userlist.txt"),
+        format!("pool_mode = {}", config.pool_mode),
+        format!("default_pool_size = {}", config.default_pool_size),
+        format!("max_client_conn = {}", config.max_client_conn),
+        format!("stats_users = {STATS_USER}"),
+        // The search_path of a schema is passed as options by the clients, PgBouncer sets it with
+        // the connect_query instead
+        "ignore_startup_parameters = extra_float_digits,options".to_string(),
+    ];
+    if let Some(database_tls) = database_tls {
+        // PgBouncer does not read the libpq environment variables
+        ini.extend(database_tls.env_vars().into_iter().filter_map(|env| {
+            let setting = match env.name.as_str() {
+                "PGSSLMODE" => "server_tls_sslmode",
+                "PGSSLROOTCERT" => "server_tls_ca_file",
+                "PGSSLCERT" => "server_tls_cert_file",
+                "PGSSLKEY" => "server_tls_key_file",
+                _ => return None,
+            };
+            Some(format!("{setting} = {}", env.value.unwrap_or_default()))
+        }));
+    }
+    // Must be the last line, as the included file starts the databases section
+    ini.push(format!("%include {PGBOUNCER_GENERATED_DIR}/databases.ini"));
+
+    let hba = [
+        format!("host pgbouncer {STATS_USER} 127.0.0.1/32 trust"),
+        "host all all 127.0.0.1/32 scram-sha-256".to_string(),
+    ];
+
+    vec![
+        (PGBOUNCER_INI, ini.join("\n") + "\n"),
+        (PGBOUNCER_HBA, hba.join("\n") + "\n"),
+    ]
+}
+
+/// Shell command pointing the connection URI of the Odoo containers to the local PgBouncer.
+pub fn rewrite_connection_uri_command() -> String {
+    export_env_var_from_command(
+        SQL_ALCHEMY_CONN_ENV,
+        &format!(
+            "python3 -c 'import os; \
+            from sqlalchemy.engine import make_url; \
+            print(make_url(os.environ[\"{SQL_ALCHEMY_CONN_ENV}\"])\
+            .set(host=\"127.0.0.1\", port={PGBOUNCER_PORT})\
+            .render_as_string(hide_password=False))'"
+        ),
+    )
+}
+
+/// Adds the init container generating the dynamic part of the configuration, PgBouncer itself
+/// and the exporter for its statistics. `config_volume_name` is the volume of the rolegroup
+/// ConfigMap.
+pub fn add_pgbouncer_containers(
+    pb: &mut PodBuilder,
+    odoo: &OdooCluster,
+    config: &PgBouncerConfig,
+    resolved_product_image: &ResolvedProductImage,
+    config_volume_name: &str,
+) -> Result<(), stackable_operator::error::Error> {
+    let cluster_config = &odoo.spec.cluster_config;
+
+    let mut commands = vec![format!("python3 -c '{GENERATE_CONFIG_SCRIPT}'")];
+    let mut init_container = ContainerBuilder::new("pgbouncer-config")?;
+    init_container
+        .image_from_product_image(resolved_product_image)
+        .command(vec!["/bin/bash".to_string(), "-c".to_string()])
+        .add_env_var("PGBOUNCER_DIR", PGBOUNCER_GENERATED_DIR)
+        .add_env_var("PGBOUNCER_STATS_USER", STATS_USER)
+        .add_env_var(
+            "DATABASE_SCHEMA",
+            cluster_config
+                .database
+                .schema
+                .as_deref()
+                .unwrap_or_default(),
+        )
+        .add_volume_mount(PGBOUNCER_GENERATED_VOLUME_NAME, PGBOUNCER_GENERATED_DIR)
+        .resources(small_resources());
+    let connection_credentials = cluster_config.database.connection_credentials();
+    if let Some(cnpg_app_secret) = cluster_config.database.cnpg_app_secret() {
+        init_container.add_env_vars(vec![env_var_from_secret(
+            SQL_ALCHEMY_CONN_ENV,
+            &cnpg_app_secret,
+            CNPG_APP_SECRET_URI_KEY,
+        )]);
+    } else if cluster_config.credentials_provider.is_some() {
+        let exports = connection_credentials
+            .iter()
+            .map(|(var_name, key)| export_env_var_from_file(var_name, &credentials_file(key)));
+        commands.splice(0..0, exports);
+        init_container.add_volume_mount(CREDENTIALS_VOLUME_NAME, CREDENTIALS_DIR);
+    } else {
+        let connection_secret = cluster_config
+            .database
+            .connection_secret(&cluster_config.credentials_secret);
+        init_container.add_env_vars(
+            connection_credentials
+                .iter()
+                .map(|(var_name, key)| env_var_from_secret(var_name, connection_secret, key))
+                .collect(),
+        );
+    }
+    init_container.add_env_vars(cluster_config.database.connection_env_vars());
+    if cluster_config.database.assembles_connection_uri() {
+        commands.insert(commands.len() - 1, assemble_connection_uri_command());
+    }
+    commands.insert(0, "set -euo pipefail".to_string());
+    init_container.args(vec![commands.join("; ")]);
+    pb.add_init_container(init_container.build());
+
+    let mut pgbouncer_container = ContainerBuilder::new("pgbouncer")?;
+    pgbouncer_container
+        .image(&config.image)
+        .command(vec![
+            "/opt/bitnami/pgbouncer/bin/pgbouncer".to_string(),
+            format!("{PGBOUNCER_CONFIG_DIR}/{PGBOUNCER_INI}"),
+        ])
+        .add_volume_mount(config_volume_name, PGBOUNCER_CONFIG_DIR)
+        .add_volume_mount(PGBOUNCER_GENERATED_VOLUME_NAME, PGBOUNCER_GENERATED_DIR)
+        .resources(small_resources());
+    if let Some(database_tls) = &cluster_config.database.tls {
+        pgbouncer_container.add_volume_mounts(database_tls.volume_mounts());
+    }
+    pb.add_container(pgbouncer_container.build());
+
+    pb.add_container(
+        ContainerBuilder::new("pgbouncer-exporter")?
+            .image(&config.exporter_image)
+            .args(vec![
+                format!(
+                    "--pgBouncer.connectionString=postgres://{STATS_USER}@127.0.0.1:\
+                    {PGBOUNCER_PORT}/pgbouncer?sslmode=disable"
+                ),
+                format!("--web.listen-address=:{PGBOUNCER_METRICS_PORT}"),
+            ])
+            .add_container_port(PGBOUNCER_METRICS_PORT_NAME, PGBOUNCER_METRICS_PORT)
+            .resources(small_resources())
+            .build(),
+    );
+
+    pb.add_volume(
+        VolumeBuilder::new(PGBOUNCER_GENERATED_VOLUME_NAME)
+            .empty_dir(EmptyDirVolumeSource::default())
+            .build(),
+    );
+
+    Ok(())
+}
+
+fn small_resources() -> ResourceRequirements {
+    ResourceRequirementsBuilder::new()
+        .with_cpu_request("100m")
+        .with_cpu_limit("200m")
+        .with_memory_request("64Mi")
+        .with_memory_limit("64Mi")
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_files() {
+        let config: PgBouncerConfig = serde_yaml::from_str("poolMode: transaction").unwrap();
+        let database_tls: DatabaseTlsConfig =
+            serde_yaml::from_str("caSecret: postgresql-ca").unwrap();
+
+        let files = config_files(&config, Some(&database_tls));
+        let (name, ini) = &files[0];
+        assert_eq!(&PGBOUNCER_INI, name);
+        assert!(ini.contains("pool_mode = transaction\n"));
+        assert!(ini.contains("server_tls_sslmode = require\n"));
+        assert!(ini.contains("server_tls_ca_file = /stackable/database-tls/ca/ca.crt\n"));
+        assert!(ini.ends_with("%include /stackable/pgbouncer/databases.ini\n"));
+    }
+
+    #[test]
+    fn test_init_container_fails_fast() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+        spec:
+          image:
+            productVersion: 2.6.1
+          clusterConfig:
+            credentialsProvider:
+              secretsStoreCsi:
+                secretProviderClass: vault-odoo
+            connectionPooling:
+              pgbouncer: {}
+          ",
+        )
+        .unwrap();
+        let mut pb = PodBuilder::new();
+        add_pgbouncer_containers(
+            &mut pb,
+            &odoo,
+            pgbouncer_config(&odoo).unwrap(),
+            &odoo.spec.image.resolve("odoo"),
+            "config",
+        )
+        .unwrap();
+        let pod_template = pb.build_template();
+        let init_container = &pod_template.spec.unwrap().init_containers.unwrap()[0];
+        let command = &init_container.args.as_ref().unwrap()[0];
+        assert!(command.starts_with(&format!(
+            "set -euo pipefail; {SQL_ALCHEMY_CONN_ENV}=\"$(cat "
+        )));
+        assert!(command.ends_with(&format!("python3 -c '{GENERATE_CONFIG_SCRIPT}'")));
+    }
+
+    #[test]
+    fn test_rewrite_connection_uri_command_fails_fast() {
+        // The URI is missing, so the rewrite fails before Odoo is started
+        let output = std::process::Command::new("bash")
+            .args([
+                "-c",
+                &format!("{}; echo started", rewrite_connection_uri_command()),
+            ])
+            .env_remove(SQL_ALCHEMY_CONN_ENV)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+}
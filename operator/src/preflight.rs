@@ -0,0 +1,94 @@
+//! Checks before the rollout that the credentials are complete and the database server can be
+//! resolved, so a misconfiguration is reported in the status instead of by crash-looping pods.
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::OdooCluster;
+use stackable_operator::{client::Client, k8s_openapi::api::core::v1::Secret, kube::ResourceExt};
+use tokio::net::lookup_host;
+
+use crate::{database_health, odoo_controller::credentials_env_vars};
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to retrieve the Secret [{name}]"))]
+    GetSecret {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Returns a description of every check which failed.
+pub async fn preflight_failures(client: &Client, odoo: &OdooCluster) -> Result<Vec<String>> {
+    let cluster_config = &odoo.spec.cluster_config;
+    let database = &cluster_config.database;
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let mut failures = Vec::new();
+
+    // Credentials from a provider are only available inside the pods
+    if cluster_config.credentials_provider.is_none() {
+        let credentials_secret = &cluster_config.credentials_secret;
+        let connection_secret = database.connection_secret(credentials_secret);
+        let mut required_keys = vec![(
+            credentials_secret.as_str(),
+            credentials_env_vars(odoo)
+                .map(|(_, key)| *key)
+                .collect::<Vec<_>>(),
+        )];
+        let connection_keys = database
+            .connection_credentials()
+            .iter()
+            .map(|(_, key)| *key);
+        if connection_secret == credentials_secret {
+            required_keys[0].1.extend(connection_keys);
+        } else {
+            required_keys.push((connection_secret, connection_keys.collect()));
+        }
+
+        for (name, keys) in required_keys {
+            failures.extend(missing_secret_keys(client, name, &namespace, &keys).await?);
+        }
+    }
+
+    if failures.is_empty() {
+        match database_health::database_address(client, odoo).await {
+            Ok(Some((host, port))) => {
+                if lookup_host((host.as_str(), port)).await.is_err() {
+                    failures.push(format!("the database host {host} cannot be resolved"));
+                }
+            }
+            Ok(None) => {}
+            Err(error) => failures.push(error.to_string()),
+        }
+    }
+
+    Ok(failures)
+}
+
+async fn missing_secret_keys(
+    client: &Client,
+    name: &str,
+    namespace: &str,
+    keys: &[&str],
+) -> Result<Vec<String>> {
+    let secret = match client
+        .get_opt::<Secret>(name, namespace)
+        .await
+        .context(GetSecretSnafu { name })?
+    {
+        Some(secret) => secret,
+        None => return Ok(vec![format!("the Secret {name} does not exist")]),
+    };
+    Ok(keys
+        .iter()
+        .filter(|key| {
+            !secret
+                .data
+                .as_ref()
+                .map_or(false, |data| data.contains_key(**key))
+        })
+        .map(|key| format!("the Secret {name} has no key {key}"))
+        .collect())
+}
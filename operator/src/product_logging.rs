@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
-use snafu::{OptionExt, ResultExt, Snafu};
-use sovrin_cloud_crd::STACKABLE_LOG_DIR;
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{LogFileRotation, LogFormat, OdooAuditLoggingConfig, STACKABLE_LOG_DIR};
 use stackable_operator::{
     builder::ConfigMapBuilder,
     client::Client,
@@ -10,7 +10,8 @@ use stackable_operator::{
     product_logging::{
         self,
         spec::{
-            AutomaticContainerLogConfig, ContainerLogConfig, ContainerLogConfigChoice, Logging,
+            AutomaticContainerLogConfig, ConfigMapLogConfig, ContainerLogConfig,
+            ContainerLogConfigChoice, CustomContainerLogConfig, Logging,
         },
     },
     role_utils::RoleGroupRef,
@@ -39,6 +40,10 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 const VECTOR_AGGREGATOR_CM_ENTRY: &str = "ADDRESS";
 const LOG_CONFIG_FILE: &str = "log_config.py";
 const LOG_FILE: &str = "odoo.py.json";
+/// Deliberately not ending with `.py.json`, so the audit events are not picked up by the
+/// default Vector source for Python logs.
+const AUDIT_LOG_FILE: &str = "audit.json";
+const AUDIT_LOGGER: &str = "odoo.addons.auditlog";
 
 /// Return the address of the Vector aggregator if the corresponding ConfigMap name is given in the
 /// cluster spec
@@ -73,13 +78,76 @@ pub async fn resolve_vector_aggregator_address<T: Resource>(
     Ok(vector_aggregator_address)
 }
 
+/// Return the ConfigMap of the custom log configuration of the container if one is given. It is
+/// mounted instead of the generated log configuration, so it must provide `log_config.py`.
+pub async fn resolve_custom_log_config<C, T>(
+    client: &Client,
+    cluster: &T,
+    logging: &Logging<C>,
+    container: &C,
+) -> Result<Option<ConfigMap>>
+where
+    C: Ord,
+    T: Resource,
+{
+    let Some(config_map) = custom_log_config_map_name(logging, container) else {
+        return Ok(None);
+    };
+    let namespace = cluster
+        .meta()
+        .namespace
+        .as_deref()
+        .context(ObjectHasNoNamespaceSnafu)?;
+    let custom_log_config = client
+        .get::<ConfigMap>(config_map, namespace)
+        .await
+        .context(ConfigMapNotFoundSnafu {
+            cm_name: config_map.to_string(),
+        })?;
+    validate_custom_log_config(&custom_log_config, config_map)?;
+    Ok(Some(custom_log_config))
+}
+
+/// Return the name of the ConfigMap of the custom log configuration of the container
+fn custom_log_config_map_name<'a, C: Ord>(
+    logging: &'a Logging<C>,
+    container: &C,
+) -> Option<&'a str> {
+    if let Some(ContainerLogConfig {
+        choice:
+            Some(ContainerLogConfigChoice::Custom(CustomContainerLogConfig {
+                custom: ConfigMapLogConfig { config_map },
+            })),
+    }) = logging.containers.get(container)
+    {
+        Some(config_map)
+    } else {
+        None
+    }
+}
+
+fn validate_custom_log_config(custom_log_config: &ConfigMap, cm_name: &str) -> Result<()> {
+    ensure!(
+        matches!(&custom_log_config.data, Some(data) if data.contains_key(LOG_CONFIG_FILE)),
+        MissingConfigMapEntrySnafu {
+            entry: LOG_CONFIG_FILE,
+            cm_name,
+        }
+    );
+    Ok(())
+}
+
 /// Extend the ConfigMap with logging and Vector configurations
+#[allow(clippy::too_many_arguments)]
 pub fn extend_config_map_with_log_config<C, K>(
     rolegroup: &RoleGroupRef<K>,
     vector_aggregator_address: Option<&str>,
     logging: &Logging<C>,
     main_container: &C,
     vector_container: &C,
+    audit_logging: Option<&OdooAuditLoggingConfig>,
+    log_file_rotation: Option<&LogFileRotation>,
+    log_format: LogFormat,
     cm_builder: &mut ConfigMapBuilder,
 ) -> Result<()>
     where
@@ -91,7 +159,13 @@ pub fn extend_config_map_with_log_config<C, K>(
                 }) = logging.containers.get(main_container)
     {
         let log_dir = format!("{STACKABLE_LOG_DIR}/{main_container}");
-        cm_builder.add_data(LOG_CONFIG_FILE, create_odoo_config(log_config, &log_dir));
+        let log_file_rotation = log_file_rotation.unwrap_or(&LogFileRotation::DEFAULT);
+        let mut odoo_config =
+            create_odoo_config(log_config, &log_dir, log_file_rotation, log_format);
+        if audit_logging.is_some() {
+            odoo_config.push_str(&create_audit_log_config(&log_dir, log_file_rotation));
+        }
+        cm_builder.add_data(LOG_CONFIG_FILE, odoo_config);
     }
 
     let vector_log_config = if let Some(ContainerLogConfig {
@@ -104,20 +178,34 @@ pub fn extend_config_map_with_log_config<C, K>(
     };
 
     if logging.enable_vector_agent {
+        let vector_aggregator_address =
+            vector_aggregator_address.context(MissingVectorAggregatorAddressSnafu)?;
+        let mut vector_config = product_logging::framework::create_vector_config(
+            rolegroup,
+            vector_aggregator_address,
+            vector_log_config,
+        );
+        if let Some(audit_logging) = audit_logging {
+            vector_config.push_str(&create_audit_vector_config(
+                audit_logging,
+                vector_aggregator_address,
+            ));
+        }
         cm_builder.add_data(
             product_logging::framework::VECTOR_CONFIG_FILE,
-            product_logging::framework::create_vector_config(
-                rolegroup,
-                vector_aggregator_address.context(MissingVectorAggregatorAddressSnafu)?,
-                vector_log_config,
-            ),
+            vector_config,
         );
     }
 
     Ok(())
 }
 
-fn create_odoo_config(log_config: &AutomaticContainerLogConfig, log_dir: &str) -> String {
+fn create_odoo_config(
+    log_config: &AutomaticContainerLogConfig,
+    log_dir: &str,
+    log_file_rotation: &LogFileRotation,
+    log_format: LogFormat,
+) -> String {
     let loggers_config = log_config
         .loggers
         .iter()
@@ -161,13 +249,13 @@ LOGGING_CONFIG['formatters']['json'] = {{
 LOGGING_CONFIG.setdefault('handlers', {{}})
 LOGGING_CONFIG['handlers'].setdefault('console', {{}})
 LOGGING_CONFIG['handlers']['console']['level'] = {console_log_level}
-LOGGING_CONFIG['handlers']['file'] = {{
+{console_formatter}LOGGING_CONFIG['handlers']['file'] = {{
     'class': 'logging.handlers.RotatingFileHandler',
     'level': {file_log_level},
     'formatter': 'json',
     'filename': '{log_dir}/{LOG_FILE}',
-    'maxBytes': 1048576,
-    'backupCount': 1,
+    'maxBytes': {max_bytes},
+    'backupCount': {backup_count},
 }}
 
 LOGGING_CONFIG['root'] = {{
@@ -189,5 +277,132 @@ LOGGING_CONFIG['root'] = {{
             .and_then(|file| file.level)
             .unwrap_or_default()
             .to_python_expression(),
+        console_formatter = match log_format {
+            LogFormat::Plain => "",
+            LogFormat::Json => "LOGGING_CONFIG['handlers']['console']['formatter'] = 'json'\n",
+        },
+        max_bytes = log_file_rotation.max_bytes(),
+        backup_count = log_file_rotation.backup_count,
+    )
+}
+
+/// Routes the records of the `auditlog` module into a dedicated file instead of the
+/// application log.
+fn create_audit_log_config(log_dir: &str, log_file_rotation: &LogFileRotation) -> String {
+    format!(
+        "
+LOGGING_CONFIG['handlers']['audit'] = {{
+    'class': 'logging.handlers.RotatingFileHandler',
+    'level': logging.INFO,
+    'formatter': 'json',
+    'filename': '{log_dir}/{AUDIT_LOG_FILE}',
+    'maxBytes': {max_bytes},
+    'backupCount': {backup_count},
+}}
+LOGGING_CONFIG['loggers']['{AUDIT_LOGGER}'] = {{
+    'level': logging.INFO,
+    'handlers': ['audit'],
+    'propagate': False,
+}}
+",
+        max_bytes = log_file_rotation.max_bytes(),
+        backup_count = log_file_rotation.backup_count,
+    )
+}
+
+/// Ships the audit log through its own pipeline, tagged with the configured stream, so it is
+/// not mixed with the application logs in the aggregator.
+fn create_audit_vector_config(
+    audit_logging: &OdooAuditLoggingConfig,
+    vector_aggregator_address: &str,
+) -> String {
+    format!(
+        r#"
+[sources.files_audit]
+type = "file"
+include = ["{STACKABLE_LOG_DIR}/*/{AUDIT_LOG_FILE}"]
+
+[transforms.processed_files_audit]
+inputs = ["files_audit"]
+type = "remap"
+source = '''
+. = parse_json!(string!(.message))
+.stream = "{stream}"
+'''
+
+[sinks.aggregator_audit]
+inputs = ["processed_files_audit"]
+type = "vector"
+address = "{vector_aggregator_address}"
+"#,
+        stream = audit_logging.stream,
     )
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sovrin_cloud_crd::Container;
+
+    #[test]
+    fn test_custom_log_config_map_name() {
+        let logging = Logging {
+            enable_vector_agent: false,
+            containers: [(
+                Container::Odoo,
+                ContainerLogConfig {
+                    choice: Some(ContainerLogConfigChoice::Custom(CustomContainerLogConfig {
+                        custom: ConfigMapLogConfig {
+                            config_map: "odoo-log-config".into(),
+                        },
+                    })),
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+
+        assert_eq!(
+            Some("odoo-log-config"),
+            custom_log_config_map_name(&logging, &Container::Odoo)
+        );
+        assert_eq!(
+            None,
+            custom_log_config_map_name(&logging, &Container::Vector)
+        );
+    }
+
+    #[test]
+    fn test_validate_custom_log_config() {
+        let custom_log_config: ConfigMap = serde_yaml::from_str(
+            "
+            apiVersion: v1
+            kind: ConfigMap
+            metadata:
+              name: odoo-log-config
+            data:
+              log_config.py: |
+                LOGGING_CONFIG = {}
+            ",
+        )
+        .unwrap();
+        assert!(validate_custom_log_config(&custom_log_config, "odoo-log-config").is_ok());
+
+        let mut without_log_config = custom_log_config;
+        without_log_config.data = Some(
+            [("logging.yaml".to_string(), String::new())]
+                .into_iter()
+                .collect(),
+        );
+        assert!(matches!(
+            validate_custom_log_config(&without_log_config, "odoo-log-config"),
+            Err(Error::MissingConfigMapEntry {
+                entry: LOG_CONFIG_FILE,
+                ..
+            })
+        ));
+
+        without_log_config.data = None;
+        assert!(validate_custom_log_config(&without_log_config, "odoo-log-config").is_err());
+    }
+}
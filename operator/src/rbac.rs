@@ -1,7 +1,15 @@
+use sovrin_cloud_crd::OdooCluster;
 use stackable_operator::builder::ObjectMetaBuilder;
 use stackable_operator::k8s_openapi::api::core::v1::ServiceAccount;
-use stackable_operator::k8s_openapi::api::rbac::v1::{RoleBinding, RoleRef, Subject};
+use stackable_operator::k8s_openapi::api::rbac::v1::{
+    ClusterRole, ClusterRoleBinding, PolicyRule, Role, RoleBinding, RoleRef, Subject,
+};
 use stackable_operator::kube::{Resource, ResourceExt};
+use std::collections::BTreeMap;
+
+pub const KUBERNETES_EXECUTOR: &str = "KubernetesExecutor";
+const OPERATOR_RBAC_NAME: &str = "odoo-operator";
+const PRODUCT_CLUSTER_ROLE_NAME: &str = "odoo-clusterrole";
 
 /// Obsolete: only used in the DB controller for historical reasons. Scheduled for deletion
 /// once the entire DB controller is deleted as discused here: <https://github.com/stackabletech/airflow-operator/issues/259>
@@ -41,4 +49,362 @@ pub fn build_rbac_resources<T: Resource>(
     };
 
     (service_account, role_binding)
-}
\ No newline at end of file
+}
+
+/// The service account of the pods of the cluster, also used by the jobs acting on its behalf.
+pub fn cluster_service_account_name(odoo: &OdooCluster) -> String {
+    format!("{}-serviceaccount", odoo.name_any())
+}
+
+/// Build least-privilege RBAC objects for the pods of a single [`OdooCluster`].
+///
+/// Instead of binding a shared ClusterRole, a namespaced `Role` is created per cluster which
+/// only grants what the pods actually need: nothing at all, unless the `KubernetesExecutor`
+/// is used, which manages pods on its own. The revisions synced by git-sync are fetched and
+/// annotated by the operator, so the pods cannot patch each other. If nothing is needed, neither
+/// the Role nor its binding are returned.
+pub fn build_cluster_rbac_resources(
+    odoo: &OdooCluster,
+    labels: BTreeMap<String, String>,
+) -> stackable_operator::error::OperatorResult<(ServiceAccount, Option<(Role, RoleBinding)>)> {
+    let sa_name = cluster_service_account_name(odoo);
+
+    let service_account = ServiceAccount {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(sa_name.clone())
+            .ownerreference_from_resource(odoo, None, Some(true))?
+            .with_labels(labels.clone())
+            .build(),
+        ..ServiceAccount::default()
+    };
+
+    let mut rules = Vec::new();
+    if odoo.spec.cluster_config.executor.as_deref() == Some(KUBERNETES_EXECUTOR) {
+        rules.push(PolicyRule {
+            api_groups: Some(vec!["".to_string()]),
+            resources: Some(vec!["pods".to_string()]),
+            verbs: ["create", "delete", "get", "list", "patch", "watch"]
+                .map(String::from)
+                .to_vec(),
+            ..PolicyRule::default()
+        });
+        rules.push(PolicyRule {
+            api_groups: Some(vec!["".to_string()]),
+            resources: Some(vec!["pods/log".to_string()]),
+            verbs: vec!["get".to_string()],
+            ..PolicyRule::default()
+        });
+    }
+
+    if rules.is_empty() {
+        return Ok((service_account, None));
+    }
+
+    let role_name = cluster_role_name(odoo);
+    let role = Role {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(role_name.clone())
+            .ownerreference_from_resource(odoo, None, Some(true))?
+            .with_labels(labels.clone())
+            .build(),
+        rules: Some(rules),
+    };
+
+    let role_binding = RoleBinding {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            // Not `{cluster_name}-rolebinding`, which binds the shared ClusterRole in existing
+            // clusters. The roleRef of a binding is immutable, so the old binding is replaced
+            // by this one and deleted as orphan.
+            .name(format!("{role_name}-binding"))
+            .ownerreference_from_resource(odoo, None, Some(true))?
+            .with_labels(labels)
+            .build(),
+        role_ref: RoleRef {
+            kind: "Role".to_string(),
+            name: role_name,
+            api_group: "rbac.authorization.k8s.io".to_string(),
+        },
+        subjects: Some(vec![Subject {
+            kind: "ServiceAccount".to_string(),
+            name: sa_name,
+            namespace: odoo.namespace(),
+            ..Subject::default()
+        }]),
+    };
+
+    Ok((service_account, Some((role, role_binding))))
+}
+
+/// The name of the Role granting the permissions the pods of the cluster need.
+pub fn cluster_role_name(odoo: &OdooCluster) -> String {
+    format!("{}-role", odoo.name_any())
+}
+
+/// Renders the RBAC objects the operator itself needs as multi-document YAML.
+///
+/// With a `watch_namespace` the namespaced permissions are granted by a Role in that namespace,
+/// otherwise by a ClusterRole. Cluster-scoped resources like AuthenticationClasses always need a
+/// ClusterRole.
+pub fn operator_rbac_manifests(
+    watch_namespace: Option<&str>,
+    service_account_name: &str,
+    service_account_namespace: &str,
+) -> Result<String, serde_yaml::Error> {
+    let subjects = Some(vec![Subject {
+        kind: "ServiceAccount".to_string(),
+        name: service_account_name.to_string(),
+        namespace: Some(service_account_namespace.to_string()),
+        ..Subject::default()
+    }]);
+    let cluster_rules = vec![
+        policy_rule(
+            "authentication.stackable.tech",
+            &["authenticationclasses"],
+            &["get", "list", "watch"],
+        ),
+        // Only needed with a namespace label selector
+        policy_rule("", &["namespaces"], &["get", "list", "watch"]),
+    ];
+    let cluster_role_ref = RoleRef {
+        kind: "ClusterRole".to_string(),
+        name: format!("{OPERATOR_RBAC_NAME}-clusterrole"),
+        api_group: "rbac.authorization.k8s.io".to_string(),
+    };
+
+    let mut documents = vec![serde_yaml::to_string(&ClusterRole {
+        metadata: ObjectMetaBuilder::new().name(PRODUCT_CLUSTER_ROLE_NAME).build(),
+        rules: Some(vec![policy_rule("", &["events"], &["create", "patch"])]),
+        ..ClusterRole::default()
+    })?];
+
+    match watch_namespace {
+        Some(namespace) => {
+            let role_name = format!("{OPERATOR_RBAC_NAME}-role");
+            documents.push(serde_yaml::to_string(&ClusterRole {
+                metadata: ObjectMetaBuilder::new().name(&cluster_role_ref.name).build(),
+                rules: Some(cluster_rules),
+                ..ClusterRole::default()
+            })?);
+            documents.push(serde_yaml::to_string(&ClusterRoleBinding {
+                metadata: ObjectMetaBuilder::new()
+                    .name(format!("{OPERATOR_RBAC_NAME}-clusterrolebinding"))
+                    .build(),
+                role_ref: cluster_role_ref,
+                subjects: subjects.clone(),
+            })?);
+            documents.push(serde_yaml::to_string(&Role {
+                metadata: ObjectMetaBuilder::new()
+                    .name(&role_name)
+                    .namespace(namespace)
+                    .build(),
+                rules: Some(operator_namespaced_rules()),
+            })?);
+            documents.push(serde_yaml::to_string(&RoleBinding {
+                metadata: ObjectMetaBuilder::new()
+                    .name(format!("{OPERATOR_RBAC_NAME}-rolebinding"))
+                    .namespace(namespace)
+                    .build(),
+                role_ref: RoleRef {
+                    kind: "Role".to_string(),
+                    name: role_name,
+                    api_group: "rbac.authorization.k8s.io".to_string(),
+                },
+                subjects,
+            })?);
+        }
+        None => {
+            let mut rules = cluster_rules;
+            rules.extend(operator_namespaced_rules());
+            documents.push(serde_yaml::to_string(&ClusterRole {
+                metadata: ObjectMetaBuilder::new().name(&cluster_role_ref.name).build(),
+                rules: Some(rules),
+                ..ClusterRole::default()
+            })?);
+            documents.push(serde_yaml::to_string(&ClusterRoleBinding {
+                metadata: ObjectMetaBuilder::new()
+                    .name(format!("{OPERATOR_RBAC_NAME}-clusterrolebinding"))
+                    .build(),
+                role_ref: cluster_role_ref,
+                subjects,
+            })?);
+        }
+    }
+
+    Ok(documents.join("---\n"))
+}
+
+/// The permissions the operator needs in the namespaces of the clusters it manages.
+fn operator_namespaced_rules() -> Vec<PolicyRule> {
+    const ALL: &[&str] = &["create", "delete", "get", "list", "patch", "watch"];
+    vec![
+        policy_rule("", &["configmaps", "services", "serviceaccounts"], ALL),
+        // Secrets are only written for the operator-managed secret key
+        policy_rule("", &["secrets"], &["create", "get", "list", "patch", "watch"]),
+        policy_rule("", &["events"], &["create", "patch"]),
+        policy_rule("events.k8s.io", &["events"], &["create", "patch"]),
+        // The operator can only grant the pod permissions needed by the KubernetesExecutor if
+        // it holds them itself
+        policy_rule("", &["pods"], ALL),
+        policy_rule("", &["pods/log"], &["get"]),
+        // The health of git-sync is queried via the API server
+        policy_rule("", &["pods/proxy"], &["get"]),
+        policy_rule("apps", &["deployments", "statefulsets"], ALL),
+        policy_rule("batch", &["jobs"], ALL),
+        policy_rule("networking.k8s.io", &["ingresses", "networkpolicies"], ALL),
+        policy_rule("rbac.authorization.k8s.io", &["roles", "rolebindings"], ALL),
+        policy_rule("postgresql.cnpg.io", &["clusters"], &["get", "list", "watch"]),
+        // Referenced S3 buckets and connections are resolved for the backup
+        policy_rule(
+            "s3.stackable.tech",
+            &["s3buckets", "s3connections"],
+            &["get"],
+        ),
+        PolicyRule {
+            resource_names: Some(vec![PRODUCT_CLUSTER_ROLE_NAME.to_string()]),
+            ..policy_rule("rbac.authorization.k8s.io", &["clusterroles"], &["bind"])
+        },
+        policy_rule(
+            "odoo.stackable.tech",
+            &["odooclusters", "odoodatabases", "odoodbs", "odoousers"],
+            ALL,
+        ),
+        policy_rule(
+            "odoo.stackable.tech",
+            &[
+                "odooclusters/status",
+                "odoodatabases/status",
+                "odoodbs/status",
+                "odoousers/status",
+            ],
+            &["patch"],
+        ),
+    ]
+}
+
+fn policy_rule(api_group: &str, resources: &[&str], verbs: &[&str]) -> PolicyRule {
+    PolicyRule {
+        api_groups: Some(vec![api_group.to_string()]),
+        resources: Some(resources.iter().map(|r| r.to_string()).collect()),
+        verbs: verbs.iter().map(|v| v.to_string()).collect(),
+        ..PolicyRule::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn odoo(cluster_config: &str) -> OdooCluster {
+        serde_yaml::from_str(&format!(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            {cluster_config}
+          "
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_no_role_without_permissions() {
+        let (service_account, role_and_binding) =
+            build_cluster_rbac_resources(&odoo(""), BTreeMap::new()).unwrap();
+
+        assert_eq!("odoo-serviceaccount", service_account.name_any());
+        assert_eq!(None, role_and_binding);
+    }
+
+    #[test]
+    fn test_no_role_for_git_sync() {
+        // The operator annotates the pods with the synced revisions
+        let (_, role_and_binding) = build_cluster_rbac_resources(
+            &odoo(
+                "addonsGitSync:
+              - repo: https://github.com/OCA/server-tools",
+            ),
+            BTreeMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(None, role_and_binding);
+    }
+
+    #[test]
+    fn test_role_for_kubernetes_executor() {
+        let (service_account, role_and_binding) = build_cluster_rbac_resources(
+            &odoo("executor: KubernetesExecutor"),
+            BTreeMap::new(),
+        )
+        .unwrap();
+        let (role, role_binding) = role_and_binding.unwrap();
+
+        assert_eq!("odoo-role", role.name_any());
+        assert_eq!(
+            vec![
+                (vec!["pods".to_string()], 6),
+                (vec!["pods/log".to_string()], 1)
+            ],
+            role.rules
+                .unwrap_or_default()
+                .into_iter()
+                .map(|rule| (rule.resources.unwrap_or_default(), rule.verbs.len()))
+                .collect::<Vec<_>>()
+        );
+
+        // A new name, because the roleRef of the former `odoo-rolebinding` cannot be changed
+        assert_eq!("odoo-role-binding", role_binding.name_any());
+        assert_eq!("Role", role_binding.role_ref.kind);
+        assert_eq!("odoo-role", role_binding.role_ref.name);
+        assert_eq!(
+            Some(service_account.name_any()),
+            role_binding
+                .subjects
+                .and_then(|subjects| subjects.into_iter().next())
+                .map(|subject| subject.name)
+        );
+    }
+
+    #[test]
+    fn test_operator_can_watch_namespaces() {
+        // Namespaces are cluster-scoped, so they are granted by the ClusterRole even if the
+        // operator only watches a single namespace
+        let manifests = operator_rbac_manifests(Some("odoo"), "odoo-operator", "default").unwrap();
+        let cluster_role: ClusterRole = serde_yaml::from_str(
+            manifests
+                .split("---\n")
+                .find(|document| {
+                    document.contains("kind: ClusterRole\n")
+                        && document.contains("name: odoo-operator-clusterrole\n")
+                })
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(cluster_role.rules.unwrap_or_default().iter().any(|rule| {
+            rule.resources.as_deref() == Some(&["namespaces".to_string()])
+                && rule.verbs.contains(&"watch".to_string())
+        }));
+    }
+
+    #[test]
+    fn test_operator_can_resolve_s3_references() {
+        assert!(operator_namespaced_rules().iter().any(|rule| {
+            rule.api_groups.as_deref() == Some(&["s3.stackable.tech".to_string()])
+                && rule.resources.as_deref()
+                    == Some(&["s3buckets".to_string(), "s3connections".to_string()])
+                && rule.verbs == vec!["get".to_string()]
+        }));
+    }
+}
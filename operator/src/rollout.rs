@@ -0,0 +1,439 @@
+//! Rolls out a new product image role by role. The database is migrated first by
+//! [`crate::upgrade`], then the workers and schedulers, which run the background jobs and the
+//! cron jobs, and the webservers last. Until then the webservers keep serving the previous
+//! version, as the partition of their rolling update holds back all of their pods. The progress
+//! is reported as `status.rollout`.
+//!
+//! If the database is upgraded for the new image, the webservers are stopped instead from the
+//! start of the upgrade until they are rolled out, as the previous version must not serve the
+//! upgraded schema. This maintenance is reported as `status.rollout.maintenance`.
+//!
+//! Rolegroups with the `canary` rollout strategy are rolled out before all others, regardless of
+//! their role. The other rolegroups are held back the same way until the pods of the canaries
+//! are ready for their soak time, which is reported as `status.canaries`.
+//!
+//! Rolegroups running as Deployment are held back by pausing them, see [`crate::workload`].
+use std::time::Duration;
+
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{
+    CanaryStatus, Container, OdooCluster, OdooRole, RolloutStatus, UpgradeCondition, WorkloadType,
+    APP_NAME,
+};
+use stackable_operator::{
+    client::Client,
+    k8s_openapi::{
+        api::{
+            apps::v1::{
+                Deployment, RollingUpdateStatefulSetStrategy, StatefulSet,
+                StatefulSetUpdateStrategy,
+            },
+            core::v1::PodTemplateSpec,
+        },
+        apimachinery::pkg::apis::meta::v1::{LabelSelector, Time},
+        chrono::Utc,
+        serde_json,
+    },
+    kube::ResourceExt,
+    labels::{APP_COMPONENT_LABEL, APP_INSTANCE_LABEL, APP_NAME_LABEL},
+};
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to retrieve the workload [{name}]"))]
+    GetWorkload {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to list the webservers to stop them during the upgrade"))]
+    ListWebservers {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("failed to stop the webserver [{name}] during the upgrade"))]
+    StopWebserver {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Returns the status of the rollout if the webserver rolegroup must be held back, because it
+/// rolls out a new image while the other roles did not finish their rollout yet.
+pub async fn webserver_rollout(
+    client: &Client,
+    odoo: &OdooCluster,
+    statefulset: &StatefulSet,
+    workload_type: WorkloadType,
+    others_rolled_out: bool,
+) -> Result<Option<RolloutStatus>> {
+    let Some(image) = statefulset
+        .spec
+        .as_ref()
+        .and_then(|spec| odoo_image(&spec.template))
+    else {
+        return Ok(None);
+    };
+    if others_rolled_out {
+        return Ok(None);
+    }
+
+    // The held back workload already contains the new image, so the rollout is continued
+    // according to the status
+    let previous_rollout = odoo
+        .status
+        .as_ref()
+        .and_then(|status| status.rollout.as_ref())
+        .filter(|rollout| rollout.image == image);
+    if let Some(rollout) = previous_rollout {
+        return Ok(Some(rollout.clone()));
+    }
+
+    let name = statefulset.name_any();
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let current_template = match workload_type {
+        WorkloadType::StatefulSet => client
+            .get_opt::<StatefulSet>(&name, &namespace)
+            .await
+            .context(GetWorkloadSnafu { name })?
+            .and_then(|statefulset| statefulset.spec)
+            .map(|spec| spec.template),
+        WorkloadType::Deployment => client
+            .get_opt::<Deployment>(&name, &namespace)
+            .await
+            .context(GetWorkloadSnafu { name })?
+            .and_then(|deployment| deployment.spec)
+            .map(|spec| spec.template),
+    };
+    let current_image = current_template
+        .as_ref()
+        .and_then(odoo_image)
+        .map(str::to_string);
+    // A new workload has no pods to hold back
+    Ok(current_image
+        .filter(|current_image| current_image != image)
+        .map(|_| RolloutStatus {
+            image: image.to_string(),
+            started_at: Time(Utc::now()),
+            maintenance: false,
+        }))
+}
+
+/// Whether the webservers are stopped while the upgrade is in this condition. The database is
+/// not changed while it is backed up, but may be partially upgraded after a failure.
+pub fn requires_maintenance(condition: &UpgradeCondition) -> bool {
+    !matches!(
+        condition,
+        UpgradeCondition::BackingUp | UpgradeCondition::BackupFailed
+    )
+}
+
+/// Stops the webservers while the database is upgraded for the `image` and returns the status
+/// of the rollout, which keeps them stopped until they are rolled out with the image.
+pub async fn stop_webservers(
+    client: &Client,
+    odoo: &OdooCluster,
+    image: &str,
+) -> Result<RolloutStatus> {
+    let rollout = maintenance_rollout(odoo, image);
+    if odoo.spec.cluster_operation.reconciliation_paused {
+        return Ok(rollout);
+    }
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let selector = webserver_selector(odoo);
+    let stop = serde_json::json!({ "spec": { "replicas": 0 } });
+    let statefulsets = client
+        .list_with_label_selector::<StatefulSet>(&namespace, &selector)
+        .await
+        .context(ListWebserversSnafu)?;
+    for statefulset in statefulsets {
+        if statefulset.spec.as_ref().and_then(|spec| spec.replicas) != Some(0) {
+            tracing::info!(name = statefulset.name_any(), "Stopping the webserver");
+            client
+                .merge_patch(&statefulset, &stop)
+                .await
+                .context(StopWebserverSnafu {
+                    name: statefulset.name_any(),
+                })?;
+        }
+    }
+    let deployments = client
+        .list_with_label_selector::<Deployment>(&namespace, &selector)
+        .await
+        .context(ListWebserversSnafu)?;
+    for deployment in deployments {
+        if deployment.spec.as_ref().and_then(|spec| spec.replicas) != Some(0) {
+            tracing::info!(name = deployment.name_any(), "Stopping the webserver");
+            client
+                .merge_patch(&deployment, &stop)
+                .await
+                .context(StopWebserverSnafu {
+                    name: deployment.name_any(),
+                })?;
+        }
+    }
+    Ok(rollout)
+}
+
+/// The rollout of the `image` in maintenance. The start of a maintenance which is already
+/// reported is kept.
+fn maintenance_rollout(odoo: &OdooCluster, image: &str) -> RolloutStatus {
+    let started_at = odoo
+        .status
+        .as_ref()
+        .and_then(|status| status.rollout.as_ref())
+        .filter(|rollout| rollout.image == image && rollout.maintenance)
+        .map(|rollout| rollout.started_at.clone());
+    RolloutStatus {
+        image: image.to_string(),
+        started_at: started_at.unwrap_or_else(|| Time(Utc::now())),
+        maintenance: true,
+    }
+}
+
+/// Selects the workloads of all webserver rolegroups of the cluster.
+fn webserver_selector(odoo: &OdooCluster) -> LabelSelector {
+    LabelSelector {
+        match_labels: Some(
+            [
+                (APP_NAME_LABEL, APP_NAME.to_string()),
+                (APP_INSTANCE_LABEL, odoo.name_any()),
+                (APP_COMPONENT_LABEL, OdooRole::Webserver.to_string()),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+        ),
+        ..LabelSelector::default()
+    }
+}
+
+/// The status of a canary rolegroup after its workload `name` was applied. The time since when
+/// the pods are ready is kept as long as the revision does not change, so a canary is soaked once
+/// after every change, and once when it is observed the first time.
+pub fn canary_status(
+    odoo: &OdooCluster,
+    name: &str,
+    revision: Option<String>,
+    rolled_out: bool,
+) -> Option<CanaryStatus> {
+    let revision = revision?;
+    let ready_since = if rolled_out {
+        let previous_ready_since = odoo
+            .status
+            .as_ref()
+            .and_then(|status| status.canaries.get(name))
+            .filter(|canary| canary.revision == revision)
+            .and_then(|canary| canary.ready_since.clone());
+        Some(previous_ready_since.unwrap_or_else(|| Time(Utc::now())))
+    } else {
+        None
+    };
+    Some(CanaryStatus {
+        revision,
+        ready_since,
+    })
+}
+
+/// Whether the pods of the canary are ready for at least the soak time.
+pub fn is_soaked(canary: Option<&CanaryStatus>, soak_time: Duration) -> bool {
+    match canary.and_then(|canary| canary.ready_since.as_ref()) {
+        Some(ready_since) => (Utc::now() - ready_since.0).to_std().unwrap_or_default() >= soak_time,
+        None => false,
+    }
+}
+
+/// Keeps all pods of the StatefulSet at their current revision. StatefulSets with the `OnDelete`
+/// update strategy are left as they are, as their pods are only updated manually anyway.
+pub fn hold_back(statefulset: &mut StatefulSet) {
+    if let Some(spec) = statefulset.spec.as_mut() {
+        let on_delete = spec
+            .update_strategy
+            .as_ref()
+            .and_then(|update_strategy| update_strategy.type_.as_deref())
+            == Some("OnDelete");
+        if on_delete {
+            return;
+        }
+        spec.update_strategy = Some(StatefulSetUpdateStrategy {
+            type_: Some("RollingUpdate".to_string()),
+            rolling_update: Some(RollingUpdateStatefulSetStrategy {
+                partition: Some(spec.replicas.unwrap_or(1)),
+                ..RollingUpdateStatefulSetStrategy::default()
+            }),
+        });
+    }
+}
+
+/// Whether all pods of the StatefulSet run its current revision and are ready.
+pub fn is_rolled_out(statefulset: &StatefulSet) -> bool {
+    let (Some(spec), Some(status)) = (&statefulset.spec, &statefulset.status) else {
+        return false;
+    };
+    status.observed_generation >= statefulset.metadata.generation
+        && status.current_revision == status.update_revision
+        && status.ready_replicas.unwrap_or_default() == spec.replicas.unwrap_or(1)
+}
+
+fn odoo_image(template: &PodTemplateSpec) -> Option<&str> {
+    template
+        .spec
+        .as_ref()?
+        .containers
+        .iter()
+        .find(|container| container.name == Container::Odoo.to_string())?
+        .image
+        .as_deref()
+}
+
+#[cfg(test)]
+mod tests {
+    use stackable_operator::k8s_openapi::{
+        api::apps::v1::{StatefulSetSpec, StatefulSetStatus},
+        chrono,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_is_rolled_out() {
+        let mut statefulset = StatefulSet {
+            spec: Some(StatefulSetSpec {
+                replicas: Some(2),
+                ..StatefulSetSpec::default()
+            }),
+            status: Some(StatefulSetStatus {
+                current_revision: Some("odoo-1".to_string()),
+                update_revision: Some("odoo-2".to_string()),
+                ready_replicas: Some(2),
+                ..StatefulSetStatus::default()
+            }),
+            ..StatefulSet::default()
+        };
+        assert!(!is_rolled_out(&statefulset));
+
+        if let Some(status) = statefulset.status.as_mut() {
+            status.current_revision = Some("odoo-2".to_string());
+        }
+        assert!(is_rolled_out(&statefulset));
+
+        hold_back(&mut statefulset);
+        assert_eq!(
+            Some(2),
+            statefulset
+                .spec
+                .and_then(|spec| spec.update_strategy)
+                .and_then(|update_strategy| update_strategy.rolling_update)
+                .and_then(|rolling_update| rolling_update.partition)
+        );
+    }
+
+    #[test]
+    fn test_is_soaked() {
+        let soak_time = Duration::from_secs(300);
+        let canary = |ready_for: Option<i64>| CanaryStatus {
+            revision: "odoo-2".to_string(),
+            ready_since: ready_for
+                .map(|seconds| Time(Utc::now() - chrono::Duration::seconds(seconds))),
+        };
+        assert!(!is_soaked(None, soak_time));
+        assert!(!is_soaked(Some(&canary(None)), soak_time));
+        assert!(!is_soaked(Some(&canary(Some(60))), soak_time));
+        assert!(is_soaked(Some(&canary(Some(600))), soak_time));
+    }
+
+    #[test]
+    fn test_hold_back() {
+        let statefulset = |type_: &str| StatefulSet {
+            spec: Some(StatefulSetSpec {
+                replicas: Some(3),
+                update_strategy: Some(StatefulSetUpdateStrategy {
+                    type_: Some(type_.to_string()),
+                    rolling_update: None,
+                }),
+                ..StatefulSetSpec::default()
+            }),
+            ..StatefulSet::default()
+        };
+
+        let mut rolling = statefulset("RollingUpdate");
+        hold_back(&mut rolling);
+        assert_eq!(
+            Some(3),
+            rolling
+                .spec
+                .and_then(|spec| spec.update_strategy)
+                .and_then(|update_strategy| update_strategy.rolling_update)
+                .and_then(|rolling_update| rolling_update.partition)
+        );
+
+        // The pods are only replaced manually anyway
+        let mut on_delete = statefulset("OnDelete");
+        hold_back(&mut on_delete);
+        assert_eq!(statefulset("OnDelete"), on_delete);
+    }
+
+    #[test]
+    fn test_requires_maintenance() {
+        assert!(!requires_maintenance(&UpgradeCondition::BackingUp));
+        assert!(!requires_maintenance(&UpgradeCondition::BackupFailed));
+        assert!(requires_maintenance(&UpgradeCondition::Upgrading));
+        assert!(requires_maintenance(&UpgradeCondition::UpgradingModules));
+        // The schema may be partially upgraded
+        assert!(requires_maintenance(&UpgradeCondition::Failed));
+        assert!(requires_maintenance(&UpgradeCondition::ModuleUpgradeFailed));
+    }
+
+    #[test]
+    fn test_maintenance_rollout() {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooCluster
+            metadata:
+              name: odoo
+              namespace: default
+            spec:
+              image:
+                productVersion: 2.6.1
+                stackableVersion: 0.0.0-dev
+              clusterConfig:
+                credentialsSecret: simple-odoo-credentials
+            status:
+              rollout:
+                image: odoo:17.0
+                startedAt: 2024-01-01T00:00:00Z
+            ",
+        )
+        .unwrap();
+        let started_at = |odoo: &OdooCluster| {
+            odoo.status
+                .as_ref()
+                .and_then(|status| status.rollout.as_ref())
+                .map(|rollout| rollout.started_at.clone())
+        };
+
+        // The maintenance starts if the webservers were only held back
+        let rollout = maintenance_rollout(&odoo, "odoo:17.0");
+        assert!(rollout.maintenance);
+        assert_ne!(started_at(&odoo), Some(rollout.started_at.clone()));
+
+        // and is continued afterwards
+        if let Some(status) = odoo.status.as_mut() {
+            status.rollout = Some(rollout);
+        }
+        let rollout = maintenance_rollout(&odoo, "odoo:17.0");
+        assert_eq!(started_at(&odoo), Some(rollout.started_at));
+        assert_eq!("odoo:18.0", maintenance_rollout(&odoo, "odoo:18.0").image);
+
+        assert_eq!(
+            Some(&"webserver".to_string()),
+            webserver_selector(&odoo)
+                .match_labels
+                .as_ref()
+                .and_then(|labels| labels.get(APP_COMPONENT_LABEL))
+        );
+    }
+}
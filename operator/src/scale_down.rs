@@ -0,0 +1,641 @@
+//! Scales down worker rolegroups without killing the jobs running on the removed pods.
+//!
+//! The pods which are removed are annotated with `DRAIN_ANNOTATION` first. The annotation is
+//! mounted into the pods via the downward API, so the worker stops consuming, finishes its running
+//! jobs and then fails the readiness probe of its container. Once that is observed on a pod which
+//! was annotated before, the pod is annotated with `DRAINED_ANNOTATION`, so a pod which is not
+//! ready for another reason, e.g. while it restarts, is not taken for drained. The replicas are
+//! only reduced once all removed pods are drained or `clusterConfig.workerDrainTimeoutSeconds` has
+//! passed. The progress is reported as `status.scalingDown.<statefulset>` and as `Progressing`
+//! condition.
+//!
+//! If the replicas are raised again before the scale-down completed, the annotations are removed
+//! from the pods which are kept, and their workers are started again.
+use serde::Serialize;
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{Container, OdooCluster, ScalingDownStatus};
+use stackable_operator::{
+    client::Client,
+    k8s_openapi::{
+        api::{
+            apps::v1::StatefulSet,
+            core::v1::{
+                DownwardAPIVolumeFile, DownwardAPIVolumeSource, ExecAction, ObjectFieldSelector,
+                Pod, Probe, Volume,
+            },
+        },
+        apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time},
+        chrono::Utc,
+        serde_json,
+    },
+    kube::ResourceExt,
+    status::condition::{
+        ClusterCondition, ClusterConditionSet, ClusterConditionStatus, ClusterConditionType,
+        ConditionBuilder,
+    },
+};
+use std::collections::BTreeMap;
+
+pub const DRAIN_ANNOTATION: &str = "odoo.sovrin.cloud/drain";
+/// When the worker of the pod was observed to have finished its jobs
+pub const DRAINED_ANNOTATION: &str = "odoo.sovrin.cloud/drained";
+pub const DRAIN_VOLUME_NAME: &str = "drain";
+pub const DRAIN_DIR: &str = "/stackable/drain";
+const DRAIN_FILE: &str = "drain";
+/// Created by the worker container once its worker finished the running jobs
+const DRAINED_FILE: &str = "/tmp/drained";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to retrieve the StatefulSet [{name}]"))]
+    GetStatefulSet {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to retrieve the pod [{name}]"))]
+    GetPod {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to annotate the pod [{name}] for draining"))]
+    AnnotatePod {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to list the pods of the StatefulSet [{name}]"))]
+    ListPods {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to remove the drain annotations from the pod [{name}]"))]
+    UndrainPod {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Serialize)]
+struct MetadataPatch {
+    metadata: ObjectMeta,
+}
+
+/// Drains the pods which are removed if the replicas of the StatefulSet are reduced. Returns the
+/// status of the scale-down as long as the current replicas must be kept.
+pub async fn reconcile_scale_down(
+    client: &Client,
+    odoo: &OdooCluster,
+    statefulset: &StatefulSet,
+) -> Result<Option<ScalingDownStatus>> {
+    let name = statefulset.name_any();
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let target_replicas = statefulset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    let scaling_down = odoo
+        .status
+        .as_ref()
+        .map_or(false, |status| status.scaling_down.contains_key(&name));
+    if scaling_down {
+        undrain_kept_pods(client, statefulset, &namespace, target_replicas).await?;
+    }
+    let Some(replicas) = client
+        .get_opt::<StatefulSet>(&name, &namespace)
+        .await
+        .context(GetStatefulSetSnafu { name: &name })?
+        .and_then(|current| current.spec)
+        .and_then(|spec| spec.replicas)
+    else {
+        return Ok(None);
+    };
+    if replicas <= target_replicas {
+        return Ok(None);
+    }
+
+    let mut removed_pods = Vec::new();
+    for ordinal in target_replicas..replicas {
+        let pod_name = format!("{name}-{ordinal}");
+        // Pods which are already gone do not need to be drained
+        if let Some(pod) = client
+            .get_opt::<Pod>(&pod_name, &namespace)
+            .await
+            .context(GetPodSnafu { name: &pod_name })?
+        {
+            removed_pods.push(pod);
+        }
+    }
+
+    let plan = plan_scale_down(
+        odoo,
+        &name,
+        replicas,
+        target_replicas,
+        &removed_pods,
+        Time(Utc::now()),
+    );
+    for (pod, annotation) in removed_pods.iter().zip(plan.annotations) {
+        let Some((key, value)) = annotation else {
+            continue;
+        };
+        let pod_name = pod.name_any();
+        let patch = MetadataPatch {
+            metadata: ObjectMeta {
+                annotations: Some([(key.to_string(), value)].into()),
+                ..ObjectMeta::default()
+            },
+        };
+        client
+            .merge_patch(pod, patch)
+            .await
+            .context(AnnotatePodSnafu { name: &pod_name })?;
+        if key == DRAIN_ANNOTATION {
+            tracing::info!(pod = pod_name, "Draining the pod before the scale-down");
+        } else {
+            tracing::info!(pod = pod_name, "The pod is drained");
+        }
+    }
+    Ok(plan.status)
+}
+
+/// Removes the drain annotations from the pods which are kept after all, because the replicas
+/// were raised again before the scale-down completed. Their workers restart once the annotation
+/// is gone, see [`drain_command`].
+async fn undrain_kept_pods(
+    client: &Client,
+    statefulset: &StatefulSet,
+    namespace: &str,
+    target_replicas: i32,
+) -> Result<()> {
+    let name = statefulset.name_any();
+    let Some(selector) = statefulset.spec.as_ref().map(|spec| spec.selector.clone()) else {
+        return Ok(());
+    };
+    let pods = client
+        .list_with_label_selector::<Pod>(namespace, &selector)
+        .await
+        .context(ListPodsSnafu { name: &name })?;
+    let patch = serde_json::json!({
+        "metadata": {
+            "annotations": {
+                DRAIN_ANNOTATION: null,
+                DRAINED_ANNOTATION: null,
+            }
+        }
+    });
+    for pod in kept_drained_pods(&pods, &name, target_replicas) {
+        let pod_name = pod.name_any();
+        client
+            .merge_patch(pod, &patch)
+            .await
+            .context(UndrainPodSnafu { name: &pod_name })?;
+        tracing::info!(pod = pod_name, "Cancelled the draining of the pod");
+    }
+    Ok(())
+}
+
+/// The pods of the StatefulSet `name` with an ordinal below `target_replicas`, which carry one of
+/// the drain annotations.
+fn kept_drained_pods<'a>(pods: &'a [Pod], name: &str, target_replicas: i32) -> Vec<&'a Pod> {
+    pods.iter()
+        .filter(|pod| {
+            pod.name_any()
+                .strip_prefix(&format!("{name}-"))
+                .and_then(|ordinal| ordinal.parse::<i32>().ok())
+                .map_or(false, |ordinal| ordinal < target_replicas)
+        })
+        .filter(|pod| {
+            let annotations = pod.annotations();
+            annotations.contains_key(DRAIN_ANNOTATION)
+                || annotations.contains_key(DRAINED_ANNOTATION)
+        })
+        .collect()
+}
+
+/// Reports the StatefulSets which are scaling down as `Progressing` condition.
+pub struct ScalingDownConditionBuilder<'a>(pub &'a BTreeMap<String, ScalingDownStatus>);
+
+impl ConditionBuilder for ScalingDownConditionBuilder<'_> {
+    fn build_conditions(&self) -> ClusterConditionSet {
+        let (status, message) = if self.0.is_empty() {
+            (
+                ClusterConditionStatus::False,
+                "No worker is scaling down.".to_string(),
+            )
+        } else {
+            let statefulsets = self
+                .0
+                .iter()
+                .map(|(name, scaling_down)| {
+                    format!(
+                        "{name} from {} to {} replicas",
+                        scaling_down.replicas, scaling_down.target_replicas
+                    )
+                })
+                .collect::<Vec<_>>();
+            (
+                ClusterConditionStatus::True,
+                format!(
+                    "Draining the removed workers to scale down {}",
+                    statefulsets.join(", ")
+                ),
+            )
+        };
+
+        let cond = ClusterCondition {
+            reason: Some("ScalingDown".to_string()),
+            message: Some(message),
+            status,
+            type_: ClusterConditionType::Progressing,
+            last_transition_time: None,
+            last_update_time: None,
+        };
+
+        vec![cond].into()
+    }
+}
+
+/// The decisions of a reconciliation of a scale-down.
+#[derive(Debug, PartialEq)]
+struct ScaleDownPlan {
+    /// The annotation to add to each of the removed pods, if any
+    annotations: Vec<Option<(&'static str, String)>>,
+    /// Set as long as the current replicas must be kept
+    status: Option<ScalingDownStatus>,
+}
+
+/// The progress of draining a pod which is removed.
+#[derive(Debug, PartialEq)]
+enum DrainState {
+    NotRequested,
+    Draining,
+    /// The worker finished its jobs, which is not recorded on the pod yet
+    Finished,
+    Drained,
+}
+
+fn plan_scale_down(
+    odoo: &OdooCluster,
+    name: &str,
+    replicas: i32,
+    target_replicas: i32,
+    removed_pods: &[Pod],
+    now: Time,
+) -> ScaleDownPlan {
+    // A scale-down which is already in progress keeps its start time, even if the target changed
+    let started_at = odoo
+        .status
+        .as_ref()
+        .and_then(|status| status.scaling_down.get(name))
+        .map(|scaling_down| scaling_down.started_at.clone())
+        .unwrap_or_else(|| now.clone());
+    let elapsed = (now.0 - started_at.0).to_std().unwrap_or_default();
+
+    let mut drained = true;
+    let mut annotations = Vec::new();
+    for pod in removed_pods {
+        let drain_state = drain_state(pod);
+        drained &= matches!(drain_state, DrainState::Finished | DrainState::Drained);
+        annotations.push(match drain_state {
+            DrainState::NotRequested => Some((DRAIN_ANNOTATION, "true".to_string())),
+            DrainState::Finished => Some((DRAINED_ANNOTATION, now.0.to_rfc3339())),
+            DrainState::Draining | DrainState::Drained => None,
+        });
+    }
+
+    let status = (!drained && elapsed < odoo.worker_drain_timeout()).then_some(ScalingDownStatus {
+        replicas,
+        target_replicas,
+        started_at,
+    });
+    ScaleDownPlan {
+        annotations,
+        status,
+    }
+}
+
+/// Only a pod which was annotated for draining before counts as finished, so a pod which was not
+/// ready yet when it was annotated is not taken for drained.
+fn drain_state(pod: &Pod) -> DrainState {
+    let annotations = pod.annotations();
+    if annotations.contains_key(DRAINED_ANNOTATION) {
+        DrainState::Drained
+    } else if !annotations.contains_key(DRAIN_ANNOTATION) {
+        DrainState::NotRequested
+    } else if worker_finished(pod) {
+        DrainState::Finished
+    } else {
+        DrainState::Draining
+    }
+}
+
+/// Wraps the command of the worker, so it stops once the pod is annotated for draining and the
+/// pod stays drained instead of being restarted. If the annotation is removed again, the
+/// container exits, so it is restarted with a new worker.
+pub fn drain_command(command: &str) -> String {
+    format!(
+        "{command} & worker=$!; \
+        trap 'kill -TERM $worker' TERM; \
+        while kill -0 $worker 2>/dev/null; do \
+        if [ \"$(cat {DRAIN_DIR}/{DRAIN_FILE} 2>/dev/null)\" = true ]; then \
+        kill -TERM $worker; wait $worker; touch {DRAINED_FILE}; \
+        trap 'exit 0' TERM; \
+        while [ \"$(cat {DRAIN_DIR}/{DRAIN_FILE} 2>/dev/null)\" = true ]; do sleep 1; done; \
+        echo 'The draining was cancelled, restarting the worker'; rm -f {DRAINED_FILE}; exit 0; \
+        fi; \
+        sleep 5; \
+        done; \
+        wait $worker"
+    )
+}
+
+/// Fails once the worker of the pod is drained.
+pub fn drained_probe() -> Probe {
+    Probe {
+        exec: Some(ExecAction {
+            command: Some(vec![
+                "/bin/bash".to_string(),
+                "-c".to_string(),
+                format!("test ! -f {DRAINED_FILE}"),
+            ]),
+        }),
+        period_seconds: Some(5),
+        ..Probe::default()
+    }
+}
+
+/// Exposes the drain annotation of the pod as file in `DRAIN_DIR`.
+pub fn drain_volume() -> Volume {
+    Volume {
+        name: DRAIN_VOLUME_NAME.to_string(),
+        downward_api: Some(DownwardAPIVolumeSource {
+            items: Some(vec![DownwardAPIVolumeFile {
+                path: DRAIN_FILE.to_string(),
+                field_ref: Some(ObjectFieldSelector {
+                    field_path: format!("metadata.annotations['{DRAIN_ANNOTATION}']"),
+                    ..ObjectFieldSelector::default()
+                }),
+                ..DownwardAPIVolumeFile::default()
+            }]),
+            ..DownwardAPIVolumeSource::default()
+        }),
+        ..Volume::default()
+    }
+}
+
+/// Whether the worker container runs, but fails the readiness probe, which only fails once the
+/// worker finished its jobs. A container which is waiting or terminated, e.g. while it restarts,
+/// has not finished.
+fn worker_finished(pod: &Pod) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.container_statuses.as_ref())
+        .into_iter()
+        .flatten()
+        .find(|container_status| container_status.name == Container::Odoo.to_string())
+        .map_or(false, |container_status| {
+            container_status.started == Some(true)
+                && !container_status.ready
+                && container_status
+                    .state
+                    .as_ref()
+                    .map_or(false, |state| state.running.is_some())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sovrin_cloud_crd::OdooClusterStatus;
+    use stackable_operator::k8s_openapi::chrono::{self, TimeZone};
+    use stackable_operator::status::condition::compute_conditions;
+
+    const STATEFULSET: &str = "odoo-worker-default";
+
+    fn odoo(scaling_down_since: Option<Time>) -> OdooCluster {
+        let mut odoo: OdooCluster = serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            workerDrainTimeoutSeconds: 600
+          ",
+        )
+        .unwrap();
+        odoo.status = scaling_down_since.map(|started_at| OdooClusterStatus {
+            scaling_down: [(
+                STATEFULSET.to_string(),
+                ScalingDownStatus {
+                    replicas: 3,
+                    target_replicas: 1,
+                    started_at,
+                },
+            )]
+            .into(),
+            ..OdooClusterStatus::default()
+        });
+        odoo
+    }
+
+    fn pod(ordinal: u8, annotations: &str, worker_state: &str) -> Pod {
+        serde_yaml::from_str(&format!(
+            "
+        apiVersion: v1
+        kind: Pod
+        metadata:
+          name: {STATEFULSET}-{ordinal}
+          annotations: {{{annotations}}}
+        status:
+          containerStatuses:
+            - name: odoo
+              image: odoo
+              imageID: ''
+              ready: false
+              restartCount: 0
+              started: true
+              state:
+                {worker_state}: {{}}
+          "
+        ))
+        .unwrap()
+    }
+
+    fn now() -> Time {
+        Time(chrono::Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap())
+    }
+
+    #[test]
+    fn test_scale_down_requests_draining() {
+        let plan = plan_scale_down(
+            &odoo(None),
+            STATEFULSET,
+            3,
+            1,
+            // Not ready, because it is still starting, but not drained
+            &[pod(1, "", "running"), pod(2, "", "waiting")],
+            now(),
+        );
+        assert_eq!(
+            ScaleDownPlan {
+                annotations: vec![
+                    Some((DRAIN_ANNOTATION, "true".to_string())),
+                    Some((DRAIN_ANNOTATION, "true".to_string())),
+                ],
+                status: Some(ScalingDownStatus {
+                    replicas: 3,
+                    target_replicas: 1,
+                    started_at: now(),
+                }),
+            },
+            plan
+        );
+    }
+
+    #[test]
+    fn test_scale_down_records_drained_pods() {
+        let started_at = Time(now().0 - chrono::Duration::minutes(1));
+        let plan = plan_scale_down(
+            &odoo(Some(started_at.clone())),
+            STATEFULSET,
+            3,
+            1,
+            &[
+                pod(1, "odoo.sovrin.cloud/drain: 'true'", "running"),
+                // Restarting, so the drain probe did not fail
+                pod(2, "odoo.sovrin.cloud/drain: 'true'", "waiting"),
+            ],
+            now(),
+        );
+        assert_eq!(
+            ScaleDownPlan {
+                annotations: vec![
+                    Some((DRAINED_ANNOTATION, "2024-01-01T12:00:00+00:00".to_string())),
+                    None,
+                ],
+                status: Some(ScalingDownStatus {
+                    replicas: 3,
+                    target_replicas: 1,
+                    started_at,
+                }),
+            },
+            plan
+        );
+    }
+
+    #[test]
+    fn test_scale_down_completes() {
+        let started_at = Time(now().0 - chrono::Duration::minutes(1));
+        let plan = plan_scale_down(
+            &odoo(Some(started_at)),
+            STATEFULSET,
+            3,
+            1,
+            &[
+                // The recorded state counts, even if the pod restarted since
+                pod(
+                    1,
+                    "odoo.sovrin.cloud/drain: 'true', odoo.sovrin.cloud/drained: '2024-01-01'",
+                    "waiting",
+                ),
+                pod(2, "odoo.sovrin.cloud/drain: 'true'", "running"),
+            ],
+            now(),
+        );
+        assert_eq!(
+            ScaleDownPlan {
+                annotations: vec![
+                    None,
+                    Some((DRAINED_ANNOTATION, "2024-01-01T12:00:00+00:00".to_string())),
+                ],
+                status: None,
+            },
+            plan
+        );
+    }
+
+    #[test]
+    fn test_scale_down_times_out() {
+        let started_at = Time(now().0 - chrono::Duration::minutes(10));
+        let plan = plan_scale_down(
+            &odoo(Some(started_at)),
+            STATEFULSET,
+            3,
+            1,
+            &[pod(1, "odoo.sovrin.cloud/drain: 'true'", "waiting")],
+            now(),
+        );
+        assert_eq!(vec![None], plan.annotations);
+        assert_eq!(None, plan.status);
+    }
+
+    #[test]
+    fn test_drain_command() {
+        let command = drain_command("odoo celery worker");
+        assert!(command.starts_with("odoo celery worker & worker=$!;"));
+        assert!(command.contains("cat /stackable/drain/drain"));
+        assert!(command.contains("touch /tmp/drained"));
+        assert!(command.contains("rm -f /tmp/drained; exit 0;"));
+    }
+
+    #[test]
+    fn test_raised_replicas_undrain_the_kept_pods() {
+        let pods = [
+            pod(0, "", "running"),
+            pod(1, "odoo.sovrin.cloud/drain: 'true'", "running"),
+            pod(
+                2,
+                "odoo.sovrin.cloud/drain: 'true', odoo.sovrin.cloud/drained: '2024-01-01'",
+                "running",
+            ),
+            pod(3, "odoo.sovrin.cloud/drain: 'true'", "running"),
+        ];
+
+        // Scaled down from 4 to 1 and raised to 3 again, so the pod 3 is still removed
+        let names = kept_drained_pods(&pods, STATEFULSET, 3)
+            .into_iter()
+            .map(ResourceExt::name_any)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["odoo-worker-default-1", "odoo-worker-default-2"],
+            names
+        );
+        assert!(kept_drained_pods(&pods, STATEFULSET, 1).is_empty());
+        // The pods of another StatefulSet with the same prefix are not touched
+        assert!(kept_drained_pods(&pods, "odoo-worker", 4).is_empty());
+    }
+
+    #[test]
+    fn test_scaling_down_condition() {
+        let scaling_down = BTreeMap::from([(
+            STATEFULSET.to_string(),
+            ScalingDownStatus {
+                replicas: 3,
+                target_replicas: 1,
+                started_at: now(),
+            },
+        )]);
+        let conditions =
+            compute_conditions(&odoo(None), &[&ScalingDownConditionBuilder(&scaling_down)]);
+        let condition = conditions
+            .iter()
+            .find(|condition| condition.type_ == ClusterConditionType::Progressing)
+            .unwrap();
+        assert_eq!(ClusterConditionStatus::True, condition.status);
+        assert_eq!(
+            Some("Draining the removed workers to scale down odoo-worker-default from 3 to 1 replicas"),
+            condition.message.as_deref()
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Manages the webserver secret key if its rotation is requested via
+//! `clusterConfig.secretKeyGeneration`.
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{OdooCluster, SecretKeyStatus};
+use stackable_operator::{
+    builder::ObjectMetaBuilder,
+    client::Client,
+    k8s_openapi::{api::core::v1::Secret, apimachinery::pkg::apis::meta::v1::Time, chrono::Utc},
+    kube::ResourceExt,
+};
+use std::{collections::BTreeMap, fs::File, io::Read};
+
+use crate::odoo_controller::AIRFLOW_CONTROLLER_NAME;
+
+pub const SECRET_KEY_KEY: &str = "secretKey";
+pub const SECRET_KEY_GENERATION_ANNOTATION: &str = "odoo.sovrin.cloud/secret-key-generation";
+
+const SECRET_KEY_BYTES: usize = 32;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("failed to generate a random secret key"))]
+    GenerateSecretKey { source: std::io::Error },
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to get the secret key Secret [{name}]"))]
+    GetSecret {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to apply the secret key Secret [{name}]"))]
+    ApplySecret {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Name of the Secret containing the secret key managed by the operator.
+pub fn secret_key_secret_name(odoo: &OdooCluster) -> String {
+    format!("{}-secret-key", odoo.name_any())
+}
+
+/// Generates a new secret key whenever the requested generation differs from the one recorded
+/// in the status, and returns the status to record. The pods are rolled by the StatefulSets,
+/// as the generation is part of their pod templates. A key already generated for the
+/// generation is kept, e.g. if the status could not be written after the rotation.
+pub async fn reconcile_secret_key(
+    client: &Client,
+    odoo: &OdooCluster,
+) -> Result<Option<SecretKeyStatus>> {
+    let generation = match odoo.spec.cluster_config.secret_key_generation {
+        Some(generation) => generation,
+        None => return Ok(None),
+    };
+    if let Some(current_status) = current_status(odoo, generation) {
+        return Ok(Some(current_status));
+    }
+    let name = secret_key_secret_name(odoo);
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let existing_secret = client
+        .get_opt::<Secret>(&name, &namespace)
+        .await
+        .context(GetSecretSnafu { name: name.clone() })?;
+    if let Some(existing_status) = existing_secret
+        .as_ref()
+        .and_then(|secret| existing_status(secret, generation))
+    {
+        return Ok(Some(existing_status));
+    }
+
+    let secret = build_secret_key_secret(odoo, generation)?;
+    client
+        .apply_patch(AIRFLOW_CONTROLLER_NAME, &secret, &secret)
+        .await
+        .context(ApplySecretSnafu { name })?;
+    tracing::info!(generation, "Rotated the secret key");
+
+    Ok(Some(SecretKeyStatus {
+        generation,
+        rotated_at: Time(Utc::now()),
+    }))
+}
+
+/// The recorded status if the current key was generated for the requested generation.
+fn current_status(odoo: &OdooCluster, generation: u32) -> Option<SecretKeyStatus> {
+    odoo.status
+        .as_ref()
+        .and_then(|status| status.secret_key.clone())
+        .filter(|current_status| current_status.generation == generation)
+}
+
+/// The status of the existing Secret if its key was generated for the requested generation. It
+/// was rotated when it was last applied.
+fn existing_status(secret: &Secret, generation: u32) -> Option<SecretKeyStatus> {
+    let key_generation = secret.annotations().get(SECRET_KEY_GENERATION_ANNOTATION);
+    if key_generation != Some(&generation.to_string()) {
+        return None;
+    }
+    let rotated_at = secret
+        .metadata
+        .managed_fields
+        .iter()
+        .flatten()
+        .filter_map(|managed_fields| managed_fields.time.clone())
+        .max_by_key(|time| time.0)
+        .or_else(|| secret.metadata.creation_timestamp.clone())
+        .unwrap_or_else(|| Time(Utc::now()));
+    Some(SecretKeyStatus {
+        generation,
+        rotated_at,
+    })
+}
+
+fn build_secret_key_secret(odoo: &OdooCluster, generation: u32) -> Result<Secret> {
+    Ok(Secret {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(secret_key_secret_name(odoo))
+            .ownerreference_from_resource(odoo, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            .with_annotation(SECRET_KEY_GENERATION_ANNOTATION, generation.to_string())
+            .build(),
+        string_data: Some(BTreeMap::from([(
+            SECRET_KEY_KEY.to_string(),
+            generate_secret_key().context(GenerateSecretKeySnafu)?,
+        )])),
+        ..Secret::default()
+    })
+}
+
+fn generate_secret_key() -> std::io::Result<String> {
+    let mut bytes = [0u8; SECRET_KEY_BYTES];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn odoo_cluster() -> OdooCluster {
+        serde_yaml::from_str(
+            "
+        apiVersion: odoo.stackable.tech/v1alpha1
+        kind: OdooCluster
+        metadata:
+          name: odoo
+          namespace: default
+          uid: 12345678-1234-1234-1234-123456789012
+        spec:
+          image:
+            productVersion: 2.6.1
+            stackableVersion: 0.0.0-dev
+          clusterConfig:
+            credentialsSecret: simple-odoo-credentials
+            secretKeyGeneration: 2
+        status:
+          secretKey:
+            generation: 1
+            rotatedAt: 2023-06-01T12:00:00Z
+          ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_current_status() {
+        let odoo = odoo_cluster();
+
+        assert_eq!(None, current_status(&odoo, 2));
+        assert_eq!(
+            Some(1),
+            current_status(&odoo, 1).map(|status| status.generation)
+        );
+    }
+
+    #[test]
+    fn test_existing_status() {
+        let secret: Secret = serde_yaml::from_str(
+            "
+        apiVersion: v1
+        kind: Secret
+        metadata:
+          name: odoo-secret-key
+          annotations:
+            odoo.sovrin.cloud/secret-key-generation: \"2\"
+          creationTimestamp: 2023-01-01T12:00:00Z
+          managedFields:
+            - manager: odoo.sovrin.cloud_odoocluster
+              operation: Apply
+              time: 2023-06-02T12:00:00Z
+          ",
+        )
+        .unwrap();
+
+        // The key of the generation is kept rather than generated again
+        assert_eq!(
+            Some(SecretKeyStatus {
+                generation: 2,
+                rotated_at: Time("2023-06-02T12:00:00Z".parse().unwrap()),
+            }),
+            existing_status(&secret, 2)
+        );
+        assert_eq!(None, existing_status(&secret, 3));
+    }
+
+    #[test]
+    fn test_build_secret_key_secret() {
+        let odoo = odoo_cluster();
+
+        let secret = build_secret_key_secret(&odoo, 2).unwrap();
+        assert_eq!("odoo-secret-key", secret.name_any());
+        assert_eq!(Some("default".to_string()), secret.namespace());
+        assert_eq!(1, secret.owner_references().len());
+        assert_eq!(
+            Some(&"2".to_string()),
+            secret.annotations().get(SECRET_KEY_GENERATION_ANNOTATION)
+        );
+
+        let secret_key = secret.string_data.unwrap().remove(SECRET_KEY_KEY).unwrap();
+        assert_eq!(2 * SECRET_KEY_BYTES, secret_key.len());
+        assert!(secret_key.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // Every rotation generates a new key
+        let other_secret = build_secret_key_secret(&odoo, 2).unwrap();
+        assert_ne!(
+            Some(&secret_key),
+            other_secret
+                .string_data
+                .as_ref()
+                .unwrap()
+                .get(SECRET_KEY_KEY)
+        );
+    }
+}
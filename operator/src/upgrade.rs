@@ -0,0 +1,443 @@
+//! Migrates the database when `spec.image.productVersion` changes and updates the modules
+//! according to `clusterConfig.moduleUpgradePolicy` when the product image changes. The
+//! StatefulSets are only rolled out with the new image once the jobs completed, and the
+//! webservers are stopped while they run, see [`crate::rollout`].
+use snafu::{OptionExt, ResultExt, Snafu};
+use sovrin_cloud_crd::{
+    cnpg::CNPG_APP_SECRET_URI_KEY,
+    credentials::{credentials_file, CREDENTIALS_DIR, CREDENTIALS_VOLUME_NAME},
+    database::SQL_ALCHEMY_CONN_ENV,
+    OdooCluster, UpgradeCondition, UpgradeStatus, AIRFLOW_UID,
+};
+use stackable_operator::{
+    builder::{
+        resources::ResourceRequirementsBuilder, ContainerBuilder, ObjectMetaBuilder,
+        PodSecurityContextBuilder,
+    },
+    client::Client,
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::{
+        api::{
+            batch::v1::{Job, JobSpec},
+            core::v1::{EnvVar, PodSpec, PodTemplateSpec, Volume},
+        },
+        apimachinery::pkg::apis::meta::v1::Time,
+        chrono::Utc,
+    },
+    kube::ResourceExt,
+};
+use std::collections::BTreeMap;
+
+use crate::{
+    backup, controller_commons, network_policy,
+    odoo_controller::AIRFLOW_CONTROLLER_NAME,
+    utils::{
+        add_labels, assemble_connection_uri_command, env_var_from_secret, export_env_var_from_file,
+        get_job_state, JobState,
+    },
+};
+
+const CONTAINER_NAME: &str = "odoo-upgrade-db";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("invalid container name"))]
+    InvalidContainerName {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::error::Error,
+    },
+    #[snafu(display("invalid module upgrade policy"))]
+    InvalidModuleUpgradePolicy { source: sovrin_cloud_crd::Error },
+    #[snafu(display("failed to back up the database before the upgrade"))]
+    Backup { source: crate::backup::Error },
+    #[snafu(display("failed to retrieve the upgrade job [{name}]"))]
+    GetUpgradeJob {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+    #[snafu(display("failed to apply the upgrade job [{name}]"))]
+    ApplyUpgradeJob {
+        source: stackable_operator::error::Error,
+        name: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Runs the migration job and then the module upgrade job if needed, and returns the status of
+/// the upgrade as long as the rollout must be held back. Returns `None` if the database is up to
+/// date. The jobs are labeled with the `selected_labels` of the cluster.
+pub async fn reconcile_upgrade(
+    client: &Client,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    sa_name: &str,
+    selected_labels: &BTreeMap<String, String>,
+) -> Result<Option<UpgradeStatus>> {
+    if let Some(upgrade) = reconcile_migration(
+        client,
+        odoo,
+        resolved_product_image,
+        sa_name,
+        selected_labels,
+    )
+    .await?
+    {
+        return Ok(Some(upgrade));
+    }
+    reconcile_module_upgrade(
+        client,
+        odoo,
+        resolved_product_image,
+        sa_name,
+        selected_labels,
+    )
+    .await
+}
+
+/// Runs the migration job if the product version differs from the one the database was
+/// migrated for.
+async fn reconcile_migration(
+    client: &Client,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    sa_name: &str,
+    selected_labels: &BTreeMap<String, String>,
+) -> Result<Option<UpgradeStatus>> {
+    let to_version = &resolved_product_image.product_version;
+    let from_version = match odoo
+        .status
+        .as_ref()
+        .and_then(|status| status.product_version.as_ref())
+    {
+        Some(from_version) if from_version != to_version => from_version,
+        // A new database is initialized with the current version by the OdooDB
+        _ => return Ok(None),
+    };
+
+    let name = job_name(odoo, to_version);
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    // The pod template of a job is immutable, so an existing job is left as it is
+    let job = client
+        .get_opt::<Job>(&name, &namespace)
+        .await
+        .context(GetUpgradeJobSnafu { name: &name })?;
+
+    let condition = match job {
+        Some(job) => upgrade_condition(&job),
+        None => {
+            let backup_state = match &odoo.spec.cluster_config.upgrade_backup {
+                Some(backup_config) => backup::reconcile_backup(
+                    client,
+                    odoo,
+                    backup_config,
+                    resolved_product_image,
+                    sa_name,
+                    to_version,
+                    selected_labels,
+                )
+                .await
+                .context(BackupSnafu)?,
+                None => JobState::Complete,
+            };
+            match backup_state {
+                JobState::Complete => {
+                    tracing::info!(from_version, to_version, "Migrating the database");
+                    let mut job = build_upgrade_job(
+                        odoo,
+                        resolved_product_image,
+                        sa_name,
+                        &name,
+                        "odoo db upgrade",
+                    )?;
+                    add_labels(&mut job, selected_labels);
+                    let job = client
+                        .apply_patch(AIRFLOW_CONTROLLER_NAME, &job, &job)
+                        .await
+                        .context(ApplyUpgradeJobSnafu { name: &name })?;
+                    upgrade_condition(&job)
+                }
+                JobState::InProgress => Some(UpgradeCondition::BackingUp),
+                JobState::Failed => Some(UpgradeCondition::BackupFailed),
+            }
+        }
+    };
+    let condition = match condition {
+        Some(condition) => condition,
+        None => return Ok(None),
+    };
+
+    Ok(Some(UpgradeStatus {
+        from_version: from_version.clone(),
+        to_version: to_version.clone(),
+        condition,
+        started_at: started_at(odoo, to_version),
+    }))
+}
+
+/// Runs the module upgrade job if the modules are updated and the product image differs from the
+/// one the pods were last rolled out with. Clusters rolled out before the image was recorded are
+/// not updated.
+async fn reconcile_module_upgrade(
+    client: &Client,
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    sa_name: &str,
+    selected_labels: &BTreeMap<String, String>,
+) -> Result<Option<UpgradeStatus>> {
+    let Some(modules) = odoo
+        .modules_to_upgrade()
+        .context(InvalidModuleUpgradePolicySnafu)?
+    else {
+        return Ok(None);
+    };
+    let to_image = &resolved_product_image.image;
+    match odoo
+        .status
+        .as_ref()
+        .and_then(|status| status.modules_upgraded_for_image.as_ref())
+    {
+        Some(from_image) if from_image != to_image => {}
+        _ => return Ok(None),
+    }
+
+    let name = module_upgrade_job_name(odoo, to_image);
+    let namespace = odoo.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let job = match client
+        .get_opt::<Job>(&name, &namespace)
+        .await
+        .context(GetUpgradeJobSnafu { name: &name })?
+    {
+        Some(job) => job,
+        None => {
+            tracing::info!(modules, image = to_image, "Upgrading the modules");
+            let mut job = build_upgrade_job(
+                odoo,
+                resolved_product_image,
+                sa_name,
+                &name,
+                &format!("odoo -u {modules} --stop-after-init"),
+            )?;
+            add_labels(&mut job, selected_labels);
+            client
+                .apply_patch(AIRFLOW_CONTROLLER_NAME, &job, &job)
+                .await
+                .context(ApplyUpgradeJobSnafu { name: &name })?
+        }
+    };
+    let condition = match get_job_state(&job) {
+        JobState::Complete => return Ok(None),
+        JobState::InProgress => UpgradeCondition::UpgradingModules,
+        JobState::Failed => UpgradeCondition::ModuleUpgradeFailed,
+    };
+
+    let to_version = &resolved_product_image.product_version;
+    Ok(Some(UpgradeStatus {
+        from_version: odoo
+            .status
+            .as_ref()
+            .and_then(|status| status.product_version.clone())
+            .unwrap_or_else(|| to_version.clone()),
+        to_version: to_version.clone(),
+        condition,
+        started_at: started_at(odoo, to_version),
+    }))
+}
+
+/// The start of the running upgrade to `to_version`, or now if it is just starting.
+fn started_at(odoo: &OdooCluster, to_version: &str) -> Time {
+    odoo.status
+        .as_ref()
+        .and_then(|status| status.upgrade.as_ref())
+        .filter(|upgrade| upgrade.to_version == to_version)
+        .map(|upgrade| upgrade.started_at.clone())
+        .unwrap_or_else(|| Time(Utc::now()))
+}
+
+/// Returns `None` once the upgrade job completed.
+fn upgrade_condition(job: &Job) -> Option<UpgradeCondition> {
+    match get_job_state(job) {
+        JobState::Complete => None,
+        JobState::InProgress => Some(UpgradeCondition::Upgrading),
+        JobState::Failed => Some(UpgradeCondition::Failed),
+    }
+}
+
+/// The job is named after the image, so every image gets its own job. Job names are limited to
+/// 63 characters, as they are used as label value.
+fn module_upgrade_job_name(odoo: &OdooCluster, image: &str) -> String {
+    let image = image.rsplit('/').next().unwrap_or(image).to_lowercase();
+    let mut name = format!(
+        "{}-modules-{}",
+        odoo.name_any(),
+        image.replace(|c: char| !c.is_ascii_alphanumeric(), "-")
+    );
+    name.truncate(63);
+    name.trim_end_matches('-').to_string()
+}
+
+/// The job is named after the target version, so every upgrade gets its own job.
+fn job_name(odoo: &OdooCluster, to_version: &str) -> String {
+    format!(
+        "{}-upgrade-{}",
+        odoo.name_any(),
+        to_version.to_lowercase().replace(['.', '_', '+'], "-")
+    )
+}
+
+fn build_upgrade_job(
+    odoo: &OdooCluster,
+    resolved_product_image: &ResolvedProductImage,
+    sa_name: &str,
+    name: &str,
+    command: &str,
+) -> Result<Job> {
+    let cluster_config = &odoo.spec.cluster_config;
+
+    let mut commands = Vec::new();
+    let mut env = Vec::new();
+
+    let mut cb = ContainerBuilder::new(CONTAINER_NAME).context(InvalidContainerNameSnafu)?;
+    let mut volumes = Vec::new();
+    add_database_connection(odoo, &mut cb, &mut commands, &mut env, &mut volumes);
+    if cluster_config.database.assembles_connection_uri() {
+        commands.push(assemble_connection_uri_command());
+    }
+    commands.push(command.to_string());
+
+    cb.image_from_product_image(resolved_product_image)
+        .command(vec!["/bin/bash".to_string()])
+        .args(vec![String::from("-c"), commands.join("; ")])
+        .add_env_vars(env)
+        .resources(
+            ResourceRequirementsBuilder::new()
+                .with_cpu_request("100m")
+                .with_cpu_limit("400m")
+                .with_memory_request("512Mi")
+                .with_memory_limit("512Mi")
+                .build(),
+        );
+
+    let mut pod_spec = PodSpec {
+        containers: vec![cb.build()],
+        restart_policy: Some("Never".to_string()),
+        service_account: Some(sa_name.to_string()),
+        image_pull_secrets: resolved_product_image.pull_secrets.clone(),
+        security_context: Some(
+            PodSecurityContextBuilder::new()
+                .run_as_user(AIRFLOW_UID)
+                .run_as_group(0)
+                .build(),
+        ),
+        volumes: Some(volumes),
+        ..Default::default()
+    };
+    if let Some(ca_bundle) = &cluster_config.ca_bundle {
+        controller_commons::add_ca_bundle(&mut pod_spec, ca_bundle, resolved_product_image);
+    }
+    controller_commons::apply_compliance(&mut pod_spec, &cluster_config.compliance);
+
+    Ok(Job {
+        metadata: ObjectMetaBuilder::new()
+            .name_and_namespace(odoo)
+            .name(name)
+            .ownerreference_from_resource(odoo, None, Some(true))
+            .context(ObjectMissingMetadataForOwnerRefSnafu)?
+            .build(),
+        spec: Some(JobSpec {
+            template: PodTemplateSpec {
+                metadata: Some(network_policy::job_pod_metadata(
+                    odoo,
+                    AIRFLOW_CONTROLLER_NAME,
+                    &resolved_product_image.app_version_label,
+                    "upgrade",
+                )),
+                spec: Some(pod_spec),
+            },
+            ..Default::default()
+        }),
+        status: None,
+    })
+}
+
+/// Provides the connection URI as `AIRFLOW__CORE__SQL_ALCHEMY_CONN` and the TLS settings of
+/// the database to a job container. If the credentials contain the connection fields instead of
+/// the URI, only the libpq environment variables are provided, the URI has to be assembled with
+/// [`assemble_connection_uri_command`] by the containers which need it.
+pub fn add_database_connection(
+    odoo: &OdooCluster,
+    cb: &mut ContainerBuilder,
+    commands: &mut Vec<String>,
+    env: &mut Vec<EnvVar>,
+    volumes: &mut Vec<Volume>,
+) {
+    let cluster_config = &odoo.spec.cluster_config;
+    let connection_credentials = cluster_config.database.connection_credentials();
+    if let Some(cnpg_app_secret) = cluster_config.database.cnpg_app_secret() {
+        env.push(env_var_from_secret(
+            SQL_ALCHEMY_CONN_ENV,
+            &cnpg_app_secret,
+            CNPG_APP_SECRET_URI_KEY,
+        ));
+    } else if let Some(credentials_provider) = &cluster_config.credentials_provider {
+        let exports = connection_credentials
+            .iter()
+            .map(|(var_name, key)| export_env_var_from_file(var_name, &credentials_file(key)));
+        commands.splice(0..0, exports);
+        volumes.push(credentials_provider.volume());
+        cb.add_volume_mount(CREDENTIALS_VOLUME_NAME, CREDENTIALS_DIR);
+    } else {
+        let connection_secret = cluster_config
+            .database
+            .connection_secret(&cluster_config.credentials_secret);
+        env.extend(
+            connection_credentials
+                .iter()
+                .map(|(var_name, key)| env_var_from_secret(var_name, connection_secret, key)),
+        );
+    }
+    env.extend(cluster_config.database.connection_env_vars());
+    if let Some(database_tls) = &cluster_config.database.tls {
+        volumes.extend(database_tls.volumes());
+        cb.add_volume_mounts(database_tls.volume_mounts());
+        env.extend(database_tls.env_vars());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_name() {
+        let odoo: OdooCluster = serde_yaml::from_str(
+            "
+            apiVersion: odoo.stackable.tech/v1alpha1
+            kind: OdooCluster
+            metadata:
+              name: odoo
+            spec:
+              image:
+                productVersion: 2.6.1
+                stackableVersion: 0.0.0-dev
+              clusterConfig:
+                credentialsSecret: simple-odoo-credentials
+            ",
+        )
+        .unwrap();
+
+        assert_eq!("odoo-upgrade-2-6-1", job_name(&odoo, "2.6.1"));
+        assert_eq!(
+            "odoo-modules-odoo-2-6-1-stackable0-0-0-dev",
+            module_upgrade_job_name(
+                &odoo,
+                "docker.stackable.tech/stackable/odoo:2.6.1-stackable0.0.0-dev"
+            )
+        );
+    }
+}
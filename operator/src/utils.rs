@@ -1,6 +1,16 @@
-use stackable_operator::k8s_openapi::api::{
-    batch::v1::Job,
-    core::v1::{EnvVar, EnvVarSource, SecretKeySelector},
+use fnv::FnvHasher;
+use sovrin_cloud_crd::database::SQL_ALCHEMY_CONN_ENV;
+use stackable_operator::{
+    client::Client,
+    k8s_openapi::api::{
+        batch::v1::Job,
+        core::v1::{ConfigMap, EnvVar, EnvVarSource, SecretKeySelector},
+    },
+    kube::{api::DeleteParams, Api, Resource, ResourceExt},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    hash::{Hash, Hasher},
 };
 
 pub enum JobState {
@@ -31,6 +41,19 @@ pub fn get_job_state(job: &Job) -> JobState {
     }
 }
 
+/// Deletes the Job together with its pods, which a Job orphans by default. The Job is only
+/// gone once its pods are deleted, so callers have to wait for it before recreating it.
+pub async fn delete_job(client: &Client, job: &Job) -> Result<(), stackable_operator::kube::Error> {
+    if job.metadata.deletion_timestamp.is_some() {
+        return Ok(());
+    }
+    let api: Api<Job> =
+        Api::namespaced(client.as_kube_client(), &job.namespace().unwrap_or_default());
+    api.delete(&job.name_any(), &DeleteParams::foreground())
+        .await
+        .map(|_| ())
+}
+
 pub fn env_var_from_secret(var_name: &str, secret: &str, secret_key: &str) -> EnvVar {
     EnvVar {
         name: String::from(var_name),
@@ -44,4 +67,251 @@ pub fn env_var_from_secret(var_name: &str, secret: &str, secret_key: &str) -> En
         }),
         ..Default::default()
     }
-}
\ No newline at end of file
+}
+
+/// Returns a hash of the content of the ConfigMaps. FNV is used as it does not change between Rust
+/// versions, so upgrading the operator does not roll the pods annotated with it.
+pub fn config_map_hash(config_maps: &[&ConfigMap]) -> String {
+    let mut hasher = FnvHasher::default();
+    for config_map in config_maps {
+        config_map.data.hash(&mut hasher);
+        for (key, value) in config_map.binary_data.iter().flatten() {
+            key.hash(&mut hasher);
+            value.0.hash(&mut hasher);
+        }
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Returns a shell command exporting the content of the given file as environment variable.
+/// Used for credentials which are mounted as files instead of being read from a Secret.
+pub fn export_env_var_from_file(var_name: &str, file: &str) -> String {
+    export_env_var_from_command(var_name, &format!("cat {file}"))
+}
+
+/// Returns a shell command exporting the output of `command` as environment variable. The
+/// variable is assigned before it is exported, because `export` would mask a failure of the
+/// command, and the shell exits on a failure whether `set -e` is set or not.
+pub fn export_env_var_from_command(var_name: &str, command: &str) -> String {
+    format!("{var_name}=\"$({command})\" || exit 1; export {var_name}")
+}
+
+/// Returns a shell command exporting the connection URI assembled from the libpq environment
+/// variables, used if the credentials contain the connection fields instead of the URI.
+pub fn assemble_connection_uri_command() -> String {
+    export_env_var_from_command(
+        SQL_ALCHEMY_CONN_ENV,
+        "python3 -c 'import os; \
+        from sqlalchemy.engine import URL; \
+        print(URL.create(\"postgresql+psycopg2\", \
+        username=os.environ[\"PGUSER\"], password=os.environ[\"PGPASSWORD\"], \
+        host=os.environ[\"PGHOST\"], port=int(os.environ[\"PGPORT\"]), \
+        database=os.environ[\"PGDATABASE\"]).render_as_string(hide_password=False))'",
+    )
+}
+
+/// The labels of a custom resource which the label selector refers to, e.g. `shard` of
+/// `shard in (a,b)`. The objects created for the custom resource carry them, so their watchers
+/// select the same objects with the label selector as the watcher of the custom resources.
+pub fn selected_labels(
+    label_selector: Option<&str>,
+    labels: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let Some(label_selector) = label_selector else {
+        return BTreeMap::new();
+    };
+    let keys = label_selector_keys(label_selector);
+    labels
+        .iter()
+        .filter(|(key, _)| keys.contains(key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// The keys of the requirements of a label selector. The requirements are separated by commas,
+/// except within the values of `in` and `notin`.
+fn label_selector_keys(label_selector: &str) -> BTreeSet<&str> {
+    let mut keys = BTreeSet::new();
+    let mut in_values = false;
+    let mut start = 0;
+    let end = (label_selector.len(), ',');
+    for (index, character) in label_selector.char_indices().chain([end]) {
+        match character {
+            '(' => in_values = true,
+            ')' => in_values = false,
+            ',' if !in_values => {
+                let requirement = label_selector[start..index].trim();
+                let requirement = requirement.trim_start_matches('!').trim_start();
+                let key_len = requirement
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || "-_./".contains(c)))
+                    .unwrap_or(requirement.len());
+                if key_len > 0 {
+                    keys.insert(&requirement[..key_len]);
+                }
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    keys
+}
+
+/// Adds the labels to the object, the labels it already has are kept.
+pub fn add_labels<T: Resource>(object: &mut T, labels: &BTreeMap<String, String>) {
+    let object_labels = object.labels_mut();
+    for (key, value) in labels {
+        object_labels
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_operator::k8s_openapi::ByteString;
+
+    fn config_map(name: &str, content: &str) -> ConfigMap {
+        serde_yaml::from_str(&format!(
+            "
+        apiVersion: v1
+        kind: ConfigMap
+        metadata:
+          name: {name}
+        data:
+          odoo.conf: |
+            [options]
+            {content}
+          "
+        ))
+        .unwrap()
+    }
+
+    /// Runs the command in bash followed by printing the variable in a child process, which
+    /// only sees it if it is exported.
+    fn run_exported(command: &str, var_name: &str) -> std::process::Output {
+        std::process::Command::new("bash")
+            .args([
+                "-c",
+                &format!("{command}; bash -c 'echo \"exported ${var_name}\"'"),
+            ])
+            .output()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_export_env_var_from_file() {
+        let dir = std::env::temp_dir().join(format!("export-env-var-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("password");
+        std::fs::write(&file, "s3cr3t").unwrap();
+
+        let output = run_exported(
+            &export_env_var_from_file("PGPASSWORD", file.to_str().unwrap()),
+            "PGPASSWORD",
+        );
+        assert!(output.status.success());
+        assert_eq!("exported s3cr3t\n", String::from_utf8_lossy(&output.stdout));
+
+        // A missing file stops the shell instead of exporting an empty variable
+        std::fs::remove_file(&file).unwrap();
+        let output = run_exported(
+            &export_env_var_from_file("PGPASSWORD", file.to_str().unwrap()),
+            "PGPASSWORD",
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+
+    #[test]
+    fn test_assemble_connection_uri_command() {
+        let command = assemble_connection_uri_command();
+        assert!(command.starts_with("AIRFLOW__CORE__SQL_ALCHEMY_CONN=\"$(python3 -c 'import os; "));
+        assert!(command.ends_with("')\" || exit 1; export AIRFLOW__CORE__SQL_ALCHEMY_CONN"));
+
+        // A missing connection field stops the shell instead of exporting an empty URI
+        let output = std::process::Command::new("bash")
+            .args(["-c", &format!("{command}; echo started")])
+            .env_remove("PGHOST")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+
+    #[test]
+    fn test_config_map_hash() {
+        let config_map_a = config_map("odoo-webserver-default", "workers = 2");
+        let hash = config_map_hash(&[&config_map_a]);
+        assert_eq!(16, hash.len());
+
+        // Only the content is hashed
+        assert_eq!(
+            hash,
+            config_map_hash(&[&config_map("odoo-webserver-copy", "workers = 2")])
+        );
+        assert_ne!(
+            hash,
+            config_map_hash(&[&config_map("odoo-webserver-default", "workers = 4")])
+        );
+
+        let mut with_binary_data = config_map_a.clone();
+        with_binary_data.binary_data = Some(
+            [("logo.png".to_string(), ByteString(vec![1, 2, 3]))]
+                .into_iter()
+                .collect(),
+        );
+        assert_ne!(hash, config_map_hash(&[&with_binary_data]));
+
+        // A custom log configuration is rolled out as well
+        let log_config = config_map("odoo-log-config", "log_level = debug");
+        assert_ne!(hash, config_map_hash(&[&config_map_a, &log_config]));
+    }
+
+    #[test]
+    fn test_label_selector_keys() {
+        assert_eq!(
+            BTreeSet::from(["shard", "sovrin.cloud/tier", "team", "legacy"]),
+            label_selector_keys("shard in (a, b),sovrin.cloud/tier!=dev, team, !legacy")
+        );
+        assert_eq!(
+            BTreeSet::from(["environment"]),
+            label_selector_keys("environment notin (dev,test)")
+        );
+        assert!(label_selector_keys("").is_empty());
+    }
+
+    #[test]
+    fn test_selected_labels() {
+        let labels = BTreeMap::from([
+            ("shard".to_string(), "a".to_string()),
+            ("argocd.argoproj.io/instance".to_string(), "erp".to_string()),
+        ]);
+        // Other labels are not propagated, e.g. a tracking label would let Argo CD prune them
+        assert_eq!(
+            BTreeMap::from([("shard".to_string(), "a".to_string())]),
+            selected_labels(Some("shard in (a,b)"), &labels)
+        );
+        assert!(selected_labels(None, &labels).is_empty());
+
+        let mut config_map = config_map("odoo-webserver-default", "workers = 2");
+        config_map
+            .labels_mut()
+            .insert("shard".to_string(), "b".to_string());
+        add_labels(
+            &mut config_map,
+            &BTreeMap::from([
+                ("shard".to_string(), "a".to_string()),
+                ("team".to_string(), "erp".to_string()),
+            ]),
+        );
+        assert_eq!(
+            &BTreeMap::from([
+                ("shard".to_string(), "b".to_string()),
+                ("team".to_string(), "erp".to_string()),
+            ]),
+            config_map.labels()
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! Rolegroups with the workload type `Deployment`. They are built as StatefulSet like all other
+//! rolegroups and converted just before they are applied, so the pods are the same for both
+//! workload types.
+use sovrin_cloud_crd::{RoleGroupStatus, WorkloadType};
+use stackable_operator::{
+    k8s_openapi::{
+        api::apps::v1::{
+            Deployment, DeploymentSpec, DeploymentStrategy, RollingUpdateDeployment, StatefulSet,
+        },
+        apimachinery::pkg::util::intstr::IntOrString,
+    },
+    kube::ResourceExt,
+    labels::APP_ROLE_GROUP_LABEL,
+    status::condition::{
+        statefulset::StatefulSetConditionBuilder, ClusterCondition, ClusterConditionSet,
+        ClusterConditionStatus, ClusterConditionType, ConditionBuilder,
+    },
+};
+
+use crate::{applied_objects::AppliedObjects, odoo_controller::CONFIG_HASH_ANNOTATION, rollout};
+
+const REVISION_ANNOTATION: &str = "deployment.kubernetes.io/revision";
+
+pub enum Workload {
+    StatefulSet(StatefulSet),
+    Deployment(Deployment),
+}
+
+impl Workload {
+    /// The workload of the rolegroup StatefulSet. If it is held back, its pods keep their current
+    /// revision.
+    pub fn build(
+        mut statefulset: StatefulSet,
+        workload_type: WorkloadType,
+        held_back: bool,
+    ) -> Self {
+        match workload_type {
+            WorkloadType::StatefulSet => {
+                if held_back {
+                    rollout::hold_back(&mut statefulset);
+                }
+                Workload::StatefulSet(statefulset)
+            }
+            WorkloadType::Deployment => {
+                Workload::Deployment(build_deployment(statefulset, held_back))
+            }
+        }
+    }
+
+    /// Scales the workload down to zero replicas, like `ClusterResources` does with the workloads
+    /// of stopped clusters.
+    pub fn stop(&mut self) {
+        match self {
+            Workload::StatefulSet(statefulset) => {
+                if let Some(spec) = statefulset.spec.as_mut() {
+                    spec.replicas = Some(0);
+                }
+            }
+            Workload::Deployment(deployment) => {
+                if let Some(spec) = deployment.spec.as_mut() {
+                    spec.replicas = Some(0);
+                }
+            }
+        }
+    }
+
+    /// Records the workload, so it is not deleted as orphan.
+    pub fn record_in(&self, applied_objects: &mut AppliedObjects) {
+        match self {
+            Workload::StatefulSet(statefulset) => applied_objects.record(statefulset),
+            Workload::Deployment(deployment) => applied_objects.record(deployment),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Workload::StatefulSet(statefulset) => statefulset.name_any(),
+            Workload::Deployment(deployment) => deployment.name_any(),
+        }
+    }
+
+    /// Whether all pods run the current revision and are ready.
+    pub fn is_rolled_out(&self) -> bool {
+        match self {
+            Workload::StatefulSet(statefulset) => rollout::is_rolled_out(statefulset),
+            Workload::Deployment(deployment) => is_rolled_out(deployment),
+        }
+    }
+
+    /// The rolegroup in the labels of the workload, which differs from the configured one for
+    /// the green generation of blue/green webservers.
+    pub fn role_group(&self) -> Option<String> {
+        let labels = match self {
+            Workload::StatefulSet(statefulset) => statefulset.labels(),
+            Workload::Deployment(deployment) => deployment.labels(),
+        };
+        labels.get(APP_ROLE_GROUP_LABEL).cloned()
+    }
+
+    /// The replicas of the workload. The generation and the configuration are only reported once
+    /// all pods are rolled out, otherwise the `previous` ones are kept.
+    pub fn role_group_status(&self, previous: Option<&RoleGroupStatus>) -> RoleGroupStatus {
+        let (generation, template, replicas, ready_replicas) = match self {
+            Workload::StatefulSet(statefulset) => (
+                statefulset.metadata.generation,
+                statefulset.spec.as_ref().map(|spec| &spec.template),
+                statefulset.spec.as_ref().and_then(|spec| spec.replicas),
+                statefulset
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.ready_replicas),
+            ),
+            Workload::Deployment(deployment) => (
+                deployment.metadata.generation,
+                deployment.spec.as_ref().map(|spec| &spec.template),
+                deployment.spec.as_ref().and_then(|spec| spec.replicas),
+                deployment
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.ready_replicas),
+            ),
+        };
+        let (rolled_out_generation, config_hash) = if self.is_rolled_out() {
+            let config_hash = template
+                .and_then(|template| template.metadata.as_ref())
+                .and_then(|metadata| metadata.annotations.as_ref())
+                .and_then(|annotations| annotations.get(CONFIG_HASH_ANNOTATION))
+                .cloned();
+            (generation, config_hash)
+        } else {
+            (
+                previous.and_then(|previous| previous.rolled_out_generation),
+                previous.and_then(|previous| previous.config_hash.clone()),
+            )
+        };
+        RoleGroupStatus {
+            replicas: replicas.unwrap_or(1),
+            ready_replicas: ready_replicas.unwrap_or_default(),
+            rolled_out_generation,
+            config_hash,
+        }
+    }
+
+    /// The revision the pods are updated to.
+    pub fn revision(&self) -> Option<String> {
+        match self {
+            Workload::StatefulSet(statefulset) => statefulset
+                .status
+                .as_ref()
+                .and_then(|status| status.update_revision.clone()),
+            Workload::Deployment(deployment) => {
+                deployment.annotations().get(REVISION_ANNOTATION).cloned()
+            }
+        }
+    }
+
+    pub fn add_to(
+        self,
+        statefulset_cond_builder: &mut StatefulSetConditionBuilder,
+        deployment_cond_builder: &mut DeploymentConditionBuilder,
+    ) {
+        match self {
+            Workload::StatefulSet(statefulset) => statefulset_cond_builder.add(statefulset),
+            Workload::Deployment(deployment) => deployment_cond_builder.add(deployment),
+        }
+    }
+}
+
+/// Converts the rolegroup StatefulSet. New pods are started before old ones are stopped, so the
+/// rolegroup keeps its capacity during updates. A paused Deployment keeps its current pods, like
+/// a StatefulSet which is held back.
+fn build_deployment(statefulset: StatefulSet, paused: bool) -> Deployment {
+    let spec = statefulset.spec.unwrap_or_default();
+    Deployment {
+        metadata: statefulset.metadata,
+        spec: Some(DeploymentSpec {
+            replicas: spec.replicas,
+            selector: spec.selector,
+            template: spec.template,
+            strategy: Some(DeploymentStrategy {
+                type_: Some("RollingUpdate".to_string()),
+                rolling_update: Some(RollingUpdateDeployment {
+                    max_surge: Some(IntOrString::String("25%".to_string())),
+                    max_unavailable: Some(IntOrString::Int(0)),
+                }),
+            }),
+            paused: paused.then_some(true),
+            ..DeploymentSpec::default()
+        }),
+        status: None,
+    }
+}
+
+fn is_rolled_out(deployment: &Deployment) -> bool {
+    let (Some(spec), Some(status)) = (&deployment.spec, &deployment.status) else {
+        return false;
+    };
+    let replicas = spec.replicas.unwrap_or(1);
+    status.observed_generation >= deployment.metadata.generation
+        && status.updated_replicas.unwrap_or_default() == replicas
+        && status.ready_replicas.unwrap_or_default() == replicas
+        && status.replicas.unwrap_or_default() == replicas
+}
+
+/// Reports the cluster as available once all Deployments have their ready replicas.
+#[derive(Default)]
+pub struct DeploymentConditionBuilder {
+    deployments: Vec<Deployment>,
+}
+
+impl DeploymentConditionBuilder {
+    pub fn add(&mut self, deployment: Deployment) {
+        self.deployments.push(deployment);
+    }
+}
+
+impl ConditionBuilder for DeploymentConditionBuilder {
+    fn build_conditions(&self) -> ClusterConditionSet {
+        let unavailable = self
+            .deployments
+            .iter()
+            .filter(|deployment| {
+                let ready_replicas = deployment
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.ready_replicas)
+                    .unwrap_or_default();
+                let replicas = deployment
+                    .spec
+                    .as_ref()
+                    .and_then(|spec| spec.replicas)
+                    .unwrap_or(1);
+                ready_replicas < replicas
+            })
+            .map(|deployment| deployment.name_any())
+            .collect::<Vec<_>>();
+        let (status, message) = if unavailable.is_empty() {
+            (
+                ClusterConditionStatus::True,
+                "All Deployments have the requested amount of ready replicas.".to_string(),
+            )
+        } else {
+            (
+                ClusterConditionStatus::False,
+                format!("Deployments {unavailable:?} missing ready replicas."),
+            )
+        };
+
+        let cond = ClusterCondition {
+            reason: None,
+            message: Some(message),
+            status,
+            type_: ClusterConditionType::Available,
+            last_transition_time: None,
+            last_update_time: None,
+        };
+
+        vec![cond].into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stackable_operator::{
+        builder::ObjectMetaBuilder,
+        k8s_openapi::api::{
+            apps::v1::{DeploymentStatus, StatefulSetSpec, StatefulSetStatus},
+            core::v1::PodTemplateSpec,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_build_deployment() {
+        let statefulset = StatefulSet {
+            metadata: ObjectMetaBuilder::new()
+                .name("odoo-webserver-default")
+                .build(),
+            spec: Some(StatefulSetSpec {
+                replicas: Some(2),
+                service_name: "odoo-webserver-default".to_string(),
+                ..StatefulSetSpec::default()
+            }),
+            ..StatefulSet::default()
+        };
+
+        let mut deployment = build_deployment(statefulset.clone(), false);
+        assert_eq!("odoo-webserver-default", deployment.name_any());
+        assert_eq!(None, deployment.spec.as_ref().and_then(|spec| spec.paused));
+        assert!(!is_rolled_out(&deployment));
+
+        deployment.metadata.generation = Some(3);
+        deployment.status = Some(DeploymentStatus {
+            observed_generation: Some(3),
+            replicas: Some(2),
+            updated_replicas: Some(2),
+            ready_replicas: Some(2),
+            ..DeploymentStatus::default()
+        });
+        assert!(is_rolled_out(&deployment));
+        assert_eq!(
+            RoleGroupStatus {
+                replicas: 2,
+                ready_replicas: 2,
+                rolled_out_generation: Some(3),
+                config_hash: None,
+            },
+            Workload::Deployment(deployment).role_group_status(None)
+        );
+
+        let deployment = build_deployment(statefulset, true);
+        assert_eq!(
+            Some(true),
+            deployment.spec.as_ref().and_then(|spec| spec.paused)
+        );
+    }
+
+    #[test]
+    fn test_stop() {
+        let statefulset = StatefulSet {
+            spec: Some(StatefulSetSpec {
+                replicas: Some(2),
+                ..StatefulSetSpec::default()
+            }),
+            ..StatefulSet::default()
+        };
+
+        for workload_type in [WorkloadType::StatefulSet, WorkloadType::Deployment] {
+            let mut workload = Workload::build(statefulset.clone(), workload_type, false);
+            workload.stop();
+            assert_eq!(0, workload.role_group_status(None).replicas);
+        }
+    }
+
+    #[test]
+    fn test_role_group_status() {
+        let statefulset = |current_revision: &str| StatefulSet {
+            metadata: ObjectMetaBuilder::new()
+                .name("odoo-webserver-green")
+                .with_label(APP_ROLE_GROUP_LABEL, "green")
+                .build(),
+            spec: Some(StatefulSetSpec {
+                replicas: Some(3),
+                template: PodTemplateSpec {
+                    metadata: Some(
+                        ObjectMetaBuilder::new()
+                            .with_annotation(CONFIG_HASH_ANNOTATION, "new")
+                            .build(),
+                    ),
+                    ..PodTemplateSpec::default()
+                },
+                ..StatefulSetSpec::default()
+            }),
+            status: Some(StatefulSetStatus {
+                observed_generation: Some(5),
+                ready_replicas: Some(2),
+                current_revision: Some(current_revision.to_string()),
+                update_revision: Some("odoo-2".to_string()),
+                ..StatefulSetStatus::default()
+            }),
+            ..StatefulSet::default()
+        };
+        let previous = RoleGroupStatus {
+            replicas: 3,
+            ready_replicas: 3,
+            rolled_out_generation: Some(4),
+            config_hash: Some("old".to_string()),
+        };
+
+        let mut rolling_out = statefulset("odoo-1");
+        rolling_out.metadata.generation = Some(5);
+        let workload = Workload::StatefulSet(rolling_out);
+        assert_eq!(Some("green".to_string()), workload.role_group());
+        // The generation and configuration of the last complete rollout are kept
+        assert_eq!(
+            RoleGroupStatus {
+                replicas: 3,
+                ready_replicas: 2,
+                rolled_out_generation: Some(4),
+                config_hash: Some("old".to_string()),
+            },
+            workload.role_group_status(Some(&previous))
+        );
+
+        let mut rolled_out = statefulset("odoo-2");
+        rolled_out.metadata.generation = Some(5);
+        if let Some(status) = rolled_out.status.as_mut() {
+            status.ready_replicas = Some(3);
+        }
+        assert_eq!(
+            RoleGroupStatus {
+                replicas: 3,
+                ready_replicas: 3,
+                rolled_out_generation: Some(5),
+                config_hash: Some("new".to_string()),
+            },
+            Workload::StatefulSet(rolled_out).role_group_status(Some(&previous))
+        );
+    }
+}